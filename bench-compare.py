@@ -0,0 +1,82 @@
+#!/usr/bin/env python3
+"""\
+Usage: bench-compare.py [options]
+Report the changes criterion measured against a saved baseline.
+
+Run the benchmarks with `-- --save-baseline <name>` on the base revision and
+with `-- --baseline <name>` on the revision being checked before running this.
+
+Options:
+    -h | --help
+        Show this usage information.
+
+    --criterion-dir <dir>
+        The directory criterion writes its results to. The default is
+        target/criterion.
+"""
+from getopt import GetoptError, getopt
+from json import load as json_load
+from os import walk
+from os.path import join as path_join
+from os.path import relpath
+from sys import argv
+from sys import exit as sys_exit
+from sys import stderr, stdout
+
+
+def load_changes(criterion_dir):
+    """
+    Returns the relative change in mean time of each benchmark, keyed by the
+    benchmark's name.
+    """
+    changes = {}
+    for dirpath, _, filenames in walk(criterion_dir):
+        if "estimates.json" not in filenames or not dirpath.endswith("/change"):
+            continue
+
+        name = relpath(dirpath, criterion_dir)[: -len("/change")]
+        with open(path_join(dirpath, "estimates.json")) as fd:
+            estimates = json_load(fd)
+        changes[name] = estimates["mean"]["point_estimate"]
+
+    return changes
+
+
+def main(args):
+    criterion_dir = "target/criterion"
+
+    try:
+        opts, args = getopt(args, "h", ["help", "criterion-dir="])
+        for opt, value in opts:
+            if opt in ("-h", "--help"):
+                usage(stdout)
+                return 0
+            if opt == "--criterion-dir":
+                criterion_dir = value
+    except GetoptError as e:
+        print(e, file=stderr)
+        usage()
+        return 2
+
+    if args:
+        print(f"Unknown argument: {args[0]}", file=stderr)
+        usage()
+        return 2
+
+    changes = load_changes(criterion_dir)
+    if not changes:
+        print(f"No baseline comparisons found in {criterion_dir}")
+        return 0
+
+    for name, change in sorted(changes.items()):
+        print(f"{name}: {change * 100.0:+.2f}%")
+
+    return 0
+
+
+def usage(fd=stderr):
+    fd.write(__doc__)
+
+
+if __name__ == "__main__":
+    sys_exit(main(argv[1:]))
@@ -0,0 +1,108 @@
+use {
+    criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput},
+    hyper::{body::Body, Request},
+    rusoto_core::Region,
+    rusoto_credential::AwsCredentials,
+    rusoto_signature::{signature::SignedRequestPayload, SignedRequest},
+    scratchstack_aws_principal::{Principal, User},
+    scratchstack_http_framework::{
+        bench_support::{bench_verifier, run_request, StaticSigningKey},
+        XmlErrorMapper,
+    },
+    tokio::runtime::Runtime,
+};
+
+const ACCESS_KEY: &str = "AKIDEXAMPLE";
+const SECRET_KEY: &str = "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+const LARGE_BODY_SIZE: usize = 1 << 20;
+
+fn signing_key_provider() -> StaticSigningKey {
+    let principal = Principal::from(vec![User::new("aws", "123456789012", "/", "bench").unwrap().into()]);
+    StaticSigningKey::new(ACCESS_KEY, SECRET_KEY, principal)
+}
+
+/// Create a hyper request signed with rusoto's signer.
+fn signed_request(secret_key: &str, body: Option<Vec<u8>>) -> Request<Body> {
+    let region = Region::Custom {
+        name: "local".to_owned(),
+        endpoint: "http://localhost".to_owned(),
+    };
+    let method = if body.is_some() {
+        "POST"
+    } else {
+        "GET"
+    };
+    let mut sr = SignedRequest::new(method, "service", &region, "/");
+    if let Some(body) = body {
+        sr.set_content_type("application/octet-stream".to_owned());
+        sr.set_payload(Some(body));
+    }
+    sr.sign(&AwsCredentials::new(ACCESS_KEY, secret_key, None, None));
+
+    let mut uri = format!("http://{}{}", sr.hostname(), sr.canonical_uri);
+    if !sr.canonical_query_string.is_empty() {
+        uri.push('?');
+        uri.push_str(&sr.canonical_query_string);
+    }
+
+    let mut builder = Request::builder().method(method).uri(uri);
+    for (name, values) in sr.headers.iter() {
+        for value in values {
+            builder = builder.header(name.as_str(), value.as_slice());
+        }
+    }
+
+    let body = match sr.payload.take() {
+        Some(SignedRequestPayload::Buffer(bytes)) => Body::from(bytes),
+        _ => Body::empty(),
+    };
+
+    builder.body(body).unwrap()
+}
+
+fn bench_verifier_paths(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let error_mapper = XmlErrorMapper::new("https://service.example.com/doc/2022-10-01/");
+    let verifier = bench_verifier("local", "service", signing_key_provider(), error_mapper.clone());
+    let cached_verifier = bench_verifier("local", "service", signing_key_provider().with_cache(), error_mapper);
+
+    let mut group = c.benchmark_group("verifier");
+
+    group.bench_function("valid_signature", |b| {
+        b.to_async(&rt).iter_batched(
+            || signed_request(SECRET_KEY, None),
+            |req| async { run_request(&verifier, req).await.unwrap() },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.bench_function("invalid_signature", |b| {
+        b.to_async(&rt).iter_batched(
+            || signed_request("WRONGKEY", None),
+            |req| async { run_request(&verifier, req).await.unwrap() },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.bench_function("cached_signing_key", |b| {
+        b.to_async(&rt).iter_batched(
+            || signed_request(SECRET_KEY, None),
+            |req| async { run_request(&cached_verifier, req).await.unwrap() },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.throughput(Throughput::Bytes(LARGE_BODY_SIZE as u64));
+    group.bench_function("large_body", |b| {
+        b.to_async(&rt).iter_batched(
+            || signed_request(SECRET_KEY, Some(vec![0x5a; LARGE_BODY_SIZE])),
+            |req| async { run_request(&verifier, req).await.unwrap() },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_verifier_paths);
+criterion_main!(benches);
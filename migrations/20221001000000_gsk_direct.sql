@@ -0,0 +1,60 @@
+-- The tables read by GetSigningKeyFromDatabase and written by CredentialAdmin, with the default names of SchemaConfig.
+
+CREATE TABLE iam_account (
+    account_id VARCHAR(12) NOT NULL PRIMARY KEY,
+    organization_id VARCHAR(34),
+    organization_path VARCHAR(2048)
+);
+
+CREATE TABLE iam_user (
+    user_id VARCHAR(32) NOT NULL PRIMARY KEY,
+    account_id VARCHAR(12) NOT NULL,
+    path VARCHAR(512) NOT NULL,
+    user_name_cased VARCHAR(64) NOT NULL
+);
+
+CREATE TABLE iam_user_credential (
+    access_key_id VARCHAR(32) NOT NULL PRIMARY KEY,
+    user_id VARCHAR(32) NOT NULL,
+    secret_key VARCHAR(128) NOT NULL,
+    status VARCHAR(16) NOT NULL,
+    expires_at BIGINT,
+    last_used_at BIGINT,
+    last_used_region VARCHAR(32),
+    last_used_service VARCHAR(64)
+);
+
+CREATE INDEX iam_user_credential_user_id ON iam_user_credential (user_id);
+
+CREATE TABLE iam_user_tag (
+    user_id VARCHAR(32) NOT NULL,
+    tag_key VARCHAR(128) NOT NULL,
+    tag_value VARCHAR(256) NOT NULL,
+    PRIMARY KEY (user_id, tag_key)
+);
+
+CREATE TABLE iam_role (
+    role_id VARCHAR(32) NOT NULL PRIMARY KEY,
+    account_id VARCHAR(12) NOT NULL,
+    path VARCHAR(512) NOT NULL,
+    role_name_cased VARCHAR(64) NOT NULL
+);
+
+CREATE TABLE iam_session_credential (
+    access_key_id VARCHAR(32) NOT NULL PRIMARY KEY,
+    role_id VARCHAR(32) NOT NULL,
+    session_name VARCHAR(64) NOT NULL,
+    secret_key VARCHAR(128) NOT NULL,
+    session_token_hash VARCHAR(64) NOT NULL,
+    issued_at BIGINT NOT NULL,
+    expiration BIGINT NOT NULL,
+    mfa_authenticated_at BIGINT
+);
+
+CREATE TABLE service_specific_credential (
+    service_specific_credential_id VARCHAR(32) NOT NULL PRIMARY KEY,
+    user_id VARCHAR(32) NOT NULL,
+    service_name VARCHAR(64) NOT NULL,
+    service_password VARCHAR(128) NOT NULL,
+    status VARCHAR(16) NOT NULL
+);
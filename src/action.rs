@@ -0,0 +1,308 @@
+use {
+    http::{header::CONTENT_TYPE, method::Method, request::Parts, HeaderMap},
+    scratchstack_arn::Arn,
+    std::{collections::HashMap, fmt::Debug},
+};
+
+/// The content type of AWS Query protocol request bodies.
+const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+
+/// The action a request performs and the resources it acts on, as determined by an [ActionResolver].
+///
+/// Authenticated requests carry this in their extensions when the verifier has an `action_resolver`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ResolvedAction {
+    service: String,
+    action: String,
+    resources: Vec<Arn>,
+}
+
+impl ResolvedAction {
+    /// Create a new [ResolvedAction] for the given service prefix (e.g. `iam`), action name (e.g. `GetUser`), and
+    /// resources.
+    pub fn new(service: impl Into<String>, action: impl Into<String>, resources: Vec<Arn>) -> Self {
+        Self {
+            service: service.into(),
+            action: action.into(),
+            resources,
+        }
+    }
+
+    /// Retreive the service prefix, e.g. `iam`.
+    #[inline]
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    /// Retreive the action name, e.g. `GetUser`.
+    #[inline]
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    /// Retreive the resources the action is performed on. This is empty for actions that don't support resource-level
+    /// permissions or whose resources couldn't be determined from the request.
+    #[inline]
+    pub fn resources(&self) -> &[Arn] {
+        &self.resources
+    }
+
+    /// Returns the action as used in IAM policies, e.g. `iam:GetUser`.
+    pub fn iam_action(&self) -> String {
+        format!("{}:{}", self.service, self.action)
+    }
+}
+
+/// Maps a request to the action it performs and the resources it acts on, so authorizers and audit logs don't need
+/// per-service knowledge of the wire protocol.
+///
+/// `body` is the buffered request body, or `None` if the body was streamed or is not available.
+pub trait ActionResolver: Debug + Send + Sync {
+    /// Returns the action the request performs, or `None` if it can't be determined.
+    fn resolve(&self, parts: &Parts, body: Option<&[u8]>) -> Option<ResolvedAction>;
+}
+
+/// An [ActionResolver] for AWS Query protocol services (e.g. IAM, STS), which name the action in the `Action`
+/// parameter of the query string or the form-encoded body.
+///
+/// Query protocol resources are named by action-specific parameters, so no resources are resolved.
+#[derive(Clone, Debug)]
+pub struct QueryActionResolver {
+    service: String,
+}
+
+impl QueryActionResolver {
+    /// Create a new [QueryActionResolver] for the given service prefix.
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+        }
+    }
+}
+
+impl ActionResolver for QueryActionResolver {
+    fn resolve(&self, parts: &Parts, body: Option<&[u8]>) -> Option<ResolvedAction> {
+        let action = parts.uri.query().and_then(query_action).or_else(|| {
+            let content_type = parts.headers.get(CONTENT_TYPE)?.to_str().ok()?;
+            if !content_type.starts_with(FORM_CONTENT_TYPE) {
+                return None;
+            }
+            query_action(std::str::from_utf8(body?).ok()?)
+        })?;
+
+        Some(ResolvedAction::new(&self.service, action, Vec::new()))
+    }
+}
+
+/// An [ActionResolver] for AWS JSON protocol services (e.g. DynamoDB, KMS), which name the action in the
+/// `X-Amz-Target` header as `<TargetPrefix>.<Action>`.
+///
+/// JSON protocol resources are named by action-specific members of the body, so no resources are resolved.
+#[derive(Clone, Debug)]
+pub struct JsonTargetActionResolver {
+    service: String,
+}
+
+impl JsonTargetActionResolver {
+    /// Create a new [JsonTargetActionResolver] for the given service prefix.
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+        }
+    }
+}
+
+impl ActionResolver for JsonTargetActionResolver {
+    fn resolve(&self, parts: &Parts, _body: Option<&[u8]>) -> Option<ResolvedAction> {
+        let action = target_operation(&parts.headers)?;
+        Some(ResolvedAction::new(&self.service, action, Vec::new()))
+    }
+}
+
+/// An [ActionResolver] for REST services (e.g. S3, Lambda), which name the action by method and path.
+///
+/// Routes are path patterns whose segments are literals, `{Name}` (matching one segment), or a trailing `{Name+}`
+/// (matching the rest of the path). Resource templates are ARNs which may refer to the captured segments along with
+/// `{Partition}`, `{Region}`, and `{Account}`. The first matching route applies.
+///
+/// ```
+/// use {http::Method, scratchstack_http_framework::RestActionResolver};
+///
+/// let resolver = RestActionResolver::new("s3")
+///     .with_route(Method::GET, "/{Bucket}/{Key+}", "GetObject", &["arn:{Partition}:s3:::{Bucket}/{Key}"])
+///     .with_route(Method::GET, "/{Bucket}", "ListBucket", &["arn:{Partition}:s3:::{Bucket}"]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RestActionResolver {
+    service: String,
+    partition: String,
+    region: String,
+    account_id: String,
+    routes: Vec<RestRoute>,
+}
+
+#[derive(Clone, Debug)]
+struct RestRoute {
+    method: Method,
+    pattern: Vec<String>,
+    action: String,
+    resources: Vec<String>,
+}
+
+impl RestActionResolver {
+    /// Create a new [RestActionResolver] for the given service prefix, in the `aws` partition with no region or
+    /// account.
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            partition: "aws".to_string(),
+            region: String::new(),
+            account_id: String::new(),
+            routes: Vec::new(),
+        }
+    }
+
+    /// Set the partition substituted for `{Partition}`, returning the updated resolver.
+    pub fn with_partition(mut self, partition: impl Into<String>) -> Self {
+        self.partition = partition.into();
+        self
+    }
+
+    /// Set the region substituted for `{Region}`, returning the updated resolver.
+    pub fn with_region(mut self, region: impl Into<String>) -> Self {
+        self.region = region.into();
+        self
+    }
+
+    /// Set the account id substituted for `{Account}`, returning the updated resolver.
+    pub fn with_account_id(mut self, account_id: impl Into<String>) -> Self {
+        self.account_id = account_id.into();
+        self
+    }
+
+    /// Add a route, returning the updated resolver.
+    pub fn with_route(mut self, method: Method, pattern: &str, action: impl Into<String>, resources: &[&str]) -> Self {
+        self.routes.push(RestRoute {
+            method,
+            pattern: split_path(pattern).map(str::to_string).collect(),
+            action: action.into(),
+            resources: resources.iter().map(|r| r.to_string()).collect(),
+        });
+        self
+    }
+
+    fn substitute(&self, template: &str, captures: &HashMap<&str, String>) -> Option<Arn> {
+        let mut result = template
+            .replace("{Partition}", &self.partition)
+            .replace("{Region}", &self.region)
+            .replace("{Account}", &self.account_id);
+        for (name, value) in captures {
+            result = result.replace(&format!("{{{name}}}"), value);
+        }
+        result.parse().ok()
+    }
+}
+
+impl ActionResolver for RestActionResolver {
+    fn resolve(&self, parts: &Parts, _body: Option<&[u8]>) -> Option<ResolvedAction> {
+        let path: Vec<&str> = split_path(parts.uri.path()).collect();
+
+        self.routes.iter().filter(|route| route.method == parts.method).find_map(|route| {
+            let captures = match_path(&route.pattern, &path)?;
+            let resources = route.resources.iter().filter_map(|r| self.substitute(r, &captures)).collect();
+            Some(ResolvedAction::new(&self.service, &route.action, resources))
+        })
+    }
+}
+
+/// Split a path into its non-empty segments.
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+/// Match path segments against a route pattern, returning the captured segments.
+fn match_path<'p>(pattern: &'p [String], path: &[&str]) -> Option<HashMap<&'p str, String>> {
+    let mut captures = HashMap::new();
+
+    for (i, segment) in pattern.iter().enumerate() {
+        match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Some(name) => match name.strip_suffix('+') {
+                Some(name) if i == pattern.len() - 1 => {
+                    if path.len() <= i {
+                        return None;
+                    }
+                    captures.insert(name, path[i..].join("/"));
+                    return Some(captures);
+                }
+                _ => {
+                    captures.insert(name, path.get(i)?.to_string());
+                }
+            },
+            None if path.get(i) == Some(&segment.as_str()) => (),
+            None => return None,
+        }
+    }
+
+    (pattern.len() == path.len()).then_some(captures)
+}
+
+/// Returns the operation named by the `X-Amz-Target` header of a JSON protocol request.
+pub(crate) fn target_operation(headers: &HeaderMap) -> Option<&str> {
+    headers.get("x-amz-target")?.to_str().ok()?.rsplit('.').next()
+}
+
+/// Returns the `Action` parameter of a query string or form-encoded body.
+pub(crate) fn query_action(query: &str) -> Option<String> {
+    query.split('&').find_map(|param| param.strip_prefix("Action=")).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{ActionResolver, JsonTargetActionResolver, QueryActionResolver, RestActionResolver},
+        http::{Method, Request},
+    };
+
+    #[test]
+    fn test_query_and_json() {
+        let resolver = QueryActionResolver::new("iam");
+        let (parts, _) = Request::get("/?Action=GetUser&Version=2010-05-08").body(()).unwrap().into_parts();
+        assert_eq!(resolver.resolve(&parts, None).unwrap().iam_action(), "iam:GetUser");
+
+        let (parts, _) = Request::post("/")
+            .header("content-type", "application/x-www-form-urlencoded; charset=utf-8")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let action = resolver.resolve(&parts, Some(b"Version=2010-05-08&Action=ListUsers")).unwrap();
+        assert_eq!(action.action(), "ListUsers");
+        assert!(action.resources().is_empty());
+        assert!(resolver.resolve(&parts, None).is_none());
+
+        let resolver = JsonTargetActionResolver::new("dynamodb");
+        let (parts, _) =
+            Request::post("/").header("x-amz-target", "DynamoDB_20120810.GetItem").body(()).unwrap().into_parts();
+        assert_eq!(resolver.resolve(&parts, None).unwrap().iam_action(), "dynamodb:GetItem");
+    }
+
+    #[test]
+    fn test_rest() {
+        let resolver = RestActionResolver::new("s3")
+            .with_route(Method::GET, "/{Bucket}/{Key+}", "GetObject", &["arn:{Partition}:s3:::{Bucket}/{Key}"])
+            .with_route(Method::GET, "/{Bucket}", "ListBucket", &["arn:{Partition}:s3:::{Bucket}"]);
+
+        let (parts, _) = Request::get("/examplebucket/photos/2006/February/sample.jpg").body(()).unwrap().into_parts();
+        let action = resolver.resolve(&parts, None).unwrap();
+        assert_eq!(action.iam_action(), "s3:GetObject");
+        assert_eq!(action.resources()[0].to_string(), "arn:aws:s3:::examplebucket/photos/2006/February/sample.jpg");
+
+        let (parts, _) = Request::get("/examplebucket").body(()).unwrap().into_parts();
+        assert_eq!(resolver.resolve(&parts, None).unwrap().action(), "ListBucket");
+
+        let (parts, _) = Request::put("/examplebucket/key").body(()).unwrap().into_parts();
+        assert!(resolver.resolve(&parts, None).is_none());
+
+        let (parts, _) = Request::get("/").body(()).unwrap().into_parts();
+        assert!(resolver.resolve(&parts, None).is_none());
+    }
+}
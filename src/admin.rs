@@ -0,0 +1,325 @@
+#![warn(clippy::all)]
+
+use {
+    crate::{
+        credentials::SecretString,
+        gsk_direct::{Binder, SchemaConfig, SigningKeyDatabase, ACCESS_KEY_STATUS_ACTIVE, ACCESS_KEY_STATUS_INACTIVE},
+    },
+    rand::{thread_rng, Rng},
+    sqlx::{migrate::Migrator, Error as SqlxError},
+    std::{
+        error::Error,
+        fmt::{Debug, Display, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// The characters of the unique part of access key ids and IAM unique ids: the RFC 4648 base32 alphabet, as AWS
+/// uses.
+const ID_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// The characters of secret access keys: the base64 alphabet.
+const SECRET_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The length of access key ids, including the `AKIA` prefix.
+pub const ACCESS_KEY_ID_LENGTH: usize = 20;
+
+/// The length of secret access keys.
+pub const SECRET_ACCESS_KEY_LENGTH: usize = 40;
+
+/// The length of IAM user ids, including the `AIDA` prefix.
+pub const USER_ID_LENGTH: usize = 21;
+
+/// Migrations creating the tables [GetSigningKeyFromDatabase][crate::gsk_direct::GetSigningKeyFromDatabase] reads,
+/// with the names and columns of the default [SchemaConfig].
+///
+/// The column types are chosen to work with PostgreSQL, MySQL, and SQLite. Services with their own schema should
+/// manage it themselves instead.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Generate a new long-term access key id: `AKIA` followed by 16 random base32 characters.
+pub fn generate_access_key_id() -> String {
+    format!("AKIA{}", random_string(ID_ALPHABET, ACCESS_KEY_ID_LENGTH - 4))
+}
+
+/// Generate a new secret access key of 40 random base64 characters.
+pub fn generate_secret_access_key() -> SecretString {
+    SecretString::new(random_string(SECRET_ALPHABET, SECRET_ACCESS_KEY_LENGTH))
+}
+
+/// Generate a new IAM user id: `AIDA` followed by 17 random base32 characters.
+pub fn generate_user_id() -> String {
+    format!("AIDA{}", random_string(ID_ALPHABET, USER_ID_LENGTH - 4))
+}
+
+fn random_string(alphabet: &[u8], len: usize) -> String {
+    let mut rng = thread_rng();
+    (0..len).map(|_| alphabet[rng.gen_range(0..alphabet.len())] as char).collect()
+}
+
+/// A newly created access key. The secret access key can't be retrieved again once this is dropped.
+#[derive(Clone, Debug)]
+pub struct NewAccessKey {
+    access_key_id: String,
+    secret_access_key: SecretString,
+}
+
+impl NewAccessKey {
+    /// Retreive the access key id.
+    #[inline]
+    pub fn access_key_id(&self) -> &str {
+        &self.access_key_id
+    }
+
+    /// Retreive the secret access key.
+    #[inline]
+    pub fn secret_access_key(&self) -> &SecretString {
+        &self.secret_access_key
+    }
+}
+
+/// Errors returned by [CredentialAdmin].
+#[derive(Debug)]
+pub enum AdminError {
+    /// The access key does not exist.
+    NoSuchAccessKey(String),
+
+    /// The database returned an error.
+    Database(SqlxError),
+}
+
+impl Display for AdminError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::NoSuchAccessKey(access_key_id) => write!(f, "The access key {access_key_id} does not exist"),
+            Self::Database(e) => write!(f, "Database error: {e}"),
+        }
+    }
+}
+
+impl Error for AdminError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::NoSuchAccessKey(_) => None,
+            Self::Database(e) => Some(e),
+        }
+    }
+}
+
+impl From<SqlxError> for AdminError {
+    fn from(e: SqlxError) -> Self {
+        Self::Database(e)
+    }
+}
+
+/// Creates users and manages their long-term access keys in the tables read by
+/// [GetSigningKeyFromDatabase][crate::gsk_direct::GetSigningKeyFromDatabase].
+///
+/// Access keys are generated in the format the verifier expects, and their secret keys are stored in the secret key
+/// column of the credential table.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// use {
+///     scratchstack_http_framework::admin::{CredentialAdmin, MIGRATOR},
+///     sqlx::any::AnyPoolOptions,
+///     std::sync::Arc,
+/// };
+///
+/// let pool = AnyPoolOptions::new().connect("postgres://localhost/iam").await?;
+/// MIGRATOR.run(&pool).await?;
+///
+/// let admin = CredentialAdmin::new(Arc::new(pool));
+/// let user_id = admin.create_user("123456789012", "/", "alice").await?;
+/// let access_key = admin.create_access_key(&user_id).await?;
+/// println!("Created access key {}", access_key.access_key_id());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct CredentialAdmin {
+    pool: Arc<dyn SigningKeyDatabase>,
+    schema: Arc<SchemaConfig>,
+}
+
+impl CredentialAdmin {
+    /// Create a new [CredentialAdmin] using the default schema.
+    pub fn new<D: SigningKeyDatabase + 'static>(pool: Arc<D>) -> Self {
+        Self {
+            pool,
+            schema: Arc::new(SchemaConfig::default()),
+        }
+    }
+
+    /// Use the given table and column names, returning the updated [CredentialAdmin].
+    pub fn with_schema(mut self, schema: SchemaConfig) -> Self {
+        self.schema = Arc::new(schema);
+        self
+    }
+
+    /// Retreive the table and column names used.
+    #[inline]
+    pub fn schema(&self) -> &SchemaConfig {
+        &self.schema
+    }
+
+    /// Create an IAM user, returning its newly generated user id.
+    pub async fn create_user(&self, account_id: &str, path: &str, user_name: &str) -> Result<String, AdminError> {
+        let user_id = generate_user_id();
+        let sql = self.schema.user_insert(&mut Binder::new(self.pool.kind()));
+        self.pool.execute_statement(&sql, &[&user_id, account_id, path, user_name]).await?;
+        Ok(user_id)
+    }
+
+    /// Create an active access key for a user.
+    pub async fn create_access_key(&self, user_id: &str) -> Result<NewAccessKey, AdminError> {
+        let access_key = NewAccessKey {
+            access_key_id: generate_access_key_id(),
+            secret_access_key: generate_secret_access_key(),
+        };
+        let sql = self.schema.access_key_insert(&mut Binder::new(self.pool.kind()));
+        self.pool
+            .execute_statement(
+                &sql,
+                &[
+                    &access_key.access_key_id,
+                    user_id,
+                    access_key.secret_access_key.expose_secret(),
+                    ACCESS_KEY_STATUS_ACTIVE,
+                ],
+            )
+            .await?;
+        Ok(access_key)
+    }
+
+    /// Mark an access key as `Inactive`, so requests signed with it are rejected.
+    pub async fn deactivate_access_key(&self, access_key_id: &str) -> Result<(), AdminError> {
+        self.set_access_key_status(access_key_id, ACCESS_KEY_STATUS_INACTIVE).await
+    }
+
+    /// Mark an access key as `Active` again.
+    pub async fn activate_access_key(&self, access_key_id: &str) -> Result<(), AdminError> {
+        self.set_access_key_status(access_key_id, ACCESS_KEY_STATUS_ACTIVE).await
+    }
+
+    /// Create a new access key for the user owning an access key, and deactivate the old one.
+    ///
+    /// The old key is kept so it can be reactivated if a client hasn't switched to the new key yet; delete it with
+    /// [CredentialAdmin::delete_access_key] once it is no longer used.
+    pub async fn rotate_access_key(&self, access_key_id: &str) -> Result<NewAccessKey, AdminError> {
+        let user_id = self.access_key_user(access_key_id).await?;
+        let access_key = self.create_access_key(&user_id).await?;
+        self.set_access_key_status(access_key_id, ACCESS_KEY_STATUS_INACTIVE).await?;
+        Ok(access_key)
+    }
+
+    /// Delete an access key.
+    pub async fn delete_access_key(&self, access_key_id: &str) -> Result<(), AdminError> {
+        let sql = self.schema.access_key_delete(&mut Binder::new(self.pool.kind()));
+        match self.pool.execute_statement(&sql, &[access_key_id]).await? {
+            0 => Err(AdminError::NoSuchAccessKey(access_key_id.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the user id of the owner of an access key.
+    async fn access_key_user(&self, access_key_id: &str) -> Result<String, AdminError> {
+        let sql = self.schema.access_key_user_query(&mut Binder::new(self.pool.kind()));
+        self.pool
+            .fetch_string(&sql, &[access_key_id])
+            .await?
+            .ok_or_else(|| AdminError::NoSuchAccessKey(access_key_id.to_string()))
+    }
+
+    async fn set_access_key_status(&self, access_key_id: &str, status: &str) -> Result<(), AdminError> {
+        // MySQL reports updates that don't change a row as affecting no rows, so check the key exists first.
+        self.access_key_user(access_key_id).await?;
+        let sql = self.schema.access_key_status_update(&mut Binder::new(self.pool.kind()));
+        self.pool.execute_statement(&sql, &[status, access_key_id]).await?;
+        Ok(())
+    }
+}
+
+impl Debug for CredentialAdmin {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("CredentialAdmin").field("schema", &self.schema).finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{
+            generate_access_key_id, generate_secret_access_key, generate_user_id, AdminError, CredentialAdmin,
+            ID_ALPHABET, MIGRATOR,
+        },
+        crate::{
+            gsk_direct::GetSigningKeyFromDatabase,
+            test_util::{request_date, signing_key_request},
+        },
+        pretty_assertions::{assert_eq, assert_ne},
+        scratchstack_aws_signature::{KSecretKey, SignatureError},
+        sqlx::any::AnyPoolOptions,
+        std::sync::Arc,
+        tower::ServiceExt,
+    };
+
+    #[test]
+    fn test_generate() {
+        let access_key_id = generate_access_key_id();
+        assert_eq!(access_key_id.len(), 20);
+        assert!(access_key_id.starts_with("AKIA"));
+        assert!(access_key_id.bytes().all(|c| ID_ALPHABET.contains(&c)));
+        assert_ne!(access_key_id, generate_access_key_id());
+
+        let secret_access_key = generate_secret_access_key();
+        assert_eq!(secret_access_key.expose_secret().len(), 40);
+        assert!(secret_access_key.expose_secret().bytes().all(|c| c.is_ascii_alphanumeric() || c == b'+' || c == b'/'));
+
+        let user_id = generate_user_id();
+        assert_eq!(user_id.len(), 21);
+        assert!(user_id.starts_with("AIDA"));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_credential_admin() {
+        // Each connection to an in-memory database gets its own database, so only allow one.
+        let pool = Arc::new(AnyPoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap());
+        MIGRATOR.run(&*pool).await.unwrap();
+
+        let admin = CredentialAdmin::new(pool.clone());
+        let gsk = GetSigningKeyFromDatabase::new(pool, "aws", "us-east-1", "example");
+        let request = |access_key: &str| signing_key_request(access_key, None, "example");
+
+        let user_id = admin.create_user("123456789012", "/", "alice").await.unwrap();
+        let access_key = admin.create_access_key(&user_id).await.unwrap();
+
+        // The verifier accepts the generated access key.
+        let response = gsk.clone().oneshot(request(access_key.access_key_id())).await.unwrap();
+        let expected = KSecretKey::from_str(access_key.secret_access_key().expose_secret()).to_ksigning(
+            request_date(),
+            "us-east-1",
+            "example",
+        );
+        assert_eq!(response.signing_key().as_ref(), expected.as_ref());
+
+        // Rotating deactivates the old key.
+        let new_access_key = admin.rotate_access_key(access_key.access_key_id()).await.unwrap();
+        let e = gsk.clone().oneshot(request(access_key.access_key_id())).await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<SignatureError>(), Some(SignatureError::InvalidClientTokenId(_))));
+        gsk.clone().oneshot(request(new_access_key.access_key_id())).await.unwrap();
+
+        admin.activate_access_key(access_key.access_key_id()).await.unwrap();
+        gsk.clone().oneshot(request(access_key.access_key_id())).await.unwrap();
+
+        admin.delete_access_key(access_key.access_key_id()).await.unwrap();
+        assert!(matches!(
+            admin.delete_access_key(access_key.access_key_id()).await,
+            Err(AdminError::NoSuchAccessKey(_))
+        ));
+        assert!(matches!(
+            admin.deactivate_access_key("AKIANOSUCHKEY0000000").await,
+            Err(AdminError::NoSuchAccessKey(_))
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+use {
+    http::{method::Method, uri::Uri},
+    std::{
+        fmt::{Debug, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+};
+
+/// A predicate deciding whether a request may be served anonymously.
+pub type AnonymousPredicate = Arc<dyn Fn(&Method, &Uri) -> bool + Send + Sync>;
+
+/// The set of routes that may be accessed without a SigV4 signature.
+///
+/// Unsigned requests matching one of these routes are forwarded to the service implementation with an anonymous
+/// (empty) [Principal][scratchstack_aws_principal::Principal] instead of being rejected. Signed requests are always
+/// verified, even when they match.
+///
+/// Path globs are matched against the request path. `*` matches any run of characters within a single path segment;
+/// `**` matches any run of characters, including `/`.
+#[derive(Clone, Default)]
+pub struct AnonymousPaths {
+    globs: Vec<String>,
+    predicates: Vec<AnonymousPredicate>,
+}
+
+impl AnonymousPaths {
+    /// Create a new, empty [AnonymousPaths] that matches nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow anonymous access to paths matching the given glob, returning the updated set.
+    pub fn with_glob(mut self, glob: impl Into<String>) -> Self {
+        self.globs.push(glob.into());
+        self
+    }
+
+    /// Allow anonymous access to requests accepted by the given predicate, returning the updated set.
+    pub fn with_predicate<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Method, &Uri) -> bool + Send + Sync + 'static,
+    {
+        self.predicates.push(Arc::new(predicate));
+        self
+    }
+
+    /// Returns the path globs.
+    #[inline]
+    pub fn globs(&self) -> &[String] {
+        &self.globs
+    }
+
+    /// Indicates whether a request with the given method and URI may be served anonymously.
+    pub fn matches(&self, method: &Method, uri: &Uri) -> bool {
+        let path = uri.path();
+        self.globs.iter().any(|glob| glob_matches(glob.as_bytes(), path.as_bytes()))
+            || self.predicates.iter().any(|predicate| predicate(method, uri))
+    }
+}
+
+/// A marker inserted into the extensions of unsigned requests passed to the implementation because they matched the
+/// verifier's [AnonymousPaths].
+///
+/// These requests carry an empty [Principal][scratchstack_aws_principal::Principal]. An
+/// [AuthorizerService][crate::AuthorizerService] only passes requests without a principal through if they are marked
+/// with this.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AnonymousRequest;
+
+impl Debug for AnonymousPaths {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("AnonymousPaths")
+            .field("globs", &self.globs)
+            .field("predicates", &self.predicates.len())
+            .finish()
+    }
+}
+
+/// Match a path against a glob where `*` does not cross `/` and `**` does.
+fn glob_matches(glob: &[u8], path: &[u8]) -> bool {
+    match glob.split_first() {
+        None => path.is_empty(),
+        Some((b'*', rest)) => {
+            if let Some(rest) = rest.strip_prefix(b"*") {
+                (0..=path.len()).any(|i| glob_matches(rest, &path[i..]))
+            } else {
+                let segment_end = path.iter().position(|&c| c == b'/').unwrap_or(path.len());
+                (0..=segment_end).any(|i| glob_matches(rest, &path[i..]))
+            }
+        }
+        Some((&c, rest)) => path.first() == Some(&c) && glob_matches(rest, &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::AnonymousPaths,
+        http::{method::Method, uri::Uri},
+    };
+
+    fn uri(s: &str) -> Uri {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_globs() {
+        let paths =
+            AnonymousPaths::new().with_glob("/").with_glob("/ping").with_glob("/public/*.html").with_glob("/static/**");
+
+        assert!(paths.matches(&Method::GET, &uri("/")));
+        assert!(paths.matches(&Method::GET, &uri("/ping?verbose=1")));
+        assert!(!paths.matches(&Method::GET, &uri("/pingx")));
+        assert!(paths.matches(&Method::GET, &uri("/public/index.html")));
+        assert!(!paths.matches(&Method::GET, &uri("/public/a/index.html")));
+        assert!(paths.matches(&Method::GET, &uri("/static/css/site.css")));
+        assert!(!paths.matches(&Method::GET, &uri("/private")));
+    }
+
+    #[test]
+    fn test_predicate() {
+        let paths =
+            AnonymousPaths::new().with_predicate(|method, uri| method == Method::GET && uri.path() == "/health");
+        assert!(paths.matches(&Method::GET, &uri("/health")));
+        assert!(!paths.matches(&Method::POST, &uri("/health")));
+    }
+}
@@ -0,0 +1,356 @@
+use {
+    crate::{
+        action::{query_action, target_operation},
+        session_keys::{SessionDataExt, PRINCIPAL_ACCOUNT, PRINCIPAL_ARN},
+        ErrorContext, RequestId, ResolvedAction,
+    },
+    chrono::{DateTime, SecondsFormat, Utc},
+    http::{header::USER_AGENT, HeaderMap, Uri},
+    hyper::{Body, Response},
+    scratchstack_aws_principal::{Principal, SessionData},
+    serde::{Serialize, Serializer},
+    std::{
+        fmt::Debug,
+        fs::{File, OpenOptions},
+        io::{Result as IoResult, Write},
+        net::IpAddr,
+        ops::Not,
+        path::Path,
+        sync::{
+            mpsc::{channel, Receiver, Sender},
+            Mutex,
+        },
+    },
+    tower::BoxError,
+};
+
+/// A record of a request handled by [AwsSigV4VerifierService][crate::AwsSigV4VerifierService], modelled on AWS
+/// CloudTrail events.
+///
+/// Events serialize to JSON with CloudTrail-style field names, e.g. `eventTime`, `sourceIPAddress`, and `requestID`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEvent {
+    #[serde(serialize_with = "serialize_event_time")]
+    event_time: DateTime<Utc>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    principal_arn: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account_id: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    action: Option<String>,
+
+    #[serde(rename = "sourceIPAddress", skip_serializing_if = "Option::is_none")]
+    source_ip_address: Option<IpAddr>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_agent: Option<String>,
+
+    #[serde(rename = "requestID", skip_serializing_if = "Option::is_none")]
+    request_id: Option<RequestId>,
+
+    http_method: String,
+
+    path: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_code: Option<&'static str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status_code: Option<u16>,
+
+    #[serde(skip_serializing_if = "<&bool>::not")]
+    dry_run: bool,
+}
+
+impl AuditEvent {
+    /// Create a new [AuditEvent] for a request. `principal` is the identity the request was authenticated as, along with
+    /// its session data, if it was authenticated.
+    pub(crate) fn new(
+        context: &ErrorContext,
+        source_ip_address: Option<IpAddr>,
+        principal: Option<(&Principal, &SessionData)>,
+        error_code: Option<&'static str>,
+    ) -> Self {
+        let (principal_arn, account_id) = match principal {
+            Some((principal, session_data)) => (
+                session_data.get_string(PRINCIPAL_ARN).map(str::to_string).or_else(|| principal_arn(principal)),
+                session_data.get_string(PRINCIPAL_ACCOUNT).map(str::to_string),
+            ),
+            None => (None, None),
+        };
+
+        Self {
+            event_time: Utc::now(),
+            principal_arn,
+            account_id,
+            action: action(context.headers(), context.uri()),
+            source_ip_address,
+            user_agent: context
+                .headers()
+                .get(USER_AGENT)
+                .map(|user_agent| String::from_utf8_lossy(user_agent.as_bytes()).into_owned()),
+            request_id: context.request_id(),
+            http_method: context.method().to_string(),
+            path: context.uri().path().to_string(),
+            error_code,
+            status_code: None,
+            dry_run: false,
+        }
+    }
+
+    /// Record the status of the response to the request, returning the updated event. The status is left unset if the
+    /// service implementation failed without a response.
+    pub(crate) fn with_response(mut self, response: &Result<Response<Body>, BoxError>) -> Self {
+        self.status_code = response.as_ref().ok().map(|response| response.status().as_u16());
+        self
+    }
+
+    /// Record the action resolved by the verifier's action resolver, if any, returning the updated event.
+    pub(crate) fn with_action(mut self, action: Option<&ResolvedAction>) -> Self {
+        if let Some(action) = action {
+            self.action = Some(action.action().to_string());
+        }
+        self
+    }
+
+    /// Mark the event as recording a denial that was not enforced because the authorizer is in dry-run mode, returning
+    /// the updated event.
+    pub(crate) fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Returns the time the event was recorded.
+    #[inline]
+    pub fn event_time(&self) -> DateTime<Utc> {
+        self.event_time
+    }
+
+    /// Returns the ARN of the principal the request was authenticated as, if it was authenticated.
+    #[inline]
+    pub fn principal_arn(&self) -> Option<&str> {
+        self.principal_arn.as_deref()
+    }
+
+    /// Returns the account id of the principal the request was authenticated as, if known.
+    #[inline]
+    pub fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    /// Returns the action requested, if it was determined by the verifier's action resolver or could be found in the
+    /// `X-Amz-Target` header or the `Action` query parameter.
+    #[inline]
+    pub fn action(&self) -> Option<&str> {
+        self.action.as_deref()
+    }
+
+    /// Returns the address of the client, if known.
+    #[inline]
+    pub fn source_ip_address(&self) -> Option<IpAddr> {
+        self.source_ip_address
+    }
+
+    /// Returns the `User-Agent` header sent by the client, if any.
+    #[inline]
+    pub fn user_agent(&self) -> Option<&str> {
+        self.user_agent.as_deref()
+    }
+
+    /// Returns the request id.
+    #[inline]
+    pub fn request_id(&self) -> Option<RequestId> {
+        self.request_id
+    }
+
+    /// Returns the request method.
+    #[inline]
+    pub fn http_method(&self) -> &str {
+        &self.http_method
+    }
+
+    /// Returns the request path.
+    #[inline]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the error code the request was rejected with, if it was rejected with a service error.
+    #[inline]
+    pub fn error_code(&self) -> Option<&'static str> {
+        self.error_code
+    }
+
+    /// Returns the HTTP status code of the response, if a response was produced.
+    #[inline]
+    pub fn status_code(&self) -> Option<u16> {
+        self.status_code
+    }
+
+    /// Indicates whether the event records a denial that was not enforced because the authorizer is in dry-run mode.
+    #[inline]
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Serialize the event to a single line of JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("AuditEvent serialization cannot fail")
+    }
+}
+
+fn serialize_event_time<S: Serializer>(event_time: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&event_time.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// Returns the ARN of the first identity of `principal`, or its name if it has no ARN (e.g. a service principal).
+pub(crate) fn principal_arn(principal: &Principal) -> Option<String> {
+    principal.first().map(ToString::to_string)
+}
+
+/// Returns the action requested: the operation named by the `X-Amz-Target` header (JSON protocol services), or the
+/// `Action` query parameter (query protocol services).
+fn action(headers: &HeaderMap, uri: &Uri) -> Option<String> {
+    match target_operation(headers) {
+        Some(operation) => Some(operation.to_string()),
+        None => query_action(uri.query()?),
+    }
+}
+
+/// Receives an [AuditEvent] for every request handled by
+/// [AwsSigV4VerifierService][crate::AwsSigV4VerifierService], whether it was authenticated, anonymous, or rejected.
+///
+/// Sinks are called on the request path and should not block for long.
+pub trait AuditSink: Debug + Send + Sync {
+    /// Record an event.
+    fn record(&self, event: &AuditEvent);
+}
+
+/// An [AuditSink] that writes each event to standard output as a line of JSON.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdoutAuditSink;
+
+impl AuditSink for StdoutAuditSink {
+    fn record(&self, event: &AuditEvent) {
+        println!("{}", event.to_json());
+    }
+}
+
+/// An [AuditSink] that appends each event to a file as a line of JSON.
+#[derive(Debug)]
+pub struct FileAuditSink {
+    file: Mutex<File>,
+}
+
+impl FileAuditSink {
+    /// Open the file at `path` for appending, creating it if necessary.
+    pub fn open<P: AsRef<Path>>(path: P) -> IoResult<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl AuditSink for FileAuditSink {
+    fn record(&self, event: &AuditEvent) {
+        let mut file = self.file.lock().unwrap();
+        if let Err(e) = writeln!(file, "{}", event.to_json()) {
+            log::error!("Failed to write audit event: {}", e);
+        }
+    }
+}
+
+/// An [AuditSink] that sends each event over a channel, e.g. to a task shipping events to a log store.
+#[derive(Debug)]
+pub struct ChannelAuditSink {
+    sender: Mutex<Sender<AuditEvent>>,
+}
+
+impl ChannelAuditSink {
+    /// Create a new [ChannelAuditSink], returning it along with the receiving end of its channel. Events recorded
+    /// after the receiver is dropped are discarded.
+    pub fn new() -> (Self, Receiver<AuditEvent>) {
+        let (sender, receiver) = channel();
+        (
+            Self {
+                sender: Mutex::new(sender),
+            },
+            receiver,
+        )
+    }
+}
+
+impl AuditSink for ChannelAuditSink {
+    fn record(&self, event: &AuditEvent) {
+        let _ = self.sender.lock().unwrap().send(event.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{AuditEvent, AuditSink, ChannelAuditSink},
+        crate::{
+            session_keys::{SessionDataExt, PRINCIPAL_ACCOUNT, PRINCIPAL_ARN},
+            ErrorContext, RequestId,
+        },
+        http::{HeaderMap, Method},
+        hyper::{Body, Response},
+        scratchstack_aws_principal::{Principal, SessionData},
+        std::net::{IpAddr, Ipv4Addr},
+    };
+
+    #[test]
+    fn test_audit_event() {
+        let mut headers = HeaderMap::new();
+        headers.insert("user-agent", "aws-cli/2.0".parse().unwrap());
+        headers.insert("x-amz-target", "DynamoDB_20120810.GetItem".parse().unwrap());
+        let request_id = RequestId::new();
+        let context = ErrorContext::new(
+            Method::POST,
+            "/".parse().unwrap(),
+            headers,
+            "us-east-1".to_string(),
+            "dynamodb".to_string(),
+            Some(request_id),
+        );
+        let mut session_data = SessionData::new();
+        session_data.set_string(PRINCIPAL_ARN, "arn:aws:iam::123456789012:user/test");
+        session_data.set_string(PRINCIPAL_ACCOUNT, "123456789012");
+        let principal = Principal::new(Vec::new());
+        let client_ip = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+
+        let event = AuditEvent::new(&context, Some(client_ip), Some((&principal, &session_data)), None)
+            .with_response(&Ok(Response::new(Body::empty())));
+        assert_eq!(event.principal_arn(), Some("arn:aws:iam::123456789012:user/test"));
+        assert_eq!(event.account_id(), Some("123456789012"));
+        assert_eq!(event.action(), Some("GetItem"));
+        assert_eq!(event.user_agent(), Some("aws-cli/2.0"));
+
+        let json: serde_json::Value = serde_json::from_str(&event.to_json()).unwrap();
+        assert_eq!(json["sourceIPAddress"], "192.0.2.1");
+        assert_eq!(json["requestID"], request_id.to_string());
+        assert_eq!(json["statusCode"], 200);
+        assert!(json.get("errorCode").is_none());
+
+        let (sink, receiver) = ChannelAuditSink::new();
+        let context = ErrorContext::new(
+            Method::GET,
+            "/?Action=GetCallerIdentity&Version=2011-06-15".parse().unwrap(),
+            HeaderMap::new(),
+            "us-east-1".to_string(),
+            "sts".to_string(),
+            None,
+        );
+        sink.record(&AuditEvent::new(&context, None, None, Some("SignatureDoesNotMatch")));
+        let event = receiver.try_recv().unwrap();
+        assert_eq!(event.action(), Some("GetCallerIdentity"));
+        assert_eq!(event.error_code(), Some("SignatureDoesNotMatch"));
+        assert_eq!(event.principal_arn(), None);
+    }
+}
@@ -0,0 +1,534 @@
+use {
+    crate::{
+        audit::principal_arn, combine_decisions, session_keys::SessionDataExt, AnonymousRequest, AuditEvent, AuditSink,
+        Clock, ConnectInfo, Decision, ErrorContext, ErrorMapper, Metrics, PolicyEvaluator, PolicyProvider,
+        RequestContext, RequestId, ResolvedAction, SystemClock, VerifierError,
+    },
+    http::request::Parts,
+    hyper::{Body, Request, Response},
+    log::{error, info, warn},
+    scratchstack_aws_principal::{Principal, SessionData},
+    std::{
+        future::Future,
+        mem::replace,
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+    },
+    tower::{BoxError, Layer, Service},
+};
+
+/// The outcome of authorizing a request, inserted into the extensions of requests passed to the implementation by an
+/// [AuthorizerService].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuthorizationDecision {
+    decision: Decision,
+    matched_statements: Vec<String>,
+    evaluated_policies: usize,
+}
+
+impl AuthorizationDecision {
+    /// Create a new [AuthorizationDecision].
+    pub fn new(decision: Decision, matched_statements: Vec<String>, evaluated_policies: usize) -> Self {
+        Self {
+            decision,
+            matched_statements,
+            evaluated_policies,
+        }
+    }
+
+    /// Combine the decisions of identity-based policies, a permissions boundary, and SCPs with [combine_decisions],
+    /// gathering the matched statements and evaluated policies of each.
+    pub fn combine(identity: Self, boundary: Option<Self>, scps: Vec<Self>) -> Self {
+        let decision = combine_decisions(
+            identity.decision,
+            boundary.as_ref().map(|b| b.decision),
+            &scps.iter().map(|scp| scp.decision).collect::<Vec<_>>(),
+        );
+
+        let mut result = Self::new(decision, Vec::new(), 0);
+        for part in std::iter::once(identity).chain(boundary).chain(scps) {
+            result.matched_statements.extend(part.matched_statements);
+            result.evaluated_policies += part.evaluated_policies;
+        }
+        result
+    }
+
+    /// Retreive the decision.
+    #[inline]
+    pub fn decision(&self) -> Decision {
+        self.decision
+    }
+
+    /// Indicates whether the request is allowed.
+    #[inline]
+    pub fn is_allowed(&self) -> bool {
+        self.decision == Decision::Allow
+    }
+
+    /// Retreive the ids (`Sid`) of the statements that matched the request. Statements without an id are not included.
+    #[inline]
+    pub fn matched_statements(&self) -> &[String] {
+        &self.matched_statements
+    }
+
+    /// Retreive the number of policies evaluated.
+    #[inline]
+    pub fn evaluated_policies(&self) -> usize {
+        self.evaluated_policies
+    }
+}
+
+/// A Tower [Layer] that wraps a service in an [AuthorizerService].
+#[derive(Clone, Debug)]
+pub struct AuthorizerLayer<E> {
+    policy_provider: Arc<dyn PolicyProvider>,
+    evaluator: Arc<dyn PolicyEvaluator>,
+    error_mapper: E,
+    dry_run: bool,
+    metrics: Option<Arc<dyn Metrics>>,
+    audit_sink: Option<Arc<dyn AuditSink>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<E: ErrorMapper> AuthorizerLayer<E> {
+    /// Create a new [AuthorizerLayer] that evaluates the policies from `policy_provider` with `evaluator`.
+    pub fn new(policy_provider: Arc<dyn PolicyProvider>, evaluator: Arc<dyn PolicyEvaluator>, error_mapper: E) -> Self {
+        Self {
+            policy_provider,
+            evaluator,
+            error_mapper,
+            dry_run: false,
+            metrics: None,
+            audit_sink: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Forward denied requests instead of rejecting them, recording what would have been denied. See
+    /// [AuthorizerService::with_dry_run].
+    pub fn with_dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Report each decision to [Metrics::record_authorization].
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Record an [AuditEvent] for each denied request, including those forwarded in dry-run mode.
+    pub fn with_audit_sink(mut self, audit_sink: Arc<dyn AuditSink>) -> Self {
+        self.audit_sink = Some(audit_sink);
+        self
+    }
+
+    /// Read the `aws:CurrentTime` of each request from `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+impl<S, E: ErrorMapper> Layer<S> for AuthorizerLayer<E> {
+    type Service = AuthorizerService<S, E>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthorizerService {
+            inner,
+            policy_provider: self.policy_provider.clone(),
+            evaluator: self.evaluator.clone(),
+            error_mapper: self.error_mapper.clone(),
+            dry_run: self.dry_run,
+            metrics: self.metrics.clone(),
+            audit_sink: self.audit_sink.clone(),
+            clock: self.clock.clone(),
+        }
+    }
+}
+
+/// A service that authorizes requests against the policies of the principal that made them before passing them to the
+/// wrapped service.
+///
+/// Requests are authorized using the [Principal], [ResolvedAction], and session data in their extensions, so the
+/// service should normally be the implementation of an [AwsSigV4VerifierService][crate::AwsSigV4VerifierService] with
+/// an `action_resolver`. Allowed requests are passed on with the [AuthorizationDecision] in their extensions. Denied
+/// requests, and authenticated requests whose action could not be resolved, are rejected with an `AccessDenied` error
+/// rendered by the error mapper, as are requests without a principal. Only requests the verifier marked as
+/// [AnonymousRequest]s (those to its anonymous paths) are passed through without one.
+///
+/// In dry-run mode, denied requests are forwarded with the [AuthorizationDecision] in their extensions rather than
+/// rejected, as are requests that could not be authorized because of a failure; denials are still logged, reported to
+/// the metrics, and recorded by the audit sink with `dryRun` set. This allows IAM enforcement to be rolled out on an
+/// existing service without breaking clients whose policies are incomplete.
+#[derive(Clone, Debug)]
+pub struct AuthorizerService<S, E> {
+    inner: S,
+    policy_provider: Arc<dyn PolicyProvider>,
+    evaluator: Arc<dyn PolicyEvaluator>,
+    error_mapper: E,
+    dry_run: bool,
+    metrics: Option<Arc<dyn Metrics>>,
+    audit_sink: Option<Arc<dyn AuditSink>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<S, E: ErrorMapper> AuthorizerService<S, E> {
+    /// Create a new [AuthorizerService] that evaluates the policies from `policy_provider` with `evaluator`.
+    pub fn new(
+        inner: S,
+        policy_provider: Arc<dyn PolicyProvider>,
+        evaluator: Arc<dyn PolicyEvaluator>,
+        error_mapper: E,
+    ) -> Self {
+        AuthorizerLayer::new(policy_provider, evaluator, error_mapper).layer(inner)
+    }
+
+    /// Forward denied requests instead of rejecting them, recording what would have been denied.
+    pub fn with_dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Report each decision to [Metrics::record_authorization].
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Record an [AuditEvent] for each denied request, including those forwarded in dry-run mode.
+    pub fn with_audit_sink(mut self, audit_sink: Arc<dyn AuditSink>) -> Self {
+        self.audit_sink = Some(audit_sink);
+        self
+    }
+
+    /// Read the `aws:CurrentTime` of each request from `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+impl<S, E, B> Service<Request<B>> for AuthorizerService<S, E>
+where
+    S: Service<Request<B>, Response = Response<Body>, Error = BoxError> + Clone + Send + 'static,
+    S::Future: Send,
+    E: ErrorMapper,
+    B: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, c: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(c)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        // The clone may not be ready, so use the instance that was polled and leave the clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = replace(&mut self.inner, clone);
+        let policy_provider = self.policy_provider.clone();
+        let evaluator = self.evaluator.clone();
+        let error_mapper = self.error_mapper.clone();
+        let dry_run = self.dry_run;
+        let metrics = self.metrics.clone();
+        let audit_sink = self.audit_sink.clone();
+        let now = self.clock.now();
+
+        Box::pin(async move {
+            let principal = match req.extensions().get::<Principal>() {
+                Some(principal) if !principal.is_empty() => principal.clone(),
+                _ if req.extensions().get::<AnonymousRequest>().is_some() => return inner.call(req).await,
+                _ => {
+                    // The request did not come through the verifier, or a hook dropped its principal.
+                    info!("Denying request without a principal");
+                    let request_id = req.extensions().get::<RequestId>().copied();
+                    let error = VerifierError::AccessDenied {
+                        principal_arn: "anonymous".to_string(),
+                        action: req.extensions().get::<ResolvedAction>().map(ResolvedAction::iam_action),
+                        resource: None,
+                        explicit_deny: false,
+                    };
+                    return error_mapper.map_error(error.into(), request_id).await;
+                }
+            };
+            let (mut parts, body) = req.into_parts();
+            let request_id = parts.extensions.get::<RequestId>().copied();
+            let action = parts.extensions.get::<ResolvedAction>().cloned();
+
+            let decision = match &action {
+                Some(action) => {
+                    let context = RequestContext::from_request(&parts, now);
+                    match policy_provider.get_effective_policies(&principal).await {
+                        Ok(policies) => policies.evaluate(evaluator.as_ref(), action, &context),
+                        Err(e) => Err(e),
+                    }
+                }
+                None => {
+                    info!("Action of request from {} could not be resolved", principal);
+                    Ok(AuthorizationDecision::new(Decision::DefaultDeny, Vec::new(), 0))
+                }
+            };
+            let iam_action = action.as_ref().map(ResolvedAction::iam_action).unwrap_or_default();
+
+            let decision = match decision {
+                Ok(decision) => decision,
+                Err(e) if dry_run => {
+                    warn!("Dry run: failed to authorize {} for {}: {}", principal, iam_action, e);
+                    return inner.call(Request::from_parts(parts, body)).await;
+                }
+                Err(e) => {
+                    error!("Failed to authorize {} for {}: {}", principal, iam_action, e);
+                    return error_mapper.map_error(VerifierError::InternalFailure.into(), request_id).await;
+                }
+            };
+
+            if let Some(metrics) = &metrics {
+                metrics.record_authorization(decision.decision(), dry_run);
+            }
+
+            if !decision.is_allowed() {
+                if let Some(audit_sink) = &audit_sink {
+                    audit_sink.record(&denial_event(&parts, &principal, action.as_ref(), dry_run));
+                }
+
+                if !dry_run {
+                    info!("Denying {} for {}: {:?}", iam_action, principal, decision.decision());
+                    let error = access_denied(&parts, &principal, action.as_ref(), &decision);
+                    return error_mapper.map_error(error.into(), request_id).await;
+                }
+
+                warn!("Dry run: would deny {} for {}: {:?}", iam_action, principal, decision.decision());
+            }
+
+            parts.extensions.insert(decision);
+            inner.call(Request::from_parts(parts, body)).await
+        })
+    }
+}
+
+/// Returns the error a denied request is rejected with, describing the principal, action, and resource.
+fn access_denied(
+    parts: &Parts,
+    principal: &Principal,
+    action: Option<&ResolvedAction>,
+    decision: &AuthorizationDecision,
+) -> VerifierError {
+    let principal_arn = parts
+        .extensions
+        .get::<SessionData>()
+        .and_then(|session_data| session_data.principal_arn())
+        .map(str::to_string)
+        .or_else(|| principal_arn(principal))
+        .unwrap_or_default();
+
+    VerifierError::AccessDenied {
+        principal_arn,
+        action: action.map(ResolvedAction::iam_action),
+        resource: action.and_then(|a| a.resources().first()).map(ToString::to_string),
+        explicit_deny: decision.decision() == Decision::Deny,
+    }
+}
+
+/// Returns the audit event for a denied request.
+fn denial_event(parts: &Parts, principal: &Principal, action: Option<&ResolvedAction>, dry_run: bool) -> AuditEvent {
+    let context = ErrorContext::new(
+        parts.method.clone(),
+        parts.uri.clone(),
+        parts.headers.clone(),
+        String::new(),
+        action.map(|a| a.service().to_string()).unwrap_or_default(),
+        parts.extensions.get::<RequestId>().copied(),
+    );
+    let client_ip = parts.extensions.get::<ConnectInfo>().map(|ci| ci.client_ip());
+    let session_data = parts.extensions.get::<SessionData>().cloned().unwrap_or_else(SessionData::new);
+
+    AuditEvent::new(&context, client_ip, Some((principal, &session_data)), Some("AccessDenied"))
+        .with_action(action)
+        .with_dry_run(dry_run)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{AuthorizationDecision, AuthorizerService},
+        crate::{
+            test_util::{StaticPolicies, GET_ONLY_POLICY},
+            AnonymousRequest, ChannelAuditSink, ConnectInfo, Decision, FixedClock, IamPolicyEvaluator, ResolvedAction,
+            XmlErrorMapper,
+        },
+        chrono::{TimeZone, Utc},
+        http::StatusCode,
+        hyper::{Body, Request, Response},
+        scratchstack_aws_principal::{Principal, User},
+        std::sync::Arc,
+        tower::{service_fn, BoxError, ServiceExt},
+    };
+
+    const USER_ARN: &str = "arn:aws:iam::123456789012:user/test";
+
+    #[test]
+    fn test_combine() {
+        let allow = AuthorizationDecision::new(Decision::Allow, vec!["A".to_string()], 2);
+        let boundary = AuthorizationDecision::new(Decision::Allow, vec!["B".to_string()], 1);
+        let scp = AuthorizationDecision::new(Decision::DefaultDeny, Vec::new(), 1);
+
+        let combined = AuthorizationDecision::combine(allow.clone(), Some(boundary.clone()), Vec::new());
+        assert!(combined.is_allowed());
+        assert_eq!(combined.matched_statements(), &["A".to_string(), "B".to_string()]);
+        assert_eq!(combined.evaluated_policies(), 3);
+
+        let combined = AuthorizationDecision::combine(allow, Some(boundary), vec![scp]);
+        assert_eq!(combined.decision(), Decision::DefaultDeny);
+        assert_eq!(combined.evaluated_policies(), 4);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_authorizer_service() {
+        let inner = service_fn(|req: Request<Body>| async move {
+            let decision = req.extensions().get::<AuthorizationDecision>().unwrap();
+            assert_eq!(decision.matched_statements(), &["AllowGet".to_string()]);
+            assert_eq!(decision.evaluated_policies(), 1);
+            Ok::<_, BoxError>(Response::new(Body::empty()))
+        });
+        let service = AuthorizerService::new(
+            inner,
+            Arc::new(StaticPolicies::new(&[GET_ONLY_POLICY])),
+            Arc::new(IamPolicyEvaluator::new()),
+            XmlErrorMapper::new("https://iam.amazonaws.com/doc/2010-05-08/"),
+        );
+        let request = |action: Option<&str>| {
+            let mut req = Request::get("/").body(Body::empty()).unwrap();
+            req.extensions_mut()
+                .insert(Principal::from(vec![User::new("aws", "123456789012", "/", "test").unwrap().into()]));
+            if let Some(action) = action {
+                req.extensions_mut().insert(ResolvedAction::new("iam", action, vec![USER_ARN.parse().unwrap()]));
+            }
+            req
+        };
+
+        let response = service.clone().oneshot(request(Some("GetUser"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = service.clone().oneshot(request(Some("DeleteUser"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("<Code>AccessDenied</Code>"));
+        assert!(body.contains(
+            "<Message>User: arn:aws:iam::123456789012:user/test is not authorized to perform: iam:DeleteUser on \
+             resource: arn:aws:iam::123456789012:user/test with an explicit deny</Message>"
+        ));
+
+        let response = service.clone().oneshot(request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // Requests without a principal are denied unless the verifier marked them as anonymous.
+        let inner = service_fn(|req: Request<Body>| async move {
+            assert!(req.extensions().get::<AuthorizationDecision>().is_none());
+            Ok::<_, BoxError>(Response::new(Body::empty()))
+        });
+        let service = AuthorizerService::new(
+            inner,
+            Arc::new(StaticPolicies::new(&[GET_ONLY_POLICY])),
+            Arc::new(IamPolicyEvaluator::new()),
+            XmlErrorMapper::new("https://iam.amazonaws.com/doc/2010-05-08/"),
+        );
+        let response = service.clone().oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(String::from_utf8_lossy(&body)
+            .contains("<Message>User: anonymous is not authorized to perform this operation</Message>"));
+
+        let mut req = Request::get("/").body(Body::empty()).unwrap();
+        req.extensions_mut().insert(Principal::new(Vec::new()));
+        let response = service.clone().oneshot(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let mut req = Request::get("/").body(Body::empty()).unwrap();
+        req.extensions_mut().insert(Principal::new(Vec::new()));
+        req.extensions_mut().insert(AnonymousRequest);
+        let response = service.oneshot(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_dry_run() {
+        let inner = service_fn(|req: Request<Body>| async move {
+            let decision = req.extensions().get::<AuthorizationDecision>().unwrap();
+            assert_eq!(decision.decision(), Decision::DefaultDeny);
+            Ok::<_, BoxError>(Response::new(Body::empty()))
+        });
+        let (audit_sink, events) = ChannelAuditSink::new();
+        let service = AuthorizerService::new(
+            inner,
+            Arc::new(StaticPolicies::new(&[GET_ONLY_POLICY])),
+            Arc::new(IamPolicyEvaluator::new()),
+            XmlErrorMapper::new("https://iam.amazonaws.com/doc/2010-05-08/"),
+        )
+        .with_dry_run()
+        .with_audit_sink(Arc::new(audit_sink));
+
+        let mut req = Request::get("/").body(Body::empty()).unwrap();
+        req.extensions_mut()
+            .insert(Principal::from(vec![User::new("aws", "123456789012", "/", "test").unwrap().into()]));
+        req.extensions_mut().insert(ResolvedAction::new("iam", "CreateUser", Vec::new()));
+
+        let response = service.oneshot(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let event = events.try_recv().unwrap();
+        assert!(event.dry_run());
+        assert_eq!(event.error_code(), Some("AccessDenied"));
+        assert_eq!(event.action(), Some("CreateUser"));
+        assert_eq!(event.principal_arn(), Some(USER_ARN));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_conditions() {
+        const POLICY: &str = r#"{
+            "Version": "2012-10-17",
+            "Statement": {
+                "Sid": "AllowFromOfficeUntilOctober2",
+                "Effect": "Allow",
+                "Action": "iam:GetUser",
+                "Resource": "*",
+                "Condition": {
+                    "IpAddress": {"aws:SourceIp": "192.0.2.0/24"},
+                    "DateLessThan": {"aws:CurrentTime": "2022-10-02T00:00:00Z"}
+                }
+            }
+        }"#;
+        let service = |day| {
+            let inner =
+                service_fn(|_req: Request<Body>| async move { Ok::<_, BoxError>(Response::new(Body::empty())) });
+            AuthorizerService::new(
+                inner,
+                Arc::new(StaticPolicies::new(&[POLICY])),
+                Arc::new(IamPolicyEvaluator::new()),
+                XmlErrorMapper::new("https://iam.amazonaws.com/doc/2010-05-08/"),
+            )
+            .with_clock(Arc::new(FixedClock::new(Utc.with_ymd_and_hms(2022, 10, day, 12, 0, 0).unwrap())))
+        };
+        let request = |client_ip: &str| {
+            let mut req = Request::get("/").body(Body::empty()).unwrap();
+            req.extensions_mut()
+                .insert(Principal::from(vec![User::new("aws", "123456789012", "/", "test").unwrap().into()]));
+            req.extensions_mut().insert(ResolvedAction::new("iam", "GetUser", Vec::new()));
+            req.extensions_mut().insert(ConnectInfo::new(format!("{client_ip}:443").parse().unwrap(), None));
+            req
+        };
+
+        let response = service(1).oneshot(request("192.0.2.1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = service(1).oneshot(request("198.51.100.1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // aws:CurrentTime comes from the authorizer's clock.
+        let response = service(3).oneshot(request("192.0.2.1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
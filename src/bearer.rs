@@ -0,0 +1,116 @@
+use {
+    crate::RequestId,
+    http::header::HeaderMap,
+    scratchstack_aws_principal::{Principal, SessionData},
+    std::fmt::{Debug, Formatter, Result as FmtResult},
+    tower::{util::BoxCloneService, BoxError},
+};
+
+/// A request to resolve a bearer token to the principal it was issued to.
+#[derive(Clone)]
+pub struct BearerTokenRequest {
+    token: String,
+    request_id: RequestId,
+}
+
+impl BearerTokenRequest {
+    /// Create a new [BearerTokenRequest].
+    pub fn new(token: impl Into<String>, request_id: RequestId) -> Self {
+        Self {
+            token: token.into(),
+            request_id,
+        }
+    }
+
+    /// Returns the bearer token.
+    #[inline]
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Returns the id of the request the token was presented with.
+    #[inline]
+    pub fn request_id(&self) -> RequestId {
+        self.request_id
+    }
+}
+
+impl Debug for BearerTokenRequest {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("BearerTokenRequest")
+            .field("token", &"<redacted>")
+            .field("request_id", &self.request_id)
+            .finish()
+    }
+}
+
+/// The principal and session data a bearer token resolves to.
+#[derive(Clone, Debug)]
+pub struct BearerTokenResponse {
+    principal: Principal,
+    session_data: SessionData,
+}
+
+impl BearerTokenResponse {
+    /// Create a new [BearerTokenResponse].
+    pub fn new(principal: Principal, session_data: SessionData) -> Self {
+        Self {
+            principal,
+            session_data,
+        }
+    }
+
+    /// Returns the principal the token was issued to.
+    #[inline]
+    pub fn principal(&self) -> &Principal {
+        &self.principal
+    }
+
+    /// Returns the session data associated with the token.
+    #[inline]
+    pub fn session_data(&self) -> &SessionData {
+        &self.session_data
+    }
+
+    /// Consume this [BearerTokenResponse], returning the principal and session data.
+    pub fn into_parts(self) -> (Principal, SessionData) {
+        (self.principal, self.session_data)
+    }
+}
+
+/// A type-erased bearer token provider, used to authenticate requests carrying an `Authorization: Bearer` header
+/// instead of a SigV4 signature.
+///
+/// The provider should return a [SignatureError][scratchstack_aws_signature::SignatureError] (such as
+/// `InvalidClientTokenId`) for unknown or expired tokens so the error mapper can render it.
+pub type BoxGetBearerToken = BoxCloneService<BearerTokenRequest, BearerTokenResponse, BoxError>;
+
+/// Returns the token from an `Authorization: Bearer <token>` header, if present.
+pub(crate) fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let (scheme, token) = headers.get("authorization")?.to_str().ok()?.trim().split_once(' ')?;
+    if scheme.eq_ignore_ascii_case("bearer") {
+        Some(token.trim())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::bearer_token, http::header::HeaderMap};
+
+    #[test]
+    fn test_bearer_token() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(bearer_token(&headers), None);
+
+        headers.insert("authorization", "Bearer abc.def".parse().unwrap());
+        assert_eq!(bearer_token(&headers), Some("abc.def"));
+
+        headers.insert("authorization", "bearer  xyz".parse().unwrap());
+        assert_eq!(bearer_token(&headers), Some("xyz"));
+
+        headers.insert("authorization", "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/...".parse().unwrap());
+        assert_eq!(bearer_token(&headers), None);
+    }
+}
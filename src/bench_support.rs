@@ -0,0 +1,167 @@
+use {
+    crate::{AwsSigV4VerifierService, ErrorMapper},
+    chrono::NaiveDate,
+    hyper::{body::Body, Request, Response},
+    scratchstack_aws_principal::{Principal, SessionData},
+    scratchstack_aws_signature::{
+        GetSigningKeyRequest, GetSigningKeyResponse, KSecretKey, KSigningKey, SignatureError,
+    },
+    std::{
+        future::{ready, Ready},
+        sync::{Arc, Mutex},
+        task::{Context, Poll},
+    },
+    tower::{BoxError, Service, ServiceExt},
+};
+
+/// A signing key provider that recognizes a single, fixed access key.
+///
+/// By default, the signing key is derived from the secret key on every request, just as a database-backed provider
+/// would. Calling [StaticSigningKey::with_cache] keeps the most recently derived signing key around so the derivation
+/// cost can be excluded from the measurement.
+#[derive(Clone)]
+pub struct StaticSigningKey {
+    access_key: String,
+    secret_key: KSecretKey,
+    principal: Principal,
+    session_data: SessionData,
+    cache: Option<Arc<Mutex<Option<CachedSigningKey>>>>,
+}
+
+#[derive(Clone)]
+struct CachedSigningKey {
+    request_date: NaiveDate,
+    region: String,
+    service: String,
+    signing_key: KSigningKey,
+}
+
+impl StaticSigningKey {
+    /// Create a new [StaticSigningKey] that accepts the given access key and secret key and authenticates requests as
+    /// the given principal.
+    pub fn new(access_key: &str, secret_key: &str, principal: Principal) -> Self {
+        Self {
+            access_key: access_key.to_string(),
+            secret_key: KSecretKey::from_str(secret_key),
+            principal,
+            session_data: SessionData::new(),
+            cache: None,
+        }
+    }
+
+    /// Attach the given session data to every successful response.
+    pub fn with_session_data(mut self, session_data: SessionData) -> Self {
+        self.session_data = session_data;
+        self
+    }
+
+    /// Cache the most recently derived signing key.
+    pub fn with_cache(mut self) -> Self {
+        self.cache = Some(Arc::new(Mutex::new(None)));
+        self
+    }
+
+    fn signing_key(&self, req: &GetSigningKeyRequest) -> KSigningKey {
+        let Some(cache) = &self.cache else {
+            return self.secret_key.to_ksigning(req.request_date(), req.region(), req.service());
+        };
+
+        let mut cache = cache.lock().unwrap();
+        if let Some(cached) = cache.as_ref() {
+            if cached.request_date == req.request_date().naive_utc()
+                && cached.region == req.region()
+                && cached.service == req.service()
+            {
+                return cached.signing_key;
+            }
+        }
+
+        let signing_key = self.secret_key.to_ksigning(req.request_date(), req.region(), req.service());
+        *cache = Some(CachedSigningKey {
+            request_date: req.request_date().naive_utc(),
+            region: req.region().to_string(),
+            service: req.service().to_string(),
+            signing_key,
+        });
+        signing_key
+    }
+}
+
+impl Service<GetSigningKeyRequest> for StaticSigningKey {
+    type Response = GetSigningKeyResponse;
+    type Error = BoxError;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: GetSigningKeyRequest) -> Self::Future {
+        if req.access_key() != self.access_key {
+            return ready(Err(SignatureError::InvalidClientTokenId(
+                "The AWS access key provided does not exist in our records.".to_string(),
+            )
+            .into()));
+        }
+
+        let signing_key = self.signing_key(&req);
+        let response = GetSigningKeyResponse::builder()
+            .principal(self.principal.clone())
+            .session_data(self.session_data.clone())
+            .signing_key(signing_key)
+            .build()
+            .map_err(Into::into);
+        ready(response)
+    }
+}
+
+/// A service implementation that returns an empty `200 OK` response for every request.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EmptyResponseService;
+
+impl Service<Request<Body>> for EmptyResponseService {
+    type Response = Response<Body>;
+    type Error = BoxError;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: Request<Body>) -> Self::Future {
+        ready(Ok(Response::new(Body::empty())))
+    }
+}
+
+/// Create a verifier for benchmarking with the given signing key provider and error mapper.
+///
+/// The verifier accepts `application/octet-stream` and `application/x-www-form-urlencoded` request bodies.
+pub fn bench_verifier<E: ErrorMapper>(
+    region: &str,
+    service: &str,
+    get_signing_key: StaticSigningKey,
+    error_mapper: E,
+) -> AwsSigV4VerifierService<StaticSigningKey, EmptyResponseService, E> {
+    AwsSigV4VerifierService::builder()
+        .region(region)
+        .service(service)
+        .allowed_content_types(vec![
+            "application/octet-stream".to_string(),
+            "application/x-www-form-urlencoded".to_string(),
+        ])
+        .get_signing_key(get_signing_key)
+        .implementation(EmptyResponseService)
+        .error_mapper(error_mapper)
+        .build()
+        .expect("all required verifier fields are set")
+}
+
+/// Send a single request through a verifier (or any other service), returning the response.
+///
+/// The verifier is cloned so that the same instance can be reused across benchmark iterations.
+pub async fn run_request<T>(service: &T, request: Request<Body>) -> Result<Response<Body>, BoxError>
+where
+    T: Service<Request<Body>, Response = Response<Body>, Error = BoxError> + Clone,
+{
+    service.clone().oneshot(request).await
+}
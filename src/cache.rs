@@ -0,0 +1,399 @@
+use {
+    crate::{AuthFailure, AuthFailureObserver, AuthOutcome, Metrics, NoopMetrics},
+    chrono::NaiveDate,
+    scratchstack_aws_signature::{GetSigningKeyRequest, GetSigningKeyResponse, SignatureError},
+    std::{
+        collections::{HashMap, VecDeque},
+        fmt::{Debug, Formatter, Result as FmtResult},
+        future::Future,
+        pin::Pin,
+        sync::{Arc, Mutex},
+        task::{Context, Poll},
+        time::{Duration, Instant},
+    },
+    tower::{BoxError, Service, ServiceExt},
+};
+
+/// Identifies a signing key lookup: the access key, session token, request date, region, and service.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct CacheKey {
+    access_key: String,
+    session_token: Option<String>,
+    request_date: NaiveDate,
+    region: String,
+    service: String,
+}
+
+impl From<&GetSigningKeyRequest> for CacheKey {
+    fn from(req: &GetSigningKeyRequest) -> Self {
+        Self {
+            access_key: req.access_key().to_string(),
+            session_token: req.session_token().map(str::to_string),
+            request_date: req.request_date().naive_utc(),
+            region: req.region().to_string(),
+            service: req.service().to_string(),
+        }
+    }
+}
+
+/// A signing key provider wrapper that remembers the responses of the wrapped provider, so frequently used access
+/// keys don't require a database query on every request.
+///
+/// Responses are kept for `ttl`; when more than `capacity` are held, the oldest is evicted. Clones share the same
+/// cache.
+///
+/// A cached response outlives changes to the underlying credentials for up to `ttl`. To make revoked or rotated keys
+/// take effect sooner, install [CachingSigningKeyService::observer] as the verifier's
+/// [auth_failure_observer][crate::AwsSigV4VerifierService::auth_failure_observer]: an access key whose signature does
+/// not match is then evicted, so the next request looks it up afresh.
+///
+/// By default, failed lookups are not cached. [CachingSigningKeyService::with_negative_ttl] additionally caches
+/// `InvalidClientTokenId` results, so repeated requests with an unknown access key don't each query the wrapped
+/// provider.
+///
+/// Cache hits and misses are reported to the [Metrics] given to [CachingSigningKeyService::with_metrics] as the
+/// provider `cache`.
+pub struct CachingSigningKeyService<G> {
+    inner: G,
+    cache: Arc<SigningKeyCache>,
+    negative_ttl: Option<Duration>,
+    metrics: Arc<dyn Metrics>,
+}
+
+impl<G> CachingSigningKeyService<G> {
+    /// Create a new [CachingSigningKeyService] holding at most `capacity` responses for `ttl` each.
+    pub fn new(inner: G, ttl: Duration, capacity: usize) -> Self {
+        Self {
+            inner,
+            cache: Arc::new(SigningKeyCache::new(ttl, capacity)),
+            negative_ttl: None,
+            metrics: Arc::new(NoopMetrics),
+        }
+    }
+
+    /// Also cache `InvalidClientTokenId` results for `ttl`. This should be short, since a newly created access key is
+    /// rejected until its negative entry expires or is [invalidated][CachingSigningKeyService::invalidate].
+    pub fn with_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_ttl = Some(ttl);
+        self
+    }
+
+    /// Report cache hits and misses to the given [Metrics].
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Returns an [AuthFailureObserver] that evicts the cached responses for access keys whose signatures don't
+    /// match.
+    pub fn observer(&self) -> Arc<dyn AuthFailureObserver> {
+        self.cache.clone()
+    }
+
+    /// Evict all cached responses for an access key.
+    pub fn invalidate(&self, access_key: &str) {
+        self.cache.invalidate(access_key);
+    }
+
+    /// Evict all cached responses.
+    pub fn clear(&self) {
+        let mut inner = self.cache.inner.lock().unwrap();
+        inner.entries.clear();
+        inner.order.clear();
+    }
+
+    /// Returns the number of cached lookups, including any that have expired but have not yet been evicted.
+    pub fn len(&self) -> usize {
+        self.cache.inner.lock().unwrap().entries.len()
+    }
+
+    /// Indicates whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<G: Clone> Clone for CachingSigningKeyService<G> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            cache: self.cache.clone(),
+            negative_ttl: self.negative_ttl,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+impl<G> Debug for CachingSigningKeyService<G> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("CachingSigningKeyService")
+            .field("inner", &std::any::type_name::<G>())
+            .field("cache", &self.cache)
+            .field("negative_ttl", &self.negative_ttl)
+            .field("metrics", &self.metrics)
+            .finish()
+    }
+}
+
+impl<G> Service<GetSigningKeyRequest> for CachingSigningKeyService<G>
+where
+    G: Service<GetSigningKeyRequest, Response = GetSigningKeyResponse, Error = BoxError> + Clone + Send + 'static,
+    G::Future: Send,
+{
+    type Response = GetSigningKeyResponse;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        // The wrapped provider is only needed on a cache miss, and is readied then.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: GetSigningKeyRequest) -> Self::Future {
+        let inner = self.inner.clone();
+        let cache = self.cache.clone();
+        let negative_ttl = self.negative_ttl;
+        let metrics = self.metrics.clone();
+
+        Box::pin(async move {
+            let key = CacheKey::from(&req);
+            let entry = cache.get(&key);
+            metrics.record_signing_key_cache("cache", entry.is_some());
+            match entry {
+                Some(CacheEntry::Found(response)) => return Ok(response),
+                Some(CacheEntry::Unknown(message)) => return Err(SignatureError::InvalidClientTokenId(message).into()),
+                None => (),
+            }
+
+            match inner.oneshot(req).await {
+                Ok(response) => {
+                    cache.insert(key, CacheEntry::Found(response.clone()), cache.ttl);
+                    Ok(response)
+                }
+                Err(e) => {
+                    if let (Some(ttl), Some(SignatureError::InvalidClientTokenId(message))) =
+                        (negative_ttl, e.downcast_ref::<SignatureError>())
+                    {
+                        cache.insert(key, CacheEntry::Unknown(message.clone()), ttl);
+                    }
+                    Err(e)
+                }
+            }
+        })
+    }
+}
+
+/// The state shared by clones of a [CachingSigningKeyService].
+pub(crate) struct SigningKeyCache {
+    ttl: Duration,
+    capacity: usize,
+    inner: Mutex<SigningKeyCacheInner>,
+}
+
+/// The cached result of a lookup.
+#[derive(Clone)]
+pub(crate) enum CacheEntry {
+    /// The wrapped provider returned a response.
+    Found(GetSigningKeyResponse),
+
+    /// The wrapped provider rejected the access key with `InvalidClientTokenId` and this message.
+    Unknown(String),
+}
+
+#[derive(Default)]
+struct SigningKeyCacheInner {
+    entries: HashMap<CacheKey, (CacheEntry, Instant)>,
+    order: VecDeque<(CacheKey, Instant)>,
+}
+
+impl SigningKeyCache {
+    pub(crate) fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            inner: Mutex::new(SigningKeyCacheInner::default()),
+        }
+    }
+
+    #[cfg(feature = "gsk_direct")]
+    #[inline]
+    pub(crate) fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub(crate) fn get(&self, key: &CacheKey) -> Option<CacheEntry> {
+        let inner = self.inner.lock().unwrap();
+        match inner.entries.get(key) {
+            Some((entry, expires)) if *expires > Instant::now() => Some(entry.clone()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn insert(&self, key: CacheKey, entry: CacheEntry, ttl: Duration) {
+        let now = Instant::now();
+        let expires = now + ttl;
+        let mut inner = self.inner.lock().unwrap();
+
+        inner.entries.insert(key.clone(), (entry, expires));
+        inner.order.push_back((key, expires));
+
+        // Entries are evicted in insertion order. With a shorter negative TTL, an expired entry may sit behind a live
+        // one until the capacity forces it out; get() ignores it in the meantime.
+        while inner.entries.len() > self.capacity
+            || matches!(inner.order.front(), Some((_, oldest_expires)) if *oldest_expires <= now)
+        {
+            let Some((key, expires)) = inner.order.pop_front() else {
+                break;
+            };
+
+            // Skip entries that have since been replaced.
+            if matches!(inner.entries.get(&key), Some((_, e)) if *e == expires) {
+                inner.entries.remove(&key);
+            }
+        }
+    }
+
+    pub(crate) fn invalidate(&self, access_key: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.retain(|key, _| key.access_key != access_key);
+        inner.order.retain(|(key, _)| key.access_key != access_key);
+    }
+}
+
+impl Debug for SigningKeyCache {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("SigningKeyCache")
+            .field("ttl", &self.ttl)
+            .field("capacity", &self.capacity)
+            .field("len", &self.inner.lock().unwrap().entries.len())
+            .finish()
+    }
+}
+
+impl AuthFailureObserver for SigningKeyCache {
+    fn on_auth_failure(&self, failure: &AuthFailure) {
+        if let (AuthOutcome::SignatureMismatch, Some(access_key)) = (failure.outcome(), failure.access_key()) {
+            self.invalidate(access_key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::CachingSigningKeyService,
+        crate::{test_util::signing_key_request, AuthFailure, AuthOutcome, RequestId},
+        scratchstack_aws_principal::{Principal, User},
+        scratchstack_aws_signature::{GetSigningKeyRequest, GetSigningKeyResponse, KSecretKey, SignatureError},
+        std::{
+            sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc,
+            },
+            time::Duration,
+        },
+        tower::{service_fn, BoxError, ServiceExt},
+    };
+
+    fn request(access_key: &str) -> GetSigningKeyRequest {
+        signing_key_request(access_key, None, "service")
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_caching() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let inner = {
+            let lookups = lookups.clone();
+            service_fn(move |req: GetSigningKeyRequest| {
+                lookups.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    let k_signing =
+                        KSecretKey::from_str("secret").to_ksigning(req.request_date(), req.region(), req.service());
+                    let principal =
+                        Principal::from(vec![User::new("aws", "123456789012", "/", "test").unwrap().into()]);
+                    Ok::<_, BoxError>(
+                        GetSigningKeyResponse::builder().principal(principal).signing_key(k_signing).build().unwrap(),
+                    )
+                }
+            })
+        };
+
+        let service = CachingSigningKeyService::new(inner, Duration::from_secs(60), 1);
+        service.clone().oneshot(request("AKIDEXAMPLE")).await.unwrap();
+        service.clone().oneshot(request("AKIDEXAMPLE")).await.unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+        // Exceeding the capacity evicts the oldest entry.
+        service.clone().oneshot(request("AKIDOTHER")).await.unwrap();
+        assert_eq!(service.len(), 1);
+        service.clone().oneshot(request("AKIDEXAMPLE")).await.unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 3);
+
+        // A signature mismatch evicts the access key.
+        service.observer().on_auth_failure(&AuthFailure::new(
+            None,
+            Some("AKIDEXAMPLE".to_string()),
+            AuthOutcome::SignatureMismatch,
+            Some("SignatureDoesNotMatch"),
+            RequestId::new(),
+        ));
+        assert!(service.is_empty());
+        service.clone().oneshot(request("AKIDEXAMPLE")).await.unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 4);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_negative_caching() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let inner = {
+            let lookups = lookups.clone();
+            service_fn(move |_: GetSigningKeyRequest| {
+                lookups.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    Err::<GetSigningKeyResponse, _>(BoxError::from(SignatureError::InvalidClientTokenId(
+                        "The AWS access key provided does not exist in our records".to_string(),
+                    )))
+                }
+            })
+        };
+
+        // Without a negative TTL, every lookup reaches the provider.
+        let service = CachingSigningKeyService::new(inner.clone(), Duration::from_secs(60), 10);
+        service.clone().oneshot(request("AKIDBOGUS")).await.unwrap_err();
+        service.clone().oneshot(request("AKIDBOGUS")).await.unwrap_err();
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+        assert!(service.is_empty());
+
+        let service = CachingSigningKeyService::new(inner, Duration::from_secs(60), 10)
+            .with_negative_ttl(Duration::from_millis(50));
+        service.clone().oneshot(request("AKIDBOGUS")).await.unwrap_err();
+        let e = service.clone().oneshot(request("AKIDBOGUS")).await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<SignatureError>(), Some(SignatureError::InvalidClientTokenId(_))));
+        assert_eq!(lookups.load(Ordering::SeqCst), 3);
+
+        // Negative entries expire on their own TTL.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        service.clone().oneshot(request("AKIDBOGUS")).await.unwrap_err();
+        assert_eq!(lookups.load(Ordering::SeqCst), 4);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test_log::test(tokio::test)]
+    async fn test_cache_metrics() {
+        let inner = service_fn(|req: GetSigningKeyRequest| async move {
+            let k_signing = KSecretKey::from_str("secret").to_ksigning(req.request_date(), req.region(), req.service());
+            let principal = Principal::from(vec![User::new("aws", "123456789012", "/", "test").unwrap().into()]);
+            Ok::<_, BoxError>(
+                GetSigningKeyResponse::builder().principal(principal).signing_key(k_signing).build().unwrap(),
+            )
+        });
+
+        let metrics = Arc::new(crate::CounterMetrics::new());
+        let service = CachingSigningKeyService::new(inner, Duration::from_secs(60), 10).with_metrics(metrics.clone());
+        service.clone().oneshot(request("AKIDEXAMPLE")).await.unwrap();
+        service.clone().oneshot(request("AKIDEXAMPLE")).await.unwrap();
+        service.clone().oneshot(request("AKIDOTHER")).await.unwrap();
+        assert_eq!(metrics.cache_hits(), 1);
+        assert_eq!(metrics.cache_misses(), 2);
+    }
+}
@@ -0,0 +1,542 @@
+use {
+    crate::{date::DateHeaderOptions, SigningDetails, VerifierError},
+    chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc},
+    http::{
+        header::{HeaderMap, DATE},
+        request::Parts,
+        uri::Uri,
+    },
+    scratchstack_aws_signature::SignatureOptions,
+    sha2::{Digest, Sha256},
+};
+
+/// The `x-amz-content-sha256` header.
+pub(crate) const X_AMZ_CONTENT_SHA256: &str = "x-amz-content-sha256";
+
+/// The `x-amz-date` header.
+pub(crate) const X_AMZ_DATE: &str = "x-amz-date";
+
+/// The algorithm identifier for SigV4 (HMAC-SHA256) signatures.
+pub(crate) const AWS4_HMAC_SHA256: &str = "AWS4-HMAC-SHA256";
+
+/// Headers whose values are comma-separated lists, and so may legitimately be repeated.
+const REPEATABLE_HEADERS: &[&str] = &[
+    "accept",
+    "accept-encoding",
+    "accept-language",
+    "cache-control",
+    "forwarded",
+    "if-match",
+    "if-none-match",
+    "via",
+    "x-forwarded-for",
+];
+
+/// The payload hash used when the payload is not included in the signature.
+pub(crate) const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// The format of the `X-Amz-Date` header and query parameter.
+const ISO8601_BASIC_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// The longest validity period, in seconds, a presigned request may declare with `X-Amz-Expires`: seven days.
+const MAX_PRESIGNED_EXPIRES_SECONDS: i64 = 604_800;
+
+/// The error returned for a missing or out of range `X-Amz-Expires`.
+const MSG_INVALID_X_AMZ_EXPIRES: &str = "X-Amz-Expires must be a number of seconds from 1 to 604800 (one week)";
+
+/// The signature parameters supplied by the client, either in the `Authorization` header or in the query string of a
+/// presigned URL.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct AuthParams {
+    /// The signing algorithm, e.g. `AWS4-HMAC-SHA256`.
+    pub(crate) algorithm: String,
+
+    /// The access key id.
+    pub(crate) access_key: String,
+
+    /// The credential scope following the access key, e.g. `20150830/us-east-1/iam/aws4_request`.
+    pub(crate) scope: String,
+
+    /// The lowercase names of the signed headers, in the order given by the client.
+    pub(crate) signed_headers: Vec<String>,
+
+    /// The hex-encoded signature.
+    pub(crate) signature: String,
+
+    /// Whether the parameters came from the query string rather than the `Authorization` header.
+    pub(crate) presigned: bool,
+}
+
+impl AuthParams {
+    /// Extract the signature parameters from the request headers or query string, if present.
+    pub(crate) fn from_request_head(headers: &HeaderMap, uri: &Uri) -> Option<Self> {
+        if let Some(auth) = headers.get("authorization") {
+            return Self::from_authorization_header(auth.to_str().ok()?);
+        }
+
+        let query = uri.query()?;
+        let params = query_params(query);
+        let get = |name: &str| params.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone());
+        let algorithm = get("X-Amz-Algorithm")?;
+        let (access_key, scope) = split_credential(&get("X-Amz-Credential")?)?;
+        let signed_headers = get("X-Amz-SignedHeaders")?.split(';').map(str::to_ascii_lowercase).collect();
+        let signature = get("X-Amz-Signature")?;
+
+        Some(Self {
+            algorithm,
+            access_key,
+            scope,
+            signed_headers,
+            signature,
+            presigned: true,
+        })
+    }
+
+    /// Parse an `Authorization` header of the form
+    /// `<algorithm> Credential=<key>/<scope>, SignedHeaders=<h1>;<h2>, Signature=<sig>`.
+    pub(crate) fn from_authorization_header(header: &str) -> Option<Self> {
+        let (algorithm, rest) = header.trim().split_once(' ')?;
+        let mut credential = None;
+        let mut signed_headers = None;
+        let mut signature = None;
+
+        for param in rest.split(',') {
+            let (key, value) = param.trim().split_once('=')?;
+            match key {
+                "Credential" => credential = Some(value),
+                "SignedHeaders" => signed_headers = Some(value),
+                "Signature" => signature = Some(value),
+                _ => return None,
+            }
+        }
+
+        let (access_key, scope) = split_credential(credential?)?;
+        Some(Self {
+            algorithm: algorithm.to_string(),
+            access_key,
+            scope,
+            signed_headers: signed_headers?.split(';').map(str::to_ascii_lowercase).collect(),
+            signature: signature?.to_string(),
+            presigned: false,
+        })
+    }
+
+    /// Returns the components of the credential scope.
+    pub(crate) fn scope_parts(&self) -> Vec<&str> {
+        self.scope.split('/').collect()
+    }
+}
+
+fn split_credential(credential: &str) -> Option<(String, String)> {
+    let (access_key, scope) = credential.split_once('/')?;
+    Some((access_key.to_string(), scope.to_string()))
+}
+
+/// Returns the value of the given header, or the given query parameter if the header is not present.
+pub(crate) fn header_or_query_param(headers: &HeaderMap, uri: &Uri, header: &str, param: &str) -> Option<String> {
+    match headers.get(header) {
+        Some(value) => value.to_str().ok().map(str::to_string),
+        None => query_params(uri.query()?).into_iter().find(|(k, _)| k == param).map(|(_, v)| v),
+    }
+}
+
+/// Returns the timestamp of the request from the `X-Amz-Date` header or query parameter. Requests signed in the
+/// `Authorization` header may instead be timestamped by the `Date` header, as `date_header_options` allow.
+pub(crate) fn request_timestamp(
+    headers: &HeaderMap,
+    uri: &Uri,
+    presigned: bool,
+    date_header_options: DateHeaderOptions,
+) -> Option<DateTime<Utc>> {
+    match header_or_query_param(headers, uri, X_AMZ_DATE, "X-Amz-Date") {
+        // The signature library also accepts the extended format.
+        Some(amz_date) => parse_iso8601_basic(&amz_date)
+            .or_else(|| DateTime::parse_from_rfc3339(&amz_date).ok().map(|dt| dt.with_timezone(&Utc))),
+        None if !presigned && date_header_options.date_fallback() => {
+            date_header_options.parse_date(&String::from_utf8_lossy(headers.get(DATE)?.as_bytes()))
+        }
+        None => None,
+    }
+}
+
+/// Indicates whether a request signed at `timestamp` is acceptable at `server_timestamp`, allowing for clock skew and,
+/// for presigned requests, the `X-Amz-Expires` validity period. Presigned requests without a valid `X-Amz-Expires` are
+/// rejected with `AuthorizationQueryParametersError`.
+pub(crate) fn within_time_window(
+    headers: &HeaderMap,
+    uri: &Uri,
+    timestamp: DateTime<Utc>,
+    presigned: bool,
+    server_timestamp: DateTime<Utc>,
+    max_skew: Duration,
+) -> Result<bool, VerifierError> {
+    // Bounds that fall outside of the representable range don't limit the window.
+    let earliest = timestamp.checked_sub_signed(max_skew);
+    let latest = request_expiry(headers, uri, timestamp, presigned, max_skew)?;
+    Ok(earliest.is_none_or(|earliest| server_timestamp >= earliest)
+        && latest.is_none_or(|latest| server_timestamp <= latest))
+}
+
+/// Returns the latest server time at which a request with the given timestamp is still within the allowed window, or
+/// `None` if that is later than the latest representable time. Presigned requests without a valid `X-Amz-Expires` are
+/// rejected with `AuthorizationQueryParametersError`.
+pub(crate) fn request_expiry(
+    headers: &HeaderMap,
+    uri: &Uri,
+    timestamp: DateTime<Utc>,
+    presigned: bool,
+    max_skew: Duration,
+) -> Result<Option<DateTime<Utc>>, VerifierError> {
+    let validity = if presigned {
+        presigned_expires(headers, uri)?
+    } else {
+        Duration::zero()
+    };
+
+    Ok(timestamp.checked_add_signed(validity).and_then(|expiry| expiry.checked_add_signed(max_skew)))
+}
+
+/// Returns the validity period declared by the `X-Amz-Expires` parameter of a presigned request, which must be a
+/// whole number of seconds from 1 to 604800.
+fn presigned_expires(headers: &HeaderMap, uri: &Uri) -> Result<Duration, VerifierError> {
+    let invalid = || VerifierError::AuthorizationQueryParametersError(MSG_INVALID_X_AMZ_EXPIRES);
+    let seconds = header_or_query_param(headers, uri, "x-amz-expires", "X-Amz-Expires")
+        .and_then(|value| value.parse::<i64>().ok())
+        .filter(|seconds| (1..=MAX_PRESIGNED_EXPIRES_SECONDS).contains(seconds))
+        .ok_or_else(invalid)?;
+    Duration::try_seconds(seconds).ok_or_else(invalid)
+}
+
+/// Parse a timestamp in ISO 8601 basic format (`YYYYMMDDTHHMMSSZ`).
+pub(crate) fn parse_iso8601_basic(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, ISO8601_BASIC_FORMAT).ok().map(|dt| Utc.from_utc_datetime(&dt))
+}
+
+/// Format a timestamp in ISO 8601 basic format (`YYYYMMDDTHHMMSSZ`).
+pub(crate) fn format_iso8601_basic(timestamp: &DateTime<Utc>) -> String {
+    timestamp.format(ISO8601_BASIC_FORMAT).to_string()
+}
+
+/// A canonical request, as defined by the AWS SigV4 signing process.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct CanonicalRequest {
+    pub(crate) method: String,
+    pub(crate) canonical_uri: String,
+    pub(crate) canonical_query: String,
+    pub(crate) canonical_headers: String,
+    pub(crate) signed_headers: String,
+    pub(crate) payload_hash: String,
+}
+
+impl CanonicalRequest {
+    /// Build the canonical request for the given request parts and body.
+    ///
+    /// If `presigned` is set, the `X-Amz-Signature` query parameter is excluded from the canonical query string.
+    pub(crate) fn new(
+        parts: &Parts,
+        body: &[u8],
+        signed_headers: &[String],
+        presigned: bool,
+        options: SignatureOptions,
+    ) -> Self {
+        let canonical_uri = canonical_uri(parts.uri.path(), options.s3);
+
+        let mut query: Vec<(String, String)> = query_params(parts.uri.query().unwrap_or(""))
+            .into_iter()
+            .filter(|(k, _)| !(presigned && k == "X-Amz-Signature"))
+            .map(|(k, v)| (uri_encode(k.as_bytes(), true), uri_encode(v.as_bytes(), true)))
+            .collect();
+        query.sort();
+        let canonical_query = query.into_iter().map(|(k, v)| format!("{k}={v}")).collect::<Vec<_>>().join("&");
+
+        let mut sorted_headers = signed_headers.to_vec();
+        sorted_headers.sort();
+        sorted_headers.dedup();
+        let mut canonical_headers = String::new();
+        for name in &sorted_headers {
+            canonical_headers.push_str(name);
+            canonical_headers.push(':');
+            canonical_headers.push_str(&canonical_header_value(&parts.headers, name));
+            canonical_headers.push('\n');
+        }
+
+        let payload_hash = match parts.headers.get(X_AMZ_CONTENT_SHA256).and_then(|v| v.to_str().ok()) {
+            Some(value) => value.to_string(),
+            None if presigned && options.s3 => UNSIGNED_PAYLOAD.to_string(),
+            None => hex::encode(Sha256::digest(body)),
+        };
+
+        Self {
+            method: parts.method.as_str().to_string(),
+            canonical_uri,
+            canonical_query,
+            canonical_headers,
+            signed_headers: sorted_headers.join(";"),
+            payload_hash,
+        }
+    }
+
+    /// Returns the canonical request string.
+    pub(crate) fn to_canonical_string(&self) -> String {
+        format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            self.method,
+            self.canonical_uri,
+            self.canonical_query,
+            self.canonical_headers,
+            self.signed_headers,
+            self.payload_hash
+        )
+    }
+
+    /// Returns the hex-encoded SHA-256 digest of the canonical request string.
+    pub(crate) fn digest(&self) -> String {
+        hex::encode(Sha256::digest(self.to_canonical_string().as_bytes()))
+    }
+}
+
+/// Returns the first signed header that appears more than once in the request, ignoring headers that may legitimately
+/// be repeated.
+pub(crate) fn duplicate_signed_header<'a>(headers: &HeaderMap, signed_headers: &'a [String]) -> Option<&'a str> {
+    signed_headers
+        .iter()
+        .filter(|name| !REPEATABLE_HEADERS.contains(&name.as_str()))
+        .find(|name| headers.get_all(name.as_str()).iter().nth(1).is_some())
+        .map(String::as_str)
+}
+
+/// Build the string to sign for the given algorithm, timestamp, credential scope, and canonical request.
+pub(crate) fn string_to_sign(
+    algorithm: &str,
+    timestamp: &DateTime<Utc>,
+    scope: &str,
+    canonical_request: &CanonicalRequest,
+) -> String {
+    format!("{}\n{}\n{}\n{}", algorithm, format_iso8601_basic(timestamp), scope, canonical_request.digest())
+}
+
+/// Describe how the request was signed. Returns `None` if the request has no timestamp.
+pub(crate) fn signing_details(
+    parts: &Parts,
+    body: &[u8],
+    auth: &AuthParams,
+    options: SignatureOptions,
+    date_header_options: DateHeaderOptions,
+) -> Option<SigningDetails> {
+    let timestamp = request_timestamp(&parts.headers, &parts.uri, auth.presigned, date_header_options)?;
+    let canonical_request = CanonicalRequest::new(parts, body, &auth.signed_headers, auth.presigned, options);
+    let string_to_sign = string_to_sign(&auth.algorithm, &timestamp, &auth.scope, &canonical_request);
+    let signed_headers =
+        canonical_request.signed_headers.split(';').filter(|h| !h.is_empty()).map(str::to_string).collect();
+
+    Some(SigningDetails::new(
+        canonical_request.to_canonical_string(),
+        string_to_sign,
+        signed_headers,
+        auth.scope.clone(),
+    ))
+}
+
+/// Split a raw query string into (percent-decoded) key/value pairs.
+pub(crate) fn query_params(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (k, v) = p.split_once('=').unwrap_or((p, ""));
+            (percent_decode(k), percent_decode(v))
+        })
+        .collect()
+}
+
+/// Split a form-encoded body into decoded key/value pairs. Unlike in a query string, `+` encodes a space.
+pub(crate) fn form_params(body: &str) -> Vec<(String, String)> {
+    body.split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (k, v) = p.split_once('=').unwrap_or((p, ""));
+            (percent_decode(&k.replace('+', " ")), percent_decode(&v.replace('+', " ")))
+        })
+        .collect()
+}
+
+/// Returns the canonical value of a header: all values joined with commas, with leading and trailing whitespace
+/// removed and internal runs of whitespace collapsed to a single space.
+fn canonical_header_value(headers: &HeaderMap, name: &str) -> String {
+    headers
+        .get_all(name)
+        .iter()
+        .map(|v| String::from_utf8_lossy(v.as_bytes()).split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Returns the canonical URI path.
+///
+/// For S3, the path is used as-is (after normalizing its percent-encoding). For other services, relative path
+/// segments are removed and each segment is URI-encoded twice.
+fn canonical_uri(path: &str, s3: bool) -> String {
+    if path.is_empty() {
+        return "/".to_string();
+    }
+
+    let mut segments: Vec<String> = Vec::new();
+    let raw_segments: Vec<&str> = path.split('/').skip(1).collect();
+    let last = raw_segments.len().saturating_sub(1);
+
+    for (i, segment) in raw_segments.into_iter().enumerate() {
+        if !s3 {
+            match segment {
+                "." => {
+                    if i == last {
+                        segments.push(String::new());
+                    }
+                    continue;
+                }
+                ".." => {
+                    segments.pop();
+                    if i == last {
+                        segments.push(String::new());
+                    }
+                    continue;
+                }
+                _ => (),
+            }
+        }
+
+        let encoded = uri_encode(percent_decode(segment).as_bytes(), true);
+        if s3 {
+            segments.push(encoded);
+        } else {
+            segments.push(uri_encode(encoded.as_bytes(), true));
+        }
+    }
+
+    format!("/{}", segments.join("/"))
+}
+
+/// Percent-decode a string. Invalid escape sequences are kept as-is.
+pub(crate) fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(hi), Some(lo)) = (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                result.push(hi << 4 | lo);
+                i += 3;
+                continue;
+            }
+        }
+
+        result.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+fn hex_value(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// URI-encode bytes as specified by the SigV4 signing process: everything except unreserved characters
+/// (`A-Z a-z 0-9 - . _ ~`) is percent-encoded with uppercase hex digits. If `encode_slash` is false, `/` is also left
+/// as-is.
+pub(crate) fn uri_encode(bytes: &[u8], encode_slash: bool) -> String {
+    let mut result = String::with_capacity(bytes.len());
+
+    for &b in bytes {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => result.push(b as char),
+            b'/' if !encode_slash => result.push('/'),
+            _ => result.push_str(&format!("%{b:02X}")),
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{canonical_uri, duplicate_signed_header, percent_decode, uri_encode, AuthParams, CanonicalRequest},
+        http::Request,
+        pretty_assertions::assert_eq,
+        scratchstack_aws_signature::SignatureOptions,
+    };
+
+    #[test]
+    fn test_duplicate_signed_header() {
+        let req = Request::get("/")
+            .header("host", "example.amazonaws.com")
+            .header("x-amz-meta-a", "1")
+            .header("x-amz-meta-a", "2")
+            .header("accept", "text/plain")
+            .header("accept", "application/json")
+            .body(())
+            .unwrap();
+        let signed = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(duplicate_signed_header(req.headers(), &signed(&["host", "accept"])), None);
+        assert_eq!(duplicate_signed_header(req.headers(), &signed(&["host", "x-amz-meta-a"])), Some("x-amz-meta-a"));
+    }
+
+    #[test]
+    fn test_uri_encoding() {
+        assert_eq!(uri_encode(b"a b/c~", true), "a%20b%2Fc~");
+        assert_eq!(uri_encode(b"a b/c~", false), "a%20b/c~");
+        assert_eq!(percent_decode("a%20b%2fc%zz%"), "a b/c%zz%");
+    }
+
+    #[test]
+    fn test_canonical_uri() {
+        assert_eq!(canonical_uri("", false), "/");
+        assert_eq!(canonical_uri("/", false), "/");
+        assert_eq!(canonical_uri("/a/./b/../c/", false), "/a/c/");
+        assert_eq!(canonical_uri("/a%20b", false), "/a%2520b");
+        assert_eq!(canonical_uri("/a%20b/../c", true), "/a%20b/../c");
+    }
+
+    #[test]
+    fn test_authorization_header() {
+        let params = AuthParams::from_authorization_header(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+             SignedHeaders=content-type;Host;x-amz-date, \
+             Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7",
+        )
+        .unwrap();
+        assert_eq!(params.algorithm, "AWS4-HMAC-SHA256");
+        assert_eq!(params.access_key, "AKIDEXAMPLE");
+        assert_eq!(params.scope, "20150830/us-east-1/iam/aws4_request");
+        assert_eq!(params.signed_headers, vec!["content-type", "host", "x-amz-date"]);
+        assert!(!params.presigned);
+
+        assert!(AuthParams::from_authorization_header("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE").is_none());
+    }
+
+    /// The `get-vanilla-query-order-key-case` request from the AWS SigV4 test suite.
+    #[test]
+    fn test_canonical_request() {
+        let (parts, _) = Request::get("/?Param2=value2&Param1=value1")
+            .header("host", "example.amazonaws.com")
+            .header("x-amz-date", "20150830T123600Z")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let signed_headers = vec!["host".to_string(), "x-amz-date".to_string()];
+        let cr = CanonicalRequest::new(&parts, b"", &signed_headers, false, SignatureOptions::default());
+        assert_eq!(
+            cr.to_canonical_string(),
+            "GET\n/\nParam1=value1&Param2=value2\nhost:example.amazonaws.com\nx-amz-date:20150830T123600Z\n\n\
+             host;x-amz-date\ne3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(cr.digest(), "816cd5b414d056048ba4f7c5386d6e0533120fb1fcfa93762cf0fc39e2cf19e0");
+    }
+}
@@ -0,0 +1,125 @@
+use {
+    crate::RequestId,
+    chrono::{DateTime, SecondsFormat, Utc},
+    std::collections::HashMap,
+};
+
+/// A catalog of client-facing error message templates, keyed by error code.
+///
+/// Templates may contain the following placeholders:
+/// * `{code}`: The error code.
+/// * `{message}`: The message the error would have had without the catalog.
+/// * `{request_id}`: The request id, or an empty string if unknown.
+/// * `{server_time}`: The time the message was rendered, in RFC 3339 format.
+///
+/// Literal braces are written as `{{` and `}}`. Unknown placeholders are left as-is.
+///
+/// Error codes without a template keep their original message.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MessageCatalog {
+    templates: HashMap<String, String>,
+}
+
+impl MessageCatalog {
+    /// Create a new, empty [MessageCatalog].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a template for the given error code, returning the updated catalog.
+    pub fn with_template(mut self, code: impl Into<String>, template: impl Into<String>) -> Self {
+        self.insert(code, template);
+        self
+    }
+
+    /// Add a template for the given error code, returning the previous template if any.
+    pub fn insert(&mut self, code: impl Into<String>, template: impl Into<String>) -> Option<String> {
+        self.templates.insert(code.into(), template.into())
+    }
+
+    /// Returns the template for the given error code.
+    pub fn template(&self, code: &str) -> Option<&str> {
+        self.templates.get(code).map(String::as_str)
+    }
+
+    /// Render the message for the given error code, if the catalog has a template for it.
+    pub fn render(&self, code: &str, message: &str, request_id: Option<RequestId>) -> Option<String> {
+        self.render_at(code, message, request_id, Utc::now())
+    }
+
+    /// Render the message for the given error code as of the given server time.
+    pub fn render_at(
+        &self,
+        code: &str,
+        message: &str,
+        request_id: Option<RequestId>,
+        server_time: DateTime<Utc>,
+    ) -> Option<String> {
+        let template = self.template(code)?;
+
+        Some(expand(template, |name| match name {
+            "code" => Some(code.to_string()),
+            "message" => Some(message.to_string()),
+            "request_id" => Some(request_id.map(|r| r.to_string()).unwrap_or_default()),
+            "server_time" => Some(server_time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            _ => None,
+        }))
+    }
+}
+
+/// Expand `{name}` placeholders in a template using the given lookup function.
+fn expand<F: Fn(&str) -> Option<String>>(template: &str, lookup: F) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(pos) = rest.find(['{', '}']) {
+        result.push_str(&rest[..pos]);
+        let c = rest.as_bytes()[pos];
+        rest = &rest[pos + 1..];
+
+        if rest.as_bytes().first() == Some(&c) {
+            // Escaped brace.
+            result.push(c as char);
+            rest = &rest[1..];
+        } else if c == b'{' {
+            match rest.find('}').and_then(|end| Some((end, lookup(&rest[..end])?))) {
+                Some((end, value)) => {
+                    result.push_str(&value);
+                    rest = &rest[end + 1..];
+                }
+                None => result.push('{'),
+            }
+        } else {
+            result.push('}');
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::MessageCatalog,
+        crate::RequestId,
+        chrono::{TimeZone, Utc},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_render() {
+        let catalog = MessageCatalog::new()
+            .with_template("SignatureDoesNotMatch", "Bad signature ({code}) at {server_time}; ref {request_id}")
+            .with_template("InvalidClientTokenId", "{{{message}}} {unknown}");
+        let now = Utc.with_ymd_and_hms(2022, 10, 1, 12, 30, 0).unwrap();
+        let request_id = RequestId::from_timestamp_and_random(0, 1);
+
+        assert_eq!(
+            catalog.render_at("SignatureDoesNotMatch", "ignored", Some(request_id), now).unwrap(),
+            format!("Bad signature (SignatureDoesNotMatch) at 2022-10-01T12:30:00Z; ref {request_id}")
+        );
+        assert_eq!(catalog.render_at("InvalidClientTokenId", "no key", None, now).unwrap(), "{no key} {unknown}");
+        assert_eq!(catalog.render_at("ExpiredToken", "expired", None, now), None);
+    }
+}
@@ -0,0 +1,216 @@
+use {
+    crate::VerifierError,
+    http::{header::HeaderMap, request::Parts},
+    md5::Md5,
+    sha1::Sha1,
+    sha2::{Digest, Sha256},
+};
+
+/// The `Content-MD5` header.
+const CONTENT_MD5: &str = "content-md5";
+
+/// The header listing the checksums sent as trailers of an `aws-chunked` body.
+const X_AMZ_TRAILER: &str = "x-amz-trailer";
+
+/// An algorithm that may be used in an `x-amz-checksum-*` header.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ChecksumAlgorithm {
+    /// CRC-32, as used by Ethernet and zlib.
+    Crc32,
+
+    /// CRC-32C (Castagnoli).
+    Crc32c,
+
+    /// SHA-1.
+    Sha1,
+
+    /// SHA-256.
+    Sha256,
+}
+
+impl ChecksumAlgorithm {
+    /// All supported algorithms, in the order their headers are checked.
+    pub const ALL: [Self; 4] = [Self::Crc32, Self::Crc32c, Self::Sha1, Self::Sha256];
+
+    /// Returns the name of the algorithm as used by AWS, e.g. `CRC32`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Crc32 => "CRC32",
+            Self::Crc32c => "CRC32C",
+            Self::Sha1 => "SHA1",
+            Self::Sha256 => "SHA256",
+        }
+    }
+
+    /// Returns the name of the header carrying a checksum computed with this algorithm.
+    pub fn header_name(&self) -> &'static str {
+        match self {
+            Self::Crc32 => "x-amz-checksum-crc32",
+            Self::Crc32c => "x-amz-checksum-crc32c",
+            Self::Sha1 => "x-amz-checksum-sha1",
+            Self::Sha256 => "x-amz-checksum-sha256",
+        }
+    }
+
+    /// Compute the checksum of `data`.
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Crc32 => crc32fast::hash(data).to_be_bytes().to_vec(),
+            Self::Crc32c => crc32c::crc32c(data).to_be_bytes().to_vec(),
+            Self::Sha1 => Sha1::digest(data).to_vec(),
+            Self::Sha256 => Sha256::digest(data).to_vec(),
+        }
+    }
+}
+
+/// A payload checksum that matched the request body, inserted into the request extensions when `verify_checksums` is
+/// set on the verifier.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VerifiedChecksum {
+    algorithm: ChecksumAlgorithm,
+    value: String,
+}
+
+impl VerifiedChecksum {
+    /// Returns the algorithm used to compute the checksum.
+    #[inline]
+    pub fn algorithm(&self) -> ChecksumAlgorithm {
+        self.algorithm
+    }
+
+    /// Returns the base64-encoded checksum, as supplied by the client.
+    #[inline]
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+/// Verify the first `x-amz-checksum-*` header of a request against its body. Returns `None` if the request carries no
+/// checksum header.
+///
+/// Checksums sent as trailers of an `aws-chunked` body (announced by `x-amz-trailer`) are not verified.
+pub(crate) fn verify_checksum(headers: &HeaderMap, body: &[u8]) -> Result<Option<VerifiedChecksum>, VerifierError> {
+    for algorithm in ChecksumAlgorithm::ALL {
+        let Some(value) = headers.get(algorithm.header_name()) else {
+            continue;
+        };
+
+        let value = value.to_str().map_err(|_| VerifierError::InvalidDigest(algorithm.header_name()))?;
+        let expected = base64::decode(value).map_err(|_| VerifierError::InvalidDigest(algorithm.header_name()))?;
+        if expected != algorithm.digest(body) {
+            return Err(VerifierError::BadDigest(algorithm.name()));
+        }
+
+        return Ok(Some(VerifiedChecksum {
+            algorithm,
+            value: value.to_string(),
+        }));
+    }
+
+    if headers.contains_key(X_AMZ_TRAILER) {
+        log::debug!("Trailing checksums are not verified");
+    }
+
+    Ok(None)
+}
+
+/// Verify the `Content-MD5` header of a request, if present, against its body.
+pub(crate) fn verify_content_md5(headers: &HeaderMap, body: &[u8]) -> Result<(), VerifierError> {
+    let Some(value) = headers.get(CONTENT_MD5) else {
+        return Ok(());
+    };
+
+    let expected = base64::decode(value.as_bytes()).map_err(|_| VerifierError::InvalidDigest("Content-MD5"))?;
+    if expected.len() != 16 {
+        return Err(VerifierError::InvalidDigest("Content-MD5"));
+    }
+
+    if expected[..] != Md5::digest(body)[..] {
+        return Err(VerifierError::BadDigest("Content-MD5"));
+    }
+
+    Ok(())
+}
+
+/// The payload checks performed on authenticated requests with buffered bodies.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct PayloadChecks {
+    /// Whether `x-amz-checksum-*` headers are verified.
+    pub(crate) checksums: bool,
+
+    /// Whether the `Content-MD5` header is verified.
+    pub(crate) content_md5: bool,
+}
+
+/// Verify the payload checksums of an authenticated request with a buffered body, recording the verified checksum in
+/// its extensions.
+pub(crate) fn verify_payload(mut parts: Parts, body: &[u8], checks: PayloadChecks) -> Result<Parts, VerifierError> {
+    if checks.content_md5 {
+        verify_content_md5(&parts.headers, body)?;
+    }
+
+    if checks.checksums {
+        if let Some(checksum) = verify_checksum(&parts.headers, body)? {
+            parts.extensions.insert(checksum);
+        }
+    }
+
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{verify_checksum, verify_content_md5, ChecksumAlgorithm},
+        crate::VerifierError,
+        http::header::HeaderMap,
+    };
+
+    #[test]
+    fn test_verify_checksum() {
+        let body = b"Hello, world!";
+        let checksum = |algorithm: ChecksumAlgorithm, value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(algorithm.header_name(), value.parse().unwrap());
+            headers
+        };
+
+        assert_eq!(verify_checksum(&HeaderMap::new(), body).unwrap(), None);
+
+        for algorithm in ChecksumAlgorithm::ALL {
+            let value = base64::encode(algorithm.digest(body));
+            let verified = verify_checksum(&checksum(algorithm, &value), body).unwrap().unwrap();
+            assert_eq!(verified.algorithm(), algorithm);
+            assert_eq!(verified.value(), value);
+
+            let e = verify_checksum(&checksum(algorithm, &value), b"Goodbye, world!").unwrap_err();
+            assert!(matches!(e, VerifierError::BadDigest(_)));
+        }
+
+        // CRC-32 of "Hello, world!" is 0xebe6c6e6.
+        assert!(verify_checksum(&checksum(ChecksumAlgorithm::Crc32, "6+bG5g=="), body).is_ok());
+
+        let e = verify_checksum(&checksum(ChecksumAlgorithm::Sha256, "not base64!"), body).unwrap_err();
+        assert!(matches!(e, VerifierError::InvalidDigest("x-amz-checksum-sha256")));
+    }
+
+    #[test]
+    fn test_verify_content_md5() {
+        let body = b"Hello, world!";
+        let content_md5 = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("content-md5", value.parse().unwrap());
+            headers
+        };
+
+        assert!(verify_content_md5(&HeaderMap::new(), body).is_ok());
+        assert!(verify_content_md5(&content_md5("bNNVbesNpUvKBgtMOUeYOQ=="), body).is_ok());
+
+        let e = verify_content_md5(&content_md5("bNNVbesNpUvKBgtMOUeYOQ=="), b"Goodbye, world!").unwrap_err();
+        assert!(matches!(e, VerifierError::BadDigest("Content-MD5")));
+        assert_eq!(e.to_string(), "The Content-MD5 you specified did not match the calculated checksum.");
+
+        let e = verify_content_md5(&content_md5("6+bG5g=="), body).unwrap_err();
+        assert!(matches!(e, VerifierError::InvalidDigest("Content-MD5")));
+    }
+}
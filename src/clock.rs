@@ -0,0 +1,46 @@
+use {
+    chrono::{DateTime, Utc},
+    std::fmt::Debug,
+};
+
+/// A source of the current time used when validating request timestamps.
+///
+/// The default, [SystemClock], reads the system clock. Tests can substitute a [FixedClock] to make signature
+/// validation deterministic.
+pub trait Clock: Debug + Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// A [Clock] that reads the system clock.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A [Clock] that always returns the same time.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FixedClock {
+    now: DateTime<Utc>,
+}
+
+impl FixedClock {
+    /// Create a new [FixedClock] that always returns the given time.
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now,
+        }
+    }
+}
+
+impl Clock for FixedClock {
+    #[inline]
+    fn now(&self) -> DateTime<Utc> {
+        self.now
+    }
+}
@@ -0,0 +1,69 @@
+/// Indicates whether a `Content-Type` value is matched by one of the allowed content type patterns.
+///
+/// Patterns may be an exact media type (`application/json`), a type wildcard (`application/*` or `*/*`), or a
+/// structured syntax suffix wildcard (`*+json`). Matching is case-insensitive. If the pattern or value includes
+/// parameters (e.g. `; charset=utf-8`), they must match exactly, ignoring whitespace around `;` and `=`.
+pub(crate) fn content_type_allowed(patterns: &[String], content_type: &str) -> bool {
+    let (media_type, parameters) = split_content_type(content_type);
+    patterns.iter().any(|pattern| {
+        let (pattern_media_type, pattern_parameters) = split_content_type(pattern);
+        media_type_matches(&pattern_media_type, &media_type) && pattern_parameters == parameters
+    })
+}
+
+/// Split a content type into its lowercase media type and normalized parameters.
+fn split_content_type(content_type: &str) -> (String, Vec<String>) {
+    let mut parts = content_type.split(';');
+    let media_type = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+    let parameters = parts
+        .map(|p| match p.split_once('=') {
+            Some((name, value)) => {
+                format!("{}={}", name.trim().to_ascii_lowercase(), value.trim().to_ascii_lowercase())
+            }
+            None => p.trim().to_ascii_lowercase(),
+        })
+        .filter(|p| !p.is_empty())
+        .collect();
+    (media_type, parameters)
+}
+
+fn media_type_matches(pattern: &str, media_type: &str) -> bool {
+    if pattern == "*/*" || pattern == media_type {
+        return true;
+    }
+
+    if let Some(suffix) = pattern.strip_prefix("*+") {
+        return matches!(media_type.rsplit_once('+'), Some((_, s)) if s == suffix);
+    }
+
+    if let Some(top_level) = pattern.strip_suffix("/*") {
+        return matches!(media_type.split_once('/'), Some((t, _)) if t == top_level);
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::content_type_allowed;
+
+    #[test]
+    fn test_content_type_allowed() {
+        let patterns = |p: &[&str]| -> Vec<String> { p.iter().map(|s| s.to_string()).collect() };
+
+        assert!(content_type_allowed(&patterns(&["application/json"]), "application/json"));
+        assert!(content_type_allowed(&patterns(&["application/json"]), "Application/JSON"));
+        assert!(!content_type_allowed(&patterns(&[]), "application/json"));
+        assert!(content_type_allowed(&patterns(&["application/*"]), "application/x-amz-json-1.1"));
+        assert!(!content_type_allowed(&patterns(&["application/*"]), "text/plain"));
+        assert!(content_type_allowed(&patterns(&["*+json"]), "application/vnd.api+json"));
+        assert!(!content_type_allowed(&patterns(&["*+json"]), "application/json"));
+        assert!(content_type_allowed(&patterns(&["*/*"]), "text/plain"));
+
+        // Parameters must match when present.
+        let with_charset = patterns(&["application/json; charset=utf-8"]);
+        assert!(content_type_allowed(&with_charset, "application/json;charset=UTF-8"));
+        assert!(!content_type_allowed(&with_charset, "application/json"));
+        assert!(!content_type_allowed(&patterns(&["application/json"]), "application/json; charset=utf-8"));
+    }
+}
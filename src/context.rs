@@ -0,0 +1,195 @@
+use {
+    crate::{
+        session_keys::{
+            SessionDataExt, CURRENT_TIME, EPOCH_TIME, REQUESTED_REGION, SECURE_TRANSPORT, SOURCE_IP, USER_AGENT,
+        },
+        ConnectInfo,
+    },
+    chrono::{DateTime, Utc},
+    http::{header, request::Parts, uri::Scheme},
+    scratchstack_aws_principal::SessionData,
+    std::net::IpAddr,
+};
+
+/// The condition keys a request is evaluated against by a policy evaluator, e.g. `aws:SourceIp` and
+/// `aws:CurrentTime`, along with the principal's keys (including `aws:PrincipalTag/*`) from its [SessionData].
+///
+/// The keys are held in a [SessionData], which is what Aspen's evaluation context consumes.
+#[derive(Clone, Debug)]
+pub struct RequestContext {
+    session_data: SessionData,
+}
+
+impl RequestContext {
+    /// Create a new [RequestContextBuilder] for constructing a [RequestContext].
+    #[inline]
+    pub fn builder() -> RequestContextBuilder {
+        RequestContextBuilder::default()
+    }
+
+    /// Create a [RequestContext] for an authenticated request, taking the principal's keys from its [SessionData]
+    /// extension, the source address from its [ConnectInfo] extension, and the user agent from its headers. The
+    /// request is considered to use secure transport if its URI has the `https` scheme.
+    pub fn from_request(parts: &Parts, now: DateTime<Utc>) -> Self {
+        let mut builder = Self::builder().current_time(now);
+
+        if let Some(session_data) = parts.extensions.get::<SessionData>() {
+            builder = builder.session_data(session_data.clone());
+        }
+        if let Some(connect_info) = parts.extensions.get::<ConnectInfo>() {
+            builder = builder.source_ip(connect_info.client_ip());
+        }
+        if let Some(user_agent) = parts.headers.get(header::USER_AGENT) {
+            builder = builder.user_agent(String::from_utf8_lossy(user_agent.as_bytes()));
+        }
+
+        builder.secure_transport(parts.uri.scheme() == Some(&Scheme::HTTPS)).build()
+    }
+
+    /// Retreive the condition keys.
+    #[inline]
+    pub fn session_data(&self) -> &SessionData {
+        &self.session_data
+    }
+
+    /// Returns the condition keys, consuming the context.
+    #[inline]
+    pub fn into_session_data(self) -> SessionData {
+        self.session_data
+    }
+}
+
+impl From<RequestContext> for SessionData {
+    fn from(context: RequestContext) -> Self {
+        context.session_data
+    }
+}
+
+/// A builder for [RequestContext]. Keys set later override keys from [RequestContextBuilder::session_data].
+#[derive(Clone, Debug)]
+pub struct RequestContextBuilder {
+    session_data: SessionData,
+    source_ip: Option<IpAddr>,
+    secure_transport: Option<bool>,
+    current_time: Option<DateTime<Utc>>,
+    user_agent: Option<String>,
+    requested_region: Option<String>,
+}
+
+impl Default for RequestContextBuilder {
+    fn default() -> Self {
+        Self {
+            session_data: SessionData::new(),
+            source_ip: None,
+            secure_transport: None,
+            current_time: None,
+            user_agent: None,
+            requested_region: None,
+        }
+    }
+}
+
+impl RequestContextBuilder {
+    /// Start from the principal's session data, e.g. as returned by the signing key provider, which carries keys such
+    /// as `aws:PrincipalArn` and `aws:PrincipalTag/*`.
+    pub fn session_data(mut self, session_data: SessionData) -> Self {
+        self.session_data = session_data;
+        self
+    }
+
+    /// Set `aws:SourceIp`.
+    pub fn source_ip(mut self, source_ip: IpAddr) -> Self {
+        self.source_ip = Some(source_ip);
+        self
+    }
+
+    /// Set `aws:SecureTransport`.
+    pub fn secure_transport(mut self, secure_transport: bool) -> Self {
+        self.secure_transport = Some(secure_transport);
+        self
+    }
+
+    /// Set `aws:CurrentTime` and `aws:EpochTime`.
+    pub fn current_time(mut self, current_time: DateTime<Utc>) -> Self {
+        self.current_time = Some(current_time);
+        self
+    }
+
+    /// Set `aws:UserAgent`.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Set `aws:RequestedRegion`.
+    pub fn requested_region(mut self, requested_region: impl Into<String>) -> Self {
+        self.requested_region = Some(requested_region.into());
+        self
+    }
+
+    /// Build the [RequestContext]. `aws:CurrentTime` defaults to the current time if it was not set.
+    pub fn build(self) -> RequestContext {
+        let mut session_data = self.session_data;
+        let current_time = self.current_time.unwrap_or_else(Utc::now);
+
+        session_data.set_timestamp(CURRENT_TIME, current_time);
+        session_data.set_integer(EPOCH_TIME, current_time.timestamp());
+        if let Some(source_ip) = self.source_ip {
+            session_data.set_ip_addr(SOURCE_IP, source_ip);
+        }
+        if let Some(secure_transport) = self.secure_transport {
+            session_data.set_bool(SECURE_TRANSPORT, secure_transport);
+        }
+        if let Some(user_agent) = self.user_agent {
+            session_data.set_string(USER_AGENT, user_agent);
+        }
+        if let Some(requested_region) = self.requested_region {
+            session_data.set_string(REQUESTED_REGION, requested_region);
+        }
+
+        RequestContext {
+            session_data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::RequestContext,
+        crate::{
+            session_keys::{
+                principal_tag_key, SessionDataExt, CURRENT_TIME, EPOCH_TIME, SECURE_TRANSPORT, SOURCE_IP, USER_AGENT,
+            },
+            ConnectInfo,
+        },
+        chrono::{TimeZone, Utc},
+        http::Request,
+        scratchstack_aws_principal::SessionData,
+        std::net::{IpAddr, Ipv4Addr, SocketAddr},
+    };
+
+    #[test]
+    fn test_from_request() {
+        let mut session_data = SessionData::new();
+        session_data.set_string(&principal_tag_key("team"), "storage");
+        let now = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+
+        let mut req = Request::get("https://example.com/").header("user-agent", "aws-cli/2.0").body(()).unwrap();
+        req.extensions_mut().insert(session_data);
+        req.extensions_mut().insert(ConnectInfo::new(SocketAddr::from(([192, 0, 2, 1], 40000)), None));
+        let (parts, _) = req.into_parts();
+
+        let context = RequestContext::from_request(&parts, now).into_session_data();
+        assert_eq!(context.principal_tag("team"), Some("storage"));
+        assert_eq!(context.get_ip_addr(SOURCE_IP), Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))));
+        assert_eq!(context.get_bool(SECURE_TRANSPORT), Some(true));
+        assert_eq!(context.get_timestamp(CURRENT_TIME), Some(now));
+        assert_eq!(context.get_integer(EPOCH_TIME), Some(now.timestamp()));
+        assert_eq!(context.get_string(USER_AGENT), Some("aws-cli/2.0"));
+
+        let context = RequestContext::builder().secure_transport(false).requested_region("us-west-2").build();
+        assert_eq!(context.session_data().get_bool(SECURE_TRANSPORT), Some(false));
+        assert_eq!(context.session_data().requested_region(), Some("us-west-2"));
+    }
+}
@@ -0,0 +1,584 @@
+use {
+    crate::{ErrorMapper, RequestId, VerifierError},
+    http::{
+        header::{
+            HeaderMap, HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+            ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+            ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+        },
+        method::Method,
+        status::StatusCode,
+    },
+    hyper::{Body, Request, Response},
+    log::trace,
+    serde::Deserialize,
+    std::{
+        error::Error,
+        fmt::{Display, Formatter, Result as FmtResult},
+        future::Future,
+        mem::replace,
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+    },
+    tower::{BoxError, Layer, Service},
+};
+
+/// The headers [CorsService] always exposes to browsers: the request id headers set by the error mappers and the
+/// verifier.
+pub const CORS_EXPOSE_HEADERS: &[&str] = &["x-amzn-RequestId", "x-amz-request-id", "x-amz-id-2"];
+
+/// The methods S3 allows in a `CORSRule`.
+const S3_CORS_METHODS: [Method; 5] = [Method::GET, Method::PUT, Method::HEAD, Method::POST, Method::DELETE];
+
+/// A rule allowing cross-origin requests, modelled on the S3 `CORSRule`.
+///
+/// Origins and headers may contain a single `*` wildcard, e.g. `https://*.example.com`; `*` alone matches everything.
+/// Header names are matched case-insensitively.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CorsRule {
+    allowed_origins: Vec<String>,
+    allowed_methods: Vec<Method>,
+    allowed_headers: Vec<String>,
+    expose_headers: Vec<String>,
+    max_age: Option<u32>,
+}
+
+impl CorsRule {
+    /// Create a new [CorsRule] that allows nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow requests from origins matching the given pattern, returning the updated rule.
+    pub fn with_allowed_origin(mut self, origin: impl Into<String>) -> Self {
+        self.allowed_origins.push(origin.into());
+        self
+    }
+
+    /// Allow the given request method, returning the updated rule.
+    pub fn with_allowed_method(mut self, method: Method) -> Self {
+        self.allowed_methods.push(method);
+        self
+    }
+
+    /// Allow request headers matching the given pattern, returning the updated rule.
+    pub fn with_allowed_header(mut self, header: impl Into<String>) -> Self {
+        self.allowed_headers.push(header.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Expose the given response header to browsers, returning the updated rule.
+    pub fn with_expose_header(mut self, header: impl Into<String>) -> Self {
+        self.expose_headers.push(header.into());
+        self
+    }
+
+    /// Set the time, in seconds, browsers may cache the preflight response, returning the updated rule.
+    pub fn with_max_age(mut self, max_age: u32) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Retreive the allowed origin patterns.
+    #[inline]
+    pub fn allowed_origins(&self) -> &[String] {
+        &self.allowed_origins
+    }
+
+    /// Retreive the allowed request methods.
+    #[inline]
+    pub fn allowed_methods(&self) -> &[Method] {
+        &self.allowed_methods
+    }
+
+    /// Retreive the allowed request header patterns.
+    #[inline]
+    pub fn allowed_headers(&self) -> &[String] {
+        &self.allowed_headers
+    }
+
+    /// Retreive the response headers exposed to browsers.
+    #[inline]
+    pub fn expose_headers(&self) -> &[String] {
+        &self.expose_headers
+    }
+
+    /// Retreive the time, in seconds, browsers may cache the preflight response.
+    #[inline]
+    pub fn max_age(&self) -> Option<u32> {
+        self.max_age
+    }
+
+    /// Indicates whether the rule allows a request from `origin` using `method` and sending `headers`.
+    pub fn allows(&self, origin: &str, method: &Method, headers: &[String]) -> bool {
+        self.allowed_origins.iter().any(|pattern| wildcard_matches(pattern, origin))
+            && self.allowed_methods.contains(method)
+            && headers.iter().all(|header| {
+                let header = header.to_ascii_lowercase();
+                self.allowed_headers.iter().any(|pattern| wildcard_matches(pattern, &header))
+            })
+    }
+
+    /// Indicates whether any of the rule's origin patterns is `*`.
+    fn any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|pattern| pattern == "*")
+    }
+}
+
+/// A set of [CorsRule]s. The first rule allowing a request applies to it.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CorsConfiguration {
+    rules: Vec<CorsRule>,
+}
+
+impl CorsConfiguration {
+    /// Create a new, empty [CorsConfiguration] that allows nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse an S3 `CORSConfiguration` document, as accepted by `PutBucketCors`:
+    ///
+    /// ```xml
+    /// <CORSConfiguration>
+    ///   <CORSRule>
+    ///     <AllowedOrigin>https://www.example.com</AllowedOrigin>
+    ///     <AllowedMethod>PUT</AllowedMethod>
+    ///     <AllowedHeader>*</AllowedHeader>
+    ///     <ExposeHeader>ETag</ExposeHeader>
+    ///     <MaxAgeSeconds>3000</MaxAgeSeconds>
+    ///   </CORSRule>
+    /// </CORSConfiguration>
+    /// ```
+    ///
+    /// As with S3, each rule must have at least one `AllowedOrigin` and `AllowedMethod`, and methods are limited to
+    /// `GET`, `PUT`, `HEAD`, `POST`, and `DELETE`. `ID` elements are accepted and ignored.
+    pub fn from_s3_xml(xml: &str) -> Result<Self, InvalidCorsConfiguration> {
+        let document: XmlCorsConfiguration =
+            quick_xml::de::from_str(xml).map_err(|e| InvalidCorsConfiguration(e.to_string()))?;
+        let mut result = Self::new();
+
+        for xml_rule in document.rules {
+            if xml_rule.allowed_origins.is_empty() {
+                return Err(InvalidCorsConfiguration("CORSRule is missing AllowedOrigin".to_string()));
+            }
+            if xml_rule.allowed_methods.is_empty() {
+                return Err(InvalidCorsConfiguration("CORSRule is missing AllowedMethod".to_string()));
+            }
+
+            let mut rule = CorsRule::new();
+            for origin in xml_rule.allowed_origins {
+                rule = rule.with_allowed_origin(origin);
+            }
+            for method in xml_rule.allowed_methods {
+                match S3_CORS_METHODS.iter().find(|m| m.as_str() == method) {
+                    Some(method) => rule = rule.with_allowed_method(method.clone()),
+                    None => {
+                        return Err(InvalidCorsConfiguration(format!(
+                            "Found unsupported HTTP method in CORS config. Unsupported method is {method}"
+                        )))
+                    }
+                }
+            }
+            for header in xml_rule.allowed_headers {
+                rule = rule.with_allowed_header(header);
+            }
+            for header in xml_rule.expose_headers {
+                rule = rule.with_expose_header(header);
+            }
+            rule.max_age = xml_rule.max_age;
+            result = result.with_rule(rule);
+        }
+
+        Ok(result)
+    }
+
+    /// Add a rule, returning the updated configuration.
+    pub fn with_rule(mut self, rule: CorsRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Retreive the rules.
+    #[inline]
+    pub fn rules(&self) -> &[CorsRule] {
+        &self.rules
+    }
+
+    /// Returns the first rule allowing a request from `origin` using `method` and sending `headers`.
+    pub fn find_rule(&self, origin: &str, method: &Method, headers: &[String]) -> Option<&CorsRule> {
+        self.rules.iter().find(|rule| rule.allows(origin, method, headers))
+    }
+
+    /// Answer a CORS preflight request. Returns `None` if the request is not a preflight request, or a preflight
+    /// request that no rule allows.
+    pub fn preflight_response(&self, method: &Method, headers: &HeaderMap) -> Option<Response<Body>> {
+        let preflight = Preflight::from_request(method, headers)?;
+        let rule = self.find_rule(&preflight.origin, &preflight.method, &preflight.headers)?;
+
+        let mut builder = Response::builder()
+            .status(StatusCode::OK)
+            .header(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin(rule, &preflight.origin)?)
+            .header(ACCESS_CONTROL_ALLOW_METHODS, preflight.method.as_str())
+            .header(VARY, "Origin, Access-Control-Request-Headers, Access-Control-Request-Method");
+        if !preflight.headers.is_empty() {
+            builder = builder.header(ACCESS_CONTROL_ALLOW_HEADERS, preflight.headers.join(", "));
+        }
+        if let Some(max_age) = rule.max_age {
+            builder = builder.header(ACCESS_CONTROL_MAX_AGE, max_age);
+        }
+
+        builder.body(Body::empty()).ok()
+    }
+}
+
+/// The error returned when an S3 `CORSConfiguration` document can't be parsed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvalidCorsConfiguration(String);
+
+impl Display for InvalidCorsConfiguration {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "Invalid CORS configuration: {}", self.0)
+    }
+}
+
+impl Error for InvalidCorsConfiguration {}
+
+/// The S3 `CORSConfiguration` document.
+#[derive(Debug, Deserialize)]
+struct XmlCorsConfiguration {
+    #[serde(rename = "CORSRule", default)]
+    rules: Vec<XmlCorsRule>,
+}
+
+/// A `CORSRule` element of an S3 `CORSConfiguration` document.
+#[derive(Debug, Deserialize)]
+struct XmlCorsRule {
+    #[serde(rename = "AllowedOrigin", default)]
+    allowed_origins: Vec<String>,
+
+    #[serde(rename = "AllowedMethod", default)]
+    allowed_methods: Vec<String>,
+
+    #[serde(rename = "AllowedHeader", default)]
+    allowed_headers: Vec<String>,
+
+    #[serde(rename = "ExposeHeader", default)]
+    expose_headers: Vec<String>,
+
+    #[serde(rename = "MaxAgeSeconds", default)]
+    max_age: Option<u32>,
+}
+
+/// A Tower [Layer] that wraps a service in a [CorsService].
+#[derive(Clone, Debug)]
+pub struct CorsLayer<E> {
+    config: Arc<CorsConfiguration>,
+    error_mapper: E,
+}
+
+impl<E: ErrorMapper> CorsLayer<E> {
+    /// Create a new [CorsLayer] applying the given rules. Preflights no rule allows are rendered by the error mapper.
+    pub fn new(config: CorsConfiguration, error_mapper: E) -> Self {
+        Self {
+            config: Arc::new(config),
+            error_mapper,
+        }
+    }
+}
+
+impl<S, E: ErrorMapper> Layer<S> for CorsLayer<E> {
+    type Service = CorsService<S, E>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CorsService {
+            inner,
+            config: self.config.clone(),
+            error_mapper: self.error_mapper.clone(),
+        }
+    }
+}
+
+/// A service that handles CORS before passing requests to the wrapped service.
+///
+/// The wrapped service is normally an [AwsSigV4VerifierService][crate::AwsSigV4VerifierService]: browsers never sign
+/// preflight requests, so they are answered here without reaching the verifier. Preflights no rule allows are rejected
+/// with `AccessForbidden`. Other requests with an `Origin` header are passed on, and the response is annotated with the
+/// `Access-Control-Allow-Origin` and `Access-Control-Expose-Headers` headers of the matching rule (plus
+/// [CORS_EXPOSE_HEADERS]).
+#[derive(Clone, Debug)]
+pub struct CorsService<S, E> {
+    inner: S,
+    config: Arc<CorsConfiguration>,
+    error_mapper: E,
+}
+
+impl<S, E: ErrorMapper> CorsService<S, E> {
+    /// Create a new [CorsService] applying the given rules.
+    pub fn new(inner: S, config: CorsConfiguration, error_mapper: E) -> Self {
+        CorsLayer::new(config, error_mapper).layer(inner)
+    }
+
+    /// Retreive the CORS rules.
+    #[inline]
+    pub fn config(&self) -> &CorsConfiguration {
+        &self.config
+    }
+}
+
+impl<S, E, B> Service<Request<B>> for CorsService<S, E>
+where
+    S: Service<Request<B>, Response = Response<Body>, Error = BoxError> + Clone + Send + 'static,
+    S::Future: Send,
+    E: ErrorMapper,
+    B: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, c: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(c)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        // The clone may not be ready, so use the instance that was polled and leave the clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = replace(&mut self.inner, clone);
+        let config = self.config.clone();
+        let error_mapper = self.error_mapper.clone();
+
+        Box::pin(async move {
+            if Preflight::from_request(req.method(), req.headers()).is_some() {
+                return match config.preflight_response(req.method(), req.headers()) {
+                    Some(response) => Ok(response),
+                    None => {
+                        trace!("CORS preflight not allowed: {:?}", req.headers().get(ORIGIN));
+                        let request_id = req.extensions().get::<RequestId>().copied();
+                        error_mapper.map_error(VerifierError::CorsForbidden.into(), request_id).await
+                    }
+                };
+            }
+
+            let origin = req.headers().get(ORIGIN).and_then(|origin| origin.to_str().ok()).map(str::to_string);
+            let method = req.method().clone();
+            let mut response = inner.call(req).await?;
+
+            if let Some(origin) = origin {
+                if let Some(rule) = config.find_rule(&origin, &method, &[]) {
+                    let headers = response.headers_mut();
+                    if let Some(allow_origin) = allow_origin(rule, &origin) {
+                        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+                    }
+
+                    let expose: Vec<&str> = rule
+                        .expose_headers
+                        .iter()
+                        .map(String::as_str)
+                        .chain(CORS_EXPOSE_HEADERS.iter().copied())
+                        .collect();
+                    if let Ok(expose) = HeaderValue::from_str(&expose.join(", ")) {
+                        headers.insert(ACCESS_CONTROL_EXPOSE_HEADERS, expose);
+                    }
+                    headers.append(VARY, HeaderValue::from_static("Origin"));
+                }
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+/// The parameters of a CORS preflight request.
+pub(crate) struct Preflight {
+    pub(crate) origin: String,
+    pub(crate) method: Method,
+    pub(crate) headers: Vec<String>,
+}
+
+impl Preflight {
+    /// Returns the parameters of a CORS preflight request: an `OPTIONS` request with `Origin` and
+    /// `Access-Control-Request-Method` headers. Returns `None` for other requests.
+    pub(crate) fn from_request(method: &Method, headers: &HeaderMap) -> Option<Self> {
+        if method != Method::OPTIONS {
+            return None;
+        }
+
+        let origin = headers.get(ORIGIN)?.to_str().ok()?.to_string();
+        let method = Method::from_bytes(headers.get(ACCESS_CONTROL_REQUEST_METHOD)?.as_bytes()).ok()?;
+        let headers = headers
+            .get_all(ACCESS_CONTROL_REQUEST_HEADERS)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|header| header.trim().to_ascii_lowercase())
+            .filter(|header| !header.is_empty())
+            .collect();
+
+        Some(Self {
+            origin,
+            method,
+            headers,
+        })
+    }
+}
+
+/// Returns the `Access-Control-Allow-Origin` value for a request from `origin` allowed by `rule`.
+pub(crate) fn allow_origin(rule: &CorsRule, origin: &str) -> Option<HeaderValue> {
+    if rule.any_origin() {
+        Some(HeaderValue::from_static("*"))
+    } else {
+        HeaderValue::from_str(origin).ok()
+    }
+}
+
+/// Match a value against a pattern containing at most one `*` wildcard.
+fn wildcard_matches(pattern: &str, value: &str) -> bool {
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => {
+            value.len() >= prefix.len() + suffix.len() && value.starts_with(prefix) && value.ends_with(suffix)
+        }
+        None => pattern == value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{CorsConfiguration, CorsRule, CorsService},
+        crate::XmlErrorMapper,
+        http::{header::HeaderMap, method::Method, status::StatusCode},
+        hyper::{Body, Request, Response},
+        tower::{service_fn, BoxError, ServiceExt},
+    };
+
+    #[test]
+    fn test_preflight() {
+        let cors = CorsConfiguration::new().with_rule(
+            CorsRule::new()
+                .with_allowed_origin("https://*.example.com")
+                .with_allowed_method(Method::PUT)
+                .with_allowed_header("Content-Type")
+                .with_allowed_header("x-amz-*")
+                .with_max_age(3000),
+        );
+        let preflight = |origin: &str, method: &str, headers: &str| {
+            let mut map = HeaderMap::new();
+            map.insert("origin", origin.parse().unwrap());
+            map.insert("access-control-request-method", method.parse().unwrap());
+            if !headers.is_empty() {
+                map.insert("access-control-request-headers", headers.parse().unwrap());
+            }
+            map
+        };
+
+        let response = cors
+            .preflight_response(
+                &Method::OPTIONS,
+                &preflight("https://www.example.com", "PUT", "content-type, X-Amz-Date"),
+            )
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["access-control-allow-origin"], "https://www.example.com");
+        assert_eq!(response.headers()["access-control-allow-methods"], "PUT");
+        assert_eq!(response.headers()["access-control-allow-headers"], "content-type, x-amz-date");
+        assert_eq!(response.headers()["access-control-max-age"], "3000");
+
+        assert!(cors.preflight_response(&Method::OPTIONS, &preflight("https://evil.test", "PUT", "")).is_none());
+        assert!(cors
+            .preflight_response(&Method::OPTIONS, &preflight("https://www.example.com", "DELETE", ""))
+            .is_none());
+        assert!(cors
+            .preflight_response(&Method::OPTIONS, &preflight("https://www.example.com", "PUT", "authorization"))
+            .is_none());
+        assert!(cors.preflight_response(&Method::PUT, &preflight("https://www.example.com", "PUT", "")).is_none());
+    }
+
+    #[test]
+    fn test_from_s3_xml() {
+        let cors = CorsConfiguration::from_s3_xml(
+            r#"<CORSConfiguration>
+                <CORSRule>
+                    <ID>uploads</ID>
+                    <AllowedOrigin>https://www.example.com</AllowedOrigin>
+                    <AllowedMethod>PUT</AllowedMethod>
+                    <AllowedMethod>POST</AllowedMethod>
+                    <AllowedHeader>*</AllowedHeader>
+                    <ExposeHeader>ETag</ExposeHeader>
+                    <MaxAgeSeconds>3000</MaxAgeSeconds>
+                </CORSRule>
+                <CORSRule>
+                    <AllowedOrigin>*</AllowedOrigin>
+                    <AllowedMethod>GET</AllowedMethod>
+                </CORSRule>
+            </CORSConfiguration>"#,
+        )
+        .unwrap();
+
+        let rules = cors.rules();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].allowed_origins(), ["https://www.example.com"]);
+        assert_eq!(rules[0].allowed_methods(), [Method::PUT, Method::POST]);
+        assert_eq!(rules[0].allowed_headers(), ["*"]);
+        assert_eq!(rules[0].expose_headers(), ["ETag"]);
+        assert_eq!(rules[0].max_age(), Some(3000));
+        assert_eq!(rules[1].allowed_methods(), [Method::GET]);
+        assert_eq!(rules[1].max_age(), None);
+
+        let e = CorsConfiguration::from_s3_xml(
+            "<CORSConfiguration><CORSRule><AllowedOrigin>*</AllowedOrigin><AllowedMethod>PATCH</AllowedMethod>\
+             </CORSRule></CORSConfiguration>",
+        )
+        .unwrap_err();
+        assert!(e.to_string().contains("Unsupported method is PATCH"));
+
+        assert!(CorsConfiguration::from_s3_xml(
+            "<CORSConfiguration><CORSRule><AllowedMethod>GET</AllowedMethod></CORSRule></CORSConfiguration>"
+        )
+        .is_err());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_cors_service() {
+        let inner = service_fn(|req: Request<Body>| async move {
+            assert_ne!(req.method(), Method::OPTIONS);
+            Ok::<_, BoxError>(Response::new(Body::empty()))
+        });
+        let cors = CorsConfiguration::new().with_rule(
+            CorsRule::new()
+                .with_allowed_origin("https://www.example.com")
+                .with_allowed_method(Method::GET)
+                .with_expose_header("ETag"),
+        );
+        let service = CorsService::new(inner, cors, XmlErrorMapper::new("https://sts.amazonaws.com/doc/2011-06-15/"));
+
+        let preflight = |origin: &str| {
+            Request::options("/")
+                .header("origin", origin)
+                .header("access-control-request-method", "GET")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = service.clone().oneshot(preflight("https://www.example.com")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["access-control-allow-methods"], "GET");
+
+        let response = service.clone().oneshot(preflight("https://evil.test")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let req = Request::get("/").header("origin", "https://www.example.com").body(Body::empty()).unwrap();
+        let response = service.clone().oneshot(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["access-control-allow-origin"], "https://www.example.com");
+        assert_eq!(
+            response.headers()["access-control-expose-headers"],
+            "ETag, x-amzn-RequestId, x-amz-request-id, x-amz-id-2"
+        );
+
+        let req = Request::get("/").body(Body::empty()).unwrap();
+        let response = service.oneshot(req).await.unwrap();
+        assert!(!response.headers().contains_key("access-control-allow-origin"));
+    }
+}
@@ -0,0 +1,159 @@
+#[allow(deprecated)]
+use chrono::Date;
+
+use {
+    chrono::Utc,
+    scratchstack_aws_signature::{KSecretKey, KSigningKey},
+    serde::{Deserialize, Deserializer},
+    std::fmt::{Debug, Formatter, Result as FmtResult},
+    zeroize::Zeroizing,
+};
+
+/// Compares two byte strings in time that depends only on their lengths, not their contents.
+///
+/// Use this instead of `==` when comparing a secret (or a hash of one, such as a session token hash) against a value
+/// supplied by a caller, so the comparison can't be used to guess the secret a byte at a time.
+///
+/// ```
+/// use scratchstack_http_framework::credentials::constant_time_eq;
+///
+/// assert!(constant_time_eq(b"secret", b"secret"));
+/// assert!(!constant_time_eq(b"secret", b"Secret"));
+/// assert!(!constant_time_eq(b"secret", b"secrets"));
+/// ```
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut diff = 0u8;
+    for (x, y) in a.iter().zip(b) {
+        diff |= x ^ y;
+    }
+
+    // Keep the compiler from short-circuiting the loop above.
+    std::hint::black_box(diff) == 0
+}
+
+/// A secret, such as a secret access key or service-specific password, that is zeroed when dropped and never printed.
+///
+/// The [Debug] implementation prints `SecretString(<redacted>)`, so secrets held in structures that derive [Debug]
+/// don't end up in logs; [expose_secret][SecretString::expose_secret] must be called to read the secret. Comparisons
+/// take constant time.
+///
+/// ```
+/// use scratchstack_http_framework::credentials::SecretString;
+///
+/// let secret_key = SecretString::new("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY");
+/// assert_eq!(format!("{:?}", secret_key), "SecretString(<redacted>)");
+/// assert_eq!(secret_key.expose_secret(), "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY");
+/// ```
+#[derive(Clone, Default)]
+pub struct SecretString(Zeroizing<String>);
+
+impl SecretString {
+    /// Create a new [SecretString], taking ownership of the secret.
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(Zeroizing::new(secret.into()))
+    }
+
+    /// Retreive the secret.
+    #[inline]
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+
+    /// Derive the SigV4 signing key for a date, region, and service from this secret access key. See
+    /// [derive_signing_key].
+    #[allow(deprecated)]
+    #[inline]
+    pub fn signing_key(&self, date: Date<Utc>, region: &str, service: &str) -> KSigningKey {
+        derive_signing_key(self.expose_secret(), date, region, service)
+    }
+}
+
+impl Debug for SecretString {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str("SecretString(<redacted>)")
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl PartialEq for SecretString {
+    fn eq(&self, other: &Self) -> bool {
+        constant_time_eq(self.expose_secret().as_bytes(), other.expose_secret().as_bytes())
+    }
+}
+
+impl Eq for SecretString {}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+/// Derive the SigV4 signing key (`kSigning`) for a date, region, and service from a secret access key.
+///
+/// The date is the request date of a [GetSigningKeyRequest][scratchstack_aws_signature::GetSigningKeyRequest]. The
+/// derivation is done by [KSecretKey], the only way `scratchstack-aws-signature` provides to create a [KSigningKey].
+/// [KSecretKey] and the intermediate keys don't zero their memory when dropped, so the providers in this crate keep
+/// their secret keys in [SecretString]s and only pass the secret to [KSecretKey] here, for as long as the derivation
+/// takes.
+#[allow(deprecated)]
+pub fn derive_signing_key(secret_key: &str, date: Date<Utc>, region: &str, service: &str) -> KSigningKey {
+    KSecretKey::from_str(secret_key).to_ksigning(date, region, service)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{constant_time_eq, derive_signing_key, SecretString},
+        chrono::{TimeZone, Utc},
+        pretty_assertions::{assert_eq, assert_ne},
+        scratchstack_aws_signature::KSecretKey,
+    };
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"", b""));
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"ab"));
+    }
+
+    #[test]
+    fn test_secret_string() {
+        let secret = SecretString::from("secret");
+        assert_eq!(format!("{:?}", secret), "SecretString(<redacted>)");
+        assert_eq!(format!("{:?}", Some(secret.clone())), "Some(SecretString(<redacted>))");
+        assert_eq!(secret, SecretString::new("secret".to_string()));
+        assert_ne!(secret, SecretString::default());
+
+        let secret: SecretString = serde_json::from_str(r#""from-json""#).unwrap();
+        assert_eq!(secret.expose_secret(), "from-json");
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_derive_signing_key() {
+        let secret_key = "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+        let date = Utc.ymd(2015, 8, 30);
+        let expected = KSecretKey::from_str(secret_key).to_ksigning(date, "us-east-1", "iam");
+
+        assert_eq!(derive_signing_key(secret_key, date, "us-east-1", "iam").as_ref(), expected.as_ref());
+        assert_eq!(SecretString::new(secret_key).signing_key(date, "us-east-1", "iam").as_ref(), expected.as_ref());
+        assert_ne!(derive_signing_key(secret_key, date, "us-west-2", "iam").as_ref(), expected.as_ref());
+    }
+}
@@ -0,0 +1,176 @@
+use {
+    crate::{
+        canonical::{header_or_query_param, parse_iso8601_basic, X_AMZ_DATE},
+        VerifierError,
+    },
+    chrono::{DateTime, NaiveDateTime, TimeZone, Utc},
+    http::{
+        header::{HeaderMap, DATE},
+        uri::Uri,
+    },
+};
+
+/// The RFC 7231 `IMF-fixdate` format, e.g. `Sun, 30 Aug 2015 12:36:00 GMT`.
+const IMF_FIXDATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Options controlling which request timestamps are accepted.
+///
+/// The defaults accept an `X-Amz-Date` header or query parameter, falling back to the `Date` header in any RFC 2822
+/// form. Stricter settings are useful for conformance testing against AWS. The same options decide which timestamp the
+/// verifier checks against its clock.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DateHeaderOptions {
+    date_fallback: bool,
+    rfc2822_date: bool,
+    strict_iso8601: bool,
+}
+
+impl Default for DateHeaderOptions {
+    fn default() -> Self {
+        Self {
+            date_fallback: true,
+            rfc2822_date: true,
+            strict_iso8601: false,
+        }
+    }
+}
+
+impl DateHeaderOptions {
+    /// Create a new [DateHeaderOptions] with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether header-signed requests without `X-Amz-Date` may be timestamped by the `Date` header. If not, they
+    /// are rejected with `IncompleteSignature`.
+    pub fn with_date_fallback(mut self, date_fallback: bool) -> Self {
+        self.date_fallback = date_fallback;
+        self
+    }
+
+    /// Set whether the `Date` header may use any RFC 2822 form (e.g. a numeric zone or no day of the week). If not, it
+    /// must be an RFC 7231 `IMF-fixdate` such as `Sun, 30 Aug 2015 12:36:00 GMT`.
+    pub fn with_rfc2822_date(mut self, rfc2822_date: bool) -> Self {
+        self.rfc2822_date = rfc2822_date;
+        self
+    }
+
+    /// Set whether `X-Amz-Date` values must be in ISO 8601 basic format (`YYYYMMDDTHHMMSSZ`). If so, other values are
+    /// rejected with the `IncompleteSignature` error AWS returns for them.
+    pub fn with_strict_iso8601(mut self, strict_iso8601: bool) -> Self {
+        self.strict_iso8601 = strict_iso8601;
+        self
+    }
+
+    /// Retreive whether the `Date` header is accepted in place of `X-Amz-Date`.
+    #[inline]
+    pub fn date_fallback(&self) -> bool {
+        self.date_fallback
+    }
+
+    /// Retreive whether the `Date` header may use any RFC 2822 form.
+    #[inline]
+    pub fn rfc2822_date(&self) -> bool {
+        self.rfc2822_date
+    }
+
+    /// Retreive whether `X-Amz-Date` values must be in ISO 8601 basic format.
+    #[inline]
+    pub fn strict_iso8601(&self) -> bool {
+        self.strict_iso8601
+    }
+
+    /// Check the timestamp of a signed request against these options. Missing timestamps are left to the signature
+    /// library to report.
+    pub(crate) fn check(&self, headers: &HeaderMap, uri: &Uri, presigned: bool) -> Result<(), VerifierError> {
+        if let Some(amz_date) = header_or_query_param(headers, uri, X_AMZ_DATE, "X-Amz-Date") {
+            if self.strict_iso8601 && parse_iso8601_basic(&amz_date).is_none() {
+                return Err(VerifierError::InvalidAmzDate(amz_date));
+            }
+
+            return Ok(());
+        }
+
+        // Presigned requests are always timestamped by the X-Amz-Date query parameter.
+        if presigned {
+            return Ok(());
+        }
+
+        let Some(date) = headers.get(DATE) else {
+            return Ok(());
+        };
+
+        if !self.date_fallback {
+            return Err(VerifierError::IncompleteSignature(
+                "Authorization header requires existence of a 'X-Amz-Date' header.",
+            ));
+        }
+
+        let date = String::from_utf8_lossy(date.as_bytes());
+        match self.parse_date(&date) {
+            Some(_) => Ok(()),
+            None => Err(VerifierError::InvalidDateHeader(date.into_owned())),
+        }
+    }
+
+    /// Parse a `Date` header value in a form these options accept.
+    pub(crate) fn parse_date(&self, date: &str) -> Option<DateTime<Utc>> {
+        if self.rfc2822_date {
+            DateTime::parse_from_rfc2822(date).ok().map(|date| date.with_timezone(&Utc))
+        } else {
+            NaiveDateTime::parse_from_str(date, IMF_FIXDATE_FORMAT).ok().map(|date| Utc.from_utc_datetime(&date))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::DateHeaderOptions,
+        crate::VerifierError,
+        http::{header::HeaderMap, uri::Uri},
+    };
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_date_header_options() {
+        let uri = Uri::from_static("/");
+        let amz_date = headers(&[("x-amz-date", "20150830T123600Z")]);
+        let extended_amz_date = headers(&[("x-amz-date", "2015-08-30T12:36:00Z")]);
+        let imf_date = headers(&[("date", "Sun, 30 Aug 2015 12:36:00 GMT")]);
+        let rfc2822_date = headers(&[("date", "30 Aug 2015 12:36:00 +0000")]);
+
+        let options = DateHeaderOptions::default();
+        assert!(options.check(&amz_date, &uri, false).is_ok());
+        assert!(options.check(&extended_amz_date, &uri, false).is_ok());
+        assert!(options.check(&imf_date, &uri, false).is_ok());
+        assert!(options.check(&rfc2822_date, &uri, false).is_ok());
+        assert!(options.check(&HeaderMap::new(), &uri, false).is_ok());
+
+        let options = DateHeaderOptions::new().with_strict_iso8601(true);
+        assert!(options.check(&amz_date, &uri, false).is_ok());
+        let e = options.check(&extended_amz_date, &uri, false).unwrap_err();
+        assert!(matches!(e, VerifierError::InvalidAmzDate(_)));
+        assert_eq!(
+            e.to_string(),
+            "Date must be in ISO-8601 'basic format'. Got '2015-08-30T12:36:00Z'. See http://en.wikipedia.org/wiki/ISO_8601"
+        );
+        let presigned = Uri::from_static("/?X-Amz-Date=2015-08-30T12%3A36%3A00Z");
+        assert!(options.check(&HeaderMap::new(), &presigned, true).is_err());
+
+        let options = DateHeaderOptions::new().with_rfc2822_date(false);
+        assert!(options.check(&imf_date, &uri, false).is_ok());
+        assert!(matches!(options.check(&rfc2822_date, &uri, false), Err(VerifierError::InvalidDateHeader(_))));
+
+        let options = DateHeaderOptions::new().with_date_fallback(false);
+        assert!(options.check(&amz_date, &uri, false).is_ok());
+        assert!(matches!(options.check(&imf_date, &uri, false), Err(VerifierError::IncompleteSignature(_))));
+    }
+}
@@ -0,0 +1,430 @@
+use {
+    crate::{router::OperationError, ErrorFault, MessageCatalog, RequestId, SigningDetails, StandardError},
+    http::{header::HeaderMap, method::Method, status::StatusCode, uri::Uri},
+    scratchstack_aws_signature::SignatureError,
+    scratchstack_errors::ServiceError,
+    std::{
+        error::Error,
+        fmt::{Display, Formatter, Result as FmtResult},
+    },
+    tower::BoxError,
+};
+
+/// Errors raised by the verifier itself, outside of the signature validation process.
+///
+/// Unlike [SignatureError], these variants carry the raw data describing the failure rather than a preformatted
+/// message. The client-facing message is only produced when the error is displayed, typically when an
+/// [ErrorMapper][crate::ErrorMapper] serializes it.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum VerifierError {
+    /// The request method is not one of the allowed request methods.
+    InvalidRequestMethod(Method),
+
+    /// The content type of the request is not one of the allowed content types.
+    InvalidContentType,
+
+    /// The signature parameters of the request are missing or malformed.
+    IncompleteSignature(&'static str),
+
+    /// The query-string signature parameters of a presigned request are invalid, e.g. an `X-Amz-Expires` value outside
+    /// of the accepted range.
+    AuthorizationQueryParametersError(&'static str),
+
+    /// The signature of the request does not match the signature computed by the server.
+    SignatureDoesNotMatch,
+
+    /// The request timestamp is outside of the allowed window.
+    RequestExpired,
+
+    /// The request was signed with an unsigned payload, but the service requires signed payloads.
+    UnsignedPayloadNotAllowed,
+
+    /// The `x-amz-content-sha256` header does not match the hash of the request body.
+    ContentSha256Mismatch,
+
+    /// The service implementation failed while handling the request.
+    InternalFailure,
+
+    /// The request signature has already been seen within the allowed time window.
+    RequestReplayed,
+
+    /// The request body is larger than the maximum size, in bytes, accepted by the service.
+    RequestEntityTooLarge(usize),
+
+    /// The request method is not allowed for the resource. Unlike [InvalidRequestMethod][Self::InvalidRequestMethod],
+    /// this is reported with a 405 status.
+    MethodNotAllowed(Method),
+
+    /// The request was signed with long-term credentials or without a session token, but the service only accepts
+    /// temporary credentials.
+    SessionTokenRequired,
+
+    /// The session token supplied with the request is malformed.
+    InvalidSessionToken,
+
+    /// The client has been denied because of too many recent authentication failures.
+    SourceDenied,
+
+    /// The client has exceeded its request rate.
+    Throttling,
+
+    /// The client has exceeded its request rate. This is the S3 form of [Throttling][Self::Throttling], reported with
+    /// a 503 status.
+    SlowDown,
+
+    /// The `X-Amz-Date` value is not in ISO 8601 basic format. This carries the value supplied by the client.
+    InvalidAmzDate(String),
+
+    /// The `Date` header is not in an accepted format. This carries the value supplied by the client.
+    InvalidDateHeader(String),
+
+    /// A signed header appears more than once in the request. This carries the name of the header.
+    DuplicateSignedHeader(String),
+
+    /// A payload checksum header is malformed. This carries the name of the header.
+    InvalidDigest(&'static str),
+
+    /// A payload checksum does not match the request body. This carries the name of the checksum, e.g. `CRC32`.
+    BadDigest(&'static str),
+
+    /// A CORS preflight request is not allowed by any CORS rule.
+    CorsForbidden,
+
+    /// A request parameter is missing or invalid. This carries a description of the problem.
+    InvalidInput(String),
+
+    /// An AWS Query protocol request does not have an `Action` parameter.
+    MissingAction,
+
+    /// An AWS Query protocol request names an action the service does not implement.
+    InvalidAction {
+        /// The `Action` parameter of the request.
+        action: String,
+
+        /// The `Version` parameter of the request, if the action is implemented but not for this version.
+        version: Option<String>,
+    },
+
+    /// An AWS JSON protocol request names an operation the service does not implement in its `X-Amz-Target` header.
+    UnknownOperation,
+
+    /// The body of an AWS JSON protocol request could not be deserialized. This carries a description of the problem.
+    Serialization(String),
+
+    /// The principal is not authorized to perform the requested action.
+    AccessDenied {
+        /// The ARN of the principal.
+        principal_arn: String,
+
+        /// The IAM action, e.g. `iam:GetUser`, if it was resolved.
+        action: Option<String>,
+
+        /// The ARN of the resource, if it was resolved.
+        resource: Option<String>,
+
+        /// Whether a statement explicitly denied the request.
+        explicit_deny: bool,
+    },
+
+    /// An AWS Query protocol request names an API version the service does not implement. This carries the
+    /// requested version.
+    NoSuchVersion(String),
+}
+
+impl Display for VerifierError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::InvalidRequestMethod(method) => write!(f, "Unsupported request method '{method}'"),
+            Self::InvalidContentType => f.write_str("The content-type of the request is unsupported"),
+            Self::IncompleteSignature(detail) => f.write_str(detail),
+            Self::AuthorizationQueryParametersError(detail) => f.write_str(detail),
+            Self::SignatureDoesNotMatch => f.write_str(
+                "The request signature we calculated does not match the signature you provided. Check your AWS Secret \
+                 Access Key and signing method. Consult the service documentation for details.",
+            ),
+            Self::RequestExpired => f.write_str("Request timestamp is outside of the allowed time window"),
+            Self::UnsignedPayloadNotAllowed => f.write_str("This service does not accept unsigned payloads"),
+            Self::ContentSha256Mismatch => {
+                f.write_str("The provided 'x-amz-content-sha256' header does not match what was computed")
+            }
+            Self::InternalFailure => {
+                f.write_str("The request processing has failed because of an unknown error, exception or failure.")
+            }
+            Self::RequestReplayed => f.write_str("The request signature has already been used"),
+            Self::RequestEntityTooLarge(max) => write!(f, "The request body must be no larger than {max} bytes"),
+            Self::MethodNotAllowed(method) => write!(f, "The method '{method}' is not allowed against this resource"),
+            Self::SessionTokenRequired => f.write_str("This service only accepts temporary security credentials"),
+            Self::InvalidSessionToken => f.write_str("The security token included in the request is invalid"),
+            Self::SourceDenied => f.write_str("Too many failed authentication attempts; try again later"),
+            Self::Throttling => f.write_str("Rate exceeded"),
+            Self::SlowDown => f.write_str("Please reduce your request rate."),
+            Self::InvalidAmzDate(value) => write!(
+                f,
+                "Date must be in ISO-8601 'basic format'. Got '{value}'. See http://en.wikipedia.org/wiki/ISO_8601"
+            ),
+            Self::InvalidDateHeader(value) => write!(f, "Invalid date format in the Date header. Got '{value}'."),
+            Self::DuplicateSignedHeader(name) => write!(f, "The signed header '{name}' must not be repeated"),
+            Self::InvalidDigest(header) => write!(f, "Value for {header} header is invalid."),
+            Self::BadDigest(name) => write!(f, "The {name} you specified did not match the calculated checksum."),
+            Self::CorsForbidden => f.write_str(
+                "CORSResponse: This CORS request is not allowed. This is usually because the evalution of Origin, \
+                 request method / Access-Control-Request-Method or Access-Control-Request-Headers are not whitelisted \
+                 by the resource's CORS spec.",
+            ),
+            Self::InvalidInput(detail) => f.write_str(detail),
+            Self::MissingAction => f.write_str("Missing Action"),
+            Self::UnknownOperation => f.write_str(""),
+            Self::Serialization(detail) => f.write_str(detail),
+            Self::InvalidAction {
+                action,
+                version: Some(version),
+            } => write!(f, "Could not find operation {action} for version {version}"),
+            Self::InvalidAction {
+                action,
+                version: None,
+            } => write!(f, "The action {action} is not valid for this web service."),
+            Self::AccessDenied {
+                principal_arn,
+                action: Some(action),
+                resource,
+                explicit_deny,
+            } => {
+                let resource = resource.as_deref().unwrap_or("*");
+                write!(f, "User: {principal_arn} is not authorized to perform: {action} on resource: {resource}")?;
+                if *explicit_deny {
+                    f.write_str(" with an explicit deny")?;
+                }
+                Ok(())
+            }
+            Self::AccessDenied {
+                principal_arn,
+                action: None,
+                ..
+            } => write!(f, "User: {principal_arn} is not authorized to perform this operation"),
+            Self::NoSuchVersion(version) => {
+                write!(f, "The requested version ({version}) of this service does not exist")
+            }
+        }
+    }
+}
+
+impl Error for VerifierError {}
+
+impl ServiceError for VerifierError {
+    fn error_code(&self) -> &'static str {
+        match self {
+            Self::InvalidRequestMethod(_) => "InvalidRequestMethod",
+            Self::InvalidContentType => "InvalidContentType",
+            Self::IncompleteSignature(_) => "IncompleteSignature",
+            Self::AuthorizationQueryParametersError(_) => "AuthorizationQueryParametersError",
+            Self::SignatureDoesNotMatch => "SignatureDoesNotMatch",
+            Self::RequestExpired => "RequestExpired",
+            Self::UnsignedPayloadNotAllowed => "InvalidRequest",
+            Self::ContentSha256Mismatch => "XAmzContentSHA256Mismatch",
+            Self::InternalFailure => "InternalFailure",
+            Self::RequestReplayed => "RequestReplayed",
+            Self::RequestEntityTooLarge(_) => "RequestEntityTooLarge",
+            Self::MethodNotAllowed(_) => "MethodNotAllowed",
+            Self::SessionTokenRequired => "InvalidClientTokenId",
+            Self::InvalidSessionToken => "InvalidClientTokenId",
+            Self::SourceDenied => "AccessDenied",
+            Self::Throttling => "Throttling",
+            Self::SlowDown => "SlowDown",
+            Self::InvalidAmzDate(_) => "IncompleteSignature",
+            Self::InvalidDateHeader(_) => "IncompleteSignature",
+            Self::DuplicateSignedHeader(_) => "InvalidRequest",
+            Self::InvalidDigest(_) => "InvalidDigest",
+            Self::BadDigest(_) => "BadDigest",
+            Self::CorsForbidden => "AccessForbidden",
+            Self::InvalidInput(_) => "InvalidInput",
+            Self::MissingAction => "MissingAction",
+            Self::UnknownOperation => "UnknownOperationException",
+            Self::Serialization(_) => "SerializationException",
+            Self::InvalidAction {
+                ..
+            } => "InvalidAction",
+            Self::AccessDenied {
+                ..
+            } => "AccessDenied",
+            Self::NoSuchVersion(_) => "NoSuchVersion",
+        }
+    }
+
+    fn http_status(&self) -> StatusCode {
+        match self {
+            Self::InvalidRequestMethod(_) => StatusCode::BAD_REQUEST,
+            Self::InvalidContentType => StatusCode::BAD_REQUEST,
+            Self::IncompleteSignature(_) => StatusCode::BAD_REQUEST,
+            Self::AuthorizationQueryParametersError(_) => StatusCode::BAD_REQUEST,
+            Self::SignatureDoesNotMatch => StatusCode::FORBIDDEN,
+            Self::RequestExpired => StatusCode::BAD_REQUEST,
+            Self::UnsignedPayloadNotAllowed => StatusCode::BAD_REQUEST,
+            Self::ContentSha256Mismatch => StatusCode::BAD_REQUEST,
+            Self::InternalFailure => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::RequestReplayed => StatusCode::FORBIDDEN,
+            Self::RequestEntityTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::MethodNotAllowed(_) => StatusCode::METHOD_NOT_ALLOWED,
+            Self::SessionTokenRequired => StatusCode::FORBIDDEN,
+            Self::InvalidSessionToken => StatusCode::FORBIDDEN,
+            Self::SourceDenied => StatusCode::FORBIDDEN,
+            Self::Throttling => StatusCode::BAD_REQUEST,
+            Self::SlowDown => StatusCode::SERVICE_UNAVAILABLE,
+            Self::InvalidAmzDate(_) => StatusCode::BAD_REQUEST,
+            Self::InvalidDateHeader(_) => StatusCode::BAD_REQUEST,
+            Self::DuplicateSignedHeader(_) => StatusCode::BAD_REQUEST,
+            Self::InvalidDigest(_) => StatusCode::BAD_REQUEST,
+            Self::BadDigest(_) => StatusCode::BAD_REQUEST,
+            Self::CorsForbidden => StatusCode::FORBIDDEN,
+            Self::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            Self::MissingAction => StatusCode::BAD_REQUEST,
+            Self::UnknownOperation => StatusCode::BAD_REQUEST,
+            Self::Serialization(_) => StatusCode::BAD_REQUEST,
+            Self::InvalidAction {
+                ..
+            } => StatusCode::BAD_REQUEST,
+            Self::AccessDenied {
+                ..
+            } => StatusCode::FORBIDDEN,
+            Self::NoSuchVersion(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// Information about the request that failed, passed to
+/// [ErrorMapper::map_error_with_context][crate::ErrorMapper::map_error_with_context].
+#[derive(Clone, Debug)]
+pub struct ErrorContext {
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    region: String,
+    service: String,
+    request_id: Option<RequestId>,
+    signing_details: Option<SigningDetails>,
+}
+
+impl ErrorContext {
+    /// Create a new [ErrorContext] from the request head and the region and service the request was verified against.
+    pub fn new(
+        method: Method,
+        uri: Uri,
+        headers: HeaderMap,
+        region: String,
+        service: String,
+        request_id: Option<RequestId>,
+    ) -> Self {
+        Self {
+            method,
+            uri,
+            headers,
+            region,
+            service,
+            request_id,
+            signing_details: None,
+        }
+    }
+
+    /// Attach the canonical request and string to sign computed by the server for a request whose signature did not
+    /// match.
+    pub fn with_signing_details(mut self, signing_details: SigningDetails) -> Self {
+        self.signing_details = Some(signing_details);
+        self
+    }
+
+    /// Returns the request method.
+    #[inline]
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Returns the request URI.
+    #[inline]
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// Returns the request headers.
+    #[inline]
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Returns the region the request was verified against.
+    #[inline]
+    pub fn region(&self) -> &str {
+        &self.region
+    }
+
+    /// Returns the service the request was verified against.
+    #[inline]
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    /// Returns the request id, if one was assigned.
+    #[inline]
+    pub fn request_id(&self) -> Option<RequestId> {
+        self.request_id
+    }
+
+    /// Returns the canonical request and string to sign computed by the server, if the request's signature did not
+    /// match and the verifier is configured to reveal them.
+    #[inline]
+    pub fn signing_details(&self) -> Option<&SigningDetails> {
+        self.signing_details.as_ref()
+    }
+}
+
+/// Returns the error as a [ServiceError] if it is one of the error types known to this crate.
+pub(crate) fn as_service_error(error: &BoxError) -> Option<&(dyn ServiceError + 'static)> {
+    if let Some(e) = error.downcast_ref::<SignatureError>() {
+        Some(e)
+    } else if let Some(e) = error.downcast_ref::<VerifierError>() {
+        Some(e)
+    } else if let Some(e) = error.downcast_ref::<StandardError>() {
+        Some(e)
+    } else if let Some(e) = error.downcast_ref::<OperationError>() {
+        Some(e.service_error())
+    } else {
+        None
+    }
+}
+
+/// Returns the fault of the error, if it was given explicitly rather than implied by its HTTP status.
+pub(crate) fn error_fault(error: &BoxError) -> Option<ErrorFault> {
+    if let Some(e) = error.downcast_ref::<StandardError>() {
+        Some(e.fault())
+    } else if let Some(e) = error.downcast_ref::<OperationError>() {
+        e.fault()
+    } else {
+        None
+    }
+}
+
+/// Returns the error code used by the AWS JSON and REST-JSON protocols, whose exception shapes are suffixed with
+/// `Exception` where the query protocol's codes are not, e.g. `AccessDeniedException`.
+pub(crate) fn json_error_code(error_code: &'static str) -> &'static str {
+    match error_code {
+        "AccessDenied" => "AccessDeniedException",
+        _ => error_code,
+    }
+}
+
+/// Returns the client-facing message for an error, rendered through the message catalog if one is configured.
+pub(crate) fn client_message(
+    error: &(dyn ServiceError + 'static),
+    message_catalog: Option<&MessageCatalog>,
+    request_id: Option<RequestId>,
+) -> Option<String> {
+    let message = error.to_string();
+    if let Some(rendered) = message_catalog.and_then(|c| c.render(error.error_code(), &message, request_id)) {
+        return Some(rendered);
+    }
+
+    if message.is_empty() {
+        None
+    } else {
+        Some(message)
+    }
+}
@@ -0,0 +1,204 @@
+use {
+    http::StatusCode,
+    scratchstack_errors::ServiceError,
+    std::{
+        error::Error,
+        fmt::{Display, Formatter, Result as FmtResult},
+    },
+};
+
+/// Which party is responsible for an error, reported as the `Type` of AWS Query protocol error responses.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ErrorFault {
+    /// The request was invalid or not permitted; retrying it unchanged will fail again.
+    Sender,
+
+    /// The service failed to process a valid request; retrying it may succeed.
+    Receiver,
+}
+
+impl ErrorFault {
+    /// Returns the fault as it appears in error responses, `Sender` or `Receiver`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sender => "Sender",
+            Self::Receiver => "Receiver",
+        }
+    }
+}
+
+impl Display for ErrorFault {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An error in one of the standard AWS error families, usually created through the [ServiceErrorCatalog].
+///
+/// Handlers can return these directly (they convert into a [BoxError][tower::BoxError]) or as the error of an
+/// [Operation][crate::router::Operation]; the error mappers render them with their code, status, fault, and message in
+/// the protocol's error format.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StandardError {
+    code: &'static str,
+    status: StatusCode,
+    fault: ErrorFault,
+    message: String,
+}
+
+impl StandardError {
+    /// Create a new [StandardError] with the given error code, HTTP status, fault, and message.
+    pub fn new(code: &'static str, status: StatusCode, fault: ErrorFault, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            status,
+            fault,
+            message: message.into(),
+        }
+    }
+
+    /// Retreive the party responsible for the error.
+    #[inline]
+    pub fn fault(&self) -> ErrorFault {
+        self.fault
+    }
+
+    /// Retreive the client-facing message.
+    #[inline]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for StandardError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for StandardError {}
+
+impl ServiceError for StandardError {
+    fn error_code(&self) -> &'static str {
+        self.code
+    }
+
+    fn http_status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+/// Constructors for the standard AWS error families, with the error code, HTTP status, and fault AWS services use for
+/// them.
+///
+/// ```
+/// use scratchstack_http_framework::{ErrorFault, ServiceErrorCatalog};
+///
+/// let error = ServiceErrorCatalog::resource_not_found("Table: Books not found");
+/// assert_eq!(error.fault(), ErrorFault::Sender);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct ServiceErrorCatalog;
+
+impl ServiceErrorCatalog {
+    /// `ValidationException` (400): the input fails to satisfy the constraints of the operation.
+    pub fn validation(message: impl Into<String>) -> StandardError {
+        StandardError::new("ValidationException", StatusCode::BAD_REQUEST, ErrorFault::Sender, message)
+    }
+
+    /// `ThrottlingException` (400): the request was denied due to request throttling.
+    pub fn throttling(message: impl Into<String>) -> StandardError {
+        StandardError::new("ThrottlingException", StatusCode::BAD_REQUEST, ErrorFault::Sender, message)
+    }
+
+    /// `ResourceNotFoundException` (404): the resource named in the request does not exist.
+    pub fn resource_not_found(message: impl Into<String>) -> StandardError {
+        StandardError::new("ResourceNotFoundException", StatusCode::NOT_FOUND, ErrorFault::Sender, message)
+    }
+
+    /// `ResourceInUseException` (400): the resource is being used by another operation.
+    pub fn resource_in_use(message: impl Into<String>) -> StandardError {
+        StandardError::new("ResourceInUseException", StatusCode::BAD_REQUEST, ErrorFault::Sender, message)
+    }
+
+    /// `ConflictException` (409): the request conflicts with the current state of the resource.
+    pub fn conflict(message: impl Into<String>) -> StandardError {
+        StandardError::new("ConflictException", StatusCode::CONFLICT, ErrorFault::Sender, message)
+    }
+
+    /// `AccessDeniedException` (403): the caller does not have permission to perform the action.
+    pub fn access_denied(message: impl Into<String>) -> StandardError {
+        StandardError::new("AccessDeniedException", StatusCode::FORBIDDEN, ErrorFault::Sender, message)
+    }
+
+    /// `ServiceQuotaExceededException` (402): the request would exceed a service quota.
+    pub fn service_quota_exceeded(message: impl Into<String>) -> StandardError {
+        StandardError::new("ServiceQuotaExceededException", StatusCode::PAYMENT_REQUIRED, ErrorFault::Sender, message)
+    }
+
+    /// `LimitExceededException` (400): the request would exceed a limit on the number of resources.
+    pub fn limit_exceeded(message: impl Into<String>) -> StandardError {
+        StandardError::new("LimitExceededException", StatusCode::BAD_REQUEST, ErrorFault::Sender, message)
+    }
+
+    /// `InvalidParameterValue` (400): a parameter of an AWS Query protocol request has an invalid value.
+    pub fn invalid_parameter_value(message: impl Into<String>) -> StandardError {
+        StandardError::new("InvalidParameterValue", StatusCode::BAD_REQUEST, ErrorFault::Sender, message)
+    }
+
+    /// `MissingParameter` (400): a required parameter of an AWS Query protocol request is missing.
+    pub fn missing_parameter(message: impl Into<String>) -> StandardError {
+        StandardError::new("MissingParameter", StatusCode::BAD_REQUEST, ErrorFault::Sender, message)
+    }
+
+    /// `InternalServerException` (500): the service failed to process the request.
+    pub fn internal_server(message: impl Into<String>) -> StandardError {
+        StandardError::new("InternalServerException", StatusCode::INTERNAL_SERVER_ERROR, ErrorFault::Receiver, message)
+    }
+
+    /// `ServiceUnavailableException` (503): the service is temporarily unable to process the request.
+    pub fn service_unavailable(message: impl Into<String>) -> StandardError {
+        StandardError::new(
+            "ServiceUnavailableException",
+            StatusCode::SERVICE_UNAVAILABLE,
+            ErrorFault::Receiver,
+            message,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{ErrorFault, ServiceErrorCatalog, StandardError},
+        crate::{ErrorMapper, JsonErrorMapper, XmlErrorMapper},
+        http::StatusCode,
+        pretty_assertions::assert_eq,
+        scratchstack_errors::ServiceError,
+    };
+
+    #[test_log::test(tokio::test)]
+    async fn test_error_catalog() {
+        let error = ServiceErrorCatalog::validation("1 validation error detected");
+        assert_eq!(error.error_code(), "ValidationException");
+        assert_eq!(error.http_status(), StatusCode::BAD_REQUEST);
+        assert_eq!(error.to_string(), "1 validation error detected");
+
+        let response = JsonErrorMapper::new().map_error(error.into(), None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            serde_json::json!({"__type": "ValidationException", "message": "1 validation error detected"})
+        );
+
+        // The fault is reported as given, even when it doesn't match the status.
+        let error = StandardError::new("RequestLimitExceeded", StatusCode::SERVICE_UNAVAILABLE, ErrorFault::Sender, "");
+        let response = XmlErrorMapper::new("urn:test").map_error(error.into(), None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("<Type>Sender</Type><Code>RequestLimitExceeded</Code>"));
+        assert!(!body.contains("<Message>"));
+    }
+}
@@ -0,0 +1,839 @@
+#![warn(clippy::all)]
+
+use {
+    crate::VerifierError,
+    bytes::{BufMut, Bytes, BytesMut},
+    chrono::{DateTime, TimeZone, Utc},
+    crc32fast::Hasher,
+    hmac::{Hmac, Mac},
+    http::{header::CONTENT_TYPE, request::Parts},
+    hyper::{
+        body::{HttpBody, Sender},
+        Body, Request, Response,
+    },
+    scratchstack_aws_signature::KSigningKey,
+    scratchstack_errors::ServiceError,
+    serde::{de::DeserializeOwned, Serialize},
+    sha2::{Digest, Sha256},
+    std::fmt::{Debug, Formatter, Result as FmtResult},
+    tower::BoxError,
+    uuid::Uuid,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The content type of event-stream bodies.
+pub const EVENT_STREAM_CONTENT_TYPE: &str = "application/vnd.amazon.eventstream";
+
+/// The `x-amz-content-sha256` value of requests whose event-stream bodies are signed frame by frame.
+pub const STREAMING_EVENTS_PAYLOAD: &str = "STREAMING-AWS4-HMAC-SHA256-EVENTS";
+
+/// The largest message accepted when decoding, 16 MiB.
+const MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+
+/// The length of the prelude: the total length, headers length, and prelude CRC.
+const PRELUDE_LEN: usize = 12;
+
+/// The length of the message CRC ending each message.
+const MESSAGE_CRC_LEN: usize = 4;
+
+/// The value of an event-stream message header.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EventHeaderValue {
+    /// A boolean, encoded in the header's type.
+    Bool(bool),
+
+    /// A signed 8-bit integer.
+    Byte(i8),
+
+    /// A signed 16-bit integer.
+    Short(i16),
+
+    /// A signed 32-bit integer.
+    Integer(i32),
+
+    /// A signed 64-bit integer.
+    Long(i64),
+
+    /// Up to 32,767 bytes of binary data.
+    ByteArray(Bytes),
+
+    /// Up to 32,767 bytes of UTF-8 text.
+    String(String),
+
+    /// A timestamp with millisecond precision.
+    Timestamp(DateTime<Utc>),
+
+    /// A UUID.
+    Uuid(Uuid),
+}
+
+impl EventHeaderValue {
+    fn type_id(&self) -> u8 {
+        match self {
+            Self::Bool(true) => 0,
+            Self::Bool(false) => 1,
+            Self::Byte(_) => 2,
+            Self::Short(_) => 3,
+            Self::Integer(_) => 4,
+            Self::Long(_) => 5,
+            Self::ByteArray(_) => 6,
+            Self::String(_) => 7,
+            Self::Timestamp(_) => 8,
+            Self::Uuid(_) => 9,
+        }
+    }
+
+    fn encode(&self, buffer: &mut BytesMut) {
+        buffer.put_u8(self.type_id());
+        match self {
+            Self::Bool(_) => (),
+            Self::Byte(value) => buffer.put_i8(*value),
+            Self::Short(value) => buffer.put_i16(*value),
+            Self::Integer(value) => buffer.put_i32(*value),
+            Self::Long(value) => buffer.put_i64(*value),
+            Self::ByteArray(value) => {
+                buffer.put_u16(value.len() as u16);
+                buffer.put_slice(value);
+            }
+            Self::String(value) => {
+                buffer.put_u16(value.len() as u16);
+                buffer.put_slice(value.as_bytes());
+            }
+            Self::Timestamp(value) => buffer.put_i64(value.timestamp_millis()),
+            Self::Uuid(value) => buffer.put_slice(value.as_bytes()),
+        }
+    }
+
+    fn decode(type_id: u8, data: &mut &[u8]) -> Result<Self, VerifierError> {
+        let value = match type_id {
+            0 => Self::Bool(true),
+            1 => Self::Bool(false),
+            2 => Self::Byte(take(data, 1)?[0] as i8),
+            3 => Self::Short(i16::from_be_bytes(take(data, 2)?.try_into().expect("took 2 bytes"))),
+            4 => Self::Integer(i32::from_be_bytes(take(data, 4)?.try_into().expect("took 4 bytes"))),
+            5 => Self::Long(i64::from_be_bytes(take(data, 8)?.try_into().expect("took 8 bytes"))),
+            6 | 7 => {
+                let len = u16::from_be_bytes(take(data, 2)?.try_into().expect("took 2 bytes")) as usize;
+                let value = take(data, len)?;
+                if type_id == 6 {
+                    Self::ByteArray(Bytes::copy_from_slice(value))
+                } else {
+                    Self::String(
+                        String::from_utf8(value.to_vec()).map_err(|_| malformed("Header value is not valid UTF-8"))?,
+                    )
+                }
+            }
+            8 => {
+                let millis = i64::from_be_bytes(take(data, 8)?.try_into().expect("took 8 bytes"));
+                Self::Timestamp(
+                    Utc.timestamp_millis_opt(millis).single().ok_or_else(|| malformed("Invalid timestamp"))?,
+                )
+            }
+            9 => Self::Uuid(Uuid::from_slice(take(data, 16)?).expect("took 16 bytes")),
+            _ => return Err(malformed("Unknown header value type")),
+        };
+        Ok(value)
+    }
+}
+
+impl From<&str> for EventHeaderValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for EventHeaderValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+/// A message in an AWS event stream (`application/vnd.amazon.eventstream`): a set of headers and a binary payload,
+/// framed with a prelude giving their lengths and protected by CRC32 checksums.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Message {
+    headers: Vec<(String, EventHeaderValue)>,
+    payload: Bytes,
+}
+
+impl Message {
+    /// Create a new [Message] with the given payload and no headers.
+    pub fn new(payload: impl Into<Bytes>) -> Self {
+        Self {
+            headers: Vec::new(),
+            payload: payload.into(),
+        }
+    }
+
+    /// Add a header, returning the updated message. Header names and string and byte array values must be no longer
+    /// than 255 and 32,767 bytes, respectively.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<EventHeaderValue>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Retreive the headers, in order.
+    #[inline]
+    pub fn headers(&self) -> &[(String, EventHeaderValue)] {
+        &self.headers
+    }
+
+    /// Returns the value of the first header with the given name.
+    pub fn header(&self, name: &str) -> Option<&EventHeaderValue> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    /// Returns the value of the first header with the given name if it is a string.
+    pub fn string_header(&self, name: &str) -> Option<&str> {
+        match self.header(name)? {
+            EventHeaderValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Retreive the payload.
+    #[inline]
+    pub fn payload(&self) -> &Bytes {
+        &self.payload
+    }
+
+    /// Encode the message in the event-stream binary format.
+    pub fn encode(&self) -> Bytes {
+        let mut headers = BytesMut::new();
+        for (name, value) in &self.headers {
+            headers.put_u8(name.len() as u8);
+            headers.put_slice(name.as_bytes());
+            value.encode(&mut headers);
+        }
+
+        let total_len = PRELUDE_LEN + headers.len() + self.payload.len() + MESSAGE_CRC_LEN;
+        let mut buffer = BytesMut::with_capacity(total_len);
+        buffer.put_u32(total_len as u32);
+        buffer.put_u32(headers.len() as u32);
+        buffer.put_u32(crc32(&buffer));
+        buffer.put_slice(&headers);
+        buffer.put_slice(&self.payload);
+        buffer.put_u32(crc32(&buffer));
+        buffer.freeze()
+    }
+
+    /// Decode the message at the start of the buffer, removing it from the buffer. This returns `None` if the buffer
+    /// doesn't yet hold the whole message, and a `SerializationException` if the message is malformed.
+    pub fn decode(buffer: &mut BytesMut) -> Result<Option<Self>, VerifierError> {
+        if buffer.len() < PRELUDE_LEN {
+            return Ok(None);
+        }
+
+        let total_len = u32::from_be_bytes(buffer[0..4].try_into().expect("prelude is 12 bytes")) as usize;
+        let headers_len = u32::from_be_bytes(buffer[4..8].try_into().expect("prelude is 12 bytes")) as usize;
+        if crc32(&buffer[0..8]).to_be_bytes() != buffer[8..12] {
+            return Err(malformed("Prelude checksum mismatch"));
+        }
+        if total_len > MAX_MESSAGE_LEN || total_len < PRELUDE_LEN + headers_len + MESSAGE_CRC_LEN {
+            return Err(malformed("Invalid message length"));
+        }
+        if buffer.len() < total_len {
+            return Ok(None);
+        }
+
+        let message = buffer.split_to(total_len).freeze();
+        let (contents, message_crc) = message.split_at(total_len - MESSAGE_CRC_LEN);
+        if crc32(contents).to_be_bytes() != message_crc {
+            return Err(malformed("Message checksum mismatch"));
+        }
+
+        let mut data = &message[PRELUDE_LEN..PRELUDE_LEN + headers_len];
+        let mut headers = Vec::new();
+        while !data.is_empty() {
+            let name_len = take(&mut data, 1)?[0] as usize;
+            let name = String::from_utf8(take(&mut data, name_len)?.to_vec())
+                .map_err(|_| malformed("Header name is not valid UTF-8"))?;
+            let type_id = take(&mut data, 1)?[0];
+            headers.push((name, EventHeaderValue::decode(type_id, &mut data)?));
+        }
+
+        Ok(Some(Self {
+            headers,
+            payload: message.slice(PRELUDE_LEN + headers_len..total_len - MESSAGE_CRC_LEN),
+        }))
+    }
+}
+
+/// The signing key and seed signature used to verify the `:chunk-signature` of each frame of a signed event-stream
+/// request, established by the request's own SigV4 signature.
+///
+/// [AwsSigV4VerifierService][crate::AwsSigV4VerifierService] inserts this into the extensions of authenticated requests
+/// whose `x-amz-content-sha256` is `STREAMING-AWS4-HMAC-SHA256-EVENTS`, and streams their bodies to the service
+/// unverified; [EventStreamReceiver::from_request] then verifies each frame as it is read.
+#[derive(Clone)]
+pub struct ChunkSigningContext {
+    signing_key: Vec<u8>,
+    credential_scope: String,
+    seed_signature: String,
+}
+
+impl ChunkSigningContext {
+    /// Create a new [ChunkSigningContext] from the request's signing key, credential scope (e.g.
+    /// `20220101/us-east-1/transcribe/aws4_request`), and hex-encoded signature.
+    pub fn new(
+        signing_key: &KSigningKey,
+        credential_scope: impl Into<String>,
+        seed_signature: impl Into<String>,
+    ) -> Self {
+        let signing_key: &[u8; 32] = signing_key.as_ref();
+        Self {
+            signing_key: signing_key.to_vec(),
+            credential_scope: credential_scope.into(),
+            seed_signature: seed_signature.into(),
+        }
+    }
+
+    /// Retreive the credential scope.
+    #[inline]
+    pub fn credential_scope(&self) -> &str {
+        &self.credential_scope
+    }
+
+    /// Retreive the hex-encoded signature of the request, which chains to the signature of the first frame.
+    #[inline]
+    pub fn seed_signature(&self) -> &str {
+        &self.seed_signature
+    }
+
+    /// Returns the MAC of a frame, chained from the previous frame's signature.
+    fn frame_mac(&self, prior_signature: &str, date: DateTime<Utc>, payload: &[u8]) -> HmacSha256 {
+        let mut date_header = BytesMut::new();
+        date_header.put_u8(5);
+        date_header.put_slice(b":date");
+        EventHeaderValue::Timestamp(date).encode(&mut date_header);
+
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256-PAYLOAD\n{}\n{}\n{}\n{}\n{}",
+            date.format("%Y%m%dT%H%M%SZ"),
+            self.credential_scope,
+            prior_signature,
+            hex::encode(Sha256::digest(&date_header)),
+            hex::encode(Sha256::digest(payload)),
+        );
+
+        let mut mac = HmacSha256::new_from_slice(&self.signing_key).expect("HMAC accepts keys of any length");
+        mac.update(string_to_sign.as_bytes());
+        mac
+    }
+}
+
+impl Debug for ChunkSigningContext {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("ChunkSigningContext")
+            .field("credential_scope", &self.credential_scope)
+            .field("seed_signature", &self.seed_signature)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A typed, asynchronous stream of the messages of an event-stream request body.
+///
+/// If the request was signed for event streaming, each frame's `:chunk-signature` is verified against the signature
+/// chain starting from the request's own signature before its message is returned, and the stream must end with the
+/// signed empty frame; otherwise a `SignatureDoesNotMatch` error is returned. Malformed frames are returned as
+/// `SerializationException` errors. The stream ends after the first error.
+///
+/// ```
+/// use {
+///     http::request::Parts,
+///     hyper::{Body, Request},
+///     scratchstack_http_framework::eventstream::EventStreamReceiver,
+///     serde::Deserialize,
+///     tower::BoxError,
+/// };
+///
+/// #[derive(Deserialize)]
+/// struct AudioEvent {
+///     #[serde(rename = "AudioChunk")]
+///     audio_chunk: String,
+/// }
+///
+/// async fn start_stream_transcription(req: Request<Body>) -> Result<usize, BoxError> {
+///     let (_parts, mut events) = EventStreamReceiver::from_request(req);
+///     let mut total = 0;
+///     while let Some(event) = events.next_event::<AudioEvent>().await {
+///         let (_event_type, event) = event?;
+///         total += event.audio_chunk.len();
+///     }
+///     Ok(total)
+/// }
+/// ```
+pub struct EventStreamReceiver {
+    body: Body,
+    buffer: BytesMut,
+    signing: Option<(ChunkSigningContext, String)>,
+    done: bool,
+}
+
+impl EventStreamReceiver {
+    /// Create a new [EventStreamReceiver] for an unsigned event-stream body.
+    pub fn new(body: Body) -> Self {
+        Self {
+            body,
+            buffer: BytesMut::new(),
+            signing: None,
+            done: false,
+        }
+    }
+
+    /// Create a new [EventStreamReceiver] for an event-stream body whose frames are signed.
+    pub fn with_chunk_signing(body: Body, context: ChunkSigningContext) -> Self {
+        let prior_signature = context.seed_signature.clone();
+        Self {
+            signing: Some((context, prior_signature)),
+            ..Self::new(body)
+        }
+    }
+
+    /// Split a request into its head and a receiver for its body, verifying frame signatures if the verifier
+    /// inserted a [ChunkSigningContext].
+    pub fn from_request(req: Request<Body>) -> (Parts, Self) {
+        let (parts, body) = req.into_parts();
+        let receiver = match parts.extensions.get::<ChunkSigningContext>() {
+            Some(context) => Self::with_chunk_signing(body, context.clone()),
+            None => Self::new(body),
+        };
+        (parts, receiver)
+    }
+
+    /// Returns the next message, or `None` at the end of the stream.
+    pub async fn next_message(&mut self) -> Option<Result<Message, BoxError>> {
+        loop {
+            if self.done {
+                return None;
+            }
+
+            let result = match Message::decode(&mut self.buffer) {
+                Ok(Some(frame)) => self.unwrap_frame(frame).map_err(Into::into).transpose(),
+                Ok(None) => match self.body.data().await {
+                    Some(Ok(chunk)) => {
+                        self.buffer.extend_from_slice(&chunk);
+                        continue;
+                    }
+                    Some(Err(e)) => Some(Err(e.into())),
+                    // Signed streams must end with the empty frame; otherwise they may have been truncated.
+                    None if self.buffer.is_empty() && self.signing.is_none() => None,
+                    None => Some(Err(malformed("Incomplete event stream").into())),
+                },
+                Err(e) => Some(Err(e.into())),
+            };
+
+            if !matches!(result, Some(Ok(_))) {
+                self.done = true;
+            }
+            return result;
+        }
+    }
+
+    /// Returns the event type and JSON payload of the next message, or `None` at the end of the stream. Messages
+    /// other than events are rejected with a `SerializationException`.
+    pub async fn next_event<T: DeserializeOwned>(&mut self) -> Option<Result<(String, T), BoxError>> {
+        let message = match self.next_message().await? {
+            Ok(message) => message,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let result: Result<_, BoxError> =
+            match (message.string_header(":message-type"), message.string_header(":event-type")) {
+                (Some("event"), Some(event_type)) => serde_json::from_slice(message.payload())
+                    .map(|event| (event_type.to_string(), event))
+                    .map_err(|e| malformed(&e.to_string()).into()),
+                _ => Err(malformed("Expected an event message").into()),
+            };
+        if result.is_err() {
+            self.done = true;
+        }
+        Some(result)
+    }
+
+    /// Verify a frame of a signed stream and return the message it carries, or `None` for the final empty frame. Frames
+    /// of unsigned streams are returned as-is.
+    fn unwrap_frame(&mut self, frame: Message) -> Result<Option<Message>, VerifierError> {
+        let Some((context, prior_signature)) = &mut self.signing else {
+            return Ok(Some(frame));
+        };
+
+        let (Some(EventHeaderValue::Timestamp(date)), Some(EventHeaderValue::ByteArray(signature))) =
+            (frame.header(":date"), frame.header(":chunk-signature"))
+        else {
+            return Err(VerifierError::SignatureDoesNotMatch);
+        };
+
+        context
+            .frame_mac(prior_signature, *date, frame.payload())
+            .verify_slice(signature)
+            .map_err(|_| VerifierError::SignatureDoesNotMatch)?;
+        *prior_signature = hex::encode(signature);
+
+        if frame.payload().is_empty() {
+            return Ok(None);
+        }
+
+        let mut payload = BytesMut::from(&frame.payload()[..]);
+        match Message::decode(&mut payload)? {
+            Some(message) if payload.is_empty() => Ok(Some(message)),
+            _ => Err(malformed("Invalid frame payload")),
+        }
+    }
+}
+
+impl Debug for EventStreamReceiver {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("EventStreamReceiver")
+            .field("signing", &self.signing.as_ref().map(|(context, _)| context))
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns an event-stream response body and the [EventStreamSender] handlers use to write messages to it.
+pub fn channel() -> (EventStreamSender, Body) {
+    let (sender, body) = Body::channel();
+    (
+        EventStreamSender {
+            sender,
+        },
+        body,
+    )
+}
+
+/// Returns a `200 OK` response streaming the given event-stream body.
+pub fn response(body: Body) -> Result<Response<Body>, BoxError> {
+    Response::builder().header(CONTENT_TYPE, EVENT_STREAM_CONTENT_TYPE).body(body).map_err(Into::into)
+}
+
+/// A handle for writing messages to an event-stream response body, created by [channel].
+///
+/// Events, exceptions, and errors are written with the `:message-type`, `:event-type`, `:exception-type`,
+/// `:error-code`, `:error-message`, and `:content-type` headers AWS SDKs expect. Each message is sent as soon as it is
+/// written; the stream ends when the sender is dropped.
+///
+/// ```
+/// use {
+///     hyper::{Body, Response},
+///     scratchstack_http_framework::eventstream,
+///     serde::Serialize,
+///     tower::BoxError,
+/// };
+///
+/// #[derive(Serialize)]
+/// struct Progress {
+///     #[serde(rename = "BytesProcessed")]
+///     bytes_processed: u64,
+/// }
+///
+/// async fn select_object_content() -> Result<Response<Body>, BoxError> {
+///     let (mut sender, body) = eventstream::channel();
+///     tokio::spawn(async move {
+///         sender.send_event("Progress", &Progress { bytes_processed: 1024 }).await?;
+///         sender.send_event("End", &serde_json::json!({})).await
+///     });
+///     eventstream::response(body)
+/// }
+/// ```
+pub struct EventStreamSender {
+    sender: Sender,
+}
+
+impl EventStreamSender {
+    /// Write a message to the stream.
+    pub async fn send(&mut self, message: Message) -> Result<(), BoxError> {
+        self.sender.send_data(message.encode()).await.map_err(Into::into)
+    }
+
+    /// Write an event of the given type with `event` serialized as its JSON payload.
+    pub async fn send_event<T: Serialize + ?Sized>(&mut self, event_type: &str, event: &T) -> Result<(), BoxError> {
+        let payload = serde_json::to_vec(event)?;
+        self.send_raw_event(event_type, "application/json", payload).await
+    }
+
+    /// Write an event of the given type with a payload of the given content type.
+    pub async fn send_raw_event(
+        &mut self,
+        event_type: &str,
+        content_type: &str,
+        payload: impl Into<Bytes>,
+    ) -> Result<(), BoxError> {
+        let message = Message::new(payload)
+            .with_header(":message-type", "event")
+            .with_header(":event-type", event_type)
+            .with_header(":content-type", content_type);
+        self.send(message).await
+    }
+
+    /// Write a modeled exception of the given type with `exception` serialized as its JSON payload. Clients treat this
+    /// as the end of the stream.
+    pub async fn send_exception<T: Serialize + ?Sized>(
+        &mut self,
+        exception_type: &str,
+        exception: &T,
+    ) -> Result<(), BoxError> {
+        let message = Message::new(serde_json::to_vec(exception)?)
+            .with_header(":message-type", "exception")
+            .with_header(":exception-type", exception_type)
+            .with_header(":content-type", "application/json");
+        self.send(message).await
+    }
+
+    /// Write an unmodeled error with the error's code and message. Clients treat this as the end of the stream.
+    pub async fn send_error(&mut self, error: &(dyn ServiceError + Send + Sync)) -> Result<(), BoxError> {
+        let message = Message::new(Bytes::new())
+            .with_header(":message-type", "error")
+            .with_header(":error-code", error.error_code())
+            .with_header(":error-message", error.to_string());
+        self.send(message).await
+    }
+
+    /// Abort the stream, so the client sees the response end with an error rather than a clean end of stream.
+    pub fn abort(self) {
+        self.sender.abort()
+    }
+}
+
+impl Debug for EventStreamSender {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("EventStreamSender").finish_non_exhaustive()
+    }
+}
+
+/// Returns a `SerializationException` for a malformed event stream.
+fn malformed(detail: &str) -> VerifierError {
+    VerifierError::Serialization(format!("Invalid event stream: {detail}"))
+}
+
+/// Remove the given number of bytes from the start of the data.
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], VerifierError> {
+    if data.len() < len {
+        return Err(malformed("Truncated header"));
+    }
+    let (taken, rest) = data.split_at(len);
+    *data = rest;
+    Ok(taken)
+}
+
+/// Returns the CRC32 checksum of the data.
+fn crc32(data: &[u8]) -> u32 {
+    let mut hasher = Hasher::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{
+            channel, crc32, ChunkSigningContext, EventHeaderValue, EventStreamReceiver, HmacSha256, Message,
+            STREAMING_EVENTS_PAYLOAD,
+        },
+        crate::{
+            canonical::{string_to_sign, CanonicalRequest, AWS4_HMAC_SHA256},
+            test_util::request_date,
+            AwsSigV4VerifierService, FixedClock, ServiceErrorCatalog, VerifierError, XmlErrorMapper,
+        },
+        bytes::{Bytes, BytesMut},
+        chrono::{TimeZone, Utc},
+        hmac::Mac,
+        http::StatusCode,
+        hyper::{Body, Request, Response},
+        pretty_assertions::assert_eq,
+        scratchstack_aws_signature::{GetSigningKeyRequest, GetSigningKeyResponse, KSecretKey, SignatureOptions},
+        serde::Deserialize,
+        std::sync::Arc,
+        tower::{service_fn, BoxError, ServiceExt},
+        uuid::Uuid,
+    };
+
+    #[test]
+    fn test_encode() {
+        let encoded = Message::new(&b"{}"[..]).with_header(":event-type", "End").encode();
+        assert_eq!(encoded.len(), 12 + 18 + 2 + 4);
+        assert_eq!(&encoded[0..4], &36u32.to_be_bytes());
+        assert_eq!(&encoded[4..8], &18u32.to_be_bytes());
+        assert_eq!(&encoded[8..12], &crc32(&encoded[0..8]).to_be_bytes());
+        assert_eq!(&encoded[12..24], b"\x0b:event-type");
+        assert_eq!(&encoded[24..30], b"\x07\x00\x03End");
+        assert_eq!(&encoded[30..32], b"{}");
+        assert_eq!(&encoded[32..], &crc32(&encoded[..32]).to_be_bytes());
+
+        // An empty message is only the prelude and the message CRC.
+        let encoded = Message::default().encode();
+        assert_eq!(encoded.len(), 16);
+        assert_eq!(&encoded[8..12], &0x05c248ebu32.to_be_bytes());
+
+        let encoded = Message::new(Bytes::new())
+            .with_header("b", EventHeaderValue::Bool(false))
+            .with_header("i", EventHeaderValue::Integer(-2))
+            .encode();
+        assert_eq!(&encoded[12..21], b"\x01b\x01\x01i\x04\xff\xff\xff");
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_sender() {
+        let (mut sender, body) = channel();
+        tokio::spawn(async move {
+            sender.send_event("Stats", &serde_json::json!({"Records": 1})).await.unwrap();
+            sender.send_error(&ServiceErrorCatalog::internal_server("Stream failed")).await.unwrap();
+        });
+
+        let body = hyper::body::to_bytes(body).await.unwrap();
+        let first_len = u32::from_be_bytes(body[0..4].try_into().unwrap()) as usize;
+        let first = &body[..first_len];
+        assert!(first.windows(5).any(|w| w == b"Stats"));
+        assert!(first.ends_with(&crc32(&first[..first_len - 4]).to_be_bytes()));
+
+        let second = &body[first_len..];
+        assert_eq!(u32::from_be_bytes(second[0..4].try_into().unwrap()) as usize, second.len());
+        assert!(second.windows(23).any(|w| w == b"InternalServerException"));
+    }
+
+    #[test]
+    fn test_decode() {
+        let message = Message::new(&b"payload"[..])
+            .with_header("s", "text")
+            .with_header("b", EventHeaderValue::Bool(true))
+            .with_header("y", EventHeaderValue::Byte(-1))
+            .with_header("h", EventHeaderValue::Short(300))
+            .with_header("l", EventHeaderValue::Long(1 << 40))
+            .with_header("a", EventHeaderValue::ByteArray(Bytes::from_static(b"\x00\x01")))
+            .with_header("t", EventHeaderValue::Timestamp(Utc.timestamp_millis_opt(1_664_625_600_123).unwrap()))
+            .with_header("u", EventHeaderValue::Uuid(Uuid::from_u128(1)));
+        let encoded = message.encode();
+
+        // Partial messages are left in the buffer until the rest arrives.
+        let mut buffer = BytesMut::from(&encoded[..20]);
+        assert_eq!(Message::decode(&mut buffer).unwrap(), None);
+        buffer.extend_from_slice(&encoded[20..]);
+        buffer.extend_from_slice(&encoded[..4]);
+        assert_eq!(Message::decode(&mut buffer).unwrap(), Some(message));
+        assert_eq!(&buffer[..], &encoded[..4]);
+
+        let mut corrupted = BytesMut::from(&encoded[..]);
+        corrupted[20] ^= 1;
+        assert!(matches!(Message::decode(&mut corrupted), Err(VerifierError::Serialization(_))));
+        let mut corrupted = BytesMut::from(&encoded[..]);
+        corrupted[3] ^= 1;
+        assert!(matches!(Message::decode(&mut corrupted), Err(VerifierError::Serialization(_))));
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct AudioEvent {
+        #[serde(rename = "AudioChunk")]
+        audio_chunk: String,
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_signed_receiver() {
+        let signing_key = KSecretKey::from_str("secret").to_ksigning(request_date(), "us-east-1", "transcribe");
+        let context =
+            ChunkSigningContext::new(&signing_key, "20221001/us-east-1/transcribe/aws4_request", "00".repeat(32));
+        let date = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+
+        let event = |chunk: &str| {
+            Message::new(format!(r#"{{"AudioChunk": "{chunk}"}}"#))
+                .with_header(":message-type", "event")
+                .with_header(":event-type", "AudioEvent")
+                .encode()
+        };
+        let mut prior = context.seed_signature().to_string();
+        let mut sign = |payload: Bytes| {
+            let signature = context.frame_mac(&prior, date, &payload).finalize().into_bytes();
+            prior = hex::encode(signature);
+            Message::new(payload)
+                .with_header(":date", EventHeaderValue::Timestamp(date))
+                .with_header(":chunk-signature", EventHeaderValue::ByteArray(Bytes::copy_from_slice(&signature)))
+                .encode()
+        };
+        let frames = [sign(event("AAAA")), sign(event("BBBB")), sign(Bytes::new())];
+
+        let receive = |frames: Vec<Bytes>| {
+            let context = context.clone();
+            async move {
+                let body = Body::wrap_stream(futures::stream::iter(frames.into_iter().map(Ok::<_, BoxError>)));
+                let mut receiver = EventStreamReceiver::with_chunk_signing(body, context);
+                let mut events = Vec::new();
+                while let Some(event) = receiver.next_event::<AudioEvent>().await {
+                    match event {
+                        Ok((_, event)) => events.push(event.audio_chunk),
+                        Err(e) => return Err((events, e)),
+                    }
+                }
+                Ok(events)
+            }
+        };
+
+        assert_eq!(receive(frames.to_vec()).await.unwrap(), vec!["AAAA", "BBBB"]);
+
+        // Frames split across body chunks are reassembled.
+        let joined: Vec<u8> = frames.concat();
+        let chunks = joined.chunks(7).map(Bytes::copy_from_slice).collect();
+        assert_eq!(receive(chunks).await.unwrap(), vec!["AAAA", "BBBB"]);
+
+        // Reordered, dropped, and truncated frames are rejected.
+        let (events, e) = receive(vec![frames[1].clone(), frames[0].clone()]).await.unwrap_err();
+        assert!(events.is_empty());
+        assert!(matches!(e.downcast_ref::<VerifierError>(), Some(VerifierError::SignatureDoesNotMatch)));
+        let (events, e) = receive(vec![frames[0].clone(), frames[2].clone()]).await.unwrap_err();
+        assert_eq!(events, vec!["AAAA"]);
+        assert!(matches!(e.downcast_ref::<VerifierError>(), Some(VerifierError::SignatureDoesNotMatch)));
+        let (events, e) = receive(frames[..2].to_vec()).await.unwrap_err();
+        assert_eq!(events, vec!["AAAA", "BBBB"]);
+        assert!(matches!(e.downcast_ref::<VerifierError>(), Some(VerifierError::Serialization(_))));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_verifier_chunk_signing_context() {
+        let signing_key = KSecretKey::from_str("secret").to_ksigning(request_date(), "us-east-1", "transcribe");
+        let scope = "20221001/us-east-1/transcribe/aws4_request";
+        let date = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+
+        let (mut parts, _) = Request::post("/stream-transcription")
+            .header("host", "transcribe.us-east-1.amazonaws.com")
+            .header("x-amz-content-sha256", STREAMING_EVENTS_PAYLOAD)
+            .header("x-amz-date", "20221001T120000Z")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let signed_headers = ["host", "x-amz-content-sha256", "x-amz-date"].map(str::to_string);
+        let canonical_request = CanonicalRequest::new(&parts, &[], &signed_headers, false, SignatureOptions::default());
+        let mut mac = HmacSha256::new_from_slice(signing_key.as_ref()).unwrap();
+        mac.update(string_to_sign(AWS4_HMAC_SHA256, &date, scope, &canonical_request).as_bytes());
+        let signature = hex::encode(mac.finalize().into_bytes());
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/{scope}, SignedHeaders={}, Signature={signature}",
+            signed_headers.join(";")
+        );
+        parts.headers.insert("authorization", authorization.parse().unwrap());
+
+        // The service receives the context to verify frames with, seeded by the request signature.
+        let implementation = service_fn(move |req: Request<Body>| {
+            let signature = signature.clone();
+            async move {
+                let context = req.extensions().get::<ChunkSigningContext>().unwrap();
+                assert_eq!(context.credential_scope(), scope);
+                assert_eq!(context.seed_signature(), signature);
+                Ok::<_, BoxError>(Response::new(Body::empty()))
+            }
+        });
+        let get_signing_key = service_fn(move |_: GetSigningKeyRequest| async move {
+            Ok::<_, BoxError>(GetSigningKeyResponse::builder().signing_key(signing_key).build().unwrap())
+        });
+        let verifier: AwsSigV4VerifierService<_, _, _> = AwsSigV4VerifierService::builder()
+            .region("us-east-1")
+            .service("transcribe")
+            .get_signing_key(get_signing_key)
+            .implementation(implementation)
+            .error_mapper(XmlErrorMapper::new("https://transcribe.amazonaws.com/doc/2017-10-26/"))
+            .clock(Arc::new(FixedClock::new(date)))
+            .build()
+            .unwrap();
+
+        let response = verifier.oneshot(Request::from_parts(parts, Body::empty())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,397 @@
+#![warn(clippy::all)]
+
+use {
+    crate::{
+        credentials::SecretString,
+        session_keys::{principal_tag_key, SessionDataExt, UserSessionData},
+        Clock, SystemClock,
+    },
+    aws_sdk_dynamodb::{model::AttributeValue, Client},
+    derive_builder::Builder,
+    log::error,
+    scratchstack_arn::Arn,
+    scratchstack_aws_principal::{Principal, PrincipalIdentity, SessionData, User},
+    scratchstack_aws_signature::{GetSigningKeyRequest, GetSigningKeyResponse, SignatureError},
+    std::{
+        collections::HashMap,
+        fmt::{Debug, Formatter, Result as FmtResult},
+        future::Future,
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+    },
+    tower::{BoxError, Service},
+};
+
+const MSG_ACCESS_KEY_PROVIDED_DOES_NOT_EXIST: &str = "The AWS access key provided does not exist in our records.";
+const MSG_SECURITY_TOKEN_INVALID: &str = "The security token included in the request is invalid.";
+
+/// The status of an access key that can be used to sign requests.
+const ACCESS_KEY_STATUS_ACTIVE: &str = "Active";
+
+/// The names of the table, index, and attributes [GetSigningKeyFromDynamoDB] uses.
+///
+/// Each item describes one access key and the IAM user it belongs to, with the string attributes `access_key_id`,
+/// `secret_key`, `user_id`, `account_id`, `path`, `user_name`, and `status`, the optional number attribute `expires_at`
+/// (in seconds since the Unix epoch), and the optional map attribute `tags` of string values. Each of these is named by
+/// the corresponding `*_attribute` field. Items without a `path` are given the path `/`; items without a `status` are
+/// active.
+///
+/// By default, items are read with `GetItem` using the access key id as the table's partition key. If `index_name` is
+/// set, they are instead found with a `Query` on that global secondary index, whose partition key must be the access
+/// key id attribute; since global secondary indexes don't support them, consistent reads are not used in that case.
+#[derive(Builder, Clone, Debug, Eq, PartialEq)]
+#[builder(setter(into))]
+pub struct DynamoDBConfig {
+    /// The table of access keys.
+    #[builder(default = "\"iam_credential\".to_string()")]
+    table_name: String,
+
+    /// The global secondary index keyed by access key id, if the access key id isn't the table's partition key.
+    #[builder(default, setter(into, strip_option))]
+    index_name: Option<String>,
+
+    /// Whether items are read with strongly consistent reads. Defaults to `true`.
+    #[builder(default = "true")]
+    consistent_read: bool,
+
+    /// The access key id attribute.
+    #[builder(default = "\"access_key_id\".to_string()")]
+    access_key_id_attribute: String,
+
+    /// The secret key attribute.
+    #[builder(default = "\"secret_key\".to_string()")]
+    secret_key_attribute: String,
+
+    /// The unique id of the user (`aws:userid`).
+    #[builder(default = "\"user_id\".to_string()")]
+    user_id_attribute: String,
+
+    /// The account id of the user.
+    #[builder(default = "\"account_id\".to_string()")]
+    account_id_attribute: String,
+
+    /// The path of the user.
+    #[builder(default = "\"path\".to_string()")]
+    path_attribute: String,
+
+    /// The user name, with its original case.
+    #[builder(default = "\"user_name\".to_string()")]
+    user_name_attribute: String,
+
+    /// The status (`Active` or `Inactive`) of the access key.
+    #[builder(default = "\"status\".to_string()")]
+    status_attribute: String,
+
+    /// The expiry of the access key, in seconds since the Unix epoch.
+    #[builder(default = "\"expires_at\".to_string()")]
+    expires_at_attribute: String,
+
+    /// The tags attached to the user, which are available to policies as `aws:PrincipalTag/<key>`.
+    #[builder(default = "\"tags\".to_string()")]
+    tags_attribute: String,
+}
+
+impl DynamoDBConfig {
+    /// Create a new [DynamoDBConfigBuilder] for overriding the default table and attribute names.
+    #[inline]
+    pub fn builder() -> DynamoDBConfigBuilder {
+        DynamoDBConfigBuilder::default()
+    }
+
+    /// Retreive the table of access keys.
+    #[inline]
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
+    /// Retreive the global secondary index keyed by access key id, if any.
+    #[inline]
+    pub fn index_name(&self) -> Option<&str> {
+        self.index_name.as_deref()
+    }
+
+    /// Indicates whether items are read with strongly consistent reads.
+    #[inline]
+    pub fn consistent_read(&self) -> bool {
+        self.consistent_read
+    }
+}
+
+impl Default for DynamoDBConfig {
+    fn default() -> Self {
+        Self::builder().build().unwrap()
+    }
+}
+
+/// An access key and the IAM user it belongs to, as read from an item.
+#[derive(Debug, PartialEq)]
+struct ItemCredential {
+    secret_key: SecretString,
+    user_id: String,
+    account_id: String,
+    path: String,
+    user_name: String,
+    status: String,
+    expires_at: Option<i64>,
+    tags: Vec<(String, String)>,
+}
+
+/// A service that provides a signing key for a given access key ID from a DynamoDB table.
+///
+/// The table and attribute names are described by the [DynamoDBConfig]. Access keys that are not found, whose status
+/// is not `Active`, or that have expired are rejected with `InvalidClientTokenId`. The principal is the IAM user the
+/// access key belongs to, and the session data includes the user's tags.
+///
+/// ```
+/// use scratchstack_http_framework::gsk_dynamodb::{DynamoDBConfig, GetSigningKeyFromDynamoDB};
+///
+/// # fn example(client: aws_sdk_dynamodb::Client) {
+/// let config = DynamoDBConfig::builder().table_name("credentials").index_name("by-access-key").build().unwrap();
+/// let get_signing_key = GetSigningKeyFromDynamoDB::new(client, "aws").with_config(config);
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct GetSigningKeyFromDynamoDB {
+    client: Client,
+    partition: String,
+    config: Arc<DynamoDBConfig>,
+    clock: Arc<dyn Clock>,
+}
+
+impl GetSigningKeyFromDynamoDB {
+    /// Create a new [GetSigningKeyFromDynamoDB] service using the default table and attribute names.
+    pub fn new(client: Client, partition: &str) -> Self {
+        Self {
+            client,
+            partition: partition.into(),
+            config: Arc::new(DynamoDBConfig::default()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Use the given table, index, and attribute names, returning the updated service.
+    pub fn with_config(mut self, config: DynamoDBConfig) -> Self {
+        self.config = Arc::new(config);
+        self
+    }
+
+    /// Use the given [Clock] to check the expiration of access keys, returning the updated service.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Retreive the table, index, and attribute names used.
+    #[inline]
+    pub fn config(&self) -> &DynamoDBConfig {
+        &self.config
+    }
+
+    /// Fetch the item for an access key, if there is one.
+    async fn fetch_item(&self, access_key: &str) -> Result<Option<HashMap<String, AttributeValue>>, BoxError> {
+        let config = &self.config;
+        let key = AttributeValue::S(access_key.to_string());
+
+        match &config.index_name {
+            None => {
+                let output = self
+                    .client
+                    .get_item()
+                    .table_name(&config.table_name)
+                    .key(&config.access_key_id_attribute, key)
+                    .consistent_read(config.consistent_read)
+                    .send()
+                    .await
+                    .map_err(internal_error)?;
+                Ok(output.item().cloned())
+            }
+
+            Some(index_name) => {
+                let output = self
+                    .client
+                    .query()
+                    .table_name(&config.table_name)
+                    .index_name(index_name)
+                    .key_condition_expression("#access_key_id = :access_key_id")
+                    .expression_attribute_names("#access_key_id", &config.access_key_id_attribute)
+                    .expression_attribute_values(":access_key_id", key)
+                    .limit(1)
+                    .send()
+                    .await
+                    .map_err(internal_error)?;
+                Ok(output.items().and_then(|items| items.first()).cloned())
+            }
+        }
+    }
+
+    /// Read the access key and user from an item.
+    fn parse_item(&self, item: &HashMap<String, AttributeValue>) -> Result<ItemCredential, BoxError> {
+        let config = &self.config;
+        let string = |name: &str| -> Result<Option<String>, BoxError> {
+            match item.get(name) {
+                None | Some(AttributeValue::Null(_)) => Ok(None),
+                Some(AttributeValue::S(s)) => Ok(Some(s.clone())),
+                Some(_) => Err(invalid_attribute(name)),
+            }
+        };
+        let required = |name: &str| string(name)?.ok_or_else(|| invalid_attribute(name));
+
+        let expires_at = match item.get(&config.expires_at_attribute) {
+            None | Some(AttributeValue::Null(_)) => None,
+            Some(AttributeValue::N(n)) => Some(n.parse().map_err(|_| invalid_attribute(&config.expires_at_attribute))?),
+            Some(_) => return Err(invalid_attribute(&config.expires_at_attribute)),
+        };
+
+        let tags = match item.get(&config.tags_attribute) {
+            None | Some(AttributeValue::Null(_)) => Vec::new(),
+            Some(AttributeValue::M(tags)) => {
+                let mut tags = tags
+                    .iter()
+                    .map(|(key, value)| match value {
+                        AttributeValue::S(value) => Ok((key.clone(), value.clone())),
+                        _ => Err(invalid_attribute(&config.tags_attribute)),
+                    })
+                    .collect::<Result<Vec<_>, BoxError>>()?;
+                tags.sort();
+                tags
+            }
+            Some(_) => return Err(invalid_attribute(&config.tags_attribute)),
+        };
+
+        Ok(ItemCredential {
+            secret_key: required(&config.secret_key_attribute)?.into(),
+            user_id: required(&config.user_id_attribute)?,
+            account_id: required(&config.account_id_attribute)?,
+            path: string(&config.path_attribute)?.unwrap_or_else(|| "/".to_string()),
+            user_name: required(&config.user_name_attribute)?,
+            status: string(&config.status_attribute)?.unwrap_or_else(|| ACCESS_KEY_STATUS_ACTIVE.to_string()),
+            expires_at,
+            tags,
+        })
+    }
+
+    async fn lookup(&self, req: GetSigningKeyRequest) -> Result<GetSigningKeyResponse, BoxError> {
+        let Some(item) = self.fetch_item(req.access_key()).await? else {
+            return Err(SignatureError::InvalidClientTokenId(MSG_ACCESS_KEY_PROVIDED_DOES_NOT_EXIST.to_string()).into());
+        };
+        let credential = self.parse_item(&item)?;
+
+        // Inactive and expired keys are reported the same way as invalid ones, as AWS does.
+        let expired = matches!(credential.expires_at, Some(expires_at) if expires_at <= self.clock.now().timestamp());
+        if credential.status != ACCESS_KEY_STATUS_ACTIVE || expired {
+            return Err(SignatureError::InvalidClientTokenId(MSG_SECURITY_TOKEN_INVALID.to_string()).into());
+        }
+
+        let user = User::new(&self.partition, &credential.account_id, &credential.path, &credential.user_name)?;
+        let user_arn: Arn = (&user).into();
+        let principal = Principal::new(vec![PrincipalIdentity::from(user)]);
+        let mut session_data = SessionData::from(
+            &UserSessionData::builder()
+                .user_name(credential.user_name)
+                .user_id(credential.user_id)
+                .account_id(credential.account_id)
+                .user_arn(user_arn.to_string())
+                .requested_region(req.region())
+                .build()?,
+        );
+        for (key, value) in credential.tags {
+            session_data.set_string(&principal_tag_key(&key), value);
+        }
+
+        let signing_key = credential.secret_key.signing_key(req.request_date(), req.region(), req.service());
+        GetSigningKeyResponse::builder()
+            .principal(principal)
+            .session_data(session_data)
+            .signing_key(signing_key)
+            .build()
+            .map_err(Into::into)
+    }
+}
+
+impl Debug for GetSigningKeyFromDynamoDB {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("GetSigningKeyFromDynamoDB")
+            .field("partition", &self.partition)
+            .field("config", &self.config)
+            .field("clock", &self.clock)
+            .finish_non_exhaustive()
+    }
+}
+
+fn internal_error<E: std::error::Error + Send + Sync + 'static>(e: E) -> BoxError {
+    error!("Failed to query for secret key: {}", e);
+    SignatureError::InternalServiceError(e.into()).into()
+}
+
+fn invalid_attribute(name: &str) -> BoxError {
+    error!("Credential item has a missing or invalid {} attribute", name);
+    SignatureError::InternalServiceError(format!("Invalid credential item attribute: {name}").into()).into()
+}
+
+impl Service<GetSigningKeyRequest> for GetSigningKeyFromDynamoDB {
+    type Response = GetSigningKeyResponse;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: GetSigningKeyRequest) -> Self::Future {
+        let this = self.clone();
+        Box::pin(async move { this.lookup(req).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{DynamoDBConfig, GetSigningKeyFromDynamoDB, ItemCredential},
+        aws_sdk_dynamodb::{model::AttributeValue, Client, Config, Region},
+        pretty_assertions::assert_eq,
+        std::collections::HashMap,
+    };
+
+    fn s(value: &str) -> AttributeValue {
+        AttributeValue::S(value.to_string())
+    }
+
+    #[test]
+    fn test_parse_item() {
+        let client = Client::from_conf(Config::builder().region(Region::new("us-east-1")).build());
+        let gsk = GetSigningKeyFromDynamoDB::new(client, "aws")
+            .with_config(DynamoDBConfig::builder().user_name_attribute("name").build().unwrap());
+        assert_eq!(gsk.config().table_name(), "iam_credential");
+        assert!(gsk.config().consistent_read());
+
+        let mut item = HashMap::from([
+            ("access_key_id".to_string(), s("AKIDEXAMPLE")),
+            ("secret_key".to_string(), s("secret")),
+            ("user_id".to_string(), s("AIDAEXAMPLE")),
+            ("account_id".to_string(), s("123456789012")),
+            ("name".to_string(), s("alice")),
+            ("expires_at".to_string(), AttributeValue::N("1700000000".to_string())),
+            ("tags".to_string(), AttributeValue::M(HashMap::from([("team".to_string(), s("blue"))]))),
+        ]);
+        assert_eq!(
+            gsk.parse_item(&item).unwrap(),
+            ItemCredential {
+                secret_key: "secret".into(),
+                user_id: "AIDAEXAMPLE".to_string(),
+                account_id: "123456789012".to_string(),
+                path: "/".to_string(),
+                user_name: "alice".to_string(),
+                status: "Active".to_string(),
+                expires_at: Some(1700000000),
+                tags: vec![("team".to_string(), "blue".to_string())],
+            }
+        );
+
+        item.insert("status".to_string(), AttributeValue::N("1".to_string()));
+        assert!(gsk.parse_item(&item).is_err());
+        item.remove("status");
+        item.remove("secret_key");
+        assert!(gsk.parse_item(&item).is_err());
+    }
+}
@@ -0,0 +1,354 @@
+#![warn(clippy::all)]
+
+use {
+    crate::{
+        credentials::SecretString,
+        session_keys::{principal_tag_key, SessionDataExt, UserSessionData},
+    },
+    log::{error, info},
+    scratchstack_arn::Arn,
+    scratchstack_aws_principal::{Principal, PrincipalIdentity, SessionData, User},
+    scratchstack_aws_signature::{GetSigningKeyRequest, GetSigningKeyResponse, SignatureError},
+    serde::Deserialize,
+    std::{
+        collections::{BTreeMap, HashMap},
+        fmt::{Debug, Formatter, Result as FmtResult},
+        fs,
+        future::{ready, Ready},
+        path::{Path, PathBuf},
+        sync::{Arc, RwLock, Weak},
+        task::{Context, Poll},
+        time::{Duration, SystemTime},
+    },
+    tokio::{task::JoinHandle, time::interval},
+    tower::{BoxError, Service},
+};
+
+const MSG_ACCESS_KEY_PROVIDED_DOES_NOT_EXIST: &str = "The AWS access key provided does not exist in our records.";
+
+/// The contents of a credentials file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CredentialsFile {
+    #[serde(default = "default_partition")]
+    partition: String,
+
+    #[serde(default)]
+    credentials: Vec<FileCredential>,
+}
+
+/// An access key and the IAM user it belongs to, as defined in a credentials file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileCredential {
+    access_key: String,
+    secret_key: SecretString,
+    account_id: String,
+    user_name: String,
+
+    #[serde(default = "default_path")]
+    path: String,
+
+    #[serde(default)]
+    user_id: Option<String>,
+
+    #[serde(default)]
+    tags: BTreeMap<String, String>,
+}
+
+fn default_partition() -> String {
+    "aws".to_string()
+}
+
+fn default_path() -> String {
+    "/".to_string()
+}
+
+/// The credentials loaded from the file, and the modification time and length of the file they were loaded from.
+#[derive(Default)]
+struct FileState {
+    partition: String,
+    credentials: HashMap<String, FileCredential>,
+    version: Option<(SystemTime, u64)>,
+}
+
+/// A signing key provider that reads access keys and the IAM users they belong to from a TOML or JSON file, for small
+/// deployments and local development.
+///
+/// Files whose name ends in `.toml` are parsed as TOML; anything else is parsed as JSON. The file holds an optional
+/// `partition` (defaulting to `aws`) and a list of `credentials`, each with an `access_key`, `secret_key`,
+/// `account_id`, and `user_name`, and optionally a `path` (defaulting to `/`), a `user_id` (defaulting to the user
+/// name), and a table of `tags`, which are available to policies as `aws:PrincipalTag/<key>`:
+///
+/// ```toml
+/// partition = "aws"
+///
+/// [[credentials]]
+/// access_key = "AKIDEXAMPLE"
+/// secret_key = "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY"
+/// account_id = "123456789012"
+/// user_name = "alice"
+/// tags = { team = "blue" }
+/// ```
+///
+/// The file is read when the provider is created. [GetSigningKeyFromFile::reload] reads it again if it has been
+/// modified, and [GetSigningKeyFromFile::watch] does so periodically in the background. If the file can't be read or
+/// parsed when reloading, the error is logged and the previously loaded credentials remain in use. Clones share the
+/// same credentials.
+#[derive(Clone)]
+pub struct GetSigningKeyFromFile {
+    path: Arc<PathBuf>,
+    state: Arc<RwLock<FileState>>,
+}
+
+impl GetSigningKeyFromFile {
+    /// Create a new [GetSigningKeyFromFile] provider, reading the credentials from the file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BoxError> {
+        let provider = Self {
+            path: Arc::new(path.as_ref().to_path_buf()),
+            state: Arc::new(RwLock::new(FileState::default())),
+        };
+        provider.read()?;
+        Ok(provider)
+    }
+
+    /// Retreive the path of the credentials file.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the number of access keys loaded.
+    pub fn len(&self) -> usize {
+        self.state.read().unwrap().credentials.len()
+    }
+
+    /// Indicates whether no access keys are loaded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Read the credentials file again if its modification time or length has changed since it was last read. Returns
+    /// `true` if the credentials were reloaded. If the file can't be read or parsed, the previously loaded credentials
+    /// are kept.
+    pub fn reload(&self) -> Result<bool, BoxError> {
+        let version = file_version(&self.path)?;
+        if version.is_some() && version == self.state.read().unwrap().version {
+            return Ok(false);
+        }
+
+        self.read()?;
+        Ok(true)
+    }
+
+    /// Check the credentials file for changes every `period`, reloading it when it has been modified. The task stops
+    /// once every clone of this provider has been dropped. This must be called from within a Tokio runtime.
+    pub fn watch(&self, period: Duration) -> JoinHandle<()> {
+        let path = self.path.clone();
+        let state = Arc::downgrade(&self.state);
+
+        tokio::spawn(async move {
+            let mut ticks = interval(period);
+            // The first tick completes immediately, and the file was just read.
+            ticks.tick().await;
+
+            loop {
+                ticks.tick().await;
+                let Some(provider) = Self::upgrade(&path, &state) else {
+                    break;
+                };
+
+                match provider.reload() {
+                    Ok(true) => info!("Reloaded credentials from {}", path.display()),
+                    Ok(false) => (),
+                    Err(e) => error!("Failed to reload credentials from {}: {}", path.display(), e),
+                }
+            }
+        })
+    }
+
+    fn upgrade(path: &Arc<PathBuf>, state: &Weak<RwLock<FileState>>) -> Option<Self> {
+        state.upgrade().map(|state| Self {
+            path: path.clone(),
+            state,
+        })
+    }
+
+    /// Read and parse the credentials file, replacing the loaded credentials.
+    fn read(&self) -> Result<(), BoxError> {
+        let version = file_version(&self.path)?;
+        let contents = fs::read_to_string(&*self.path)?;
+        let file: CredentialsFile = if self.path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&contents)?
+        } else {
+            serde_json::from_str(&contents)?
+        };
+
+        let mut credentials = HashMap::with_capacity(file.credentials.len());
+        for credential in file.credentials {
+            // Reject invalid users now rather than on every request.
+            User::new(&file.partition, &credential.account_id, &credential.path, &credential.user_name)?;
+            credentials.insert(credential.access_key.clone(), credential);
+        }
+
+        *self.state.write().unwrap() = FileState {
+            partition: file.partition,
+            credentials,
+            version,
+        };
+        Ok(())
+    }
+}
+
+/// Returns the modification time and length of a file, or `None` if the platform doesn't record modification times.
+fn file_version(path: &Path) -> Result<Option<(SystemTime, u64)>, BoxError> {
+    let metadata = fs::metadata(path)?;
+    Ok(metadata.modified().ok().map(|modified| (modified, metadata.len())))
+}
+
+impl Debug for GetSigningKeyFromFile {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("GetSigningKeyFromFile").field("path", &self.path).field("len", &self.len()).finish()
+    }
+}
+
+impl Service<GetSigningKeyRequest> for GetSigningKeyFromFile {
+    type Response = GetSigningKeyResponse;
+    type Error = BoxError;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: GetSigningKeyRequest) -> Self::Future {
+        let (partition, credential) = {
+            let state = self.state.read().unwrap();
+            (state.partition.clone(), state.credentials.get(req.access_key()).cloned())
+        };
+
+        let Some(credential) = credential else {
+            return ready(Err(SignatureError::InvalidClientTokenId(
+                MSG_ACCESS_KEY_PROVIDED_DOES_NOT_EXIST.to_string(),
+            )
+            .into()));
+        };
+
+        ready(response(&req, &partition, credential))
+    }
+}
+
+fn response(
+    req: &GetSigningKeyRequest,
+    partition: &str,
+    credential: FileCredential,
+) -> Result<GetSigningKeyResponse, BoxError> {
+    let user = User::new(partition, &credential.account_id, &credential.path, &credential.user_name)?;
+    let user_arn: Arn = (&user).into();
+    let principal = Principal::new(vec![PrincipalIdentity::from(user)]);
+    let mut session_data = SessionData::from(
+        &UserSessionData::builder()
+            .user_id(credential.user_id.unwrap_or_else(|| credential.user_name.clone()))
+            .user_name(credential.user_name)
+            .account_id(credential.account_id)
+            .user_arn(user_arn.to_string())
+            .requested_region(req.region())
+            .build()?,
+    );
+    for (key, value) in credential.tags {
+        session_data.set_string(&principal_tag_key(&key), value);
+    }
+
+    let signing_key = credential.secret_key.signing_key(req.request_date(), req.region(), req.service());
+    GetSigningKeyResponse::builder()
+        .principal(principal)
+        .session_data(session_data)
+        .signing_key(signing_key)
+        .build()
+        .map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::GetSigningKeyFromFile,
+        crate::{session_keys::SessionDataExt, test_util::signing_key_request},
+        pretty_assertions::assert_eq,
+        scratchstack_aws_signature::{GetSigningKeyRequest, SignatureError},
+        std::fs,
+        tower::ServiceExt,
+    };
+
+    fn request(access_key: &str) -> GetSigningKeyRequest {
+        signing_key_request(access_key, None, "example")
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_signing_key_from_file() {
+        let dir = std::env::temp_dir().join(format!("gsk-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let toml_path = dir.join("credentials.toml");
+        fs::write(
+            &toml_path,
+            r#"
+                [[credentials]]
+                access_key = "AKIDEXAMPLE"
+                secret_key = "secret"
+                account_id = "123456789012"
+                user_name = "alice"
+                path = "/engineering/"
+                tags = { team = "blue" }
+            "#,
+        )
+        .unwrap();
+
+        let gsk = GetSigningKeyFromFile::load(&toml_path).unwrap();
+        assert_eq!(gsk.len(), 1);
+        let response = gsk.clone().oneshot(request("AKIDEXAMPLE")).await.unwrap();
+        assert_eq!(response.session_data().username(), Some("alice"));
+        assert_eq!(response.session_data().principal_arn(), Some("arn:aws:iam::123456789012:user/engineering/alice"));
+        assert_eq!(response.session_data().principal_tag("team"), Some("blue"));
+        let e = gsk.clone().oneshot(request("AKIDOTHER")).await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<SignatureError>(), Some(SignatureError::InvalidClientTokenId(_))));
+
+        // Unmodified files aren't read again; modified ones (here, with a different length) are.
+        assert!(!gsk.reload().unwrap());
+        fs::write(
+            &toml_path,
+            r#"
+                partition = "aws-cn"
+
+                [[credentials]]
+                access_key = "AKIDOTHER"
+                secret_key = "secret"
+                account_id = "123456789012"
+                user_name = "bob"
+            "#,
+        )
+        .unwrap();
+        assert!(gsk.reload().unwrap());
+        assert!(gsk.clone().oneshot(request("AKIDEXAMPLE")).await.is_err());
+        let response = gsk.clone().oneshot(request("AKIDOTHER")).await.unwrap();
+        assert_eq!(response.session_data().principal_arn(), Some("arn:aws-cn:iam::123456789012:user/bob"));
+
+        // Invalid files are rejected, keeping the previous credentials.
+        fs::write(&toml_path, "credentials = 1").unwrap();
+        assert!(gsk.reload().is_err());
+        assert_eq!(gsk.len(), 1);
+
+        let json_path = dir.join("credentials.json");
+        fs::write(
+            &json_path,
+            r#"{"credentials": [{"access_key": "AKIDEXAMPLE", "secret_key": "secret", "account_id": "123456789012",
+                "user_name": "alice", "user_id": "AIDAEXAMPLE"}]}"#,
+        )
+        .unwrap();
+        let gsk = GetSigningKeyFromFile::load(&json_path).unwrap();
+        let response = gsk.oneshot(request("AKIDEXAMPLE")).await.unwrap();
+        assert_eq!(response.session_data().user_id(), Some("AIDAEXAMPLE"));
+
+        assert!(GetSigningKeyFromFile::load(dir.join("missing.json")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
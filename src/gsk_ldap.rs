@@ -0,0 +1,314 @@
+#![warn(clippy::all)]
+
+use {
+    crate::{
+        credentials::SecretString,
+        session_keys::{principal_tag_key, SessionDataExt, UserSessionData},
+    },
+    derive_builder::Builder,
+    ldap3::{ldap_escape, Ldap, Scope, SearchEntry},
+    log::error,
+    scratchstack_arn::Arn,
+    scratchstack_aws_principal::{Principal, PrincipalIdentity, SessionData, User},
+    scratchstack_aws_signature::{GetSigningKeyRequest, GetSigningKeyResponse, SignatureError},
+    std::{
+        collections::HashMap,
+        fmt::{Debug, Formatter, Result as FmtResult},
+        future::Future,
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+    },
+    tower::{BoxError, Service},
+};
+
+const MSG_ACCESS_KEY_PROVIDED_DOES_NOT_EXIST: &str = "The AWS access key provided does not exist in our records.";
+const MSG_SECURITY_TOKEN_INVALID: &str = "The security token included in the request is invalid.";
+
+/// The `userAccountControl` flag set on disabled Active Directory accounts.
+const UF_ACCOUNTDISABLE: i64 = 0x2;
+
+/// How [GetSigningKeyFromLdap] finds directory entries and maps their attributes to IAM users.
+///
+/// Entries are found by searching the subtree under `base_dn` for `filter` combined with an equality match of the
+/// access key id on `access_key_attribute`; exactly one entry must match. Every user belongs to `account_id` and has
+/// the path `path`. The defaults suit Active Directory.
+///
+/// ```
+/// use scratchstack_http_framework::gsk_ldap::LdapConfig;
+///
+/// let config = LdapConfig::builder()
+///     .base_dn("OU=Employees,DC=example,DC=com")
+///     .account_id("123456789012")
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Builder, Clone, Debug, Eq, PartialEq)]
+#[builder(setter(into))]
+pub struct LdapConfig {
+    /// The base DN searched for entries.
+    base_dn: String,
+
+    /// The account id every user belongs to.
+    account_id: String,
+
+    /// The path every user has. Defaults to `/`.
+    #[builder(default = "\"/\".to_string()")]
+    path: String,
+
+    /// The filter entries must also match.
+    #[builder(default = "\"(objectClass=user)\".to_string()")]
+    filter: String,
+
+    /// The attribute holding the access key id.
+    #[builder(default = "\"scratchstackAccessKeyId\".to_string()")]
+    access_key_attribute: String,
+
+    /// The attribute holding the secret key. This should be readable only by the account the provider binds as.
+    #[builder(default = "\"scratchstackSecretKey\".to_string()")]
+    secret_key_attribute: String,
+
+    /// The attribute holding the user name.
+    #[builder(default = "\"sAMAccountName\".to_string()")]
+    user_name_attribute: String,
+
+    /// The attribute holding the unique id of the user (`aws:userid`). If an entry doesn't have it, the user name is
+    /// used.
+    #[builder(default = "\"employeeID\".to_string()")]
+    user_id_attribute: String,
+
+    /// The attribute listing the DNs of the groups the user is a member of.
+    #[builder(default = "\"memberOf\".to_string()")]
+    group_attribute: String,
+
+    /// The prefix of the principal tag each group becomes: a member of `CN=Admins,OU=Groups,DC=example,DC=com` has the
+    /// principal tag `<prefix>Admins` set to `true`.
+    #[builder(default = "\"group:\".to_string()")]
+    group_tag_prefix: String,
+
+    /// Whether entries whose `userAccountControl` attribute has the disabled flag set are rejected. Defaults to `true`.
+    #[builder(default = "true")]
+    reject_disabled: bool,
+}
+
+impl LdapConfig {
+    /// Create a new [LdapConfigBuilder] for constructing an [LdapConfig].
+    #[inline]
+    pub fn builder() -> LdapConfigBuilder {
+        LdapConfigBuilder::default()
+    }
+
+    /// Returns the search filter for an access key id.
+    fn search_filter(&self, access_key: &str) -> String {
+        format!("(&{}({}={}))", self.filter, self.access_key_attribute, ldap_escape(access_key))
+    }
+
+    /// Read the access key and user from the attributes of an entry.
+    fn parse_entry(&self, attrs: &HashMap<String, Vec<String>>) -> Result<LdapCredential, BoxError> {
+        let first = |name: &str| attrs.get(name).and_then(|values| values.first()).cloned();
+        let required = |name: &str| {
+            first(name).ok_or_else(|| -> BoxError {
+                error!("Directory entry is missing the {} attribute", name);
+                SignatureError::InternalServiceError(format!("Directory entry is missing {name}").into()).into()
+            })
+        };
+
+        let user_name = required(&self.user_name_attribute)?;
+        let disabled = self.reject_disabled
+            && first("userAccountControl")
+                .and_then(|flags| flags.parse::<i64>().ok())
+                .is_some_and(|flags| flags & UF_ACCOUNTDISABLE != 0);
+
+        Ok(LdapCredential {
+            secret_key: required(&self.secret_key_attribute)?.into(),
+            user_id: first(&self.user_id_attribute).unwrap_or_else(|| user_name.clone()),
+            user_name,
+            groups: attrs
+                .get(&self.group_attribute)
+                .map(|groups| groups.iter().filter_map(|dn| group_name(dn)).collect())
+                .unwrap_or_default(),
+            disabled,
+        })
+    }
+}
+
+/// An access key and the IAM user it belongs to, as read from a directory entry.
+#[derive(Debug)]
+struct LdapCredential {
+    secret_key: SecretString,
+    user_name: String,
+    user_id: String,
+    groups: Vec<String>,
+    disabled: bool,
+}
+
+/// A service that provides a signing key for a given access key ID from an LDAP directory such as Active Directory,
+/// bridging directory identities into an AWS-compatible API.
+///
+/// The principal is an IAM user named after the directory entry, in the account and with the path given by the
+/// [LdapConfig]; each group the entry is a member of becomes a principal tag. Access keys that don't match exactly one
+/// entry, and disabled accounts, are rejected with `InvalidClientTokenId`.
+///
+/// The [Ldap] handle must already be bound as an account allowed to read the secret key attribute; it is cloned for
+/// each lookup, so lookups share its connection.
+#[derive(Clone)]
+pub struct GetSigningKeyFromLdap {
+    ldap: Ldap,
+    partition: String,
+    config: Arc<LdapConfig>,
+}
+
+impl GetSigningKeyFromLdap {
+    /// Create a new [GetSigningKeyFromLdap] service.
+    pub fn new(ldap: Ldap, partition: &str, config: LdapConfig) -> Self {
+        Self {
+            ldap,
+            partition: partition.into(),
+            config: Arc::new(config),
+        }
+    }
+
+    /// Retreive the directory search and attribute configuration.
+    #[inline]
+    pub fn config(&self) -> &LdapConfig {
+        &self.config
+    }
+
+    async fn lookup(mut self, req: GetSigningKeyRequest) -> Result<GetSigningKeyResponse, BoxError> {
+        let config = self.config.clone();
+        let attrs = [
+            config.secret_key_attribute.as_str(),
+            config.user_name_attribute.as_str(),
+            config.user_id_attribute.as_str(),
+            config.group_attribute.as_str(),
+            "userAccountControl",
+        ];
+
+        let (entries, _) = self
+            .ldap
+            .search(&config.base_dn, Scope::Subtree, &config.search_filter(req.access_key()), attrs.to_vec())
+            .await
+            .and_then(|result| result.success())
+            .map_err(internal_error)?;
+
+        // An access key matching several entries is a directory error, but is rejected rather than guessed at.
+        let [entry] = <[_; 1]>::try_from(entries).map_err(|_| -> BoxError {
+            SignatureError::InvalidClientTokenId(MSG_ACCESS_KEY_PROVIDED_DOES_NOT_EXIST.to_string()).into()
+        })?;
+        let credential = config.parse_entry(&SearchEntry::construct(entry).attrs)?;
+        if credential.disabled {
+            return Err(SignatureError::InvalidClientTokenId(MSG_SECURITY_TOKEN_INVALID.to_string()).into());
+        }
+
+        let user = User::new(&self.partition, &config.account_id, &config.path, &credential.user_name)?;
+        let user_arn: Arn = (&user).into();
+        let principal = Principal::new(vec![PrincipalIdentity::from(user)]);
+        let mut session_data = SessionData::from(
+            &UserSessionData::builder()
+                .user_name(credential.user_name)
+                .user_id(credential.user_id)
+                .account_id(config.account_id.clone())
+                .user_arn(user_arn.to_string())
+                .requested_region(req.region())
+                .build()?,
+        );
+        for group in credential.groups {
+            session_data.set_string(&principal_tag_key(&format!("{}{}", config.group_tag_prefix, group)), "true");
+        }
+
+        let signing_key = credential.secret_key.signing_key(req.request_date(), req.region(), req.service());
+        GetSigningKeyResponse::builder()
+            .principal(principal)
+            .session_data(session_data)
+            .signing_key(signing_key)
+            .build()
+            .map_err(Into::into)
+    }
+}
+
+impl Debug for GetSigningKeyFromLdap {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("GetSigningKeyFromLdap")
+            .field("partition", &self.partition)
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Service<GetSigningKeyRequest> for GetSigningKeyFromLdap {
+    type Response = GetSigningKeyResponse;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: GetSigningKeyRequest) -> Self::Future {
+        Box::pin(self.clone().lookup(req))
+    }
+}
+
+/// Returns the common name of a group from its DN, e.g. `Admins` for `CN=Admins,OU=Groups,DC=example,DC=com`.
+fn group_name(dn: &str) -> Option<String> {
+    let (attr, value) = dn.split(',').next()?.split_once('=')?;
+    attr.trim().eq_ignore_ascii_case("cn").then(|| value.trim().to_string())
+}
+
+fn internal_error<E: std::error::Error + Send + Sync + 'static>(e: E) -> BoxError {
+    error!("Failed to search directory for secret key: {}", e);
+    SignatureError::InternalServiceError(e.into()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{group_name, LdapConfig},
+        pretty_assertions::assert_eq,
+        std::collections::HashMap,
+    };
+
+    #[test]
+    fn test_ldap_config() {
+        let config = LdapConfig::builder().base_dn("DC=example,DC=com").account_id("123456789012").build().unwrap();
+        assert_eq!(
+            config.search_filter("AKID*)(cn=*"),
+            r"(&(objectClass=user)(scratchstackAccessKeyId=AKID\2a\29\28cn=\2a))"
+        );
+        assert!(LdapConfig::builder().base_dn("DC=example,DC=com").build().is_err());
+
+        assert_eq!(group_name("CN=Admins,OU=Groups,DC=example,DC=com").as_deref(), Some("Admins"));
+        assert_eq!(group_name("cn = Developers , dc=example"), Some("Developers".to_string()));
+        assert_eq!(group_name("OU=Groups,DC=example,DC=com"), None);
+    }
+
+    #[test]
+    fn test_parse_entry() {
+        let config = LdapConfig::builder().base_dn("DC=example,DC=com").account_id("123456789012").build().unwrap();
+        let mut attrs: HashMap<String, Vec<String>> = [
+            ("sAMAccountName", vec!["alice"]),
+            ("scratchstackSecretKey", vec!["secret"]),
+            ("memberOf", vec!["CN=Admins,OU=Groups,DC=example,DC=com", "OU=Staff,DC=example,DC=com"]),
+            ("userAccountControl", vec!["512"]),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.into_iter().map(String::from).collect()))
+        .collect();
+
+        let credential = config.parse_entry(&attrs).unwrap();
+        assert_eq!(credential.user_name, "alice");
+        assert_eq!(credential.user_id, "alice");
+        assert_eq!(credential.secret_key.expose_secret(), "secret");
+        assert_eq!(credential.groups, vec!["Admins".to_string()]);
+        assert!(!credential.disabled);
+
+        attrs.insert("employeeID".to_string(), vec!["E1234".to_string()]);
+        attrs.insert("userAccountControl".to_string(), vec!["514".to_string()]);
+        let credential = config.parse_entry(&attrs).unwrap();
+        assert_eq!(credential.user_id, "E1234");
+        assert!(credential.disabled);
+
+        attrs.remove("scratchstackSecretKey");
+        assert!(config.parse_entry(&attrs).is_err());
+    }
+}
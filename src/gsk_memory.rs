@@ -0,0 +1,181 @@
+use {
+    crate::credentials::SecretString,
+    scratchstack_aws_principal::{Principal, SessionData},
+    scratchstack_aws_signature::{GetSigningKeyRequest, GetSigningKeyResponse, SignatureError},
+    std::{
+        collections::HashMap,
+        fmt::{Debug, Formatter, Result as FmtResult},
+        future::{ready, Ready},
+        sync::{Arc, RwLock},
+        task::{Context, Poll},
+    },
+    tower::{BoxError, Service},
+};
+
+const MSG_ACCESS_KEY_PROVIDED_DOES_NOT_EXIST: &str = "The AWS access key provided does not exist in our records.";
+
+/// The secret key and identity of an access key held by a [GetSigningKeyFromMemory] provider.
+#[derive(Clone)]
+struct MemoryCredential {
+    secret_key: SecretString,
+    principal: Principal,
+    session_data: SessionData,
+}
+
+/// A signing key provider backed by an in-memory map of access keys, for tests, examples, and demos that don't have a
+/// database.
+///
+/// Each access key maps to its secret key and the [Principal] and [SessionData] returned for requests signed with it.
+/// Clones share the same map, so access keys can be added or removed after the provider has been handed to a
+/// verifier. Requests signed with an unknown access key are rejected with `InvalidClientTokenId`; session tokens are
+/// not checked.
+///
+/// ```
+/// use {
+///     scratchstack_aws_principal::{Principal, SessionData, User},
+///     scratchstack_http_framework::GetSigningKeyFromMemory,
+/// };
+///
+/// let user = User::new("aws", "123456789012", "/", "alice").unwrap();
+/// let get_signing_key = GetSigningKeyFromMemory::new().with_credential(
+///     "AKIDEXAMPLE",
+///     "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+///     Principal::from(vec![user.into()]),
+///     SessionData::new(),
+/// );
+/// assert!(get_signing_key.contains("AKIDEXAMPLE"));
+/// ```
+#[derive(Clone, Default)]
+pub struct GetSigningKeyFromMemory {
+    credentials: Arc<RwLock<HashMap<String, MemoryCredential>>>,
+}
+
+impl GetSigningKeyFromMemory {
+    /// Create a new [GetSigningKeyFromMemory] provider with no access keys.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an access key, returning the updated provider.
+    pub fn with_credential(
+        self,
+        access_key: &str,
+        secret_key: &str,
+        principal: Principal,
+        session_data: SessionData,
+    ) -> Self {
+        self.insert(access_key, secret_key, principal, session_data);
+        self
+    }
+
+    /// Add an access key, replacing any existing access key with the same id.
+    pub fn insert(&self, access_key: &str, secret_key: &str, principal: Principal, session_data: SessionData) {
+        let credential = MemoryCredential {
+            secret_key: SecretString::from(secret_key),
+            principal,
+            session_data,
+        };
+        self.credentials.write().unwrap().insert(access_key.to_string(), credential);
+    }
+
+    /// Remove an access key. Returns `true` if the access key was present.
+    pub fn remove(&self, access_key: &str) -> bool {
+        self.credentials.write().unwrap().remove(access_key).is_some()
+    }
+
+    /// Indicates whether the provider holds the given access key.
+    pub fn contains(&self, access_key: &str) -> bool {
+        self.credentials.read().unwrap().contains_key(access_key)
+    }
+
+    /// Returns the number of access keys held.
+    pub fn len(&self) -> usize {
+        self.credentials.read().unwrap().len()
+    }
+
+    /// Indicates whether the provider holds no access keys.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Debug for GetSigningKeyFromMemory {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let credentials = self.credentials.read().unwrap();
+        let mut access_keys: Vec<&String> = credentials.keys().collect();
+        access_keys.sort();
+        f.debug_struct("GetSigningKeyFromMemory").field("access_keys", &access_keys).finish()
+    }
+}
+
+impl Service<GetSigningKeyRequest> for GetSigningKeyFromMemory {
+    type Response = GetSigningKeyResponse;
+    type Error = BoxError;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: GetSigningKeyRequest) -> Self::Future {
+        let Some(credential) = self.credentials.read().unwrap().get(req.access_key()).cloned() else {
+            return ready(Err(SignatureError::InvalidClientTokenId(
+                MSG_ACCESS_KEY_PROVIDED_DOES_NOT_EXIST.to_string(),
+            )
+            .into()));
+        };
+
+        let signing_key = credential.secret_key.signing_key(req.request_date(), req.region(), req.service());
+        let response = GetSigningKeyResponse::builder()
+            .principal(credential.principal)
+            .session_data(credential.session_data)
+            .signing_key(signing_key)
+            .build()
+            .map_err(Into::into);
+        ready(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::GetSigningKeyFromMemory,
+        crate::{
+            session_keys::{SessionDataExt, USERNAME},
+            test_util::signing_key_request,
+        },
+        pretty_assertions::assert_eq,
+        scratchstack_aws_principal::{Principal, SessionData, User},
+        scratchstack_aws_signature::{GetSigningKeyRequest, SignatureError},
+        tower::ServiceExt,
+    };
+
+    fn request(access_key: &str) -> GetSigningKeyRequest {
+        signing_key_request(access_key, None, "example")
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_get_signing_key_from_memory() {
+        let user = User::new("aws", "123456789012", "/", "alice").unwrap();
+        let mut session_data = SessionData::new();
+        session_data.set_string(USERNAME, "alice");
+
+        let gsk = GetSigningKeyFromMemory::new();
+        assert!(gsk.is_empty());
+        gsk.clone().insert("AKIDEXAMPLE", "secret", Principal::from(vec![user.into()]), session_data);
+        assert_eq!(gsk.len(), 1);
+        assert!(gsk.contains("AKIDEXAMPLE"));
+        assert_eq!(format!("{:?}", gsk), r#"GetSigningKeyFromMemory { access_keys: ["AKIDEXAMPLE"] }"#);
+
+        let response = gsk.clone().oneshot(request("AKIDEXAMPLE")).await.unwrap();
+        assert_eq!(response.session_data().username(), Some("alice"));
+        assert!(response.principal().iter().next().is_some());
+
+        let e = gsk.clone().oneshot(request("AKIDOTHER")).await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<SignatureError>(), Some(SignatureError::InvalidClientTokenId(_))));
+
+        assert!(gsk.remove("AKIDEXAMPLE"));
+        assert!(!gsk.remove("AKIDEXAMPLE"));
+        assert!(gsk.clone().oneshot(request("AKIDEXAMPLE")).await.is_err());
+    }
+}
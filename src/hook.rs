@@ -0,0 +1,78 @@
+use {
+    crate::RequestId,
+    http::request::Parts,
+    hyper::{Body, Response},
+    scratchstack_aws_principal::{Principal, SessionData},
+    tower::{util::BoxCloneService, BoxError},
+};
+
+/// The result of a pre-authentication hook.
+#[derive(Debug)]
+pub enum PreAuthOutcome {
+    /// Continue authenticating the request using the (possibly modified) request head.
+    Continue(Parts),
+
+    /// Answer the request with the given response without authenticating it, e.g. for a denylisted client or while
+    /// the service is in maintenance mode.
+    Respond(Response<Body>),
+}
+
+/// A type-erased hook invoked with the request head before the request is authenticated.
+///
+/// Any changes the hook makes to the headers are seen by signature validation. The route is selected before the hook
+/// runs, so changes to the method or path do not affect route selection.
+pub type BoxPreAuthHook = BoxCloneService<Parts, PreAuthOutcome, BoxError>;
+
+/// A successfully authenticated request, passed to the post-authentication hook.
+#[derive(Clone, Debug)]
+pub struct AuthenticatedRequest {
+    principal: Principal,
+    session_data: SessionData,
+    request_id: RequestId,
+}
+
+impl AuthenticatedRequest {
+    /// Create a new [AuthenticatedRequest].
+    pub fn new(principal: Principal, session_data: SessionData, request_id: RequestId) -> Self {
+        Self {
+            principal,
+            session_data,
+            request_id,
+        }
+    }
+
+    /// Returns the principal the request was authenticated as.
+    #[inline]
+    pub fn principal(&self) -> &Principal {
+        &self.principal
+    }
+
+    /// Returns the session data of the request.
+    #[inline]
+    pub fn session_data(&self) -> &SessionData {
+        &self.session_data
+    }
+
+    /// Returns the session data of the request for modification.
+    #[inline]
+    pub fn session_data_mut(&mut self) -> &mut SessionData {
+        &mut self.session_data
+    }
+
+    /// Returns the request id.
+    #[inline]
+    pub fn request_id(&self) -> RequestId {
+        self.request_id
+    }
+
+    /// Consume this [AuthenticatedRequest], returning the principal and session data.
+    pub fn into_parts(self) -> (Principal, SessionData) {
+        (self.principal, self.session_data)
+    }
+}
+
+/// A type-erased hook invoked after a request is authenticated and before it is passed to the service implementation.
+///
+/// The hook may record the authentication (e.g. for auditing) and enrich the session data it returns. Errors returned
+/// by the hook are rendered by the error mapper.
+pub type BoxOnAuthenticated = BoxCloneService<AuthenticatedRequest, AuthenticatedRequest, BoxError>;
@@ -0,0 +1,201 @@
+use {
+    crate::{
+        error::{as_service_error, client_message, json_error_code},
+        ErrorMapper, MessageCatalog, RequestId,
+    },
+    async_trait::async_trait,
+    hyper::{Body, Response},
+    serde::Serialize,
+    std::sync::Arc,
+    tower::BoxError,
+};
+
+/// The content type used by the AWS JSON 1.0 protocol.
+pub const AWS_JSON_1_0_CONTENT_TYPE: &str = "application/x-amz-json-1.0";
+
+/// The content type used by the AWS JSON 1.1 protocol.
+pub const AWS_JSON_1_1_CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+
+/// An implementation of [ErrorMapper] that returns an AWS JSON 1.0/1.1 protocol body, as used by DynamoDB, SQS, and
+/// similar services: `{"__type": "<code>", "message": "<message>"}`.
+///
+/// The request id, if known, is returned in the `x-amzn-RequestId` header.
+#[derive(Clone, Debug)]
+pub struct JsonErrorMapper {
+    content_type: String,
+    message_catalog: Option<Arc<MessageCatalog>>,
+}
+
+impl JsonErrorMapper {
+    /// Create a new [JsonErrorMapper] for the AWS JSON 1.1 protocol.
+    pub fn new() -> Self {
+        Self::with_content_type(AWS_JSON_1_1_CONTENT_TYPE)
+    }
+
+    /// Create a new [JsonErrorMapper] that returns the given content type, e.g. [AWS_JSON_1_0_CONTENT_TYPE].
+    pub fn with_content_type(content_type: &str) -> Self {
+        Self {
+            content_type: content_type.to_string(),
+            message_catalog: None,
+        }
+    }
+
+    /// Use the given [MessageCatalog] to render client-facing error messages.
+    pub fn with_message_catalog(mut self, message_catalog: Arc<MessageCatalog>) -> Self {
+        self.message_catalog = Some(message_catalog);
+        self
+    }
+}
+
+impl Default for JsonErrorMapper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct JsonError {
+    #[serde(rename = "__type")]
+    r#type: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+/// An implementation of [ErrorMapper] that returns a REST-JSON protocol error, as returned by API Gateway-fronted AWS
+/// services: a `{"message": "<message>"}` body with the error code in the `x-amzn-ErrorType` header.
+///
+/// The request id, if known, is returned in the `x-amzn-RequestId` header.
+#[derive(Clone, Debug, Default)]
+pub struct RestJsonErrorMapper {
+    message_catalog: Option<Arc<MessageCatalog>>,
+}
+
+impl RestJsonErrorMapper {
+    /// Create a new [RestJsonErrorMapper].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the given [MessageCatalog] to render client-facing error messages.
+    pub fn with_message_catalog(mut self, message_catalog: Arc<MessageCatalog>) -> Self {
+        self.message_catalog = Some(message_catalog);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct RestJsonError {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+#[async_trait]
+impl ErrorMapper for JsonErrorMapper {
+    async fn map_error(self, e: BoxError, request_id: Option<RequestId>) -> Result<Response<Body>, BoxError> {
+        match as_service_error(&e) {
+            Some(service_error) => {
+                let error = JsonError {
+                    r#type: json_error_code(service_error.error_code()).to_string(),
+                    message: client_message(service_error, self.message_catalog.as_deref(), request_id),
+                };
+
+                let mut builder = Response::builder()
+                    .status(service_error.http_status())
+                    .header("Content-Type", self.content_type.as_str());
+                if let Some(request_id) = request_id {
+                    builder = builder.header("x-amzn-RequestId", request_id.to_string());
+                }
+
+                builder.body(Body::from(serde_json::to_string(&error)?)).map_err(Into::into)
+            }
+            None => Err(e),
+        }
+    }
+}
+
+#[async_trait]
+impl ErrorMapper for RestJsonErrorMapper {
+    async fn map_error(self, e: BoxError, request_id: Option<RequestId>) -> Result<Response<Body>, BoxError> {
+        match as_service_error(&e) {
+            Some(service_error) => {
+                let error = RestJsonError {
+                    message: client_message(service_error, self.message_catalog.as_deref(), request_id),
+                };
+
+                let mut builder = Response::builder()
+                    .status(service_error.http_status())
+                    .header("Content-Type", "application/json")
+                    .header("x-amzn-ErrorType", json_error_code(service_error.error_code()));
+                if let Some(request_id) = request_id {
+                    builder = builder.header("x-amzn-RequestId", request_id.to_string());
+                }
+
+                builder.body(Body::from(serde_json::to_string(&error)?)).map_err(Into::into)
+            }
+            None => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{JsonErrorMapper, RestJsonErrorMapper},
+        crate::{ErrorMapper, RequestId, VerifierError},
+        http::StatusCode,
+        pretty_assertions::assert_eq,
+    };
+
+    #[test_log::test(tokio::test)]
+    async fn test_json_error() {
+        let request_id = RequestId::from_timestamp_and_random(0, 1);
+        let response = JsonErrorMapper::new()
+            .map_error(VerifierError::SignatureDoesNotMatch.into(), Some(request_id))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(response.headers()["content-type"], "application/x-amz-json-1.1");
+        assert_eq!(response.headers()["x-amzn-requestid"], request_id.to_string().as_str());
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["__type"], "SignatureDoesNotMatch");
+        assert!(body["message"].as_str().unwrap().starts_with("The request signature we calculated"));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_rest_json_error() {
+        let response = RestJsonErrorMapper::new().map_error(VerifierError::RequestExpired.into(), None).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()["x-amzn-errortype"], "RequestExpired");
+        assert!(!response.headers().contains_key("x-amzn-requestid"));
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body, serde_json::json!({"message": "Request timestamp is outside of the allowed time window"}));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_access_denied() {
+        let error = || VerifierError::AccessDenied {
+            principal_arn: "arn:aws:iam::123456789012:user/test".to_string(),
+            action: Some("dynamodb:GetItem".to_string()),
+            resource: Some("arn:aws:dynamodb:us-west-2:123456789012:table/Books".to_string()),
+            explicit_deny: true,
+        };
+        let message = "User: arn:aws:iam::123456789012:user/test is not authorized to perform: dynamodb:GetItem on \
+                       resource: arn:aws:dynamodb:us-west-2:123456789012:table/Books with an explicit deny";
+
+        let response = JsonErrorMapper::new().map_error(error().into(), None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body, serde_json::json!({"__type": "AccessDeniedException", "message": message}));
+
+        let response = RestJsonErrorMapper::new().map_error(error().into(), None).await.unwrap();
+        assert_eq!(response.headers()["x-amzn-errortype"], "AccessDeniedException");
+    }
+}
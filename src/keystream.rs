@@ -0,0 +1,26 @@
+use {
+    hmac::{Hmac, Mac},
+    sha2::Sha256,
+};
+
+pub(crate) type HmacSha256 = Hmac<Sha256>;
+
+/// Derive a key for the given purpose.
+pub(crate) fn derive_key(key: &[u8], purpose: &[u8]) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(purpose);
+    mac.finalize().into_bytes().into()
+}
+
+/// XOR data with the HMAC-SHA256 counter mode keystream for the nonce. This both encrypts and decrypts.
+pub(crate) fn apply_keystream(key: &[u8; 32], nonce: &[u8], data: &mut [u8]) {
+    for (counter, chunk) in data.chunks_mut(32).enumerate() {
+        let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(nonce);
+        mac.update(&(counter as u64).to_be_bytes());
+        let block = mac.finalize().into_bytes();
+        for (byte, key_byte) in chunk.iter_mut().zip(block.iter()) {
+            *byte ^= key_byte;
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use {
+    crate::{AwsSigV4VerifierService, AwsSigV4VerifierServiceBuilder, ErrorMapper},
+    bytes::Bytes,
+    http_body::Body as HttpBody,
+    hyper::{body::Body, Request, Response},
+    scratchstack_aws_signature::{GetSigningKeyRequest, GetSigningKeyResponse},
+    std::{
+        any::type_name,
+        fmt::{Debug, Formatter, Result as FmtResult},
+        marker::PhantomData,
+    },
+    tower::{BoxError, Layer, Service},
+};
+
+/// A Tower [Layer] that wraps a service in an [AwsSigV4VerifierService].
+///
+/// This allows the verifier to be composed with other middleware in a
+/// [ServiceBuilder][tower::ServiceBuilder] stack. The layer holds an [AwsSigV4VerifierServiceBuilder] with every
+/// setting of the verifier; the wrapped service becomes the verifier's implementation. As with
+/// [AwsSigV4VerifierService], the request body type defaults to [hyper::Body].
+///
+/// Layers are created with [AwsSigV4VerifierServiceBuilder::build_layer]:
+///
+/// ```ignore
+/// let layer = AwsSigV4VerifierLayer::builder()
+///     .region("us-east-1")
+///     .service("service")
+///     .get_signing_key(get_signing_key)
+///     .error_mapper(XmlErrorMapper::new("https://service.example.com/doc/2022-10-01/"))
+///     .build_layer()?;
+/// let service = ServiceBuilder::new().layer(layer).service(implementation);
+/// ```
+pub struct AwsSigV4VerifierLayer<G, S, E, B = Body> {
+    builder: AwsSigV4VerifierServiceBuilder<G, S, E>,
+    _body: PhantomData<fn() -> B>,
+}
+
+impl<G, S, E> AwsSigV4VerifierLayer<G, S, E>
+where
+    G: Clone,
+    S: Clone,
+    E: Clone,
+{
+    /// Create a new [AwsSigV4VerifierServiceBuilder] for configuring an [AwsSigV4VerifierLayer]. The implementation
+    /// need not be set.
+    #[inline]
+    pub fn builder() -> AwsSigV4VerifierServiceBuilder<G, S, E> {
+        AwsSigV4VerifierServiceBuilder::default()
+    }
+}
+
+impl<G, S, E, B> AwsSigV4VerifierLayer<G, S, E, B>
+where
+    G: Clone,
+    S: Clone,
+    E: Clone,
+{
+    /// Create a new [AwsSigV4VerifierLayer] from a builder whose required settings, other than the implementation,
+    /// have been checked.
+    pub(crate) fn new(builder: AwsSigV4VerifierServiceBuilder<G, S, E>) -> Self {
+        Self {
+            builder,
+            _body: PhantomData,
+        }
+    }
+}
+
+impl<G, S, E, B> Clone for AwsSigV4VerifierLayer<G, S, E, B>
+where
+    G: Clone,
+    S: Clone,
+    E: Clone,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.builder.clone())
+    }
+}
+
+impl<G, S, E, B> Debug for AwsSigV4VerifierLayer<G, S, E, B> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("AwsSigV4VerifierLayer")
+            .field("get_signing_key", &type_name::<G>())
+            .field("implementation", &type_name::<S>())
+            .field("error_mapper", &type_name::<E>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<G, S, E, B> Layer<S> for AwsSigV4VerifierLayer<G, S, E, B>
+where
+    G: Service<GetSigningKeyRequest, Response = GetSigningKeyResponse, Error = BoxError> + Clone + Send + 'static,
+    G::Future: Send,
+    S: Service<Request<B>, Response = Response<Body>, Error = BoxError> + Clone + Send + 'static,
+    S::Future: Send,
+    E: ErrorMapper,
+    B: HttpBody<Data = Bytes> + From<Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Service = AwsSigV4VerifierService<G, S, E, B>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        let mut builder = self.builder.clone();
+        builder.implementation(inner);
+        builder.build().expect("the other required fields of AwsSigV4VerifierService are checked by build_layer")
+    }
+}
@@ -6,25 +6,230 @@
 //! This crate provides a set of utilities for writing an AWS-like service that uses SigV4 authentication and Aspen
 //! (AWS IAM) authorization.
 
+/// Provisioning of users and access keys for the [GetSigningKeyFromDatabase][gsk_direct::GetSigningKeyFromDatabase]
+/// schema, generating access key ids and secret keys in the format the verifier accepts, plus migrations creating the
+/// default tables.
+#[cfg(feature = "admin")]
+pub mod admin;
+
+/// Utilities for benchmarking services built on this framework.
+///
+/// These take the signing key database and the service implementation out of the measurement so the cost of the
+/// verifier itself (or of a downstream service's middleware stack) can be measured in isolation.
+#[cfg(feature = "bench_support")]
+pub mod bench_support;
+
+/// Encoding of AWS event streams (`application/vnd.amazon.eventstream`), for streaming APIs such as
+/// `SelectObjectContent`.
+#[cfg(feature = "eventstream")]
+pub mod eventstream;
+
 /// For services that have direct access to the authentication database, this module provides a GetSigningKeyProvider
 /// implementation that queries the database for the secret key and converts it to a signing key.
 #[cfg(feature = "gsk_direct")]
 pub mod gsk_direct;
 
+/// For services that keep their identity data in DynamoDB, this module provides a GetSigningKeyProvider implementation
+/// that reads the secret key and the user it belongs to from a DynamoDB table.
+#[cfg(feature = "gsk_dynamodb")]
+pub mod gsk_dynamodb;
+
+/// For small deployments and local development, this module provides a GetSigningKeyProvider implementation that reads
+/// access keys and the IAM users they belong to from a TOML or JSON file, reloading it when it changes.
+#[cfg(feature = "gsk_file")]
+pub mod gsk_file;
+
+/// For services whose users live in an LDAP directory such as Active Directory, this module provides a
+/// GetSigningKeyProvider implementation that maps access keys to directory entries, with groups as principal tags.
+#[cfg(feature = "gsk_ldap")]
+pub mod gsk_ldap;
+
+/// Opaque, tamper-proof pagination tokens (`NextToken` and `Marker` values) carrying a handler's cursor state.
+#[cfg(feature = "pagination")]
+pub mod pagination;
+
+/// For services that have direct access to the IAM database, this module provides a [PolicyProvider] implementation
+/// that queries the database for the inline and managed policies of users and their groups.
+#[cfg(feature = "gsk_direct")]
+pub mod policy_direct;
+
+/// Self-contained session tokens for temporary credentials, carrying their claims signed and encrypted with a
+/// rotating service key so they can be validated without a database lookup.
+#[cfg(feature = "session_token")]
+pub mod session_token;
+
+/// A policy simulator and an implementation of the IAM `SimulatePrincipalPolicy` action, for debugging the policies of
+/// services built on this framework.
+#[cfg(feature = "simulate")]
+pub mod simulate;
+
+/// A reference implementation of the STS `GetCallerIdentity`, `GetSessionToken`, and `AssumeRole` actions, issuing
+/// temporary credentials through a pluggable [TokenIssuer][sts::TokenIssuer].
+#[cfg(feature = "sts")]
+pub mod sts;
+
+/// Helpers for handling secrets safely: constant-time comparison, a string type that is zeroed on drop and redacted
+/// from [Debug][std::fmt::Debug] output, and signing key derivation that doesn't leave the secret key in memory.
+pub mod credentials;
+
+/// Commonly used traits and types, including the upstream Scratchstack types needed to implement a service.
+///
+/// This re-exports the `scratchstack-aws-principal`, `scratchstack-aws-signature`, and `scratchstack-errors` types
+/// from the versions this crate is built against, so services don't need to pin matching versions themselves.
+///
+/// ```
+/// use scratchstack_http_framework::prelude::*;
+/// ```
+pub mod prelude;
+
+/// Protocol-specific helpers for serializing service responses.
+pub mod proto;
+
+/// Routers dispatching requests to typed handlers and [Operation][router::Operation]s by AWS protocol, for building
+/// services on top of the verifier.
+pub mod router;
+
+/// Well-known session data keys and typed accessors for [SessionData][scratchstack_aws_principal::SessionData].
+pub mod session_keys;
+
+/// Declarative constraints on handler inputs, reported by the routers as a single AWS-style `ValidationException`.
+pub mod validate;
+
+mod action;
+mod anonymous;
+mod audit;
+mod authorize;
+mod bearer;
+mod cache;
+mod canonical;
+mod catalog;
+#[cfg(feature = "checksum")]
+mod checksum;
+mod clock;
+mod content_type;
+mod context;
+mod cors;
+mod date;
+mod error;
+mod error_catalog;
+mod gsk_memory;
+mod hook;
+mod json;
+#[cfg(any(feature = "pagination", feature = "session_token"))]
+mod keystream;
+mod layer;
+mod metrics;
+mod observer;
+mod policy;
+mod policy_cache;
+mod policy_eval;
+mod profile;
+mod proxy;
+mod replay;
+mod request_ext;
 mod request_id;
+mod route;
+mod s3;
 mod service_spawn;
+#[cfg(feature = "sigv2")]
+mod sigv2;
 mod sigv4;
+mod sigv4a;
+mod span;
+#[cfg(test)]
+mod test_util;
+mod throttle;
+mod timeout;
+#[cfg(feature = "tls")]
 mod tls;
+mod typestate;
+mod validator;
 
 pub use {
-    request_id::RequestId,
-    service_spawn::{SpawnService, SpawnServiceBuilder},
+    action::{ActionResolver, JsonTargetActionResolver, QueryActionResolver, ResolvedAction, RestActionResolver},
+    anonymous::{AnonymousPaths, AnonymousPredicate, AnonymousRequest},
+    audit::{AuditEvent, AuditSink, ChannelAuditSink, FileAuditSink, StdoutAuditSink},
+    authorize::{AuthorizationDecision, AuthorizerLayer, AuthorizerService},
+    bearer::{BearerTokenRequest, BearerTokenResponse, BoxGetBearerToken},
+    cache::CachingSigningKeyService,
+    catalog::MessageCatalog,
+    clock::{Clock, FixedClock, SystemClock},
+    context::{RequestContext, RequestContextBuilder},
+    cors::{CorsConfiguration, CorsLayer, CorsRule, CorsService, InvalidCorsConfiguration, CORS_EXPOSE_HEADERS},
+    date::DateHeaderOptions,
+    error::{ErrorContext, VerifierError},
+    error_catalog::{ErrorFault, ServiceErrorCatalog, StandardError},
+    gsk_memory::GetSigningKeyFromMemory,
+    hook::{AuthenticatedRequest, BoxOnAuthenticated, BoxPreAuthHook, PreAuthOutcome},
+    json::{JsonErrorMapper, RestJsonErrorMapper, AWS_JSON_1_0_CONTENT_TYPE, AWS_JSON_1_1_CONTENT_TYPE},
+    layer::AwsSigV4VerifierLayer,
+    metrics::{AuthOutcome, LookupOutcome, Metrics, NoopMetrics},
+    observer::{AuthFailure, AuthFailureObserver, FailureRateTracker},
+    policy::{
+        combine_decisions, Decision, EffectivePolicies, Policy, PolicyAttachment, PolicyEvaluator, PolicyProvider,
+        PolicySet,
+    },
+    policy_cache::{PolicyCache, PolicyCacheHandle},
+    policy_eval::IamPolicyEvaluator,
+    profile::{ServiceProfile, UnknownProfile},
+    proxy::TrustedProxies,
+    replay::{MemoryReplayStore, ReplayKey, ReplayStore},
+    request_ext::{ConnectInfo, MissingExtension, PayloadSigning, RequestExt, SigningDetails},
+    request_id::{ExtendedRequestId, RequestId, RequestIdGenerator, RequestIdLayer, RequestIdService, X_AMZ_ID_2},
+    route::{Route, RouteBuilder, RouteBuilderError},
+    s3::{S3Bucket, S3VirtualHosts, S3XmlErrorMapper},
+    service_spawn::SpawnService,
     sigv4::{
         AwsSigV4VerifierService, AwsSigV4VerifierServiceBuilder, AwsSigV4VerifierServiceBuilderError, ErrorMapper,
-        XmlErrorMapper,
+        XmlErrorMapper, CREDENTIAL_HEADERS,
+    },
+    sigv4a::{
+        derive_signing_key, derive_verifying_key, BoxGetVerificationKey, GetVerificationKeyRequest,
+        GetVerificationKeyResponse, SIGV4A_ALGORITHM,
     },
-    tls::TlsIncoming,
+    throttle::{
+        current_client_ip, RateLimitedSigningKeyService, RateLimiter, ThrottleKey, ThrottlingLayer, ThrottlingService,
+        TokenBucketLimiter,
+    },
+    typestate::{AwsSigV4VerifierServiceTypedBuilder, SpawnServiceTypedBuilder, Unset},
+    validator::RequestValidator,
 };
 
+#[allow(deprecated)]
+pub use service_spawn::SpawnServiceBuilder;
+
+#[cfg(feature = "checksum")]
+pub use checksum::{ChecksumAlgorithm, VerifiedChecksum};
+
 #[cfg(feature = "gsk_direct")]
-pub use gsk_direct::GetSigningKeyFromDatabase;
+pub use {
+    gsk_direct::GetSigningKeyFromDatabase,
+    policy_direct::{GetPoliciesFromDatabase, PolicyTables},
+};
+
+#[cfg(feature = "gsk_dynamodb")]
+pub use gsk_dynamodb::GetSigningKeyFromDynamoDB;
+
+#[cfg(feature = "gsk_file")]
+pub use gsk_file::GetSigningKeyFromFile;
+
+#[cfg(feature = "gsk_ldap")]
+pub use gsk_ldap::GetSigningKeyFromLdap;
+
+#[cfg(feature = "metrics")]
+pub use metrics::{CounterMetrics, LOOKUP_LATENCY_BUCKETS};
+
+#[cfg(feature = "sigv2")]
+pub use sigv2::{BoxGetSecretKey, GetSecretKeyRequest, GetSecretKeyResponse, SIGV2_SIGNATURE_VERSION};
+
+#[cfg(feature = "tls")]
+pub use tls::TlsIncoming;
+
+/// Re-export of the `scratchstack-aws-principal` crate this crate is built against.
+pub use scratchstack_aws_principal;
+
+/// Re-export of the `scratchstack-aws-signature` crate this crate is built against.
+pub use scratchstack_aws_signature;
+
+/// Re-export of the `scratchstack-errors` crate this crate is built against.
+pub use scratchstack_errors;
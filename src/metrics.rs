@@ -0,0 +1,441 @@
+use {
+    crate::{error::as_service_error, Decision},
+    std::{
+        fmt::Debug,
+        future::Future,
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+        time::{Duration, Instant},
+    },
+    tower::{BoxError, Service},
+};
+
+#[cfg(feature = "metrics")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The outcome of authenticating a request, as reported to [Metrics].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum AuthOutcome {
+    /// The request was authenticated.
+    Success,
+
+    /// The request was served anonymously without being authenticated.
+    Anonymous,
+
+    /// The request signature did not match the signature computed by the server.
+    SignatureMismatch,
+
+    /// The request timestamp or security token has expired.
+    Expired,
+
+    /// The request was rejected for another reason, e.g. a disallowed method or an unknown access key.
+    Rejected,
+
+    /// The request could not be authenticated because of a server-side failure.
+    InternalError,
+}
+
+impl AuthOutcome {
+    /// Classify the error a request was rejected with.
+    pub fn from_error(error: &BoxError) -> Self {
+        match as_service_error(error) {
+            Some(e) if e.http_status().is_server_error() => Self::InternalError,
+            Some(e) => match e.error_code() {
+                "SignatureDoesNotMatch" => Self::SignatureMismatch,
+                "RequestExpired" | "ExpiredToken" => Self::Expired,
+                _ => Self::Rejected,
+            },
+            None => Self::InternalError,
+        }
+    }
+}
+
+/// The outcome of a signing key lookup made by a provider, as reported to [Metrics].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum LookupOutcome {
+    /// The access key was found and a signing key returned.
+    Found,
+
+    /// The access key does not exist or is not active (`InvalidClientTokenId`).
+    UnknownAccessKey,
+
+    /// The temporary credentials have expired (`ExpiredToken`).
+    Expired,
+
+    /// The lookup was rate limited.
+    Throttled,
+
+    /// The lookup was rejected for another reason.
+    Rejected,
+
+    /// The lookup failed because of a server-side failure, such as a database error or timeout.
+    InternalError,
+}
+
+impl LookupOutcome {
+    /// Classify the result of a lookup.
+    pub fn from_result<T>(result: &Result<T, BoxError>) -> Self {
+        let error = match result {
+            Ok(_) => return Self::Found,
+            Err(error) => error,
+        };
+
+        match as_service_error(error) {
+            Some(e) => match e.error_code() {
+                "InvalidClientTokenId" => Self::UnknownAccessKey,
+                "ExpiredToken" => Self::Expired,
+                "Throttling" | "SlowDown" => Self::Throttled,
+                _ if e.http_status().is_server_error() => Self::InternalError,
+                _ => Self::Rejected,
+            },
+            None => Self::InternalError,
+        }
+    }
+}
+
+/// The upper bounds of the buckets of the lookup latency histogram kept by [CounterMetrics]. Lookups slower than the
+/// last bound are counted in an additional bucket.
+#[cfg(feature = "metrics")]
+pub const LOOKUP_LATENCY_BUCKETS: [Duration; 8] = [
+    Duration::from_millis(1),
+    Duration::from_millis(2),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(500),
+];
+
+/// Receives measurements from [AwsSigV4VerifierService][crate::AwsSigV4VerifierService] and the signing key providers
+/// in this crate.
+///
+/// All methods default to doing nothing, so implementations only need to override the measurements they collect.
+/// Implementations are called on the request path and should not block.
+pub trait Metrics: Debug + Send + Sync {
+    /// Record the outcome of authenticating a request.
+    fn record_outcome(&self, _outcome: AuthOutcome) {}
+
+    /// Record the time taken by the signing key provider to look up a signing key.
+    fn record_signing_key_latency(&self, _latency: Duration) {}
+
+    /// Record a lookup made by a signing key provider, such as `GetSigningKeyFromDatabase` (with the `gsk_direct`
+    /// feature), that was not answered from a cache. `provider` names the provider, e.g. `database`.
+    fn record_provider_lookup(&self, _provider: &'static str, _outcome: LookupOutcome, _latency: Duration) {}
+
+    /// Record whether a lookup was answered from the cache of a signing key provider, such as
+    /// [CachingSigningKeyService][crate::CachingSigningKeyService].
+    fn record_signing_key_cache(&self, _provider: &'static str, _hit: bool) {}
+
+    /// Record the time a signing key provider waited to acquire a connection from a database connection pool, whether
+    /// or not a connection was acquired.
+    fn record_pool_acquire(&self, _wait: Duration) {}
+
+    /// Record the size, in bytes, of a request body buffered for signature validation.
+    fn record_body_size(&self, _size: usize) {}
+
+    /// Record a request signed with the deprecated SigV2 algorithm.
+    fn record_sigv2_request(&self) {}
+
+    /// Record the decision of authorizing a request with [AuthorizerService][crate::AuthorizerService]. `dry_run`
+    /// indicates the decision was not enforced.
+    fn record_authorization(&self, _decision: Decision, _dry_run: bool) {}
+}
+
+/// A [Metrics] implementation that discards all measurements.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct NoopMetrics;
+
+impl Metrics for NoopMetrics {}
+
+/// A [Metrics] implementation that keeps running totals in atomic counters.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default)]
+pub struct CounterMetrics {
+    success: AtomicU64,
+    anonymous: AtomicU64,
+    signature_mismatch: AtomicU64,
+    expired: AtomicU64,
+    rejected: AtomicU64,
+    internal_error: AtomicU64,
+    signing_key_lookups: AtomicU64,
+    signing_key_latency_us: AtomicU64,
+    lookup_outcomes: [AtomicU64; 6],
+    lookup_latency_buckets: [AtomicU64; LOOKUP_LATENCY_BUCKETS.len() + 1],
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    pool_acquisitions: AtomicU64,
+    pool_wait_us: AtomicU64,
+    bodies: AtomicU64,
+    body_bytes: AtomicU64,
+    sigv2_requests: AtomicU64,
+    authorizations_allowed: AtomicU64,
+    authorizations_denied: AtomicU64,
+    dry_run_denials: AtomicU64,
+}
+
+#[cfg(feature = "metrics")]
+impl CounterMetrics {
+    /// Create a new [CounterMetrics] with all counters set to zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn outcome_counter(&self, outcome: AuthOutcome) -> &AtomicU64 {
+        match outcome {
+            AuthOutcome::Success => &self.success,
+            AuthOutcome::Anonymous => &self.anonymous,
+            AuthOutcome::SignatureMismatch => &self.signature_mismatch,
+            AuthOutcome::Expired => &self.expired,
+            AuthOutcome::Rejected => &self.rejected,
+            AuthOutcome::InternalError => &self.internal_error,
+        }
+    }
+
+    /// Returns the number of requests with the given outcome.
+    pub fn outcome_count(&self, outcome: AuthOutcome) -> u64 {
+        self.outcome_counter(outcome).load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of signing key lookups.
+    pub fn signing_key_lookups(&self) -> u64 {
+        self.signing_key_lookups.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total time spent looking up signing keys.
+    pub fn signing_key_latency(&self) -> Duration {
+        Duration::from_micros(self.signing_key_latency_us.load(Ordering::Relaxed))
+    }
+
+    /// Returns the number of provider lookups with the given outcome.
+    pub fn lookup_count(&self, outcome: LookupOutcome) -> u64 {
+        self.lookup_outcomes[outcome as usize].load(Ordering::Relaxed)
+    }
+
+    /// Returns the lookup latency histogram: the number of provider lookups in each bucket of
+    /// [LOOKUP_LATENCY_BUCKETS], followed by the number slower than the last bucket. Counts are not cumulative.
+    pub fn lookup_latency_histogram(&self) -> Vec<u64> {
+        self.lookup_latency_buckets.iter().map(|count| count.load(Ordering::Relaxed)).collect()
+    }
+
+    /// Returns the number of lookups answered from a provider cache.
+    pub fn cache_hits(&self) -> u64 {
+        self.cache_hits.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of lookups not answered from a provider cache.
+    pub fn cache_misses(&self) -> u64 {
+        self.cache_misses.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of database connection pool acquisitions.
+    pub fn pool_acquisitions(&self) -> u64 {
+        self.pool_acquisitions.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total time spent waiting for database connections.
+    pub fn pool_wait(&self) -> Duration {
+        Duration::from_micros(self.pool_wait_us.load(Ordering::Relaxed))
+    }
+
+    /// Returns the number of request bodies buffered.
+    pub fn bodies(&self) -> u64 {
+        self.bodies.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total size, in bytes, of the request bodies buffered.
+    pub fn body_bytes(&self) -> u64 {
+        self.body_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of requests signed with SigV2.
+    pub fn sigv2_requests(&self) -> u64 {
+        self.sigv2_requests.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of requests allowed by the authorizer.
+    pub fn authorizations_allowed(&self) -> u64 {
+        self.authorizations_allowed.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of requests denied by the authorizer.
+    pub fn authorizations_denied(&self) -> u64 {
+        self.authorizations_denied.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of requests the authorizer would have denied, but forwarded because it is in dry-run mode.
+    pub fn dry_run_denials(&self) -> u64 {
+        self.dry_run_denials.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Metrics for CounterMetrics {
+    fn record_outcome(&self, outcome: AuthOutcome) {
+        self.outcome_counter(outcome).fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_signing_key_latency(&self, latency: Duration) {
+        self.signing_key_lookups.fetch_add(1, Ordering::Relaxed);
+        self.signing_key_latency_us.fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn record_provider_lookup(&self, _provider: &'static str, outcome: LookupOutcome, latency: Duration) {
+        self.lookup_outcomes[outcome as usize].fetch_add(1, Ordering::Relaxed);
+        let bucket =
+            LOOKUP_LATENCY_BUCKETS.iter().position(|bound| latency <= *bound).unwrap_or(LOOKUP_LATENCY_BUCKETS.len());
+        self.lookup_latency_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_signing_key_cache(&self, _provider: &'static str, hit: bool) {
+        let counter = if hit {
+            &self.cache_hits
+        } else {
+            &self.cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_pool_acquire(&self, wait: Duration) {
+        self.pool_acquisitions.fetch_add(1, Ordering::Relaxed);
+        self.pool_wait_us.fetch_add(wait.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn record_body_size(&self, size: usize) {
+        self.bodies.fetch_add(1, Ordering::Relaxed);
+        self.body_bytes.fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    fn record_sigv2_request(&self) {
+        self.sigv2_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_authorization(&self, decision: Decision, dry_run: bool) {
+        let counter = match decision {
+            Decision::Allow => &self.authorizations_allowed,
+            _ if dry_run => &self.dry_run_denials,
+            _ => &self.authorizations_denied,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A service wrapper that reports the latency of each call to [Metrics::record_signing_key_latency].
+#[derive(Clone, Debug)]
+pub(crate) struct TimedService<S> {
+    inner: S,
+    metrics: Arc<dyn Metrics>,
+}
+
+impl<S> TimedService<S> {
+    pub(crate) fn new(inner: S, metrics: Arc<dyn Metrics>) -> Self {
+        Self {
+            inner,
+            metrics,
+        }
+    }
+}
+
+impl<S, R> Service<R> for TimedService<S>
+where
+    S: Service<R>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = TimedFuture<S::Future>;
+
+    fn poll_ready(&mut self, c: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(c)
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        TimedFuture {
+            inner: Box::pin(self.inner.call(req)),
+            metrics: self.metrics.clone(),
+            start: Instant::now(),
+        }
+    }
+}
+
+/// The future returned by [TimedService].
+pub(crate) struct TimedFuture<F> {
+    inner: Pin<Box<F>>,
+    metrics: Arc<dyn Metrics>,
+    start: Instant,
+}
+
+impl<F: Future> Future for TimedFuture<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, c: &mut Context) -> Poll<Self::Output> {
+        let result = self.inner.as_mut().poll(c);
+        if result.is_ready() {
+            self.metrics.record_signing_key_latency(self.start.elapsed());
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{AuthOutcome, LookupOutcome},
+        crate::VerifierError,
+        scratchstack_aws_signature::SignatureError,
+        tower::BoxError,
+    };
+
+    #[test]
+    fn test_auth_outcome_from_error() {
+        let outcome = |e: BoxError| AuthOutcome::from_error(&e);
+        assert_eq!(outcome(VerifierError::SignatureDoesNotMatch.into()), AuthOutcome::SignatureMismatch);
+        assert_eq!(outcome(VerifierError::RequestExpired.into()), AuthOutcome::Expired);
+        assert_eq!(outcome(SignatureError::ExpiredToken("expired".to_string()).into()), AuthOutcome::Expired);
+        assert_eq!(outcome(VerifierError::InvalidContentType.into()), AuthOutcome::Rejected);
+        assert_eq!(outcome(VerifierError::InternalFailure.into()), AuthOutcome::InternalError);
+        assert_eq!(outcome("connection reset".into()), AuthOutcome::InternalError);
+    }
+
+    #[test]
+    fn test_lookup_outcome_from_result() {
+        let outcome = |e: BoxError| LookupOutcome::from_result::<()>(&Err(e));
+        assert_eq!(LookupOutcome::from_result::<()>(&Ok(())), LookupOutcome::Found);
+        assert_eq!(
+            outcome(SignatureError::InvalidClientTokenId("unknown".to_string()).into()),
+            LookupOutcome::UnknownAccessKey
+        );
+        assert_eq!(outcome(SignatureError::ExpiredToken("expired".to_string()).into()), LookupOutcome::Expired);
+        assert_eq!(outcome(VerifierError::Throttling.into()), LookupOutcome::Throttled);
+        assert_eq!(outcome(VerifierError::SlowDown.into()), LookupOutcome::Throttled);
+        assert_eq!(outcome(VerifierError::InvalidContentType.into()), LookupOutcome::Rejected);
+        assert_eq!(outcome("connection reset".into()), LookupOutcome::InternalError);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_counter_metrics_lookups() {
+        use {
+            super::{CounterMetrics, Metrics},
+            std::time::Duration,
+        };
+
+        let metrics = CounterMetrics::new();
+        metrics.record_provider_lookup("database", LookupOutcome::Found, Duration::from_micros(500));
+        metrics.record_provider_lookup("database", LookupOutcome::Found, Duration::from_millis(20));
+        metrics.record_provider_lookup("database", LookupOutcome::InternalError, Duration::from_secs(2));
+        metrics.record_signing_key_cache("database", true);
+        metrics.record_signing_key_cache("database", false);
+        metrics.record_pool_acquire(Duration::from_millis(3));
+
+        assert_eq!(metrics.lookup_count(LookupOutcome::Found), 2);
+        assert_eq!(metrics.lookup_count(LookupOutcome::InternalError), 1);
+        assert_eq!(metrics.lookup_count(LookupOutcome::Expired), 0);
+        assert_eq!(metrics.lookup_latency_histogram(), vec![1, 0, 0, 0, 1, 0, 0, 0, 1]);
+        assert_eq!((metrics.cache_hits(), metrics.cache_misses()), (1, 1));
+        assert_eq!(metrics.pool_acquisitions(), 1);
+        assert_eq!(metrics.pool_wait(), Duration::from_millis(3));
+    }
+}
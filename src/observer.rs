@@ -0,0 +1,233 @@
+use {
+    crate::{AuthOutcome, ConnectInfo, RequestId, RequestValidator, VerifierError},
+    async_trait::async_trait,
+    http::request::Parts,
+    std::{
+        collections::{HashMap, VecDeque},
+        fmt::Debug,
+        net::IpAddr,
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+    tower::BoxError,
+};
+
+/// The number of tracked sources above which [FailureRateTracker] drops sources with no recent failures.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// A rejected request, as reported to an [AuthFailureObserver].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuthFailure {
+    client_ip: Option<IpAddr>,
+    access_key: Option<String>,
+    outcome: AuthOutcome,
+    error_code: Option<&'static str>,
+    request_id: RequestId,
+}
+
+impl AuthFailure {
+    /// Create a new [AuthFailure].
+    pub fn new(
+        client_ip: Option<IpAddr>,
+        access_key: Option<String>,
+        outcome: AuthOutcome,
+        error_code: Option<&'static str>,
+        request_id: RequestId,
+    ) -> Self {
+        Self {
+            client_ip,
+            access_key,
+            outcome,
+            error_code,
+            request_id,
+        }
+    }
+
+    /// Returns the address of the client, if known.
+    #[inline]
+    pub fn client_ip(&self) -> Option<IpAddr> {
+        self.client_ip
+    }
+
+    /// Returns the access key the request was signed with, if it was signed.
+    #[inline]
+    pub fn access_key(&self) -> Option<&str> {
+        self.access_key.as_deref()
+    }
+
+    /// Returns the kind of failure.
+    #[inline]
+    pub fn outcome(&self) -> AuthOutcome {
+        self.outcome
+    }
+
+    /// Returns the error code the request was rejected with, if the error was a service error.
+    #[inline]
+    pub fn error_code(&self) -> Option<&'static str> {
+        self.error_code
+    }
+
+    /// Returns the request id.
+    #[inline]
+    pub fn request_id(&self) -> RequestId {
+        self.request_id
+    }
+}
+
+/// Receives every request rejected by [AwsSigV4VerifierService][crate::AwsSigV4VerifierService], e.g. to feed a
+/// fail2ban-style blocker or a security information and event management system.
+///
+/// Observers are called on the request path and should not block.
+pub trait AuthFailureObserver: Debug + Send + Sync {
+    /// Record a rejected request.
+    fn on_auth_failure(&self, failure: &AuthFailure);
+}
+
+/// An in-memory [AuthFailureObserver] that denies sources with too many recent failures.
+///
+/// A source that fails authentication `max_failures` times within `window` is denied for `deny_for`. To enforce the
+/// denial, install the tracker as both the verifier's failure observer and one of its
+/// [validators][crate::RequestValidator]; denied sources are then rejected before their signatures are checked.
+/// Failures are tracked by client address, so the verifier should be configured with its trusted proxies.
+#[derive(Debug)]
+pub struct FailureRateTracker {
+    max_failures: usize,
+    window: Duration,
+    deny_for: Duration,
+    sources: Mutex<HashMap<IpAddr, SourceState>>,
+}
+
+#[derive(Debug, Default)]
+struct SourceState {
+    failures: VecDeque<Instant>,
+    denied_until: Option<Instant>,
+}
+
+impl SourceState {
+    fn is_denied(&self, now: Instant) -> bool {
+        matches!(self.denied_until, Some(until) if until > now)
+    }
+}
+
+impl FailureRateTracker {
+    /// Create a new [FailureRateTracker].
+    pub fn new(max_failures: usize, window: Duration, deny_for: Duration) -> Self {
+        Self {
+            max_failures,
+            window,
+            deny_for,
+            sources: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Indicates whether the source is currently denied.
+    pub fn is_denied(&self, client_ip: IpAddr) -> bool {
+        let now = Instant::now();
+        self.sources.lock().unwrap().get(&client_ip).map(|state| state.is_denied(now)).unwrap_or(false)
+    }
+
+    /// Returns the number of failures recorded for the source within the window.
+    pub fn failure_count(&self, client_ip: IpAddr) -> usize {
+        let now = Instant::now();
+        self.sources
+            .lock()
+            .unwrap()
+            .get(&client_ip)
+            .map(|state| state.failures.iter().filter(|t| now.duration_since(**t) < self.window).count())
+            .unwrap_or(0)
+    }
+
+    /// Lift the denial of a source and forget its failures.
+    pub fn forgive(&self, client_ip: IpAddr) {
+        self.sources.lock().unwrap().remove(&client_ip);
+    }
+}
+
+impl AuthFailureObserver for FailureRateTracker {
+    fn on_auth_failure(&self, failure: &AuthFailure) {
+        let Some(client_ip) = failure.client_ip() else {
+            return;
+        };
+
+        let now = Instant::now();
+        let mut sources = self.sources.lock().unwrap();
+        if sources.len() > PRUNE_THRESHOLD {
+            sources.retain(|_, state| {
+                state.is_denied(now)
+                    || state.failures.back().map(|t| now.duration_since(*t) < self.window) == Some(true)
+            });
+        }
+
+        // Requests rejected because the source is already denied don't extend the denial.
+        let state = sources.entry(client_ip).or_default();
+        if state.is_denied(now) {
+            return;
+        }
+
+        state.failures.push_back(now);
+        while matches!(state.failures.front(), Some(t) if now.duration_since(*t) >= self.window) {
+            state.failures.pop_front();
+        }
+
+        if state.failures.len() >= self.max_failures {
+            state.failures.clear();
+            state.denied_until = Some(now + self.deny_for);
+        }
+    }
+}
+
+#[async_trait]
+impl RequestValidator for FailureRateTracker {
+    async fn validate(&self, parts: &Parts) -> Result<(), BoxError> {
+        match parts.extensions.get::<ConnectInfo>() {
+            Some(connect_info) if self.is_denied(connect_info.client_ip()) => Err(VerifierError::SourceDenied.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{AuthFailure, AuthFailureObserver, FailureRateTracker},
+        crate::{AuthOutcome, ConnectInfo, RequestId, RequestValidator},
+        http::Request,
+        std::{
+            net::{IpAddr, Ipv4Addr, SocketAddr},
+            time::Duration,
+        },
+    };
+
+    #[test_log::test(tokio::test)]
+    async fn test_failure_rate_tracker() {
+        let tracker = FailureRateTracker::new(3, Duration::from_secs(60), Duration::from_secs(300));
+        let abuser = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+        let other = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+        let failure = |ip| {
+            AuthFailure::new(
+                Some(ip),
+                Some("AKIDEXAMPLE".to_string()),
+                AuthOutcome::SignatureMismatch,
+                Some("SignatureDoesNotMatch"),
+                RequestId::new(),
+            )
+        };
+
+        tracker.on_auth_failure(&failure(abuser));
+        tracker.on_auth_failure(&failure(abuser));
+        tracker.on_auth_failure(&failure(other));
+        assert_eq!(tracker.failure_count(abuser), 2);
+        assert!(!tracker.is_denied(abuser));
+
+        tracker.on_auth_failure(&failure(abuser));
+        assert!(tracker.is_denied(abuser));
+        assert!(!tracker.is_denied(other));
+
+        let (mut parts, _) = Request::get("/").body(()).unwrap().into_parts();
+        parts.extensions.insert(ConnectInfo::new(SocketAddr::new(abuser, 40000), None));
+        assert!(tracker.validate(&parts).await.is_err());
+
+        tracker.forgive(abuser);
+        assert!(tracker.validate(&parts).await.is_ok());
+    }
+}
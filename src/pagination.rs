@@ -0,0 +1,256 @@
+#![warn(clippy::all)]
+
+use {
+    crate::{
+        keystream::{apply_keystream, derive_key, HmacSha256},
+        Clock, SystemClock, VerifierError,
+    },
+    hmac::Mac,
+    rand::{thread_rng, RngCore},
+    serde::{de::DeserializeOwned, Serialize},
+    std::{
+        fmt::{Debug, Formatter, Result as FmtResult},
+        sync::Arc,
+        time::Duration,
+    },
+    tower::BoxError,
+};
+
+/// The version of the token format.
+const TOKEN_VERSION: u8 = 1;
+
+/// The flag set on tokens whose cursor state is encrypted.
+const FLAG_ENCRYPTED: u8 = 1;
+
+/// The length of the version, flags, and issue time preceding the nonce and cursor state.
+const HEADER_LEN: usize = 10;
+
+/// The length of the nonce of encrypted tokens.
+const NONCE_LEN: usize = 16;
+
+/// The length of the HMAC-SHA256 tag ending each token.
+const TAG_LEN: usize = 32;
+
+/// Encodes cursor state as opaque, tamper-proof pagination tokens (e.g. `NextToken` or `Marker` values), and decodes
+/// them from subsequent requests.
+///
+/// The cursor state can be any serde type; it is serialized as JSON along with the time the token was issued, signed
+/// with HMAC-SHA256 using a key derived from the service key, and encoded with URL-safe base64. If encryption is
+/// enabled, the state is also encrypted before it is signed, so clients can't read it; the keystream is generated by
+/// HMAC-SHA256 in counter mode from a random nonce, using a separate derived key.
+///
+/// Tokens that were not issued with the same key (and encryption setting), that have been modified, or (if the codec has
+/// a time to live) that have expired are rejected with an `InvalidInput` error.
+///
+/// ```
+/// use {
+///     scratchstack_http_framework::pagination::TokenCodec,
+///     serde::{Deserialize, Serialize},
+///     std::time::Duration,
+/// };
+///
+/// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+/// struct Cursor {
+///     last_key: String,
+/// }
+///
+/// let codec = TokenCodec::new(b"service key").with_encryption().with_ttl(Duration::from_secs(3600));
+/// let token = codec.encode(&Cursor { last_key: "user-100".to_string() }).unwrap();
+/// let cursor: Cursor = codec.decode(&token).unwrap();
+/// assert_eq!(cursor.last_key, "user-100");
+/// ```
+#[derive(Clone)]
+pub struct TokenCodec {
+    signing_key: [u8; 32],
+    encryption_key: Option<[u8; 32]>,
+    base_key: [u8; 32],
+    ttl: Option<Duration>,
+    clock: Arc<dyn Clock>,
+}
+
+impl TokenCodec {
+    /// Create a new [TokenCodec] signing tokens with a key derived from `key`. Tokens are not encrypted and don't
+    /// expire.
+    pub fn new(key: &[u8]) -> Self {
+        let base_key = derive_key(key, b"scratchstack-pagination");
+        Self {
+            signing_key: derive_key(&base_key, b"signing"),
+            encryption_key: None,
+            base_key,
+            ttl: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Encrypt the cursor state of tokens, returning the updated codec.
+    pub fn with_encryption(mut self) -> Self {
+        self.encryption_key = Some(derive_key(&self.base_key, b"encryption"));
+        self
+    }
+
+    /// Reject tokens issued more than `ttl` ago, returning the updated codec.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Use the given [Clock] for the issue time of tokens and to check their expiry, returning the updated codec.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Indicates whether the cursor state of tokens is encrypted.
+    #[inline]
+    pub fn is_encrypted(&self) -> bool {
+        self.encryption_key.is_some()
+    }
+
+    /// Retreive the time to live of tokens, if they expire.
+    #[inline]
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    /// Encode cursor state as a token.
+    pub fn encode<T: Serialize>(&self, state: &T) -> Result<String, BoxError> {
+        let mut payload = serde_json::to_vec(state)?;
+
+        let mut token = Vec::with_capacity(HEADER_LEN + NONCE_LEN + payload.len() + TAG_LEN);
+        token.push(TOKEN_VERSION);
+        token.push(if self.is_encrypted() {
+            FLAG_ENCRYPTED
+        } else {
+            0
+        });
+        token.extend_from_slice(&self.clock.now().timestamp().to_be_bytes());
+
+        if let Some(encryption_key) = &self.encryption_key {
+            let mut nonce = [0; NONCE_LEN];
+            thread_rng().fill_bytes(&mut nonce);
+            apply_keystream(encryption_key, &nonce, &mut payload);
+            token.extend_from_slice(&nonce);
+        }
+
+        token.extend_from_slice(&payload);
+        let tag = self.mac(&token).finalize().into_bytes();
+        token.extend_from_slice(&tag);
+
+        Ok(base64::encode_config(token, base64::URL_SAFE_NO_PAD))
+    }
+
+    /// Decode the cursor state of a token, verifying its signature and expiry.
+    pub fn decode<T: DeserializeOwned>(&self, token: &str) -> Result<T, VerifierError> {
+        let invalid = || VerifierError::InvalidInput("Invalid pagination token".to_string());
+
+        let token = base64::decode_config(token, base64::URL_SAFE_NO_PAD).map_err(|_| invalid())?;
+        let nonce_len = if self.is_encrypted() {
+            NONCE_LEN
+        } else {
+            0
+        };
+        if token.len() < HEADER_LEN + nonce_len + TAG_LEN {
+            return Err(invalid());
+        }
+
+        let (signed, tag) = token.split_at(token.len() - TAG_LEN);
+        self.mac(signed).verify_slice(tag).map_err(|_| invalid())?;
+
+        let expected_flags = if self.is_encrypted() {
+            FLAG_ENCRYPTED
+        } else {
+            0
+        };
+        if signed[0] != TOKEN_VERSION || signed[1] != expected_flags {
+            return Err(invalid());
+        }
+
+        if let Some(ttl) = self.ttl {
+            let issued = i64::from_be_bytes(signed[2..HEADER_LEN].try_into().expect("header is 10 bytes"));
+            let age = self.clock.now().timestamp().saturating_sub(issued);
+            if age < 0 || age as u64 > ttl.as_secs() {
+                return Err(VerifierError::InvalidInput("Pagination token has expired".to_string()));
+            }
+        }
+
+        let (nonce, payload) = signed[HEADER_LEN..].split_at(nonce_len);
+        let mut payload = payload.to_vec();
+        if let Some(encryption_key) = &self.encryption_key {
+            apply_keystream(encryption_key, nonce, &mut payload);
+        }
+
+        serde_json::from_slice(&payload).map_err(|_| invalid())
+    }
+
+    fn mac(&self, data: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.signing_key).expect("HMAC accepts keys of any length");
+        mac.update(data);
+        mac
+    }
+}
+
+impl Debug for TokenCodec {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("TokenCodec")
+            .field("encrypted", &self.is_encrypted())
+            .field("ttl", &self.ttl)
+            .field("clock", &self.clock)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::TokenCodec,
+        crate::FixedClock,
+        chrono::{TimeZone, Utc},
+        pretty_assertions::assert_eq,
+        serde::{Deserialize, Serialize},
+        std::{sync::Arc, time::Duration},
+    };
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Cursor {
+        last_key: String,
+        offset: u32,
+    }
+
+    #[test]
+    fn test_token_codec() {
+        let cursor = Cursor {
+            last_key: "user-100".to_string(),
+            offset: 7,
+        };
+        let clock = |secs| Arc::new(FixedClock::new(Utc.timestamp_opt(secs, 0).unwrap()));
+
+        let codec = TokenCodec::new(b"key").with_ttl(Duration::from_secs(60)).with_clock(clock(1_000_000));
+        let token = codec.encode(&cursor).unwrap();
+        assert_eq!(codec.decode::<Cursor>(&token).unwrap(), cursor);
+        assert!(token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+
+        // Tampering, other keys, and expiry are all rejected.
+        let mut tampered = token.clone().into_bytes();
+        tampered[15] = if tampered[15] == b'A' {
+            b'B'
+        } else {
+            b'A'
+        };
+        assert!(codec.decode::<Cursor>(&String::from_utf8(tampered).unwrap()).is_err());
+        assert!(TokenCodec::new(b"other key").decode::<Cursor>(&token).is_err());
+        assert!(codec.clone().with_encryption().decode::<Cursor>(&token).is_err());
+        assert!(codec.clone().with_clock(clock(1_000_060)).decode::<Cursor>(&token).is_ok());
+        let e = codec.clone().with_clock(clock(1_000_061)).decode::<Cursor>(&token).unwrap_err();
+        assert_eq!(e.to_string(), "Pagination token has expired");
+        assert_eq!(codec.decode::<Cursor>("not a token").unwrap_err().to_string(), "Invalid pagination token");
+
+        // Encrypted tokens don't reveal the cursor state.
+        let codec = TokenCodec::new(b"key").with_encryption();
+        let token = codec.encode(&cursor).unwrap();
+        let decoded = base64::decode_config(&token, base64::URL_SAFE_NO_PAD).unwrap();
+        assert!(!String::from_utf8_lossy(&decoded).contains("user-100"));
+        assert_eq!(codec.decode::<Cursor>(&token).unwrap(), cursor);
+        assert_ne!(codec.encode(&cursor).unwrap(), token);
+        assert!(TokenCodec::new(b"key").decode::<Cursor>(&token).is_err());
+    }
+}
@@ -0,0 +1,338 @@
+use {
+    crate::{AuthorizationDecision, RequestContext, ResolvedAction},
+    async_trait::async_trait,
+    scratchstack_aws_principal::Principal,
+    std::{fmt::Debug, slice::Iter, vec::IntoIter},
+    tower::BoxError,
+};
+
+/// How a policy applies to a principal.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum PolicyAttachment {
+    /// An inline policy embedded in the principal itself.
+    Inline {
+        /// The name of the policy.
+        policy_name: String,
+    },
+
+    /// An inline policy embedded in a group the principal is a member of.
+    GroupInline {
+        /// The name of the group.
+        group_name: String,
+
+        /// The name of the policy.
+        policy_name: String,
+    },
+
+    /// A managed policy attached to the principal or to a group the principal is a member of.
+    Managed {
+        /// The ARN of the policy.
+        policy_arn: String,
+    },
+}
+
+/// An identity-based policy document that applies to a principal.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Policy {
+    attachment: PolicyAttachment,
+    document: String,
+}
+
+impl Policy {
+    /// Create a new [Policy] from its attachment and its JSON policy document.
+    pub fn new(attachment: PolicyAttachment, document: impl Into<String>) -> Self {
+        Self {
+            attachment,
+            document: document.into(),
+        }
+    }
+
+    /// Retreive how the policy applies to the principal.
+    #[inline]
+    pub fn attachment(&self) -> &PolicyAttachment {
+        &self.attachment
+    }
+
+    /// Retreive the JSON policy document.
+    #[inline]
+    pub fn document(&self) -> &str {
+        &self.document
+    }
+}
+
+/// The identity-based policies that apply to a principal.
+///
+/// A managed policy attached both directly and through a group is only included once.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PolicySet {
+    policies: Vec<Policy>,
+}
+
+impl PolicySet {
+    /// Create a new, empty [PolicySet].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a policy to the set. Managed policies already in the set are ignored.
+    pub fn push(&mut self, policy: Policy) {
+        if matches!(policy.attachment, PolicyAttachment::Managed { .. })
+            && self.policies.iter().any(|existing| existing.attachment == policy.attachment)
+        {
+            return;
+        }
+
+        self.policies.push(policy);
+    }
+
+    /// Retreive the policies in the set.
+    #[inline]
+    pub fn policies(&self) -> &[Policy] {
+        &self.policies
+    }
+
+    /// Returns an iterator over the policies in the set.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, Policy> {
+        self.policies.iter()
+    }
+
+    /// Returns the number of policies in the set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.policies.len()
+    }
+
+    /// Indicates whether the set is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+}
+
+impl Extend<Policy> for PolicySet {
+    fn extend<I: IntoIterator<Item = Policy>>(&mut self, iter: I) {
+        for policy in iter {
+            self.push(policy);
+        }
+    }
+}
+
+impl FromIterator<Policy> for PolicySet {
+    fn from_iter<I: IntoIterator<Item = Policy>>(iter: I) -> Self {
+        let mut result = Self::new();
+        result.extend(iter);
+        result
+    }
+}
+
+impl IntoIterator for PolicySet {
+    type Item = Policy;
+    type IntoIter = IntoIter<Policy>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.policies.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a PolicySet {
+    type Item = &'a Policy;
+    type IntoIter = Iter<'a, Policy>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The result of evaluating policies against a request.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Decision {
+    /// A statement allows the request and none denies it.
+    Allow,
+
+    /// A statement explicitly denies the request.
+    Deny,
+
+    /// No statement applies to the request, so it is implicitly denied.
+    DefaultDeny,
+}
+
+/// Evaluates a set of policies against a request. [IamPolicyEvaluator][crate::IamPolicyEvaluator] evaluates IAM JSON
+/// policy documents, including their conditions.
+pub trait PolicyEvaluator: Debug + Send + Sync {
+    /// Returns the decision of the policies for the action, with the condition keys in `context`.
+    fn evaluate(
+        &self,
+        policies: &PolicySet,
+        action: &ResolvedAction,
+        context: &RequestContext,
+    ) -> Result<AuthorizationDecision, BoxError>;
+}
+
+/// All of the policies governing a principal's requests: its identity-based policies, its permissions boundary, and
+/// the service control policies (SCPs) of its account.
+///
+/// SCPs are given per level of the organization hierarchy (root, each organizational unit, then the account); a request
+/// must be allowed at every level.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EffectivePolicies {
+    identity: PolicySet,
+    permissions_boundary: Option<PolicySet>,
+    service_control_policies: Vec<PolicySet>,
+}
+
+impl EffectivePolicies {
+    /// Create a new [EffectivePolicies] with the given identity-based policies and no boundary or SCPs.
+    pub fn new(identity: PolicySet) -> Self {
+        Self {
+            identity,
+            permissions_boundary: None,
+            service_control_policies: Vec::new(),
+        }
+    }
+
+    /// Set the permissions boundary, returning the updated policies.
+    pub fn with_permissions_boundary(mut self, permissions_boundary: Option<PolicySet>) -> Self {
+        self.permissions_boundary = permissions_boundary;
+        self
+    }
+
+    /// Set the SCPs, one set per level of the organization hierarchy, returning the updated policies.
+    pub fn with_service_control_policies(mut self, service_control_policies: Vec<PolicySet>) -> Self {
+        self.service_control_policies = service_control_policies;
+        self
+    }
+
+    /// Retreive the identity-based policies.
+    #[inline]
+    pub fn identity(&self) -> &PolicySet {
+        &self.identity
+    }
+
+    /// Retreive the permissions boundary, if the principal has one.
+    #[inline]
+    pub fn permissions_boundary(&self) -> Option<&PolicySet> {
+        self.permissions_boundary.as_ref()
+    }
+
+    /// Retreive the SCPs, one set per level of the organization hierarchy.
+    #[inline]
+    pub fn service_control_policies(&self) -> &[PolicySet] {
+        &self.service_control_policies
+    }
+
+    /// Evaluate each policy set and combine the decisions with [AuthorizationDecision::combine].
+    pub fn evaluate(
+        &self,
+        evaluator: &dyn PolicyEvaluator,
+        action: &ResolvedAction,
+        context: &RequestContext,
+    ) -> Result<AuthorizationDecision, BoxError> {
+        let identity = evaluator.evaluate(&self.identity, action, context)?;
+        let boundary = match &self.permissions_boundary {
+            Some(boundary) => Some(evaluator.evaluate(boundary, action, context)?),
+            None => None,
+        };
+        let scps = self
+            .service_control_policies
+            .iter()
+            .map(|scp| evaluator.evaluate(scp, action, context))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(AuthorizationDecision::combine(identity, boundary, scps))
+    }
+}
+
+/// Combine the decisions of identity-based policies, a permissions boundary, and SCPs as AWS does: an explicit deny in
+/// any of them denies the request; otherwise the boundary (if any) and every SCP level must allow it, and the
+/// identity-based policies must allow it.
+pub fn combine_decisions(identity: Decision, boundary: Option<Decision>, scps: &[Decision]) -> Decision {
+    let all = || std::iter::once(identity).chain(boundary).chain(scps.iter().copied());
+
+    if all().any(|decision| decision == Decision::Deny) {
+        Decision::Deny
+    } else if all().all(|decision| decision == Decision::Allow) {
+        Decision::Allow
+    } else {
+        Decision::DefaultDeny
+    }
+}
+
+/// A source of the policies that apply to an authenticated principal, for use when authorizing requests.
+///
+/// `policy_direct::GetPoliciesFromDatabase` (with the `gsk_direct` feature) loads policies from the same database as
+/// `gsk_direct::GetSigningKeyFromDatabase`.
+#[async_trait]
+pub trait PolicyProvider: Debug + Send + Sync {
+    /// Returns the identity-based policies that apply to the principal. Principals the provider does not know about
+    /// have no policies.
+    async fn get_policies_for_principal(&self, principal: &Principal) -> Result<PolicySet, BoxError>;
+
+    /// Returns the permissions boundary of the principal, if it has one. By default, principals have no boundary.
+    async fn get_permissions_boundary(&self, _principal: &Principal) -> Result<Option<PolicySet>, BoxError> {
+        Ok(None)
+    }
+
+    /// Returns the SCPs that apply to the principal's account, one set per level of the organization hierarchy from the
+    /// root down. By default, no SCPs apply.
+    async fn get_service_control_policies(&self, _principal: &Principal) -> Result<Vec<PolicySet>, BoxError> {
+        Ok(Vec::new())
+    }
+
+    /// Returns all of the policies governing the principal's requests.
+    async fn get_effective_policies(&self, principal: &Principal) -> Result<EffectivePolicies, BoxError> {
+        Ok(EffectivePolicies::new(self.get_policies_for_principal(principal).await?)
+            .with_permissions_boundary(self.get_permissions_boundary(principal).await?)
+            .with_service_control_policies(self.get_service_control_policies(principal).await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{combine_decisions, Decision, Policy, PolicyAttachment, PolicySet};
+
+    #[test]
+    fn test_policy_set() {
+        let managed = || {
+            Policy::new(
+                PolicyAttachment::Managed {
+                    policy_arn: "arn:aws:iam::aws:policy/ReadOnlyAccess".to_string(),
+                },
+                "{}",
+            )
+        };
+        let inline = |name: &str| {
+            Policy::new(
+                PolicyAttachment::Inline {
+                    policy_name: name.to_string(),
+                },
+                "{}",
+            )
+        };
+
+        let set: PolicySet = vec![managed(), inline("a"), managed(), inline("b")].into_iter().collect();
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.policies()[0], managed());
+        assert_eq!(set.iter().filter(|p| matches!(p.attachment(), PolicyAttachment::Inline { .. })).count(), 2);
+        assert!(PolicySet::new().is_empty());
+    }
+
+    #[test]
+    fn test_combine_decisions() {
+        use Decision::{Allow, DefaultDeny, Deny};
+
+        assert_eq!(combine_decisions(Allow, None, &[]), Allow);
+        assert_eq!(combine_decisions(DefaultDeny, None, &[]), DefaultDeny);
+        assert_eq!(combine_decisions(Allow, Some(Allow), &[Allow, Allow]), Allow);
+
+        // The boundary and SCPs gate identity allows, but don't grant anything themselves.
+        assert_eq!(combine_decisions(Allow, Some(DefaultDeny), &[]), DefaultDeny);
+        assert_eq!(combine_decisions(Allow, None, &[Allow, DefaultDeny]), DefaultDeny);
+        assert_eq!(combine_decisions(DefaultDeny, Some(Allow), &[Allow]), DefaultDeny);
+
+        // An explicit deny anywhere wins.
+        assert_eq!(combine_decisions(Allow, Some(Allow), &[Deny]), Deny);
+        assert_eq!(combine_decisions(Deny, Some(Allow), &[Allow]), Deny);
+        assert_eq!(combine_decisions(DefaultDeny, Some(Deny), &[]), Deny);
+    }
+}
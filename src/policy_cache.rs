@@ -0,0 +1,241 @@
+use {
+    crate::{EffectivePolicies, PolicyProvider, PolicySet},
+    async_trait::async_trait,
+    scratchstack_aws_principal::Principal,
+    std::{
+        collections::{HashMap, VecDeque},
+        fmt::{Debug, Formatter, Result as FmtResult},
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    },
+    tower::BoxError,
+};
+
+/// A [PolicyProvider] wrapper that remembers the policies loaded by the wrapped provider, keyed by the ARNs of the
+/// principal's identities, so policies don't need to be loaded on every request.
+///
+/// Policies are kept for `ttl`; when more than `capacity` principals are held, the oldest is evicted. A cached entry
+/// outlives changes to the underlying policies for up to `ttl`. To make changes take effect sooner, a control plane
+/// can call [PolicyCache::invalidate_principal] (or the same method on a [PolicyCacheHandle], which can be handed out
+/// separately) when a principal's policies change.
+pub struct PolicyCache<P> {
+    inner: P,
+    state: Arc<PolicyCacheState>,
+}
+
+impl<P> PolicyCache<P> {
+    /// Create a new [PolicyCache] holding the policies of at most `capacity` principals for `ttl` each.
+    pub fn new(inner: P, ttl: Duration, capacity: usize) -> Self {
+        Self {
+            inner,
+            state: Arc::new(PolicyCacheState {
+                ttl,
+                capacity,
+                inner: Mutex::new(PolicyCacheInner::default()),
+            }),
+        }
+    }
+
+    /// Returns a handle for invalidating cached policies, e.g. from a control-plane component.
+    pub fn handle(&self) -> PolicyCacheHandle {
+        PolicyCacheHandle {
+            state: self.state.clone(),
+        }
+    }
+
+    /// Evict the cached policies of any principal with an identity whose ARN is `arn`.
+    pub fn invalidate_principal(&self, arn: &str) {
+        self.state.invalidate_principal(arn);
+    }
+
+    /// Evict all cached policies.
+    pub fn clear(&self) {
+        self.state.clear();
+    }
+
+    /// Returns the number of cached principals, including any that have expired but have not yet been evicted.
+    pub fn len(&self) -> usize {
+        self.state.inner.lock().unwrap().entries.len()
+    }
+
+    /// Indicates whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<P: Debug> Debug for PolicyCache<P> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("PolicyCache").field("inner", &self.inner).field("state", &self.state).finish()
+    }
+}
+
+#[async_trait]
+impl<P: PolicyProvider> PolicyProvider for PolicyCache<P> {
+    async fn get_policies_for_principal(&self, principal: &Principal) -> Result<PolicySet, BoxError> {
+        Ok(self.get_effective_policies(principal).await?.identity().clone())
+    }
+
+    async fn get_permissions_boundary(&self, principal: &Principal) -> Result<Option<PolicySet>, BoxError> {
+        Ok(self.get_effective_policies(principal).await?.permissions_boundary().cloned())
+    }
+
+    async fn get_service_control_policies(&self, principal: &Principal) -> Result<Vec<PolicySet>, BoxError> {
+        Ok(self.get_effective_policies(principal).await?.service_control_policies().to_vec())
+    }
+
+    async fn get_effective_policies(&self, principal: &Principal) -> Result<EffectivePolicies, BoxError> {
+        let key: Vec<String> = principal.iter().map(|identity| identity.to_string()).collect();
+        if let Some(policies) = self.state.get(&key) {
+            return Ok(policies);
+        }
+
+        let policies = self.inner.get_effective_policies(principal).await?;
+        self.state.insert(key, policies.clone());
+        Ok(policies)
+    }
+}
+
+/// A handle for invalidating the policies cached by a [PolicyCache]. Clones refer to the same cache.
+#[derive(Clone, Debug)]
+pub struct PolicyCacheHandle {
+    state: Arc<PolicyCacheState>,
+}
+
+impl PolicyCacheHandle {
+    /// Evict the cached policies of any principal with an identity whose ARN is `arn`.
+    pub fn invalidate_principal(&self, arn: &str) {
+        self.state.invalidate_principal(arn);
+    }
+
+    /// Evict all cached policies.
+    pub fn clear(&self) {
+        self.state.clear();
+    }
+}
+
+/// The state shared by a [PolicyCache] and its handles.
+struct PolicyCacheState {
+    ttl: Duration,
+    capacity: usize,
+    inner: Mutex<PolicyCacheInner>,
+}
+
+#[derive(Default)]
+struct PolicyCacheInner {
+    entries: HashMap<Vec<String>, (EffectivePolicies, Instant)>,
+    order: VecDeque<(Vec<String>, Instant)>,
+}
+
+impl PolicyCacheState {
+    fn get(&self, key: &[String]) -> Option<EffectivePolicies> {
+        let inner = self.inner.lock().unwrap();
+        match inner.entries.get(key) {
+            Some((policies, expires)) if *expires > Instant::now() => Some(policies.clone()),
+            _ => None,
+        }
+    }
+
+    fn insert(&self, key: Vec<String>, policies: EffectivePolicies) {
+        let now = Instant::now();
+        let expires = now + self.ttl;
+        let mut inner = self.inner.lock().unwrap();
+
+        inner.entries.insert(key.clone(), (policies, expires));
+        inner.order.push_back((key, expires));
+
+        while inner.entries.len() > self.capacity
+            || matches!(inner.order.front(), Some((_, oldest_expires)) if *oldest_expires <= now)
+        {
+            let Some((key, expires)) = inner.order.pop_front() else {
+                break;
+            };
+
+            // Skip entries that have since been replaced.
+            if matches!(inner.entries.get(&key), Some((_, e)) if *e == expires) {
+                inner.entries.remove(&key);
+            }
+        }
+    }
+
+    fn invalidate_principal(&self, arn: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.retain(|key, _| !key.iter().any(|a| a == arn));
+        inner.order.retain(|(key, _)| !key.iter().any(|a| a == arn));
+    }
+
+    fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.clear();
+        inner.order.clear();
+    }
+}
+
+impl Debug for PolicyCacheState {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("PolicyCacheState")
+            .field("ttl", &self.ttl)
+            .field("capacity", &self.capacity)
+            .field("len", &self.inner.lock().unwrap().entries.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::PolicyCache,
+        crate::{Policy, PolicyAttachment, PolicyProvider, PolicySet},
+        async_trait::async_trait,
+        scratchstack_aws_principal::{Principal, User},
+        std::{
+            sync::atomic::{AtomicUsize, Ordering},
+            time::Duration,
+        },
+        tower::BoxError,
+    };
+
+    #[derive(Debug, Default)]
+    struct CountingProvider {
+        lookups: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl PolicyProvider for CountingProvider {
+        async fn get_policies_for_principal(&self, _principal: &Principal) -> Result<PolicySet, BoxError> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![Policy::new(
+                PolicyAttachment::Inline {
+                    policy_name: "test".to_string(),
+                },
+                "{}",
+            )]
+            .into_iter()
+            .collect())
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_policy_cache() {
+        let user = |name: &str| Principal::from(vec![User::new("aws", "123456789012", "/", name).unwrap().into()]);
+        let cache = PolicyCache::new(CountingProvider::default(), Duration::from_secs(60), 10);
+
+        let policies = cache.get_policies_for_principal(&user("alice")).await.unwrap();
+        assert_eq!(policies.len(), 1);
+        cache.get_effective_policies(&user("alice")).await.unwrap();
+        assert_eq!(cache.inner.lookups.load(Ordering::SeqCst), 1);
+
+        cache.get_effective_policies(&user("bob")).await.unwrap();
+        assert_eq!(cache.len(), 2);
+
+        // Invalidating one principal leaves the others cached.
+        cache.handle().invalidate_principal("arn:aws:iam::123456789012:user/alice");
+        assert_eq!(cache.len(), 1);
+        cache.get_effective_policies(&user("alice")).await.unwrap();
+        cache.get_effective_policies(&user("bob")).await.unwrap();
+        assert_eq!(cache.inner.lookups.load(Ordering::SeqCst), 3);
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
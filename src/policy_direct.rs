@@ -0,0 +1,287 @@
+#![warn(clippy::all)]
+
+use {
+    crate::{
+        gsk_direct::Binder,
+        policy::{Policy, PolicyAttachment, PolicyProvider, PolicySet},
+    },
+    async_trait::async_trait,
+    derive_builder::Builder,
+    log::{error, trace},
+    scratchstack_aws_principal::{Principal, PrincipalIdentity, User},
+    sqlx::{
+        any::{Any, AnyConnection},
+        query_as, Error as SqlxError, Pool,
+    },
+    std::{
+        fmt::{Debug, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+    tower::BoxError,
+};
+
+/// The names of the tables [GetPoliciesFromDatabase] reads policies from.
+///
+/// The defaults follow the naming of the `iam_user` and `iam_user_credential` tables used by
+/// [GetSigningKeyFromDatabase][crate::gsk_direct::GetSigningKeyFromDatabase]. The tables are expected to have these
+/// columns:
+///
+/// * `user_policy_table`: `user_id`, `policy_name`, `policy_document`
+/// * `user_attached_policy_table`: `user_id`, `managed_policy_id`
+/// * `group_member_table`: `group_id`, `user_id`
+/// * `group_table`: `group_id`, `group_name_cased`
+/// * `group_policy_table`: `group_id`, `policy_name`, `policy_document`
+/// * `group_attached_policy_table`: `group_id`, `managed_policy_id`
+/// * `managed_policy_table`: `managed_policy_id`, `arn`, `policy_document` (the default version)
+///
+/// A user's permissions boundary is the managed policy named by the `permissions_boundary_arn` column of `iam_user`,
+/// if it is not null.
+#[derive(Builder, Clone, Debug, Eq, PartialEq)]
+#[builder(setter(into))]
+pub struct PolicyTables {
+    /// The table of inline user policies.
+    #[builder(default = "\"iam_user_policy\".to_string()")]
+    user_policy_table: String,
+
+    /// The table of managed policies attached to users.
+    #[builder(default = "\"iam_user_attached_policy\".to_string()")]
+    user_attached_policy_table: String,
+
+    /// The table of group memberships.
+    #[builder(default = "\"iam_group_member\".to_string()")]
+    group_member_table: String,
+
+    /// The table of groups.
+    #[builder(default = "\"iam_group\".to_string()")]
+    group_table: String,
+
+    /// The table of inline group policies.
+    #[builder(default = "\"iam_group_policy\".to_string()")]
+    group_policy_table: String,
+
+    /// The table of managed policies attached to groups.
+    #[builder(default = "\"iam_group_attached_policy\".to_string()")]
+    group_attached_policy_table: String,
+
+    /// The table of managed policies.
+    #[builder(default = "\"iam_managed_policy\".to_string()")]
+    managed_policy_table: String,
+}
+
+impl PolicyTables {
+    /// Create a new [PolicyTablesBuilder] for overriding the default table names.
+    #[inline]
+    pub fn builder() -> PolicyTablesBuilder {
+        PolicyTablesBuilder::default()
+    }
+}
+
+impl Default for PolicyTables {
+    fn default() -> Self {
+        Self::builder().build().unwrap()
+    }
+}
+
+/// A [PolicyProvider] that queries the database for the inline and attached managed policies of IAM users, including
+/// those of the groups they are members of.
+///
+/// Principals other than IAM users have no policies. SCPs are not loaded.
+#[derive(Clone)]
+pub struct GetPoliciesFromDatabase {
+    pool: Arc<Pool<Any>>,
+    tables: PolicyTables,
+}
+
+impl GetPoliciesFromDatabase {
+    /// Create a new [GetPoliciesFromDatabase] provider using the default table names.
+    pub fn new(pool: Arc<Pool<Any>>) -> Self {
+        Self::with_tables(pool, PolicyTables::default())
+    }
+
+    /// Create a new [GetPoliciesFromDatabase] provider using the given table names.
+    pub fn with_tables(pool: Arc<Pool<Any>>, tables: PolicyTables) -> Self {
+        Self {
+            pool,
+            tables,
+        }
+    }
+
+    /// Retreive the table names used.
+    #[inline]
+    pub fn tables(&self) -> &PolicyTables {
+        &self.tables
+    }
+
+    async fn get_user_policies(&self, db: &mut AnyConnection, user: &User) -> Result<PolicySet, BoxError> {
+        let t = &self.tables;
+        let mut policies = PolicySet::new();
+
+        let mut binder = Binder::new(db.kind());
+        let account_id_param = binder.next_param_id();
+        let user_name_param = binder.next_param_id();
+        let sql = format!(
+            "SELECT user_id FROM iam_user WHERE account_id = {} AND user_name_cased = {}",
+            account_id_param, user_name_param
+        );
+        let user_id: String =
+            match query_as(&sql).bind(user.account_id()).bind(user.user_name()).fetch_one(&mut *db).await {
+                Ok((user_id,)) => user_id,
+                Err(SqlxError::RowNotFound) => {
+                    trace!("No IAM user {} found in account {}", user.user_name(), user.account_id());
+                    return Ok(policies);
+                }
+                Err(e) => return Err(internal_error(e)),
+            };
+
+        let mut binder = Binder::new(db.kind());
+        let sql = format!(
+            "SELECT policy_name, policy_document FROM {} WHERE user_id = {}",
+            t.user_policy_table,
+            binder.next_param_id()
+        );
+        let rows: Vec<(String, String)> =
+            query_as(&sql).bind(&user_id).fetch_all(&mut *db).await.map_err(internal_error)?;
+        policies.extend(rows.into_iter().map(|(policy_name, document)| {
+            Policy::new(
+                PolicyAttachment::Inline {
+                    policy_name,
+                },
+                document,
+            )
+        }));
+
+        let mut binder = Binder::new(db.kind());
+        let sql = format!(
+            r#"SELECT mp.arn, mp.policy_document
+               FROM {} AS ap
+               INNER JOIN {} AS mp
+               ON ap.managed_policy_id = mp.managed_policy_id
+               WHERE ap.user_id = {}"#,
+            t.user_attached_policy_table,
+            t.managed_policy_table,
+            binder.next_param_id()
+        );
+        let rows: Vec<(String, String)> =
+            query_as(&sql).bind(&user_id).fetch_all(&mut *db).await.map_err(internal_error)?;
+        policies.extend(rows.into_iter().map(managed_policy));
+
+        let mut binder = Binder::new(db.kind());
+        let sql = format!(
+            r#"SELECT g.group_name_cased, gp.policy_name, gp.policy_document
+               FROM {} AS gm
+               INNER JOIN {} AS g
+               ON gm.group_id = g.group_id
+               INNER JOIN {} AS gp
+               ON gm.group_id = gp.group_id
+               WHERE gm.user_id = {}"#,
+            t.group_member_table,
+            t.group_table,
+            t.group_policy_table,
+            binder.next_param_id()
+        );
+        let rows: Vec<(String, String, String)> =
+            query_as(&sql).bind(&user_id).fetch_all(&mut *db).await.map_err(internal_error)?;
+        policies.extend(rows.into_iter().map(|(group_name, policy_name, document)| {
+            Policy::new(
+                PolicyAttachment::GroupInline {
+                    group_name,
+                    policy_name,
+                },
+                document,
+            )
+        }));
+
+        let mut binder = Binder::new(db.kind());
+        let sql = format!(
+            r#"SELECT mp.arn, mp.policy_document
+               FROM {} AS gm
+               INNER JOIN {} AS ap
+               ON gm.group_id = ap.group_id
+               INNER JOIN {} AS mp
+               ON ap.managed_policy_id = mp.managed_policy_id
+               WHERE gm.user_id = {}"#,
+            t.group_member_table,
+            t.group_attached_policy_table,
+            t.managed_policy_table,
+            binder.next_param_id()
+        );
+        let rows: Vec<(String, String)> =
+            query_as(&sql).bind(&user_id).fetch_all(&mut *db).await.map_err(internal_error)?;
+        policies.extend(rows.into_iter().map(managed_policy));
+
+        Ok(policies)
+    }
+
+    async fn get_user_boundary(&self, db: &mut AnyConnection, user: &User) -> Result<Option<Policy>, BoxError> {
+        let mut binder = Binder::new(db.kind());
+        let account_id_param = binder.next_param_id();
+        let user_name_param = binder.next_param_id();
+        let sql = format!(
+            r#"SELECT mp.arn, mp.policy_document
+               FROM iam_user AS u
+               INNER JOIN {} AS mp
+               ON u.permissions_boundary_arn = mp.arn
+               WHERE u.account_id = {} AND u.user_name_cased = {}"#,
+            self.tables.managed_policy_table, account_id_param, user_name_param
+        );
+        let row: Option<(String, String)> = query_as(&sql)
+            .bind(user.account_id())
+            .bind(user.user_name())
+            .fetch_optional(&mut *db)
+            .await
+            .map_err(internal_error)?;
+
+        Ok(row.map(managed_policy))
+    }
+}
+
+impl Debug for GetPoliciesFromDatabase {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("GetPoliciesFromDatabase").field("tables", &self.tables).finish()
+    }
+}
+
+#[async_trait]
+impl PolicyProvider for GetPoliciesFromDatabase {
+    async fn get_policies_for_principal(&self, principal: &Principal) -> Result<PolicySet, BoxError> {
+        let mut policies = PolicySet::new();
+        let mut db = self.pool.acquire().await?;
+
+        for identity in principal.iter() {
+            if let PrincipalIdentity::User(user) = identity {
+                policies.extend(self.get_user_policies(&mut db, user).await?);
+            }
+        }
+
+        Ok(policies)
+    }
+
+    async fn get_permissions_boundary(&self, principal: &Principal) -> Result<Option<PolicySet>, BoxError> {
+        let mut boundary: Option<PolicySet> = None;
+        let mut db = self.pool.acquire().await?;
+
+        for identity in principal.iter() {
+            if let PrincipalIdentity::User(user) = identity {
+                if let Some(policy) = self.get_user_boundary(&mut db, user).await? {
+                    boundary.get_or_insert_with(PolicySet::new).push(policy);
+                }
+            }
+        }
+
+        Ok(boundary)
+    }
+}
+
+fn managed_policy((policy_arn, document): (String, String)) -> Policy {
+    Policy::new(
+        PolicyAttachment::Managed {
+            policy_arn,
+        },
+        document,
+    )
+}
+
+fn internal_error(e: SqlxError) -> BoxError {
+    error!("Failed to query for policies: {}", e);
+    e.into()
+}
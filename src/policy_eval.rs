@@ -0,0 +1,518 @@
+use {
+    crate::{AuthorizationDecision, Decision, PolicyEvaluator, PolicySet, RequestContext, ResolvedAction},
+    chrono::{DateTime, NaiveDate, TimeZone, Utc},
+    ipnet::IpNet,
+    scratchstack_aws_principal::{SessionData, SessionValue},
+    serde_json::{Map, Value},
+    std::{cmp::Ordering, net::IpAddr},
+    tower::BoxError,
+};
+
+/// The policy language version in which `${...}` is a policy variable rather than literal text.
+const POLICY_VARIABLES_VERSION: &str = "2012-10-17";
+
+/// A [PolicyEvaluator] for IAM JSON policy documents.
+///
+/// A statement applies to a request if its `Action` (or `NotAction`) matches the IAM action, its `Resource` (or
+/// `NotResource`) matches the resource, and every entry of its `Condition` holds for the keys in the [RequestContext]
+/// (e.g. `aws:SourceIp` and `aws:CurrentTime`). An action with several resources must be allowed on each of them; one
+/// without resources is evaluated against `*`. An explicit deny overrides any allow.
+///
+/// The string, numeric, date, `Bool`, IP address, ARN, and `Null` condition operators are supported, along with their
+/// `...IfExists` forms. Documents using the `ForAllValues:`/`ForAnyValue:` set operators or policy variables fail to
+/// evaluate rather than being misread.
+///
+/// The Aspen policy engine is not a dependency of this crate, so this evaluator implements the subset of the policy
+/// language above itself. An Aspen-backed [PolicyEvaluator] can be substituted where the full grammar is needed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IamPolicyEvaluator;
+
+impl IamPolicyEvaluator {
+    /// Create a new [IamPolicyEvaluator].
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl PolicyEvaluator for IamPolicyEvaluator {
+    fn evaluate(
+        &self,
+        policies: &PolicySet,
+        action: &ResolvedAction,
+        context: &RequestContext,
+    ) -> Result<AuthorizationDecision, BoxError> {
+        let mut statements = Vec::new();
+        for policy in policies {
+            let document: Value = serde_json::from_str(policy.document())?;
+            statements.extend(Statement::parse_document(&document)?);
+        }
+
+        let iam_action = action.iam_action();
+        let resources = if action.resources().is_empty() {
+            vec!["*".to_string()]
+        } else {
+            action.resources().iter().map(ToString::to_string).collect()
+        };
+
+        let mut allowed = Vec::new();
+        let mut denied = Vec::new();
+        let mut deny = false;
+        let mut all_allowed = true;
+        for resource in &resources {
+            let mut allows = false;
+            for statement in &statements {
+                if !statement.applies(&iam_action, resource, context.session_data())? {
+                    continue;
+                }
+
+                let matched = if statement.allow {
+                    allows = true;
+                    &mut allowed
+                } else {
+                    deny = true;
+                    &mut denied
+                };
+                if let Some(sid) = &statement.sid {
+                    if !matched.contains(sid) {
+                        matched.push(sid.clone());
+                    }
+                }
+            }
+            all_allowed &= allows;
+        }
+
+        let (decision, matched_statements) = if deny {
+            (Decision::Deny, denied)
+        } else if all_allowed {
+            (Decision::Allow, allowed)
+        } else {
+            (Decision::DefaultDeny, Vec::new())
+        };
+
+        Ok(AuthorizationDecision::new(decision, matched_statements, policies.len()))
+    }
+}
+
+/// A statement of a policy document.
+#[derive(Debug)]
+struct Statement {
+    sid: Option<String>,
+    allow: bool,
+    actions: Vec<String>,
+    not_action: bool,
+    resources: Vec<String>,
+    not_resource: bool,
+    conditions: Vec<Condition>,
+}
+
+impl Statement {
+    /// Parse the statements of a policy document.
+    fn parse_document(document: &Value) -> Result<Vec<Self>, BoxError> {
+        let variables = document.get("Version").and_then(Value::as_str) == Some(POLICY_VARIABLES_VERSION);
+        match document.get("Statement") {
+            Some(Value::Array(statements)) => statements.iter().map(|s| Self::parse(s, variables)).collect(),
+            Some(statement) => Ok(vec![Self::parse(statement, variables)?]),
+            None => Err("Policy document has no Statement".into()),
+        }
+    }
+
+    fn parse(statement: &Value, variables: bool) -> Result<Self, BoxError> {
+        let statement = statement.as_object().ok_or("Policy statement must be an object")?;
+        let allow = match statement.get("Effect").and_then(Value::as_str) {
+            Some("Allow") => true,
+            Some("Deny") => false,
+            _ => return Err("Policy statement must have an Effect of Allow or Deny".into()),
+        };
+        let (actions, not_action) = either(statement, "Action", "NotAction", false)?;
+        let (resources, not_resource) = either(statement, "Resource", "NotResource", variables)?;
+        let conditions = match statement.get("Condition") {
+            Some(Value::Object(operators)) => Condition::parse_block(operators, variables)?,
+            Some(_) => return Err("Policy statement Condition must be an object".into()),
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            sid: statement.get("Sid").and_then(Value::as_str).map(str::to_string),
+            allow,
+            actions,
+            not_action,
+            resources,
+            not_resource,
+            conditions,
+        })
+    }
+
+    /// Indicates whether the statement applies to the action on the resource with the given condition keys.
+    fn applies(&self, iam_action: &str, resource: &str, keys: &SessionData) -> Result<bool, BoxError> {
+        let iam_action = iam_action.to_lowercase();
+        let action_matches = self.actions.iter().any(|pattern| wildcard_matches(&pattern.to_lowercase(), &iam_action));
+        let resource_matches = self.resources.iter().any(|pattern| wildcard_matches(pattern, resource));
+        if action_matches == self.not_action || resource_matches == self.not_resource {
+            return Ok(false);
+        }
+
+        for condition in &self.conditions {
+            if !condition.holds(keys)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Returns the values of whichever of `name` or `not_name` the statement has, and whether it was `not_name`.
+fn either(
+    statement: &Map<String, Value>,
+    name: &str,
+    not_name: &str,
+    variables: bool,
+) -> Result<(Vec<String>, bool), BoxError> {
+    match (statement.get(name), statement.get(not_name)) {
+        (Some(values), None) => Ok((strings(values, variables)?, false)),
+        (None, Some(values)) => Ok((strings(values, variables)?, true)),
+        _ => Err(format!("Policy statement must have exactly one of {name} or {not_name}").into()),
+    }
+}
+
+/// Returns a policy value that may be a single value or a list as strings.
+fn strings(value: &Value, variables: bool) -> Result<Vec<String>, BoxError> {
+    let values = match value {
+        Value::Array(values) => values.iter().map(scalar).collect::<Result<Vec<_>, _>>()?,
+        value => vec![scalar(value)?],
+    };
+
+    if variables && values.iter().any(|value| value.contains("${")) {
+        return Err("Policy variables are not supported".into());
+    }
+
+    Ok(values)
+}
+
+fn scalar(value: &Value) -> Result<String, BoxError> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        _ => Err(format!("Invalid policy value: {value}").into()),
+    }
+}
+
+/// One entry of a `Condition` block: an operator, a condition key, and the values the key is compared with.
+#[derive(Debug)]
+struct Condition {
+    operator: String,
+    key: String,
+    values: Vec<String>,
+}
+
+impl Condition {
+    fn parse_block(operators: &Map<String, Value>, variables: bool) -> Result<Vec<Self>, BoxError> {
+        let mut conditions = Vec::new();
+        for (operator, keys) in operators {
+            if operator.starts_with("ForAllValues:") || operator.starts_with("ForAnyValue:") {
+                return Err(format!("Unsupported condition operator: {operator}").into());
+            }
+
+            let keys =
+                keys.as_object().ok_or_else(|| format!("Condition operator {operator} must map keys to values"))?;
+            for (key, values) in keys {
+                conditions.push(Self {
+                    operator: operator.clone(),
+                    key: key.clone(),
+                    values: strings(values, variables)?,
+                });
+            }
+        }
+        Ok(conditions)
+    }
+
+    /// Indicates whether the condition holds for the given condition keys. A key that is missing matches the negated
+    /// operators (e.g. `StringNotEquals`) and the `...IfExists` forms, and nothing else.
+    fn holds(&self, keys: &SessionData) -> Result<bool, BoxError> {
+        let value = keys.get(&self.key).filter(|value| !value.is_null());
+
+        if self.operator == "Null" {
+            return self.values.iter().try_fold(false, |holds, expected| match expected.as_str() {
+                "true" => Ok(holds || value.is_none()),
+                "false" => Ok(holds || value.is_some()),
+                _ => Err(format!("Invalid Null condition value: {expected}").into()),
+            });
+        }
+
+        let (operator, if_exists) = match self.operator.strip_suffix("IfExists") {
+            Some(operator) => (operator, true),
+            None => (self.operator.as_str(), false),
+        };
+        let negated = operator.contains("Not");
+        let operator = operator.replacen("Not", "", 1);
+
+        let Some(value) = value else {
+            return Ok(negated || if_exists);
+        };
+
+        let mut matches = false;
+        for expected in &self.values {
+            matches |= compare(&operator, value, expected)?;
+        }
+        Ok(matches != negated)
+    }
+}
+
+/// Compare a condition key's value with a policy value using a non-negated operator, e.g. `StringLike`.
+fn compare(operator: &str, value: &SessionValue, expected: &str) -> Result<bool, BoxError> {
+    let unsupported = || -> BoxError { format!("Unsupported condition operator: {operator}").into() };
+
+    match operator {
+        "StringEquals" => Ok(value.as_variable_value() == expected),
+        "StringEqualsIgnoreCase" => Ok(value.as_variable_value().to_lowercase() == expected.to_lowercase()),
+        "StringLike" => Ok(wildcard_matches(expected, &value.as_variable_value())),
+        "Bool" => match expected.to_lowercase().as_str() {
+            "true" => Ok(as_bool(value) == Some(true)),
+            "false" => Ok(as_bool(value) == Some(false)),
+            _ => Err(format!("Invalid Bool condition value: {expected}").into()),
+        },
+        "IpAddress" => {
+            let network = expected
+                .parse::<IpNet>()
+                .or_else(|_| expected.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| format!("Invalid IpAddress condition value: {expected}"))?;
+            Ok(as_ip_addr(value).is_some_and(|ip| network.contains(&ip)))
+        }
+        "ArnEquals" | "ArnLike" => Ok(arn_matches(expected, &value.as_variable_value())),
+        _ => {
+            if let Some(comparison) = operator.strip_prefix("Numeric") {
+                let expected: f64 =
+                    expected.parse().map_err(|_| format!("Invalid Numeric condition value: {expected}"))?;
+                let ordering = as_number(value).and_then(|n| n.partial_cmp(&expected));
+                ordering.map_or(Ok(false), |ordering| ordering_matches(comparison, ordering).ok_or_else(unsupported))
+            } else if let Some(comparison) = operator.strip_prefix("Date") {
+                let expected =
+                    parse_policy_date(expected).ok_or_else(|| format!("Invalid Date condition value: {expected}"))?;
+                let ordering = as_date(value).map(|date| date.cmp(&expected));
+                ordering.map_or(Ok(false), |ordering| ordering_matches(comparison, ordering).ok_or_else(unsupported))
+            } else {
+                Err(unsupported())
+            }
+        }
+    }
+}
+
+/// Indicates whether `ordering` satisfies the comparison named by an operator suffix, e.g. `LessThanEquals`.
+fn ordering_matches(comparison: &str, ordering: Ordering) -> Option<bool> {
+    match comparison {
+        "Equals" => Some(ordering.is_eq()),
+        "LessThan" => Some(ordering.is_lt()),
+        "LessThanEquals" => Some(ordering.is_le()),
+        "GreaterThan" => Some(ordering.is_gt()),
+        "GreaterThanEquals" => Some(ordering.is_ge()),
+        _ => None,
+    }
+}
+
+fn as_bool(value: &SessionValue) -> Option<bool> {
+    match value {
+        SessionValue::Bool(b) => Some(*b),
+        SessionValue::String(s) => s.to_lowercase().parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_ip_addr(value: &SessionValue) -> Option<IpAddr> {
+    match value {
+        SessionValue::IpAddr(ip) => Some(*ip),
+        SessionValue::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_number(value: &SessionValue) -> Option<f64> {
+    match value {
+        SessionValue::Integer(i) => Some(*i as f64),
+        SessionValue::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_date(value: &SessionValue) -> Option<DateTime<Utc>> {
+    match value {
+        SessionValue::Timestamp(t) => Some(*t),
+        SessionValue::Integer(i) => Utc.timestamp_opt(*i, 0).single(),
+        SessionValue::String(s) => parse_policy_date(s),
+        _ => None,
+    }
+}
+
+/// Parse a date in a policy: an ISO 8601 timestamp or date, or seconds since the epoch.
+fn parse_policy_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?));
+    }
+
+    value.parse().ok().and_then(|seconds| Utc.timestamp_opt(seconds, 0).single())
+}
+
+/// Match an ARN against a pattern component by component, so wildcards don't cross the `:` separators of the partition,
+/// service, region, and account id.
+fn arn_matches(pattern: &str, arn: &str) -> bool {
+    let pattern: Vec<_> = pattern.splitn(6, ':').collect();
+    let arn: Vec<_> = arn.splitn(6, ':').collect();
+    pattern.len() == arn.len() && pattern.iter().zip(&arn).all(|(pattern, part)| wildcard_matches(pattern, part))
+}
+
+/// Match a value against a pattern where `*` matches any run of characters and `?` matches any single character.
+fn wildcard_matches(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+    let (mut p, mut v) = (0, 0);
+    let mut star = None;
+
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, v));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == value[v] => {
+                p += 1;
+                v += 1;
+            }
+            _ => match star {
+                // Let the last `*` absorb one more character and retry.
+                Some((star_p, star_v)) => {
+                    star = Some((star_p, star_v + 1));
+                    p = star_p + 1;
+                    v = star_v + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{wildcard_matches, IamPolicyEvaluator},
+        crate::{
+            session_keys::SOURCE_IP, Decision, Policy, PolicyAttachment, PolicyEvaluator, PolicySet, RequestContext,
+            ResolvedAction,
+        },
+        chrono::{TimeZone, Utc},
+        scratchstack_aws_principal::SessionValue,
+        std::net::{IpAddr, Ipv4Addr},
+    };
+
+    fn policies(document: &str) -> PolicySet {
+        vec![Policy::new(
+            PolicyAttachment::Inline {
+                policy_name: "test".to_string(),
+            },
+            document,
+        )]
+        .into_iter()
+        .collect()
+    }
+
+    fn user(name: &str) -> ResolvedAction {
+        ResolvedAction::new("iam", "GetUser", vec![format!("arn:aws:iam::123456789012:user/{name}").parse().unwrap()])
+    }
+
+    #[test]
+    fn test_wildcard_matches() {
+        assert!(wildcard_matches("*", ""));
+        assert!(wildcard_matches("iam:Get*", "iam:GetUser"));
+        assert!(wildcard_matches("iam:*User", "iam:GetUser"));
+        assert!(wildcard_matches("a*b*c", "aXbYbZc"));
+        assert!(wildcard_matches("user/?est", "user/test"));
+        assert!(!wildcard_matches("iam:Get*", "iam:ListUsers"));
+        assert!(!wildcard_matches("user/?est", "user/est"));
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let evaluator = IamPolicyEvaluator::new();
+        let context = RequestContext::builder()
+            .current_time(Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap())
+            .source_ip(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)))
+            .secure_transport(false)
+            .build();
+        let decide = |document: &str, action: &ResolvedAction, context: &RequestContext| {
+            evaluator.evaluate(&policies(document), action, context).unwrap()
+        };
+
+        // Resources are matched with wildcards; an explicit deny wins.
+        let document = r#"{
+            "Version": "2012-10-17",
+            "Statement": [
+                {"Sid": "AllowUsers", "Effect": "Allow", "Action": "iam:get*", "Resource": "arn:aws:iam::*:user/*"},
+                {"Sid": "DenyAdmin", "Effect": "Deny", "NotAction": "iam:List*", "Resource": "arn:aws:iam::*:user/admin"}
+            ]
+        }"#;
+        let decision = decide(document, &user("test"), &context);
+        assert_eq!(decision.decision(), Decision::Allow);
+        assert_eq!(decision.matched_statements(), &["AllowUsers".to_string()]);
+        assert_eq!(decision.evaluated_policies(), 1);
+        let decision = decide(document, &user("admin"), &context);
+        assert_eq!(decision.decision(), Decision::Deny);
+        assert_eq!(decision.matched_statements(), &["DenyAdmin".to_string()]);
+        assert_eq!(
+            decide(document, &ResolvedAction::new("iam", "GetUser", Vec::new()), &context).decision(),
+            Decision::DefaultDeny
+        );
+
+        // Conditions are evaluated against the request context.
+        let document = r#"{
+            "Version": "2012-10-17",
+            "Statement": {
+                "Effect": "Allow",
+                "Action": "iam:GetUser",
+                "Resource": "*",
+                "Condition": {
+                    "NotIpAddress": {"aws:SourceIp": ["198.51.100.0/24", "203.0.113.0/24"]},
+                    "DateGreaterThanEquals": {"aws:CurrentTime": "2022-10-01"},
+                    "Bool": {"aws:SecureTransport": "false"},
+                    "StringEqualsIfExists": {"aws:PrincipalTag/team": "storage"},
+                    "Null": {"aws:MultiFactorAuthAge": "true"}
+                }
+            }
+        }"#;
+        assert_eq!(decide(document, &user("test"), &context).decision(), Decision::Allow);
+
+        let mut session_data = context.session_data().clone();
+        session_data.insert(SOURCE_IP, SessionValue::IpAddr(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 9))));
+        let elsewhere = RequestContext::builder().session_data(session_data).build();
+        assert_eq!(decide(document, &user("test"), &elsewhere).decision(), Decision::DefaultDeny);
+
+        let mut session_data = context.session_data().clone();
+        session_data.insert("aws:PrincipalTag/team", SessionValue::String("compute".to_string()));
+        let other_team = RequestContext::builder().session_data(session_data).build();
+        assert_eq!(decide(document, &user("test"), &other_team).decision(), Decision::DefaultDeny);
+
+        // Conditions on missing keys don't hold unless the operator is negated.
+        let document = r#"{
+            "Statement": {
+                "Effect": "Allow",
+                "Action": "*",
+                "Resource": "*",
+                "Condition": {"NumericLessThan": {"aws:MultiFactorAuthAge": 3600}}
+            }
+        }"#;
+        assert_eq!(decide(document, &user("test"), &context).decision(), Decision::DefaultDeny);
+
+        // Unsupported parts of the policy language are errors rather than being ignored.
+        for document in [
+            r#"{"Statement": {"Effect": "Deny", "Action": "*", "Resource": "*", "Condition": {"ForAnyValue:StringLike": {"aws:TagKeys": "x*"}}}}"#,
+            r#"{"Version": "2012-10-17", "Statement": {"Effect": "Allow", "Action": "*", "Resource": "arn:aws:iam::*:user/${aws:username}"}}"#,
+            r#"{"Statement": {"Effect": "Allow", "Action": "*"}}"#,
+        ] {
+            assert!(evaluator.evaluate(&policies(document), &user("test"), &context).is_err());
+        }
+    }
+}
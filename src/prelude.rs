@@ -0,0 +1,13 @@
+pub use {
+    crate::{
+        session_keys::SessionDataExt, AwsSigV4VerifierLayer, AwsSigV4VerifierService, Clock, ConnectInfo, ErrorContext,
+        ErrorMapper, JsonErrorMapper, PayloadSigning, RequestExt, RequestId, RestJsonErrorMapper, S3XmlErrorMapper,
+        ServiceErrorCatalog, SpawnService, StandardError, XmlErrorMapper,
+    },
+    scratchstack_aws_principal::{Principal, PrincipalIdentity, SessionData, SessionValue},
+    scratchstack_aws_signature::{
+        GetSigningKeyRequest, GetSigningKeyResponse, KSecretKey, KSigningKey, SignatureError, SignatureOptions,
+        SignedHeaderRequirements,
+    },
+    scratchstack_errors::ServiceError,
+};
@@ -0,0 +1,113 @@
+use {
+    scratchstack_aws_signature::SignatureOptions,
+    std::{
+        error::Error,
+        fmt::{Display, Formatter, Result as FmtResult},
+        str::FromStr,
+    },
+};
+
+/// A preset of the canonicalization and payload options used by a family of AWS services.
+///
+/// Selecting a profile on a verifier builder sets both `signature_options` and `allow_unsigned_payload`; either may be
+/// overridden afterwards. Profiles may also be parsed from their names: `"default"`, `"s3"`, or `"api-gateway"`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ServiceProfile {
+    /// Query and JSON protocol services such as IAM and STS: URI paths are double-encoded, form bodies are treated as
+    /// query parameters, and payloads must be signed.
+    #[default]
+    Default,
+
+    /// Amazon S3: URI paths are not double-encoded or normalized, and `UNSIGNED-PAYLOAD` is accepted.
+    S3,
+
+    /// Amazon API Gateway (`execute-api`): URI paths are double-encoded, bodies are opaque to the signature, and
+    /// payloads must be signed.
+    ApiGateway,
+}
+
+impl ServiceProfile {
+    /// Returns the name of the profile.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::S3 => "s3",
+            Self::ApiGateway => "api-gateway",
+        }
+    }
+
+    /// Returns the signature options used by the profile.
+    pub fn signature_options(&self) -> SignatureOptions {
+        match self {
+            Self::Default => SignatureOptions {
+                s3: false,
+                url_encode_form: true,
+            },
+            Self::S3 => SignatureOptions {
+                s3: true,
+                url_encode_form: false,
+            },
+            Self::ApiGateway => SignatureOptions {
+                s3: false,
+                url_encode_form: false,
+            },
+        }
+    }
+
+    /// Indicates whether the profile accepts requests signed with `x-amz-content-sha256: UNSIGNED-PAYLOAD`.
+    pub fn allow_unsigned_payload(&self) -> bool {
+        matches!(self, Self::S3)
+    }
+}
+
+impl Display for ServiceProfile {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ServiceProfile {
+    type Err = UnknownProfile;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Self::Default),
+            "s3" => Ok(Self::S3),
+            "api-gateway" | "execute-api" => Ok(Self::ApiGateway),
+            _ => Err(UnknownProfile(s.to_string())),
+        }
+    }
+}
+
+/// The error returned when parsing a [ServiceProfile] from an unrecognized name.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnknownProfile(String);
+
+impl Display for UnknownProfile {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "Unknown service profile '{}'; expected 'default', 's3', or 'api-gateway'", self.0)
+    }
+}
+
+impl Error for UnknownProfile {}
+
+#[cfg(test)]
+mod tests {
+    use super::ServiceProfile;
+
+    #[test]
+    fn test_profiles() {
+        for profile in [ServiceProfile::Default, ServiceProfile::S3, ServiceProfile::ApiGateway] {
+            assert_eq!(profile.name().parse::<ServiceProfile>().unwrap(), profile);
+        }
+
+        assert_eq!("execute-api".parse::<ServiceProfile>().unwrap(), ServiceProfile::ApiGateway);
+        assert!("s4".parse::<ServiceProfile>().is_err());
+
+        let s3 = ServiceProfile::S3;
+        assert!(s3.signature_options().s3);
+        assert!(s3.allow_unsigned_payload());
+        assert!(!ServiceProfile::Default.signature_options().s3);
+        assert!(!ServiceProfile::ApiGateway.allow_unsigned_payload());
+    }
+}
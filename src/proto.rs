@@ -0,0 +1,2 @@
+/// Helpers for REST-XML protocol services such as S3 and Route 53.
+pub mod restxml;
@@ -0,0 +1,184 @@
+//! Responses are built from typed structs serialized with quick-xml: fields holding elements are named
+//! `$unflatten=Name` (or are structs or lists, which are always elements), and other scalar fields become attributes.
+//! Lists of a `Vec` field are flattened, with one element per item named after the field, as S3 does for `Contents`;
+//! lists wrapped in a container element, as Route 53 does for `HostedZones`, use a nested struct or
+//! [members][crate::proto::restxml::members].
+//!
+//! ```
+//! use {
+//!     scratchstack_http_framework::proto::restxml::{self, S3_NAMESPACE},
+//!     serde::Serialize,
+//! };
+//!
+//! #[derive(Serialize)]
+//! struct ListBucketResult {
+//!     #[serde(rename = "$unflatten=Name")]
+//!     name: String,
+//!
+//!     #[serde(rename = "$unflatten=IsTruncated")]
+//!     is_truncated: bool,
+//!
+//!     #[serde(rename = "Contents")]
+//!     contents: Vec<Object>,
+//! }
+//!
+//! #[derive(Serialize)]
+//! struct Object {
+//!     #[serde(rename = "$unflatten=Key")]
+//!     key: String,
+//! }
+//!
+//! let (contents, is_truncated) = restxml::truncate(vec!["a", "b", "c"], 2);
+//! let result = ListBucketResult {
+//!     name: "examplebucket".to_string(),
+//!     is_truncated,
+//!     contents: contents.into_iter().map(|key| Object { key: key.to_string() }).collect(),
+//! };
+//! let response = restxml::response("ListBucketResult", S3_NAMESPACE, &result, None).unwrap();
+//! ```
+
+use {
+    crate::RequestId,
+    http::{header::CONTENT_TYPE, response::Builder, HeaderValue, StatusCode},
+    hyper::{Body, Response},
+    serde::{Serialize, Serializer},
+    tower::BoxError,
+};
+
+/// The XML namespace of S3 documents.
+pub const S3_NAMESPACE: &str = "http://s3.amazonaws.com/doc/2006-03-01/";
+
+/// The XML namespace of Route 53 documents.
+pub const ROUTE53_NAMESPACE: &str = "https://route53.amazonaws.com/doc/2013-04-01/";
+
+/// The XML declaration that starts REST-XML documents.
+const XML_DECLARATION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>"#;
+
+/// Serialize `value` as a REST-XML document whose root element is `root` in the given namespace, including the XML
+/// declaration.
+pub fn to_xml<T: Serialize>(root: &str, namespace: &str, value: &T) -> Result<String, BoxError> {
+    let mut buffer = Vec::new();
+    let mut serializer = quick_xml::se::Serializer::with_root(quick_xml::Writer::new(&mut buffer), Some(root));
+    value.serialize(&mut serializer)?;
+    let element = String::from_utf8(buffer)?;
+
+    // The serializer has no notion of namespaces, so add the xmlns attribute to the root element it wrote.
+    let rest = element.strip_prefix('<').and_then(|e| e.strip_prefix(root)).ok_or("Root element not serialized")?;
+    Ok(format!(r#"{XML_DECLARATION}<{root} xmlns="{namespace}"{rest}"#))
+}
+
+/// Returns a `200 OK` response with `value` serialized by [to_xml] and the request id, if known, in the
+/// `x-amz-request-id` header.
+pub fn response<T: Serialize>(
+    root: &str,
+    namespace: &str,
+    value: &T,
+    request_id: Option<RequestId>,
+) -> Result<Response<Body>, BoxError> {
+    let body = to_xml(root, namespace, value)?;
+    with_request_id(Response::builder().status(StatusCode::OK), request_id)
+        .header(CONTENT_TYPE, "application/xml")
+        .body(Body::from(body))
+        .map_err(Into::into)
+}
+
+/// Add the `x-amz-request-id` header to a response, if the request id is known.
+pub fn with_request_id(builder: Builder, request_id: Option<RequestId>) -> Builder {
+    match request_id {
+        Some(request_id) => builder.header("x-amz-request-id", request_id.to_string()),
+        None => builder,
+    }
+}
+
+/// Returns the `ETag` header value for an object digest (e.g. the MD5 of its content): the digest in lowercase hex,
+/// in double quotes.
+pub fn etag(digest: &[u8]) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{}\"", hex::encode(digest))).expect("hex digests are valid header values")
+}
+
+/// Split the items fetched for a page of a listing into the page and its `IsTruncated` marker. Fetch one item more than
+/// `max_items` so a full final page isn't reported as truncated.
+pub fn truncate<T>(mut items: Vec<T>, max_items: usize) -> (Vec<T>, bool) {
+    let is_truncated = items.len() > max_items;
+    items.truncate(max_items);
+    (items, is_truncated)
+}
+
+/// Serialize a list with each item in a `<member>` element, for use with `#[serde(serialize_with = "members")]` on a
+/// list field that is wrapped in a container element.
+pub fn members<S: Serializer, T: Serialize>(items: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+    #[derive(Serialize)]
+    struct Members<'a, T> {
+        member: &'a [T],
+    }
+
+    Members {
+        member: items,
+    }
+    .serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{etag, members, response, to_xml, truncate, ROUTE53_NAMESPACE, S3_NAMESPACE},
+        crate::RequestId,
+        pretty_assertions::assert_eq,
+        serde::Serialize,
+    };
+
+    #[derive(Serialize)]
+    struct ListHostedZonesResponse {
+        #[serde(rename = "HostedZones", serialize_with = "members")]
+        hosted_zones: Vec<HostedZone>,
+
+        #[serde(rename = "$unflatten=IsTruncated")]
+        is_truncated: bool,
+    }
+
+    #[derive(Serialize)]
+    struct HostedZone {
+        #[serde(rename = "$unflatten=Name")]
+        name: String,
+    }
+
+    #[test]
+    fn test_to_xml() {
+        let (zones, is_truncated) = truncate(vec!["example.com.", "example.net.", "example.org."], 2);
+        let value = ListHostedZonesResponse {
+            hosted_zones: zones
+                .into_iter()
+                .map(|name| HostedZone {
+                    name: name.to_string(),
+                })
+                .collect(),
+            is_truncated,
+        };
+
+        assert_eq!(
+            to_xml("ListHostedZonesResponse", ROUTE53_NAMESPACE, &value).unwrap(),
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?><ListHostedZonesResponse xmlns="{ROUTE53_NAMESPACE}"><HostedZones><member><Name>example.com.</Name></member><member><Name>example.net.</Name></member></HostedZones><IsTruncated>true</IsTruncated></ListHostedZonesResponse>"#
+            )
+        );
+        assert_eq!(truncate(vec![1, 2], 2), (vec![1, 2], false));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_response() {
+        let request_id = RequestId::from_timestamp_and_random(0, 1);
+        let value = HostedZone {
+            name: "example.com.".to_string(),
+        };
+
+        let response = response("HostedZone", S3_NAMESPACE, &value, Some(request_id)).unwrap();
+        assert_eq!(response.headers()["content-type"], "application/xml");
+        assert_eq!(response.headers()["x-amz-request-id"], request_id.to_string().as_str());
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(String::from_utf8_lossy(&body)
+            .ends_with(&format!(r#"<HostedZone xmlns="{S3_NAMESPACE}"><Name>example.com.</Name></HostedZone>"#)));
+
+        assert_eq!(etag(&[0xd4, 0x1d, 0x8c, 0xd9]), "\"d41d8cd9\"");
+    }
+}
@@ -0,0 +1,178 @@
+use {
+    http::header::HeaderMap,
+    ipnet::IpNet,
+    std::net::{IpAddr, SocketAddr},
+};
+
+const FORWARDED: &str = "forwarded";
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// The set of proxies (load balancers, CDNs, etc.) whose `Forwarded` and `X-Forwarded-For` headers are trusted to
+/// report the real client address.
+///
+/// When a request arrives from a trusted proxy, the forwarding chain is walked from the nearest hop outward, skipping
+/// hops that are themselves trusted proxies; the first untrusted address is taken to be the client. The `Forwarded`
+/// header ([RFC 7239](https://www.rfc-editor.org/rfc/rfc7239)) is used if present; otherwise `X-Forwarded-For` is
+/// used.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TrustedProxies {
+    networks: Vec<IpNet>,
+}
+
+impl TrustedProxies {
+    /// Create a new [TrustedProxies] that trusts the given networks.
+    pub fn new(networks: Vec<IpNet>) -> Self {
+        Self {
+            networks,
+        }
+    }
+
+    /// Returns the trusted networks.
+    #[inline]
+    pub fn networks(&self) -> &[IpNet] {
+        &self.networks
+    }
+
+    /// Indicates whether the given address belongs to a trusted proxy.
+    pub fn is_trusted(&self, addr: &IpAddr) -> bool {
+        self.networks.iter().any(|net| net.contains(addr))
+    }
+
+    /// Determine the address of the client given the address of the connection peer and the request headers.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.is_trusted(&peer) {
+            return peer;
+        }
+
+        let hops = if headers.contains_key(FORWARDED) {
+            forwarded_hops(headers)
+        } else {
+            x_forwarded_for_hops(headers)
+        };
+
+        let mut client = peer;
+        for hop in hops.into_iter().rev() {
+            match hop {
+                // An unparseable (e.g. obfuscated or "unknown") hop ends the chain we can verify.
+                None => break,
+                Some(addr) => {
+                    client = addr;
+                    if !self.is_trusted(&addr) {
+                        break;
+                    }
+                }
+            }
+        }
+
+        client
+    }
+}
+
+/// Returns the hops listed in the `Forwarded` headers, nearest hop last. Unparseable hops are `None`.
+fn forwarded_hops(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let mut hops = Vec::new();
+
+    for value in headers.get_all(FORWARDED) {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        for element in value.split(',') {
+            let for_param = element.split(';').find_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                if key.trim().eq_ignore_ascii_case("for") {
+                    Some(value.trim())
+                } else {
+                    None
+                }
+            });
+
+            if let Some(node) = for_param {
+                hops.push(parse_node(node.trim_matches('"')));
+            }
+        }
+    }
+
+    hops
+}
+
+/// Returns the hops listed in the `X-Forwarded-For` headers, nearest hop last. Unparseable hops are `None`.
+fn x_forwarded_for_hops(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let mut hops = Vec::new();
+
+    for value in headers.get_all(X_FORWARDED_FOR) {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        for node in value.split(',') {
+            hops.push(parse_node(node.trim()));
+        }
+    }
+
+    hops
+}
+
+/// Parse a node identifier: a bare IP address, a bracketed IPv6 address, or either with a port.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    if let Ok(addr) = node.parse::<IpAddr>() {
+        return Some(addr);
+    }
+
+    if let Ok(addr) = node.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+
+    // Bracketed IPv6 address without a port.
+    node.strip_prefix('[')?.strip_suffix(']')?.parse::<IpAddr>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::TrustedProxies,
+        http::header::{HeaderMap, HeaderValue},
+        pretty_assertions::assert_eq,
+        std::net::IpAddr,
+    };
+
+    fn proxies() -> TrustedProxies {
+        TrustedProxies::new(vec!["10.0.0.0/8".parse().unwrap(), "fd00::/8".parse().unwrap()])
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_untrusted_peer_ignores_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static("198.51.100.1"));
+        assert_eq!(proxies().client_ip(ip("192.0.2.1"), &headers), ip("192.0.2.1"));
+    }
+
+    #[test]
+    fn test_x_forwarded_for() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static("203.0.113.9, 198.51.100.1, 10.1.2.3"));
+        assert_eq!(proxies().client_ip(ip("10.0.0.1"), &headers), ip("198.51.100.1"));
+
+        // Only trusted hops: the leftmost one is the client.
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static("10.9.9.9"));
+        assert_eq!(proxies().client_ip(ip("10.0.0.1"), &headers), ip("10.9.9.9"));
+
+        // No headers at all.
+        assert_eq!(proxies().client_ip(ip("10.0.0.1"), &HeaderMap::new()), ip("10.0.0.1"));
+    }
+
+    #[test]
+    fn test_forwarded() {
+        let mut headers = HeaderMap::new();
+        headers.append("forwarded", HeaderValue::from_static(r#"for=192.0.2.60;proto=http;by=203.0.113.43"#));
+        headers.append("forwarded", HeaderValue::from_static(r#"For="[2001:db8:cafe::17]:4711", for=fd00::1"#));
+        headers.insert("x-forwarded-for", HeaderValue::from_static("198.51.100.1"));
+        assert_eq!(proxies().client_ip(ip("10.0.0.1"), &headers), ip("2001:db8:cafe::17"));
+    }
+
+    #[test]
+    fn test_unknown_hop_stops_chain() {
+        let mut headers = HeaderMap::new();
+        headers.insert("forwarded", HeaderValue::from_static("for=192.0.2.60, for=unknown, for=10.3.3.3"));
+        assert_eq!(proxies().client_ip(ip("10.0.0.1"), &headers), ip("10.3.3.3"));
+    }
+}
@@ -0,0 +1,188 @@
+use {
+    async_trait::async_trait,
+    chrono::{DateTime, Utc},
+    std::{
+        collections::{HashMap, VecDeque},
+        fmt::Debug,
+        sync::Mutex,
+    },
+    tower::BoxError,
+};
+
+/// Identifies a signed request for the purpose of detecting replays: the access key, signature, and request timestamp.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ReplayKey {
+    access_key: String,
+    signature: String,
+    timestamp: DateTime<Utc>,
+}
+
+impl ReplayKey {
+    /// Create a new [ReplayKey].
+    pub fn new(access_key: impl Into<String>, signature: impl Into<String>, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            access_key: access_key.into(),
+            signature: signature.into(),
+            timestamp,
+        }
+    }
+
+    /// Returns the access key the request was signed with.
+    #[inline]
+    pub fn access_key(&self) -> &str {
+        &self.access_key
+    }
+
+    /// Returns the signature of the request.
+    #[inline]
+    pub fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    /// Returns the timestamp of the request.
+    #[inline]
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+}
+
+/// A store of recently seen request signatures, used by
+/// [AwsSigV4VerifierService][crate::AwsSigV4VerifierService] to reject replayed requests.
+///
+/// [MemoryReplayStore] keeps signatures in process memory. Services running several instances behind a load balancer
+/// should implement this trait on top of a shared store (e.g. Redis `SET NX` with an expiry) instead.
+#[async_trait]
+pub trait ReplayStore: Debug + Send + Sync {
+    /// Record the key as seen until `expires`, returning `false` if it had already been seen and has not yet expired.
+    ///
+    /// `now` is the server time used to validate the request.
+    async fn check_and_insert(
+        &self,
+        key: ReplayKey,
+        now: DateTime<Utc>,
+        expires: DateTime<Utc>,
+    ) -> Result<bool, BoxError>;
+}
+
+/// A bounded, in-memory [ReplayStore].
+///
+/// Entries are dropped once they expire. When the store is full, the least recently seen entry is evicted, even if it
+/// has not expired, so the capacity should comfortably exceed the number of requests expected within the allowed
+/// clock skew.
+#[derive(Debug)]
+pub struct MemoryReplayStore {
+    capacity: usize,
+    inner: Mutex<MemoryReplayStoreInner>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryReplayStoreInner {
+    entries: HashMap<ReplayKey, DateTime<Utc>>,
+    order: VecDeque<(ReplayKey, DateTime<Utc>)>,
+}
+
+impl MemoryReplayStore {
+    /// Create a new [MemoryReplayStore] holding at most `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(MemoryReplayStoreInner::default()),
+        }
+    }
+
+    /// Returns the maximum number of entries held by the store.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of entries currently held by the store, including any that have expired but have not yet
+    /// been dropped.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    /// Indicates whether the store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl MemoryReplayStoreInner {
+    /// Remove the oldest entry, unless it has since been re-inserted with a different expiry.
+    fn pop_oldest(&mut self) -> Option<DateTime<Utc>> {
+        let (key, expires) = self.order.pop_front()?;
+        if self.entries.get(&key) == Some(&expires) {
+            self.entries.remove(&key);
+        }
+        Some(expires)
+    }
+}
+
+#[async_trait]
+impl ReplayStore for MemoryReplayStore {
+    async fn check_and_insert(
+        &self,
+        key: ReplayKey,
+        now: DateTime<Utc>,
+        expires: DateTime<Utc>,
+    ) -> Result<bool, BoxError> {
+        let mut inner = self.inner.lock().unwrap();
+
+        while let Some((_, oldest_expires)) = inner.order.front() {
+            if *oldest_expires > now {
+                break;
+            }
+            inner.pop_oldest();
+        }
+
+        if let Some(seen_expires) = inner.entries.get(&key) {
+            if *seen_expires > now {
+                return Ok(false);
+            }
+        }
+
+        inner.entries.insert(key.clone(), expires);
+        inner.order.push_back((key, expires));
+
+        while inner.entries.len() > self.capacity {
+            if inner.pop_oldest().is_none() {
+                break;
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{MemoryReplayStore, ReplayKey, ReplayStore},
+        chrono::{Duration, TimeZone, Utc},
+    };
+
+    #[test_log::test(tokio::test)]
+    async fn test_memory_replay_store() {
+        let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+        let expires = now + Duration::minutes(15);
+        let store = MemoryReplayStore::new(2);
+        let key = |signature: &str| ReplayKey::new("AKIDEXAMPLE", signature, now);
+
+        assert!(store.check_and_insert(key("a"), now, expires).await.unwrap());
+        assert!(!store.check_and_insert(key("a"), now, expires).await.unwrap());
+        assert!(store.check_and_insert(key("b"), now, expires).await.unwrap());
+        assert_eq!(store.len(), 2);
+
+        // Exceeding the capacity evicts the oldest entry.
+        assert!(store.check_and_insert(key("c"), now, expires).await.unwrap());
+        assert_eq!(store.len(), 2);
+        assert!(store.check_and_insert(key("a"), now, expires).await.unwrap());
+        assert!(!store.check_and_insert(key("c"), now, expires).await.unwrap());
+
+        // Expired entries are dropped and may be seen again.
+        let later = expires + Duration::seconds(1);
+        assert!(store.check_and_insert(key("c"), later, later + Duration::minutes(15)).await.unwrap());
+        assert_eq!(store.len(), 1);
+    }
+}
@@ -0,0 +1,221 @@
+use {
+    crate::RequestId,
+    http::{request::Parts, Extensions, Request},
+    scratchstack_aws_principal::{Principal, SessionData},
+    std::{
+        any::type_name,
+        error::Error,
+        fmt::{Display, Formatter, Result as FmtResult},
+        net::{IpAddr, SocketAddr},
+    },
+};
+
+/// Information about the connection a request was received on.
+///
+/// This is inserted into the request extensions by [AwsSigV4VerifierService][crate::AwsSigV4VerifierService] when it
+/// is spawned by [SpawnService][crate::SpawnService] (or when it is explicitly configured on the builder).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ConnectInfo {
+    remote_addr: SocketAddr,
+    local_addr: Option<SocketAddr>,
+    client_ip: Option<IpAddr>,
+}
+
+impl ConnectInfo {
+    /// Create a new [ConnectInfo] from the remote (peer) address and, if known, the local address.
+    pub fn new(remote_addr: SocketAddr, local_addr: Option<SocketAddr>) -> Self {
+        Self {
+            remote_addr,
+            local_addr,
+            client_ip: None,
+        }
+    }
+
+    /// Returns a copy of this [ConnectInfo] with the client address overridden, e.g. by a trusted proxy's
+    /// `X-Forwarded-For` header.
+    pub fn with_client_ip(self, client_ip: IpAddr) -> Self {
+        Self {
+            client_ip: Some(client_ip),
+            ..self
+        }
+    }
+
+    /// Returns the address of the remote peer of the connection.
+    #[inline]
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+
+    /// Returns the local address the connection was accepted on, if known.
+    #[inline]
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Returns the address of the client that originated the request.
+    ///
+    /// This is the peer address unless it was overridden by [TrustedProxies][crate::TrustedProxies] handling.
+    #[inline]
+    pub fn client_ip(&self) -> IpAddr {
+        self.client_ip.unwrap_or_else(|| self.remote_addr.ip())
+    }
+}
+
+/// Whether the payload of a request was covered by its signature.
+///
+/// This is inserted into the request extensions by [AwsSigV4VerifierService][crate::AwsSigV4VerifierService] after
+/// authentication, allowing the service implementation to require signed payloads for sensitive operations.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PayloadSigning {
+    /// The payload hash was included in the signature.
+    Signed,
+
+    /// The request was signed with `x-amz-content-sha256: UNSIGNED-PAYLOAD`; the payload may have been altered.
+    Unsigned,
+}
+
+impl PayloadSigning {
+    /// Indicates whether the payload was covered by the signature.
+    #[inline]
+    pub fn is_signed(&self) -> bool {
+        matches!(self, Self::Signed)
+    }
+}
+
+/// Details of how a request was signed, for audit logging and for explaining signature mismatches.
+///
+/// This is inserted into the request extensions by [AwsSigV4VerifierService][crate::AwsSigV4VerifierService] after
+/// authentication if `expose_signing_details` is enabled.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SigningDetails {
+    canonical_request: String,
+    string_to_sign: String,
+    signed_headers: Vec<String>,
+    credential_scope: String,
+}
+
+impl SigningDetails {
+    /// Create a new [SigningDetails].
+    pub fn new(
+        canonical_request: String,
+        string_to_sign: String,
+        signed_headers: Vec<String>,
+        credential_scope: String,
+    ) -> Self {
+        Self {
+            canonical_request,
+            string_to_sign,
+            signed_headers,
+            credential_scope,
+        }
+    }
+
+    /// Returns the canonical request string.
+    #[inline]
+    pub fn canonical_request(&self) -> &str {
+        &self.canonical_request
+    }
+
+    /// Returns the string to sign.
+    #[inline]
+    pub fn string_to_sign(&self) -> &str {
+        &self.string_to_sign
+    }
+
+    /// Returns the lowercase names of the signed headers, in sorted order.
+    #[inline]
+    pub fn signed_headers(&self) -> &[String] {
+        &self.signed_headers
+    }
+
+    /// Returns the credential scope, e.g. `20150830/us-east-1/iam/aws4_request`.
+    #[inline]
+    pub fn credential_scope(&self) -> &str {
+        &self.credential_scope
+    }
+}
+
+/// The error returned by [RequestExt] methods when the requested extension is not present on the request.
+///
+/// This usually indicates that the request did not pass through
+/// [AwsSigV4VerifierService][crate::AwsSigV4VerifierService].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MissingExtension {
+    type_name: &'static str,
+}
+
+impl MissingExtension {
+    /// Returns the name of the type that was missing from the request extensions.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl Display for MissingExtension {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "Request extensions do not contain a {}; is the request being passed through AwsSigV4VerifierService?",
+            self.type_name
+        )
+    }
+}
+
+impl Error for MissingExtension {}
+
+/// Convenience methods for reading the results of authentication from a request.
+pub trait RequestExt {
+    /// Returns the request extensions.
+    fn extensions_ref(&self) -> &Extensions;
+
+    /// Returns the principal the request was authenticated as.
+    fn principal(&self) -> Result<&Principal, MissingExtension> {
+        get_extension(self.extensions_ref())
+    }
+
+    /// Returns the session data associated with the authenticated principal.
+    fn session_data(&self) -> Result<&SessionData, MissingExtension> {
+        get_extension(self.extensions_ref())
+    }
+
+    /// Returns the request id assigned to the request.
+    fn request_id(&self) -> Result<RequestId, MissingExtension> {
+        get_extension(self.extensions_ref()).copied()
+    }
+
+    /// Returns information about the connection the request was received on.
+    fn connect_info(&self) -> Result<&ConnectInfo, MissingExtension> {
+        get_extension(self.extensions_ref())
+    }
+
+    /// Returns whether the payload of the request was covered by its signature.
+    fn payload_signing(&self) -> Result<PayloadSigning, MissingExtension> {
+        get_extension(self.extensions_ref()).copied()
+    }
+
+    /// Returns the details of how the request was signed.
+    fn signing_details(&self) -> Result<&SigningDetails, MissingExtension> {
+        get_extension(self.extensions_ref())
+    }
+}
+
+impl<B> RequestExt for Request<B> {
+    #[inline]
+    fn extensions_ref(&self) -> &Extensions {
+        self.extensions()
+    }
+}
+
+impl RequestExt for Parts {
+    #[inline]
+    fn extensions_ref(&self) -> &Extensions {
+        &self.extensions
+    }
+}
+
+fn get_extension<T: Send + Sync + 'static>(extensions: &Extensions) -> Result<&T, MissingExtension> {
+    extensions.get::<T>().ok_or(MissingExtension {
+        type_name: type_name::<T>(),
+    })
+}
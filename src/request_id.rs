@@ -1,20 +1,56 @@
 use {
+    crate::{ConnectInfo, TrustedProxies},
     chrono::{DateTime, TimeZone, Utc},
+    http::{
+        header::{HeaderName, HeaderValue},
+        Request, Response,
+    },
+    log::trace,
     rand::random,
     serde::{Deserialize, Deserializer, Serialize, Serializer},
+    sha2::{Digest, Sha256},
     std::{
         fmt::{Display, Formatter, Result as FmtResult},
+        future::Future,
+        mem::replace,
+        pin::Pin,
         str::FromStr,
+        sync::OnceLock,
+        task::{Context, Poll},
         time::SystemTime,
     },
-    uuid::Uuid,
+    tower::{Layer, Service},
+    uuid::{Uuid, Variant},
 };
 
+/// The header extended request ids are returned in.
+pub const X_AMZ_ID_2: &str = "x-amz-id-2";
+
+/// The headers a request id is propagated in by a trusted proxy, in order of preference.
+const PROPAGATED_REQUEST_ID_HEADERS: &[&str] = &["x-amzn-requestid", "x-request-id"];
+
+/// The header [RequestIdLayer] returns request ids in by default.
+const X_AMZN_REQUEST_ID: &str = "x-amzn-requestid";
+
+/// The offset of the node id embedded by [RequestIdGenerator] within the UUID bytes: the first two bytes of the last
+/// group, which are entirely random in a UUIDv7.
+const NODE_ID_OFFSET: usize = 10;
+
+/// The number of random bytes in an extended request id, after the host digest.
+const EXTENDED_REQUEST_ID_RANDOM_LEN: usize = 24;
+
+/// The host id of this process, used for extended request ids that aren't given one.
+static PROCESS_HOST_ID: OnceLock<[u8; 16]> = OnceLock::new();
+
 /// AWS request id implementation.
 ///
-/// This implementation abuses the UUID format the embed a timestamp in the UUID to make it easier to track down the
-/// request in the logs. This timestamp has a resolution of 1s and is based on the system clock, so it's not guaranteed
-/// to be unique; thus, a random number is also embedded in the UUID.
+/// Request ids are [UUIDv7s](https://www.rfc-editor.org/rfc/rfc9562#name-uuid-version-7): a millisecond timestamp from
+/// the system clock followed by 74 random bits. The timestamp makes it easier to track down the request in the logs,
+/// and makes request ids sort in the order they were created.
+///
+/// Earlier versions of this crate packed a whole-second timestamp and a 64-bit random number into the UUID bytes
+/// without setting the version or variant. These can still be created with
+/// [from_timestamp_and_random][RequestId::from_timestamp_and_random], and their timestamps are still decoded.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct RequestId {
     id: Uuid,
@@ -23,20 +59,25 @@ pub struct RequestId {
 impl RequestId {
     /// Create a new request id from the current system time and a random number.
     pub fn new() -> Self {
-        let now = SystemTime::now();
-        let offset = match now.duration_since(SystemTime::UNIX_EPOCH) {
-            Ok(offset) => offset,
-            Err(_) => SystemTime::UNIX_EPOCH
-                .duration_since(now)
-                .expect("SystemTime cannot be represented as a duration since the Unix epoch"),
-        };
+        Self::from_timestamp_millis_and_random(now_millis(), random())
+    }
 
-        let timestamp = offset.as_secs();
-        Self::from_timestamp(timestamp as i64)
+    /// Create a new UUIDv7 request id from the given timestamp, in milliseconds from the Unix epoch (January 1, 1970
+    /// at 00:00:00 UTC), and random number. The timestamp is truncated to 48 bits and the random number to 74 bits.
+    pub fn from_timestamp_millis_and_random(unix_timestamp_millis: u64, random: u128) -> Self {
+        let mut bytes = random.to_be_bytes();
+        bytes[0..6].copy_from_slice(&unix_timestamp_millis.to_be_bytes()[2..8]);
+        bytes[6] = 0x70 | (bytes[6] & 0x0f);
+        bytes[8] = 0x80 | (bytes[8] & 0x3f);
+
+        Self {
+            id: Uuid::from_bytes(bytes),
+        }
     }
 
-    /// Create a new request id from the given timestamp, in seconds from the Unix epoch (January 1, 1970 at
-    /// 00:00:00 UTC) and random number.
+    /// Create a new request id in the format used by earlier versions of this crate from the given timestamp, in
+    /// seconds from the Unix epoch (January 1, 1970 at 00:00:00 UTC) and random number. This is not a conformant
+    /// UUID; [RequestId::new] and [RequestId::from_timestamp_millis_and_random] should be used instead.
     pub fn from_timestamp_and_random(unix_timestamp: i64, random: u64) -> Self {
         let mut bytes = [0u8; 16];
         bytes[0..8].copy_from_slice(&unix_timestamp.to_be_bytes());
@@ -47,36 +88,71 @@ impl RequestId {
         }
     }
 
-    /// Create a new request id from the given timestamp and random number.
+    /// Create a new request id in the format used by earlier versions of this crate from the given timestamp and
+    /// random number. See [RequestId::from_timestamp_and_random].
     pub fn from_datetime_and_random<Tz: TimeZone>(datetime: DateTime<Tz>, random: u64) -> Self {
         let unix_timestamp = datetime.timestamp();
         Self::from_timestamp_and_random(unix_timestamp, random)
     }
 
-    /// Create a new request id from the given timestamp, in seconds from the Unix epoch (January 1, 1970 at
+    /// Create a new UUIDv7 request id from the given timestamp, in seconds from the Unix epoch (January 1, 1970 at
     /// 00:00:00 UTC).
     pub fn from_timestamp(unix_timestamp: i64) -> Self {
-        let random: u64 = random();
-        Self::from_timestamp_and_random(unix_timestamp, random)
+        Self::from_timestamp_millis_and_random(unix_timestamp.saturating_mul(1000) as u64, random())
     }
 
-    /// Create a new request id from the given timestamp.
+    /// Create a new UUIDv7 request id from the given timestamp, with millisecond precision.
     pub fn from_datetime<Tz: TimeZone>(datetime: DateTime<Tz>) -> Self {
-        let unix_timestamp = datetime.timestamp();
-        Self::from_timestamp(unix_timestamp)
+        Self::from_timestamp_millis_and_random(datetime.timestamp_millis() as u64, random())
+    }
+
+    /// Indicates whether this is a UUIDv7 request id rather than one in the format used by earlier versions of this
+    /// crate.
+    pub fn is_v7(&self) -> bool {
+        // Earlier request ids start with a 64-bit timestamp in seconds, whose top 32 bits are zero until 2106. The
+        // top 32 bits of a UUIDv7 timestamp in milliseconds are only zero in the first 65 seconds of 1970.
+        self.id.get_version_num() == 7
+            && self.id.get_variant() == Variant::RFC4122
+            && self.id.as_bytes()[0..4] != [0; 4]
+    }
+
+    /// Returns the Unix timestamp, in milliseconds from the Unix epoch (January 1, 1970 at 00:00:00 UTC), embedded in
+    /// this request id. Request ids in the format used by earlier versions of this crate only have whole seconds.
+    pub fn unix_timestamp_millis(&self) -> u64 {
+        if self.is_v7() {
+            let mut bytes = [0u8; 8];
+            bytes[2..8].copy_from_slice(&self.id.as_bytes()[0..6]);
+            u64::from_be_bytes(bytes)
+        } else {
+            u64::from_be_bytes(self.id.as_bytes()[0..8].try_into().unwrap()).saturating_mul(1000)
+        }
     }
 
     /// Returns the Unix timestamp, in seconds from the Unix epoch (January 1, 1970 at 00:00:00 UTC), embedded in
     /// this request id.
     #[inline]
     pub fn unix_timestamp(&self) -> u64 {
-        u64::from_be_bytes(self.id.as_bytes()[0..8].try_into().unwrap())
+        self.unix_timestamp_millis() / 1000
     }
 
     /// Returns the timestamp embedded in this request id.
     #[inline]
     pub fn datetime(&self) -> DateTime<Utc> {
-        Utc.timestamp_opt(self.unix_timestamp() as i64, 0).unwrap()
+        Utc.timestamp_millis_opt(self.unix_timestamp_millis() as i64).unwrap()
+    }
+
+    /// Returns the node id embedded in this request id by a [RequestIdGenerator], or `None` if this isn't a UUIDv7
+    /// request id.
+    ///
+    /// Request ids created without a generator have random bits here, so this is only meaningful for ids from
+    /// services known to use one.
+    pub fn node_id(&self) -> Option<u16> {
+        if self.is_v7() {
+            let bytes = self.id.as_bytes();
+            Some(u16::from_be_bytes([bytes[NODE_ID_OFFSET], bytes[NODE_ID_OFFSET + 1]]))
+        } else {
+            None
+        }
     }
 
     /// Returns this request id as a UUID.
@@ -86,6 +162,66 @@ impl RequestId {
     }
 }
 
+/// Returns the current system time, in milliseconds from the Unix epoch (January 1, 1970 at 00:00:00 UTC).
+fn now_millis() -> u64 {
+    let now = SystemTime::now();
+    let offset = match now.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(offset) => offset,
+        Err(_) => SystemTime::UNIX_EPOCH
+            .duration_since(now)
+            .expect("SystemTime cannot be represented as a duration since the Unix epoch"),
+    };
+
+    offset.as_millis() as u64
+}
+
+/// A generator of [RequestId]s that embeds a 16-bit node (host or shard) id in each one, so the host that served a
+/// request can be recovered from the request id alone with [RequestId::node_id].
+///
+/// The node id takes the place of 16 of the 74 random bits of a UUIDv7, as the first four hex digits of the last group
+/// of the request id: with node id `0x002a`, request ids look like `018390d7-b87b-7abc-8def-002a12345678`. The
+/// remaining 58 random bits keep request ids generated by the same node in the same millisecond distinct.
+///
+/// ```
+/// use scratchstack_http_framework::RequestIdGenerator;
+///
+/// let generator = RequestIdGenerator::new(42);
+/// let request_id = generator.generate();
+/// assert_eq!(request_id.node_id(), Some(42));
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RequestIdGenerator {
+    node_id: u16,
+}
+
+impl RequestIdGenerator {
+    /// Create a new [RequestIdGenerator] that embeds the given node id in each request id.
+    pub fn new(node_id: u16) -> Self {
+        Self {
+            node_id,
+        }
+    }
+
+    /// Retreive the node id embedded in each request id.
+    #[inline]
+    pub fn node_id(&self) -> u16 {
+        self.node_id
+    }
+
+    /// Create a new request id from the current system time, the node id, and a random number.
+    pub fn generate(&self) -> RequestId {
+        self.generate_at(now_millis(), random())
+    }
+
+    /// Create a new request id from the given timestamp, in milliseconds from the Unix epoch (January 1, 1970 at
+    /// 00:00:00 UTC), the node id, and the given random number. See [RequestId::from_timestamp_millis_and_random].
+    pub fn generate_at(&self, unix_timestamp_millis: u64, random: u128) -> RequestId {
+        let mut bytes = random.to_be_bytes();
+        bytes[NODE_ID_OFFSET..NODE_ID_OFFSET + 2].copy_from_slice(&self.node_id.to_be_bytes());
+        RequestId::from_timestamp_millis_and_random(unix_timestamp_millis, u128::from_be_bytes(bytes))
+    }
+}
+
 impl Default for RequestId {
     fn default() -> Self {
         Self::new()
@@ -127,3 +263,381 @@ impl Serialize for RequestId {
         serializer.serialize_str(&self.id.to_string())
     }
 }
+
+/// An S3-style extended request id (`x-amz-id-2`), generated alongside each [RequestId].
+///
+/// Extended request ids are 76 base64 characters: the SHA-256 digest of a host id and the request id, followed by 24
+/// random bytes. Support tooling that knows the host ids of a fleet can tell which host served a request with
+/// [ExtendedRequestId::matches_host], without the host id being revealed to clients.
+///
+/// [AwsSigV4VerifierService][crate::AwsSigV4VerifierService] inserts one into the extensions of each request that
+/// doesn't already have one, and returns it in the `x-amz-id-2` header of the response.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ExtendedRequestId(String);
+
+impl ExtendedRequestId {
+    /// Create a new extended request id for a request id, salted with the host id of this process: a random value
+    /// chosen when it is first needed.
+    pub fn new(request_id: RequestId) -> Self {
+        Self::for_host(request_id, PROCESS_HOST_ID.get_or_init(random))
+    }
+
+    /// Create a new extended request id for a request id, salted with the given host id (e.g. a host name or instance
+    /// id).
+    pub fn for_host(request_id: RequestId, host_id: &[u8]) -> Self {
+        let mut bytes = host_digest(request_id, host_id).to_vec();
+        bytes.extend_from_slice(&random::<[u8; EXTENDED_REQUEST_ID_RANDOM_LEN]>());
+        Self(base64::encode(bytes))
+    }
+
+    /// Indicates whether this extended request id was created for the given request id by the host with the given
+    /// host id.
+    pub fn matches_host(&self, request_id: RequestId, host_id: &[u8]) -> bool {
+        base64::decode(&self.0).is_ok_and(|bytes| {
+            bytes.len() == 32 + EXTENDED_REQUEST_ID_RANDOM_LEN && bytes[..32] == host_digest(request_id, host_id)
+        })
+    }
+
+    /// Returns this extended request id as a string.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for ExtendedRequestId {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(&self.0)
+    }
+}
+
+fn host_digest(request_id: RequestId, host_id: &[u8]) -> [u8; 32] {
+    Sha256::new().chain_update(host_id).chain_update(request_id.uuid().as_bytes()).finalize().into()
+}
+
+/// A layer that assigns each request its [RequestId] before any other processing, and returns it in a response
+/// header (`x-amzn-RequestId` by default).
+///
+/// This should be the outermost layer of the service, so the request id is available to everything underneath it,
+/// including [AwsSigV4VerifierService][crate::AwsSigV4VerifierService], which uses the request id it finds in the
+/// request extensions instead of generating its own. Requests that already have a [RequestId] extension keep it.
+///
+/// By default, a new request id is generated for every request. When requests pass through several services (e.g. a
+/// front end calling an internal API), the same id can be kept across each hop by trusting the `x-amzn-RequestId` or
+/// `x-request-id` header of requests from the proxies given to
+/// [with_trusted_proxies][RequestIdLayer::with_trusted_proxies]. The header is ignored for requests from any other
+/// peer, and if it doesn't hold a UUID.
+#[derive(Clone, Debug)]
+pub struct RequestIdLayer {
+    header: HeaderName,
+    trusted_proxies: Option<TrustedProxies>,
+    connect_info: Option<ConnectInfo>,
+    generator: Option<RequestIdGenerator>,
+}
+
+impl RequestIdLayer {
+    /// Create a new [RequestIdLayer] that generates a request id for every request and returns it in the
+    /// `x-amzn-RequestId` header.
+    pub fn new() -> Self {
+        Self {
+            header: HeaderName::from_static(X_AMZN_REQUEST_ID),
+            trusted_proxies: None,
+            connect_info: None,
+            generator: None,
+        }
+    }
+
+    /// Return the request id in the given header instead, e.g. `x-amz-request-id` for S3-style services. Responses
+    /// that already have the header are left alone.
+    pub fn with_header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Use the request id propagated in the `x-amzn-RequestId` or `x-request-id` header of requests whose peer
+    /// belongs to one of these proxies.
+    ///
+    /// The peer address is read from the [ConnectInfo] request extension, or the one given to
+    /// [with_connect_info][RequestIdLayer::with_connect_info].
+    pub fn with_trusted_proxies(mut self, trusted_proxies: TrustedProxies) -> Self {
+        self.trusted_proxies = Some(trusted_proxies);
+        self
+    }
+
+    /// Set the connection information used for requests without a [ConnectInfo] extension. This is useful when the
+    /// layer is applied to each connection as it is accepted.
+    pub fn with_connect_info(mut self, connect_info: ConnectInfo) -> Self {
+        self.connect_info = Some(connect_info);
+        self
+    }
+
+    /// Generate request ids with this generator, embedding its node id, instead of [RequestId::new]. Propagated
+    /// request ids are kept as they are.
+    pub fn with_generator(mut self, generator: RequestIdGenerator) -> Self {
+        self.generator = Some(generator);
+        self
+    }
+
+    /// Retreive the header request ids are returned in.
+    #[inline]
+    pub fn header(&self) -> &HeaderName {
+        &self.header
+    }
+
+    /// Retreive the proxies whose propagated request ids are trusted, if any.
+    #[inline]
+    pub fn trusted_proxies(&self) -> Option<&TrustedProxies> {
+        self.trusted_proxies.as_ref()
+    }
+
+    /// Retreive the generator used for new request ids, if any.
+    #[inline]
+    pub fn generator(&self) -> Option<&RequestIdGenerator> {
+        self.generator.as_ref()
+    }
+
+    /// Returns a new request id, from the generator if there is one.
+    fn generate(&self) -> RequestId {
+        match &self.generator {
+            Some(generator) => generator.generate(),
+            None => RequestId::new(),
+        }
+    }
+
+    /// Returns the request id propagated by a trusted proxy, if any.
+    fn propagated_request_id<B>(&self, req: &Request<B>) -> Option<RequestId> {
+        let trusted_proxies = self.trusted_proxies.as_ref()?;
+        let peer = req.extensions().get::<ConnectInfo>().or(self.connect_info.as_ref())?.remote_addr().ip();
+        if !trusted_proxies.is_trusted(&peer) {
+            return None;
+        }
+
+        PROPAGATED_REQUEST_ID_HEADERS
+            .iter()
+            .find_map(|name| req.headers().get(*name)?.to_str().ok()?.trim().parse::<RequestId>().ok())
+    }
+}
+
+impl Default for RequestIdLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdService {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+/// A service that assigns each request its [RequestId] before passing it to the wrapped service, and returns the
+/// request id in a response header. See [RequestIdLayer].
+#[derive(Clone, Debug)]
+pub struct RequestIdService<S> {
+    inner: S,
+    config: RequestIdLayer,
+}
+
+impl<S> RequestIdService<S> {
+    /// Create a new [RequestIdService] that generates a request id for every request and returns it in the
+    /// `x-amzn-RequestId` header.
+    pub fn new(inner: S) -> Self {
+        RequestIdLayer::new().layer(inner)
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for RequestIdService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send,
+    ReqBody: Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, c: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(c)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        // The clone may not be ready, so use the instance that was polled and leave the clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = replace(&mut self.inner, clone);
+        let header = self.config.header.clone();
+
+        let request_id = match req.extensions().get::<RequestId>() {
+            Some(request_id) => *request_id,
+            None => {
+                let request_id = match self.config.propagated_request_id(&req) {
+                    Some(request_id) => {
+                        trace!("Using propagated request-id: {}", request_id);
+                        request_id
+                    }
+                    None => {
+                        let request_id = self.config.generate();
+                        trace!("Generated request-id: {}", request_id);
+                        request_id
+                    }
+                };
+                req.extensions_mut().insert(request_id);
+                request_id
+            }
+        };
+
+        Box::pin(async move {
+            let mut response = inner.call(req).await?;
+            if let Ok(value) = HeaderValue::from_str(&request_id.to_string()) {
+                response.headers_mut().entry(header).or_insert(value);
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{ExtendedRequestId, RequestId, RequestIdGenerator, RequestIdLayer, RequestIdService},
+        crate::{ConnectInfo, TrustedProxies},
+        chrono::{TimeZone, Utc},
+        http::header::HeaderName,
+        hyper::{Body, Request, Response},
+        pretty_assertions::{assert_eq, assert_ne},
+        std::{convert::Infallible, net::SocketAddr},
+        tower::{service_fn, Layer, ServiceExt},
+    };
+
+    #[test]
+    fn test_v7() {
+        let request_id = RequestId::from_timestamp_millis_and_random(1_664_582_400_123, u128::MAX);
+        assert!(request_id.is_v7());
+        assert_eq!(request_id.uuid().get_version_num(), 7);
+        assert_eq!(request_id.to_string(), "018390d7-b87b-7fff-bfff-ffffffffffff");
+        assert_eq!(request_id.unix_timestamp_millis(), 1_664_582_400_123);
+        assert_eq!(request_id.unix_timestamp(), 1_664_582_400);
+        assert_eq!(request_id.datetime(), Utc.timestamp_millis_opt(1_664_582_400_123).unwrap());
+
+        let request_id = RequestId::new();
+        assert!(request_id.is_v7());
+        assert_ne!(RequestId::new(), request_id);
+
+        let datetime = Utc.timestamp_millis_opt(1_664_582_400_456).unwrap();
+        assert_eq!(RequestId::from_datetime(datetime).datetime(), datetime);
+    }
+
+    #[test]
+    fn test_legacy() {
+        let request_id = RequestId::from_timestamp_and_random(1_664_582_400, 1);
+        assert!(!request_id.is_v7());
+        assert_eq!(request_id.to_string(), "00000000-6337-8300-0000-000000000001");
+        assert_eq!(request_id.unix_timestamp(), 1_664_582_400);
+        assert_eq!(request_id.datetime(), Utc.timestamp_opt(1_664_582_400, 0).unwrap());
+        assert_eq!(RequestId::from_timestamp_and_random(0, 1).unix_timestamp(), 0);
+    }
+
+    #[test]
+    fn test_generator() {
+        let generator = RequestIdGenerator::new(0x002a);
+        assert_eq!(generator.node_id(), 0x002a);
+
+        let request_id = generator.generate_at(1_664_582_400_123, u128::MAX);
+        assert_eq!(request_id.to_string(), "018390d7-b87b-7fff-bfff-002affffffff");
+        assert_eq!(request_id.node_id(), Some(0x002a));
+        assert_eq!(request_id.unix_timestamp_millis(), 1_664_582_400_123);
+
+        let request_id = generator.generate();
+        assert!(request_id.is_v7());
+        assert_eq!(request_id.node_id(), Some(0x002a));
+        assert_ne!(generator.generate(), request_id);
+
+        assert_eq!(RequestIdGenerator::new(0xffff).generate_at(0, 0).node_id(), None);
+        assert_eq!(RequestIdGenerator::new(0xffff).generate_at(1_664_582_400_123, 0).node_id(), Some(0xffff));
+        assert_eq!(RequestId::from_timestamp_and_random(1_664_582_400, 1).node_id(), None);
+    }
+
+    #[test]
+    fn test_extended_request_id() {
+        let request_id = RequestId::from_timestamp_millis_and_random(1_664_582_400_123, 1);
+        let extended_request_id = ExtendedRequestId::for_host(request_id, b"host-1");
+        assert_eq!(extended_request_id.as_str().len(), 76);
+        assert!(extended_request_id.matches_host(request_id, b"host-1"));
+        assert!(!extended_request_id.matches_host(request_id, b"host-2"));
+        assert!(!extended_request_id.matches_host(RequestId::new(), b"host-1"));
+        assert_ne!(ExtendedRequestId::for_host(request_id, b"host-1"), extended_request_id);
+
+        let extended_request_id = ExtendedRequestId::new(request_id);
+        assert_eq!(extended_request_id.to_string(), extended_request_id.as_str());
+        assert!(!extended_request_id.matches_host(request_id, b"host-1"));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_request_id_layer() {
+        // Echo the request id the inner service saw in the response body.
+        let inner = service_fn(|req: Request<Body>| async move {
+            let request_id = req.extensions().get::<RequestId>().unwrap();
+            Ok::<_, Infallible>(Response::new(Body::from(request_id.to_string())))
+        });
+        let propagated = RequestId::from_timestamp_millis_and_random(1_664_582_400_123, 1);
+        let request = |peer: [u8; 4], header: &str| {
+            let mut req = Request::get("/").header(header, propagated.to_string()).body(Body::empty()).unwrap();
+            req.extensions_mut().insert(ConnectInfo::new(SocketAddr::from((peer, 40000)), None));
+            req
+        };
+        let call = |service: RequestIdService<_>, req| async move {
+            let response = service.oneshot(req).await.unwrap();
+            let header = response.headers()["x-amzn-requestid"].to_str().unwrap().to_string();
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            assert_eq!(header, String::from_utf8_lossy(&body));
+            header.parse::<RequestId>().unwrap()
+        };
+
+        // Without trusted proxies, the propagated request id is ignored.
+        let service = RequestIdService::new(inner);
+        let request_id = call(service.clone(), request([10, 0, 0, 1], "x-amzn-RequestId")).await;
+        assert_ne!(request_id, propagated);
+        assert!(request_id.is_v7());
+
+        // A request id already in the extensions is kept.
+        let mut req = Request::get("/").body(Body::empty()).unwrap();
+        req.extensions_mut().insert(propagated);
+        assert_eq!(call(service, req).await, propagated);
+
+        let layer =
+            RequestIdLayer::new().with_trusted_proxies(TrustedProxies::new(vec!["10.0.0.0/8".parse().unwrap()]));
+        let service = layer.layer(inner);
+        assert_eq!(call(service.clone(), request([10, 0, 0, 1], "x-amzn-RequestId")).await, propagated);
+        assert_eq!(call(service.clone(), request([10, 0, 0, 1], "x-request-id")).await, propagated);
+        assert_ne!(call(service.clone(), request([192, 0, 2, 1], "x-amzn-RequestId")).await, propagated);
+
+        // Propagated values that aren't request ids are replaced.
+        let mut req = request([10, 0, 0, 1], "x-amzn-RequestId");
+        req.headers_mut().insert("x-amzn-requestid", "not-a-request-id".parse().unwrap());
+        assert!(call(service, req).await.is_v7());
+
+        // The connection information can be given to the layer instead of the request.
+        let service = layer
+            .clone()
+            .with_connect_info(ConnectInfo::new(SocketAddr::from(([10, 0, 0, 2], 40000)), None))
+            .layer(inner);
+        let req = Request::get("/").header("x-request-id", propagated.to_string()).body(Body::empty()).unwrap();
+        assert_eq!(call(service, req).await, propagated);
+
+        // The request id can be returned in another header.
+        let service = RequestIdLayer::new().with_header(HeaderName::from_static("x-amz-request-id")).layer(inner);
+        let response = service.oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+        assert!(response.headers().contains_key("x-amz-request-id"));
+        assert!(!response.headers().contains_key("x-amzn-requestid"));
+
+        // New request ids can carry a node id; propagated ones are kept as they are.
+        let layer = layer.with_generator(RequestIdGenerator::new(7));
+        let service = layer.clone().layer(inner);
+        assert_eq!(call(service.clone(), Request::get("/").body(Body::empty()).unwrap()).await.node_id(), Some(7));
+        assert_eq!(call(service, request([10, 0, 0, 1], "x-request-id")).await, propagated);
+    }
+}
@@ -0,0 +1,154 @@
+use {
+    derive_builder::Builder,
+    http::method::Method,
+    scratchstack_aws_signature::{SignatureOptions, SignedHeaderRequirements},
+    std::{
+        any::type_name,
+        fmt::{Debug, Formatter, Result as FmtResult},
+    },
+};
+
+/// Per-route overrides for [AwsSigV4VerifierService][crate::AwsSigV4VerifierService] settings.
+///
+/// A route matches requests whose path starts with `path_prefix` and, if `methods` is non-empty, whose method is one
+/// of `methods`. When several routes match, the one with the longest prefix wins; ties go to the route listed first.
+/// Settings left unset fall back to the verifier's own.
+#[derive(Builder, Clone)]
+#[builder(pattern = "owned")]
+pub struct Route<S> {
+    /// The path prefix this route applies to.
+    #[builder(setter(into))]
+    path_prefix: String,
+
+    /// The request methods this route applies to. If empty, the route applies to all methods.
+    #[builder(default)]
+    methods: Vec<Method>,
+
+    /// The allowed HTTP request methods for this route.
+    #[builder(setter(into, strip_option), default)]
+    allowed_request_methods: Option<Vec<Method>>,
+
+    /// The allowed HTTP content types for this route.
+    #[builder(setter(into, strip_option), default)]
+    allowed_content_types: Option<Vec<String>>,
+
+    /// The HTTP headers that must be signed for this route.
+    #[builder(setter(strip_option), default)]
+    signed_header_requirements: Option<SignedHeaderRequirements>,
+
+    /// Options for the signature verification process for this route.
+    #[builder(setter(strip_option), default)]
+    signature_options: Option<SignatureOptions>,
+
+    /// The service implementation for this route.
+    #[builder(setter(strip_option), default)]
+    implementation: Option<S>,
+}
+
+impl<S> Route<S> {
+    /// Create a new [RouteBuilder] for constructing a [Route].
+    #[inline]
+    pub fn builder() -> RouteBuilder<S> {
+        RouteBuilder::default()
+    }
+
+    /// Retreive the path prefix this route applies to.
+    #[inline]
+    pub fn path_prefix(&self) -> &str {
+        &self.path_prefix
+    }
+
+    /// Retreive the request methods this route applies to.
+    #[inline]
+    pub fn methods(&self) -> &[Method] {
+        &self.methods
+    }
+
+    /// Retreive the allowed HTTP request methods for this route, if overridden.
+    #[inline]
+    pub fn allowed_request_methods(&self) -> Option<&Vec<Method>> {
+        self.allowed_request_methods.as_ref()
+    }
+
+    /// Retreive the allowed HTTP content types for this route, if overridden.
+    #[inline]
+    pub fn allowed_content_types(&self) -> Option<&Vec<String>> {
+        self.allowed_content_types.as_ref()
+    }
+
+    /// Retreive the HTTP headers that must be signed for this route, if overridden.
+    #[inline]
+    pub fn signed_header_requirements(&self) -> Option<&SignedHeaderRequirements> {
+        self.signed_header_requirements.as_ref()
+    }
+
+    /// Retreive the options for the signature verification process for this route, if overridden.
+    #[inline]
+    pub fn signature_options(&self) -> Option<&SignatureOptions> {
+        self.signature_options.as_ref()
+    }
+
+    /// Retreive the service implementation for this route, if overridden.
+    #[inline]
+    pub fn implementation(&self) -> Option<&S> {
+        self.implementation.as_ref()
+    }
+
+    /// Indicates whether this route applies to a request with the given method and path.
+    pub fn matches(&self, method: &Method, path: &str) -> bool {
+        path.starts_with(&self.path_prefix) && (self.methods.is_empty() || self.methods.contains(method))
+    }
+}
+
+impl<S> Debug for Route<S> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Route")
+            .field("path_prefix", &self.path_prefix)
+            .field("methods", &self.methods)
+            .field("allowed_request_methods", &self.allowed_request_methods)
+            .field("allowed_content_types", &self.allowed_content_types)
+            .field("signed_header_requirements", &self.signed_header_requirements)
+            .field("signature_options", &self.signature_options)
+            .field("implementation", &self.implementation.as_ref().map(|_| type_name::<S>()))
+            .finish()
+    }
+}
+
+/// Select the route for a request: the matching route with the longest prefix, preferring earlier routes on ties.
+pub(crate) fn select_route<'a, S>(routes: &'a [Route<S>], method: &Method, path: &str) -> Option<&'a Route<S>> {
+    let mut selected: Option<&Route<S>> = None;
+    for route in routes.iter().filter(|r| r.matches(method, path)) {
+        if selected.is_none_or(|s| route.path_prefix.len() > s.path_prefix.len()) {
+            selected = Some(route);
+        }
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{select_route, Route},
+        http::method::Method,
+    };
+
+    #[test]
+    fn test_select_route() {
+        let routes: Vec<Route<()>> = vec![
+            Route::builder().path_prefix("/").build().unwrap(),
+            Route::builder().path_prefix("/api/").methods(vec![Method::POST]).build().unwrap(),
+            Route::builder().path_prefix("/api/").build().unwrap(),
+            Route::builder().path_prefix("/api/upload").build().unwrap(),
+        ];
+
+        let prefix_of = |method: &Method, path: &str| {
+            select_route(&routes, method, path).map(|r| (r.path_prefix().to_string(), r.methods().to_vec()))
+        };
+
+        assert_eq!(prefix_of(&Method::GET, "/index.html"), Some(("/".to_string(), vec![])));
+        assert_eq!(prefix_of(&Method::POST, "/api/op"), Some(("/api/".to_string(), vec![Method::POST])));
+        assert_eq!(prefix_of(&Method::GET, "/api/op"), Some(("/api/".to_string(), vec![])));
+        assert_eq!(prefix_of(&Method::PUT, "/api/upload/1"), Some(("/api/upload".to_string(), vec![])));
+        assert!(select_route(&routes[1..], &Method::GET, "/other").is_none());
+    }
+}
@@ -0,0 +1,1291 @@
+use {
+    crate::{
+        action::{query_action, target_operation},
+        canonical::{form_params, query_params},
+        validate::{check, Validate},
+        ActionResolver, ErrorFault, ErrorMapper, RequestId, ResolvedAction, StandardError, VerifierError,
+        AWS_JSON_1_0_CONTENT_TYPE, AWS_JSON_1_1_CONTENT_TYPE,
+    },
+    async_trait::async_trait,
+    bytes::Bytes,
+    http::{header::CONTENT_TYPE, request::Parts},
+    hyper::{Body, Request, Response},
+    scratchstack_aws_principal::Principal,
+    scratchstack_errors::ServiceError,
+    serde::{
+        de::{
+            value::{Error as DeError, MapDeserializer, SeqDeserializer},
+            DeserializeOwned, Deserializer, Error as _, IntoDeserializer, Visitor,
+        },
+        forward_to_deserialize_any, Serialize,
+    },
+    std::{
+        any::Any,
+        collections::{BTreeMap, HashMap},
+        error::Error,
+        fmt::{Debug, Display, Formatter, Result as FmtResult},
+        future::Future,
+        mem::size_of,
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+    },
+    tower::{BoxError, Service},
+};
+
+/// The content type of AWS Query protocol request bodies.
+const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// A type-erased Query protocol handler, returning the serialized `<Action>Result` element, if any.
+type QueryHandler = Arc<dyn Fn(Parts, QueryValue) -> BoxFuture<Result<Option<String>, BoxError>> + Send + Sync>;
+
+/// A router for AWS Query protocol services (e.g. IAM, STS, EC2), dispatching requests to handlers by their `Action`
+/// parameter.
+///
+/// Parameters are read from the query string and, for `application/x-www-form-urlencoded` requests, the body. They
+/// are deserialized into the handler's input type with serde: `A.B=x` is the member `B` of the structure `A`, and
+/// lists are written as `A.member.1`, `A.member.2`, ... (or `A.1`, `A.2`, ... as in EC2). Scalars are parsed from their
+/// string form, so inputs can use numeric and boolean fields directly. Inputs will usually want
+/// `#[serde(rename_all = "PascalCase")]`; parameters without a matching field (including `Action`, `Version`, and any
+/// signature parameters) are ignored.
+///
+/// A handler's output is serialized with quick-xml as the `<Action>Result` element of an `<Action>Response` envelope,
+/// followed by `<ResponseMetadata><RequestId>`. Elements must be named `$unflatten=Name`, as in quick-xml 0.25, and a
+/// handler returning `()` produces a response with only the response metadata.
+///
+/// If the router has registered API versions, requests must name one of them in their `Version` parameter, and may be
+/// routed to a handler specific to that version; actions without a version-specific handler use the handler registered
+/// for all versions. This lets an API evolve the way AWS APIs do, keeping older versions' behavior for existing clients.
+///
+/// Requests without an `Action` are rejected with `MissingAction`; requests with an unregistered `Version` are rejected
+/// with `NoSuchVersion`; and requests without a `Version` (if the router has versions) or with an action that has no
+/// handler for their version are rejected with `InvalidAction`. These and handler errors are rendered by the error
+/// mapper. Inputs that can't be deserialized are rejected with `InvalidInput`, and inputs of actions registered with
+/// [with_validated_action][Self::with_validated_action] that violate their constraints with `ValidationException`.
+///
+/// ```
+/// use {
+///     scratchstack_http_framework::{router::QueryRouter, XmlErrorMapper},
+///     serde::{Deserialize, Serialize},
+///     tower::BoxError,
+/// };
+///
+/// #[derive(Deserialize)]
+/// #[serde(rename_all = "PascalCase")]
+/// struct GetUserRequest {
+///     user_name: Option<String>,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct GetUserResponse {
+///     #[serde(rename = "$unflatten=UserName")]
+///     user_name: String,
+/// }
+///
+/// const NAMESPACE: &str = "https://iam.amazonaws.com/doc/2010-05-08/";
+///
+/// let router = QueryRouter::new(NAMESPACE, XmlErrorMapper::new(NAMESPACE))
+///     .with_version("2010-05-08")
+///     .with_action("GetUser", |_parts, request: GetUserRequest| async move {
+///         Ok::<_, BoxError>(GetUserResponse {
+///             user_name: request.user_name.unwrap_or_else(|| "self".to_string()),
+///         })
+///     })
+///     .with_versioned_action("2021-01-01", "GetUser", |_parts, request: GetUserRequest| async move {
+///         let Some(user_name) = request.user_name else {
+///             return Err(BoxError::from("UserName is required"));
+///         };
+///         Ok(GetUserResponse {
+///             user_name,
+///         })
+///     });
+/// ```
+#[derive(Clone)]
+pub struct QueryRouter<E> {
+    namespace: String,
+    versions: Vec<String>,
+    handlers: HashMap<String, QueryHandler>,
+    versioned_handlers: HashMap<(String, String), QueryHandler>,
+    error_mapper: E,
+}
+
+impl<E: ErrorMapper> QueryRouter<E> {
+    /// Create a new [QueryRouter] with no actions, using `namespace` as the XML namespace of responses and rendering
+    /// errors with `error_mapper`.
+    pub fn new(namespace: &str, error_mapper: E) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            versions: Vec::new(),
+            handlers: HashMap::new(),
+            versioned_handlers: HashMap::new(),
+            error_mapper,
+        }
+    }
+
+    /// Register an API version, returning the updated router. Once a version is registered, only requests for
+    /// registered versions are accepted.
+    pub fn with_version(mut self, version: &str) -> Self {
+        if !self.versions.iter().any(|v| v == version) {
+            self.versions.push(version.to_string());
+        }
+        self
+    }
+
+    /// Register the handler for an action, returning the updated router. The handler receives the request head
+    /// (including extensions such as the [Principal][scratchstack_aws_principal::Principal]) and the deserialized
+    /// input.
+    pub fn with_action<I, O, F, Fut>(mut self, action: &str, handler: F) -> Self
+    where
+        I: DeserializeOwned + Send + 'static,
+        O: Serialize + Send + 'static,
+        F: Fn(Parts, I) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<O, BoxError>> + Send + 'static,
+    {
+        self.handlers.insert(action.to_string(), query_handler(action, handler, unchecked));
+        self
+    }
+
+    /// Register the handler for an action whose input has [Validate] constraints, returning the updated router.
+    /// Requests whose input violates them are rejected with a `ValidationException` listing every violation.
+    pub fn with_validated_action<I, O, F, Fut>(mut self, action: &str, handler: F) -> Self
+    where
+        I: DeserializeOwned + Validate + Send + 'static,
+        O: Serialize + Send + 'static,
+        F: Fn(Parts, I) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<O, BoxError>> + Send + 'static,
+    {
+        self.handlers.insert(action.to_string(), query_handler(action, handler, check::<I>));
+        self
+    }
+
+    /// Register the handler for an action in a specific API version, returning the updated router. This takes
+    /// precedence over the handler registered with [with_action][Self::with_action] for requests for that version, and
+    /// registers the version if it isn't already.
+    pub fn with_versioned_action<I, O, F, Fut>(self, version: &str, action: &str, handler: F) -> Self
+    where
+        I: DeserializeOwned + Send + 'static,
+        O: Serialize + Send + 'static,
+        F: Fn(Parts, I) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<O, BoxError>> + Send + 'static,
+    {
+        let mut router = self.with_version(version);
+        router
+            .versioned_handlers
+            .insert((version.to_string(), action.to_string()), query_handler(action, handler, unchecked));
+        router
+    }
+
+    /// Register the handlers for all of the operations in a registry, returning the updated router. Each operation is
+    /// served as the action with its [Operation::NAME].
+    pub fn with_registry(mut self, registry: &OperationRegistry) -> Self {
+        self.handlers.extend(registry.query_handlers.iter().map(|(name, handler)| (name.clone(), handler.clone())));
+        self
+    }
+
+    /// Retreive the XML namespace of responses.
+    #[inline]
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// Retreive the registered API versions. If this is empty, requests for any version are accepted.
+    #[inline]
+    pub fn versions(&self) -> &[String] {
+        &self.versions
+    }
+}
+
+impl<E> Debug for QueryRouter<E> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let mut actions: Vec<&String> = self.handlers.keys().collect();
+        actions.sort();
+        let mut versioned_actions: Vec<&(String, String)> = self.versioned_handlers.keys().collect();
+        versioned_actions.sort();
+
+        f.debug_struct("QueryRouter")
+            .field("namespace", &self.namespace)
+            .field("versions", &self.versions)
+            .field("actions", &actions)
+            .field("versioned_actions", &versioned_actions)
+            .finish()
+    }
+}
+
+impl<E: ErrorMapper> Service<Request<Body>> for QueryRouter<E> {
+    type Response = Response<Body>;
+    type Error = BoxError;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _c: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let router = self.clone();
+
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let request_id = parts.extensions.get::<RequestId>().copied();
+            let body = hyper::body::to_bytes(body).await?;
+
+            let mut params = parts.uri.query().map(query_params).unwrap_or_default();
+            let is_form = parts
+                .headers
+                .get(CONTENT_TYPE)
+                .and_then(|ct| ct.to_str().ok())
+                .map(|ct| ct.starts_with(FORM_CONTENT_TYPE))
+                .unwrap_or(false);
+            if is_form {
+                params.extend(form_params(&String::from_utf8_lossy(&body)));
+            }
+
+            let param = |name: &str| params.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone());
+            let Some(action) = param("Action") else {
+                return router.error_mapper.map_error(VerifierError::MissingAction.into(), request_id).await;
+            };
+            let version = param("Version");
+
+            if let Some(version) = &version {
+                if !router.versions.is_empty() && !router.versions.contains(version) {
+                    let error = VerifierError::NoSuchVersion(version.clone());
+                    return router.error_mapper.map_error(error.into(), request_id).await;
+                }
+            }
+
+            let handler = match &version {
+                Some(version) => router
+                    .versioned_handlers
+                    .get(&(version.clone(), action.clone()))
+                    .or_else(|| router.handlers.get(&action)),
+                None if router.versions.is_empty() => router.handlers.get(&action),
+                None => None,
+            };
+            let Some(handler) = handler else {
+                let error = VerifierError::InvalidAction {
+                    action,
+                    version,
+                };
+                return router.error_mapper.map_error(error.into(), request_id).await;
+            };
+
+            let result = match handler(parts, QueryValue::from_params(params)).await {
+                Ok(result) => result,
+                Err(e) => return router.error_mapper.map_error(e, request_id).await,
+            };
+
+            let mut body = format!(r#"<{action}Response xmlns="{}">"#, router.namespace);
+            if let Some(result) = result {
+                body.push_str(&result);
+            }
+            body.push_str("<ResponseMetadata>");
+            if let Some(request_id) = request_id {
+                body.push_str(&format!("<RequestId>{request_id}</RequestId>"));
+            }
+            body.push_str(&format!("</ResponseMetadata></{action}Response>"));
+
+            Response::builder()
+                .header(CONTENT_TYPE, "text/xml; charset=utf-8")
+                .body(Body::from(body))
+                .map_err(Into::into)
+        })
+    }
+}
+
+/// A type-erased JSON protocol handler, returning the serialized output.
+type JsonHandler = Arc<dyn Fn(Parts, Bytes) -> BoxFuture<Result<String, BoxError>> + Send + Sync>;
+
+/// A router for AWS JSON 1.0/1.1 protocol services (e.g. DynamoDB, KMS), dispatching requests to handlers by the
+/// operation named in their `X-Amz-Target` header, `<TargetPrefix>.<Operation>`.
+///
+/// Requests must have the `application/x-amz-json-1.0` or `application/x-amz-json-1.1` content type; responses are
+/// returned with the same content type and the request id in the `x-amzn-RequestId` header. The body is deserialized
+/// into the handler's input type with `serde_json` (an empty body is treated as `{}`), and the handler's output is
+/// serialized the same way; a handler returning `()` produces `{}`.
+///
+/// Requests with another content type are rejected with `InvalidContentType`; requests without a target, or whose
+/// target has a different prefix or an operation without a handler, are rejected with `UnknownOperationException`; and
+/// bodies that can't be deserialized are rejected with `SerializationException` (or, for operations registered with
+/// [with_validated_operation][Self::with_validated_operation], that violate their constraints with
+/// `ValidationException`). These and handler errors are rendered by the error mapper, normally a
+/// [JsonErrorMapper][crate::JsonErrorMapper].
+///
+/// ```
+/// use {
+///     scratchstack_http_framework::{router::JsonRouter, JsonErrorMapper, AWS_JSON_1_0_CONTENT_TYPE},
+///     serde::{Deserialize, Serialize},
+///     tower::BoxError,
+/// };
+///
+/// #[derive(Deserialize)]
+/// struct DescribeTableInput {
+///     #[serde(rename = "TableName")]
+///     table_name: String,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct DescribeTableOutput {
+///     #[serde(rename = "TableName")]
+///     table_name: String,
+/// }
+///
+/// let router = JsonRouter::new("DynamoDB_20120810", JsonErrorMapper::with_content_type(AWS_JSON_1_0_CONTENT_TYPE))
+///     .with_operation("DescribeTable", |_parts, input: DescribeTableInput| async move {
+///         Ok::<_, BoxError>(DescribeTableOutput {
+///             table_name: input.table_name,
+///         })
+///     });
+/// ```
+#[derive(Clone)]
+pub struct JsonRouter<E> {
+    target_prefix: String,
+    handlers: HashMap<String, JsonHandler>,
+    error_mapper: E,
+}
+
+impl<E: ErrorMapper> JsonRouter<E> {
+    /// Create a new [JsonRouter] with no operations for targets of the form `<target_prefix>.<Operation>`, rendering
+    /// errors with `error_mapper`.
+    pub fn new(target_prefix: &str, error_mapper: E) -> Self {
+        Self {
+            target_prefix: target_prefix.to_string(),
+            handlers: HashMap::new(),
+            error_mapper,
+        }
+    }
+
+    /// Register the handler for an operation, returning the updated router. The handler receives the request head
+    /// (including extensions such as the [Principal][scratchstack_aws_principal::Principal]) and the deserialized
+    /// input.
+    pub fn with_operation<I, O, F, Fut>(mut self, operation: &str, handler: F) -> Self
+    where
+        I: DeserializeOwned + Send + 'static,
+        O: Serialize + Send + 'static,
+        F: Fn(Parts, I) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<O, BoxError>> + Send + 'static,
+    {
+        self.handlers.insert(operation.to_string(), json_handler(handler, unchecked));
+        self
+    }
+
+    /// Register the handler for an operation whose input has [Validate] constraints, returning the updated router.
+    /// Requests whose input violates them are rejected with a `ValidationException` listing every violation.
+    pub fn with_validated_operation<I, O, F, Fut>(mut self, operation: &str, handler: F) -> Self
+    where
+        I: DeserializeOwned + Validate + Send + 'static,
+        O: Serialize + Send + 'static,
+        F: Fn(Parts, I) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<O, BoxError>> + Send + 'static,
+    {
+        self.handlers.insert(operation.to_string(), json_handler(handler, check::<I>));
+        self
+    }
+
+    /// Register the handlers for all of the operations in a registry, returning the updated router. Each operation is
+    /// served as the target `<target_prefix>.<Operation::NAME>`.
+    pub fn with_registry(mut self, registry: &OperationRegistry) -> Self {
+        self.handlers.extend(registry.json_handlers.iter().map(|(name, handler)| (name.clone(), handler.clone())));
+        self
+    }
+
+    /// Retreive the target prefix, e.g. `DynamoDB_20120810`.
+    #[inline]
+    pub fn target_prefix(&self) -> &str {
+        &self.target_prefix
+    }
+}
+
+impl<E> Debug for JsonRouter<E> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let mut operations: Vec<&String> = self.handlers.keys().collect();
+        operations.sort();
+
+        f.debug_struct("JsonRouter")
+            .field("target_prefix", &self.target_prefix)
+            .field("operations", &operations)
+            .finish()
+    }
+}
+
+impl<E: ErrorMapper> Service<Request<Body>> for JsonRouter<E> {
+    type Response = Response<Body>;
+    type Error = BoxError;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _c: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let router = self.clone();
+
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let request_id = parts.extensions.get::<RequestId>().copied();
+
+            let content_type = parts.headers.get(CONTENT_TYPE).and_then(|ct| ct.to_str().ok()).and_then(|ct| {
+                [AWS_JSON_1_0_CONTENT_TYPE, AWS_JSON_1_1_CONTENT_TYPE].into_iter().find(|json| ct.starts_with(json))
+            });
+            let Some(content_type) = content_type else {
+                return router.error_mapper.map_error(VerifierError::InvalidContentType.into(), request_id).await;
+            };
+
+            let handler = parts
+                .headers
+                .get("x-amz-target")
+                .and_then(|target| target.to_str().ok())
+                .and_then(|target| target.rsplit_once('.'))
+                .filter(|(prefix, _)| *prefix == router.target_prefix)
+                .and_then(|(_, operation)| router.handlers.get(operation));
+            let Some(handler) = handler.cloned() else {
+                return router.error_mapper.map_error(VerifierError::UnknownOperation.into(), request_id).await;
+            };
+
+            let body = hyper::body::to_bytes(body).await?;
+            let output = match handler(parts, body).await {
+                Ok(output) => output,
+                Err(e) => return router.error_mapper.map_error(e, request_id).await,
+            };
+
+            let mut builder = Response::builder().header(CONTENT_TYPE, content_type);
+            if let Some(request_id) = request_id {
+                builder = builder.header("x-amzn-RequestId", request_id.to_string());
+            }
+            builder.body(Body::from(output)).map_err(Into::into)
+        })
+    }
+}
+
+/// A strongly typed operation of a service, which can be served by any of the routers through an
+/// [OperationRegistry].
+///
+/// The router deserializes the operation's input from the wire protocol, calls [handle][Operation::handle], and
+/// serializes its output. Errors are any [ServiceError], and are rendered by the router's error mapper with their own
+/// error code and status (see [OperationError]).
+///
+/// ```
+/// use {
+///     async_trait::async_trait,
+///     http::StatusCode,
+///     scratchstack_errors::ServiceError,
+///     scratchstack_http_framework::router::{Operation, OperationContext, OperationRegistry},
+///     serde::{Deserialize, Serialize},
+///     std::{
+///         error::Error,
+///         fmt::{Display, Formatter, Result as FmtResult},
+///     },
+/// };
+///
+/// #[derive(Deserialize)]
+/// #[serde(rename_all = "PascalCase")]
+/// struct GetUserRequest {
+///     user_name: String,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct GetUserResponse {
+///     #[serde(rename = "$unflatten=UserName")]
+///     user_name: String,
+/// }
+///
+/// #[derive(Debug)]
+/// struct NoSuchEntity(String);
+///
+/// impl Display for NoSuchEntity {
+///     fn fmt(&self, f: &mut Formatter) -> FmtResult {
+///         write!(f, "The user with name {} cannot be found.", self.0)
+///     }
+/// }
+///
+/// impl Error for NoSuchEntity {}
+///
+/// impl ServiceError for NoSuchEntity {
+///     fn error_code(&self) -> &'static str {
+///         "NoSuchEntity"
+///     }
+///
+///     fn http_status(&self) -> StatusCode {
+///         StatusCode::NOT_FOUND
+///     }
+/// }
+///
+/// struct GetUser;
+///
+/// #[async_trait]
+/// impl Operation for GetUser {
+///     const NAME: &'static str = "GetUser";
+///     type Input = GetUserRequest;
+///     type Output = GetUserResponse;
+///     type Error = NoSuchEntity;
+///
+///     async fn handle(&self, _ctx: OperationContext, input: GetUserRequest) -> Result<GetUserResponse, NoSuchEntity> {
+///         Err(NoSuchEntity(input.user_name))
+///     }
+/// }
+///
+/// let registry = OperationRegistry::new("iam").with_operation(GetUser);
+/// ```
+#[async_trait]
+pub trait Operation: Send + Sync + 'static {
+    /// The name of the operation, e.g. `GetUser`. This is the Query protocol action, the JSON protocol target's
+    /// operation, and the action used for authorization.
+    const NAME: &'static str;
+
+    /// The input of the operation, deserialized from the request.
+    type Input: DeserializeOwned + Send + 'static;
+
+    /// The output of the operation, serialized as the response.
+    type Output: Serialize + Send + 'static;
+
+    /// The error returned by the operation.
+    type Error: ServiceError + Send + Sync + 'static;
+
+    /// Perform the operation.
+    async fn handle(&self, ctx: OperationContext, input: Self::Input) -> Result<Self::Output, Self::Error>;
+}
+
+/// The request an [Operation] is handling.
+#[derive(Debug)]
+pub struct OperationContext {
+    parts: Parts,
+}
+
+impl OperationContext {
+    /// Create a new [OperationContext] for the given request head.
+    pub fn new(parts: Parts) -> Self {
+        Self {
+            parts,
+        }
+    }
+
+    /// Retreive the request head, including its extensions.
+    #[inline]
+    pub fn parts(&self) -> &Parts {
+        &self.parts
+    }
+
+    /// Retreive the request id, if one was assigned.
+    #[inline]
+    pub fn request_id(&self) -> Option<RequestId> {
+        self.parts.extensions.get::<RequestId>().copied()
+    }
+
+    /// Retreive the authenticated principal, if the request was authenticated.
+    #[inline]
+    pub fn principal(&self) -> Option<&Principal> {
+        self.parts.extensions.get::<Principal>()
+    }
+
+    /// Returns the request head.
+    pub fn into_parts(self) -> Parts {
+        self.parts
+    }
+}
+
+/// An error returned by an [Operation]. Error mappers render this with the error code, status, and message of the
+/// underlying [ServiceError].
+#[derive(Debug)]
+pub struct OperationError {
+    error: Box<dyn ServiceError + Send + Sync>,
+    fault: Option<ErrorFault>,
+}
+
+impl OperationError {
+    /// Create a new [OperationError] wrapping the given error.
+    pub fn new<E: ServiceError + Send + Sync + 'static>(error: E) -> Self {
+        let fault = (&error as &dyn Any).downcast_ref::<StandardError>().map(StandardError::fault);
+        Self {
+            error: Box::new(error),
+            fault,
+        }
+    }
+
+    /// Retreive the underlying error.
+    #[inline]
+    pub fn service_error(&self) -> &(dyn ServiceError + 'static) {
+        &*self.error
+    }
+
+    /// Retreive the fault of the underlying error, if it is a [StandardError].
+    #[inline]
+    pub(crate) fn fault(&self) -> Option<ErrorFault> {
+        self.fault
+    }
+}
+
+impl Display for OperationError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        Display::fmt(&self.error, f)
+    }
+}
+
+impl Error for OperationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// The [Operation]s of a service, for registering with the routers.
+///
+/// The registry is also an [ActionResolver], resolving Query and JSON protocol requests for registered operations to
+/// the operation's [NAME][Operation::NAME] in the registry's service, so authorization uses the same action names as
+/// routing.
+#[derive(Clone)]
+pub struct OperationRegistry {
+    service: String,
+    query_handlers: HashMap<String, QueryHandler>,
+    json_handlers: HashMap<String, JsonHandler>,
+}
+
+impl OperationRegistry {
+    /// Create a new [OperationRegistry] with no operations for the given service prefix, e.g. `iam`.
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            query_handlers: HashMap::new(),
+            json_handlers: HashMap::new(),
+        }
+    }
+
+    /// Register an operation, returning the updated registry.
+    pub fn with_operation<O: Operation>(self, operation: O) -> Self {
+        self.register(operation, unchecked)
+    }
+
+    /// Register an operation whose input has [Validate] constraints, returning the updated registry. Requests whose
+    /// input violates them are rejected with a `ValidationException` listing every violation.
+    pub fn with_validated_operation<O: Operation>(self, operation: O) -> Self
+    where
+        O::Input: Validate,
+    {
+        self.register(operation, check::<O::Input>)
+    }
+
+    fn register<O: Operation>(mut self, operation: O, check: Check<O::Input>) -> Self {
+        let operation = Arc::new(operation);
+        let handler = move |parts: Parts, input: O::Input| -> BoxFuture<Result<O::Output, BoxError>> {
+            let operation = operation.clone();
+            Box::pin(async move {
+                operation.handle(OperationContext::new(parts), input).await.map_err(|e| OperationError::new(e).into())
+            })
+        };
+
+        self.query_handlers.insert(O::NAME.to_string(), query_handler(O::NAME, handler.clone(), check));
+        self.json_handlers.insert(O::NAME.to_string(), json_handler(handler, check));
+        self
+    }
+
+    /// Retreive the service prefix, e.g. `iam`.
+    #[inline]
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    /// Returns the names of the registered operations, sorted.
+    pub fn operation_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.query_handlers.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    /// Indicates whether an operation with the given name is registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.query_handlers.contains_key(name)
+    }
+}
+
+impl Debug for OperationRegistry {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("OperationRegistry")
+            .field("service", &self.service)
+            .field("operations", &self.operation_names())
+            .finish()
+    }
+}
+
+impl ActionResolver for OperationRegistry {
+    fn resolve(&self, parts: &Parts, body: Option<&[u8]>) -> Option<ResolvedAction> {
+        let operation = match target_operation(&parts.headers) {
+            Some(operation) => operation.to_string(),
+            None => parts.uri.query().and_then(query_action).or_else(|| {
+                let content_type = parts.headers.get(CONTENT_TYPE)?.to_str().ok()?;
+                if !content_type.starts_with(FORM_CONTENT_TYPE) {
+                    return None;
+                }
+                query_action(std::str::from_utf8(body?).ok()?)
+            })?,
+        };
+
+        self.contains(&operation).then(|| ResolvedAction::new(&self.service, operation, Vec::new()))
+    }
+}
+
+/// Checks the constraints of a handler's input after it is deserialized.
+type Check<I> = fn(&I) -> Result<(), StandardError>;
+
+/// A [Check] for inputs without constraints.
+fn unchecked<I>(_input: &I) -> Result<(), StandardError> {
+    Ok(())
+}
+
+/// Create the type-erased Query protocol handler for an action.
+fn query_handler<I, O, F, Fut>(action: &str, handler: F, check: Check<I>) -> QueryHandler
+where
+    I: DeserializeOwned + Send + 'static,
+    O: Serialize + Send + 'static,
+    F: Fn(Parts, I) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<O, BoxError>> + Send + 'static,
+{
+    let result_tag = format!("{action}Result");
+    Arc::new(move |parts, params| {
+        let future = I::deserialize(params)
+            .map_err(|e| BoxError::from(VerifierError::InvalidInput(e.to_string())))
+            .and_then(|input| {
+                check(&input)?;
+                Ok(handler(parts, input))
+            });
+        let result_tag = result_tag.clone();
+
+        Box::pin(async move {
+            let output = future?.await?;
+            serialize_result(&result_tag, &output)
+        })
+    })
+}
+
+/// Create the type-erased JSON protocol handler for an operation.
+fn json_handler<I, O, F, Fut>(handler: F, check: Check<I>) -> JsonHandler
+where
+    I: DeserializeOwned + Send + 'static,
+    O: Serialize + Send + 'static,
+    F: Fn(Parts, I) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<O, BoxError>> + Send + 'static,
+{
+    Arc::new(move |parts, body| {
+        let body: &[u8] = if body.is_empty() {
+            b"{}"
+        } else {
+            &body
+        };
+        let future = serde_json::from_slice(body)
+            .map_err(|e| BoxError::from(VerifierError::Serialization(e.to_string())))
+            .and_then(|input| {
+                check(&input)?;
+                Ok(handler(parts, input))
+            });
+
+        Box::pin(async move {
+            let output = future?.await?;
+            if size_of::<O>() == 0 {
+                Ok("{}".to_string())
+            } else {
+                Ok(serde_json::to_string(&output)?)
+            }
+        })
+    })
+}
+
+/// Serialize a handler's output as the given result element, or nothing if the output type has no data.
+fn serialize_result<O: Serialize>(result_tag: &str, output: &O) -> Result<Option<String>, BoxError> {
+    if size_of::<O>() == 0 {
+        return Ok(None);
+    }
+
+    let mut buffer = Vec::new();
+    let mut serializer = quick_xml::se::Serializer::with_root(quick_xml::Writer::new(&mut buffer), Some(result_tag));
+    output.serialize(&mut serializer)?;
+    Ok(Some(String::from_utf8(buffer)?))
+}
+
+/// Query protocol parameters, arranged as a tree by splitting their names on `.`.
+#[derive(Debug, Eq, PartialEq)]
+enum QueryValue {
+    Value(String),
+    Map(BTreeMap<String, QueryValue>),
+}
+
+impl QueryValue {
+    fn from_params(params: Vec<(String, String)>) -> Self {
+        let mut root = Self::Map(BTreeMap::new());
+
+        for (key, value) in params {
+            let mut node = &mut root;
+            for segment in key.split('.') {
+                if let Self::Value(_) = node {
+                    *node = Self::Map(BTreeMap::new());
+                }
+                node = match node {
+                    Self::Map(map) => map.entry(segment.to_string()).or_insert_with(|| Self::Map(BTreeMap::new())),
+                    Self::Value(_) => unreachable!(),
+                };
+            }
+            *node = Self::Value(value);
+        }
+
+        root
+    }
+
+    fn parse<T: std::str::FromStr>(self, expected: &str) -> Result<T, DeError> {
+        match self {
+            Self::Value(value) => {
+                value.parse().map_err(|_| DeError::custom(format!("expected {expected}, got '{value}'")))
+            }
+            Self::Map(_) => Err(DeError::custom(format!("expected {expected}, got a structure"))),
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeError> for QueryValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident, $ty:ty;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+                visitor.$visit(self.parse::<$ty>(stringify!($ty))?)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for QueryValue {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self {
+            Self::Value(value) => visitor.visit_string(value),
+            Self::Map(map) => visitor.visit_map(MapDeserializer::new(map.into_iter())),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool, bool;
+        deserialize_i8 => visit_i8, i8;
+        deserialize_i16 => visit_i16, i16;
+        deserialize_i32 => visit_i32, i32;
+        deserialize_i64 => visit_i64, i64;
+        deserialize_u8 => visit_u8, u8;
+        deserialize_u16 => visit_u16, u16;
+        deserialize_u32 => visit_u32, u32;
+        deserialize_u64 => visit_u64, u64;
+        deserialize_f32 => visit_f32, f32;
+        deserialize_f64 => visit_f64, f64;
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let mut map = match self {
+            // An empty list is sent as an empty value.
+            Self::Value(value) if value.is_empty() => BTreeMap::new(),
+            Self::Value(value) => return Err(DeError::custom(format!("expected a list, got '{value}'"))),
+            Self::Map(map) => map,
+        };
+        if let Some(Self::Map(members)) = map.remove("member") {
+            map = members;
+        }
+
+        let mut members = Vec::with_capacity(map.len());
+        for (index, value) in map {
+            let index: usize = index.parse().map_err(|_| DeError::custom(format!("invalid list index '{index}'")))?;
+            members.push((index, value));
+        }
+        members.sort_by_key(|(index, _)| *index);
+
+        visitor.visit_seq(SeqDeserializer::new(members.into_iter().map(|(_, value)| value)))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        match self {
+            Self::Value(value) => visitor.visit_enum(value.into_deserializer()),
+            Self::Map(_) => Err(DeError::custom("expected an enumeration value, got a structure")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        char str string bytes byte_buf unit_struct tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{JsonRouter, Operation, OperationContext, OperationRegistry, QueryRouter, QueryValue},
+        crate::{
+            validate::{Validate, Violations},
+            ActionResolver, JsonErrorMapper, RequestId, XmlErrorMapper, AWS_JSON_1_0_CONTENT_TYPE,
+        },
+        async_trait::async_trait,
+        http::{request::Parts, StatusCode},
+        hyper::{Body, Request},
+        pretty_assertions::assert_eq,
+        scratchstack_errors::ServiceError,
+        serde::{Deserialize, Serialize},
+        std::{
+            error::Error,
+            fmt::{Display, Formatter, Result as FmtResult},
+        },
+        tower::{BoxError, ServiceExt},
+    };
+
+    const NAMESPACE: &str = "https://iam.amazonaws.com/doc/2010-05-08/";
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "PascalCase")]
+    struct TagUserRequest {
+        user_name: String,
+        tags: Vec<Tag>,
+        max_items: Option<u32>,
+        dry_run: Option<bool>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "PascalCase")]
+    struct Tag {
+        key: String,
+        value: String,
+    }
+
+    #[derive(Serialize)]
+    struct TagUserResponse {
+        #[serde(rename = "$unflatten=TagCount")]
+        tag_count: usize,
+    }
+
+    fn params(query: &str) -> QueryValue {
+        QueryValue::from_params(super::query_params(query))
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let request = TagUserRequest::deserialize(params(
+            "Action=TagUser&UserName=123&Tags.member.2.Key=b&Tags.member.2.Value=2&Tags.member.10.Key=c\
+             &Tags.member.10.Value=3&Tags.member.1.Key=a&Tags.member.1.Value=1&MaxItems=50",
+        ))
+        .unwrap();
+
+        assert_eq!(request.user_name, "123");
+        assert_eq!(request.tags.iter().map(|t| t.key.as_str()).collect::<Vec<_>>(), vec!["a", "b", "c"]);
+        assert_eq!(request.max_items, Some(50));
+        assert_eq!(request.dry_run, None);
+
+        let request = TagUserRequest::deserialize(params("UserName=test&Tags=&DryRun=true")).unwrap();
+        assert!(request.tags.is_empty());
+        assert_eq!(request.dry_run, Some(true));
+
+        assert!(TagUserRequest::deserialize(params("UserName=test&Tags=&MaxItems=many")).is_err());
+        assert!(TagUserRequest::deserialize(params("Tags=")).is_err());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_query_router() {
+        let router = QueryRouter::new(NAMESPACE, XmlErrorMapper::new(NAMESPACE))
+            .with_version("2010-05-08")
+            .with_action("TagUser", |_parts: Parts, request: TagUserRequest| async move {
+                Ok::<_, BoxError>(TagUserResponse {
+                    tag_count: request.tags.len(),
+                })
+            })
+            .with_action("DeleteUser", |_parts: Parts, _request: TagUserRequest| async move { Ok::<_, BoxError>(()) });
+
+        let request_id = RequestId::from_timestamp_and_random(0, 1);
+        let mut req = Request::post("/")
+            .header("content-type", "application/x-www-form-urlencoded; charset=utf-8")
+            .body(Body::from(
+                "Action=TagUser&Version=2010-05-08&UserName=test&Tags.member.1.Key=a&Tags.member.1.Value=1",
+            ))
+            .unwrap();
+        req.extensions_mut().insert(request_id);
+
+        let response = router.clone().oneshot(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&body),
+            format!(
+                "<TagUserResponse xmlns=\"{NAMESPACE}\"><TagUserResult><TagCount>1</TagCount></TagUserResult>\
+                 <ResponseMetadata><RequestId>{request_id}</RequestId></ResponseMetadata></TagUserResponse>"
+            )
+        );
+
+        let req =
+            Request::get("/?Action=DeleteUser&Version=2010-05-08&UserName=test&Tags=").body(Body::empty()).unwrap();
+        let response = router.clone().oneshot(req).await.unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&body),
+            format!(
+                "<DeleteUserResponse xmlns=\"{NAMESPACE}\"><ResponseMetadata></ResponseMetadata></DeleteUserResponse>"
+            )
+        );
+
+        let error_code = |query: &'static str| {
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(Request::get(query).body(Body::empty()).unwrap()).await.unwrap();
+                assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                let body = String::from_utf8_lossy(&body).to_string();
+                body.split("<Code>").nth(1).unwrap().split("</Code>").next().unwrap().to_string()
+            }
+        };
+
+        assert_eq!(error_code("/?Version=2010-05-08").await, "MissingAction");
+        assert_eq!(error_code("/?Action=GetUser&Version=2010-05-08").await, "InvalidAction");
+        assert_eq!(error_code("/?Action=TagUser&Version=2006-03-01&UserName=test&Tags=").await, "NoSuchVersion");
+        assert_eq!(error_code("/?Action=TagUser&UserName=test&Tags=").await, "InvalidAction");
+        assert_eq!(error_code("/?Action=TagUser&Version=2010-05-08&Tags=").await, "InvalidInput");
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_query_router_versions() {
+        let router = QueryRouter::new(NAMESPACE, XmlErrorMapper::new(NAMESPACE))
+            .with_version("2010-05-08")
+            .with_action("TagUser", |_parts: Parts, request: TagUserRequest| async move {
+                Ok::<_, BoxError>(TagUserResponse {
+                    tag_count: request.tags.len(),
+                })
+            })
+            .with_versioned_action("2021-01-01", "TagUser", |_parts: Parts, _request: TagUserRequest| async move {
+                Ok::<_, BoxError>(TagUserResponse {
+                    tag_count: 0,
+                })
+            })
+            .with_versioned_action("2021-01-01", "DeleteUser", |_parts: Parts, _request: TagUserRequest| async move {
+                Ok::<_, BoxError>(())
+            });
+        assert_eq!(router.versions(), ["2010-05-08", "2021-01-01"]);
+
+        let call = |query: &'static str| {
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(Request::get(query).body(Body::empty()).unwrap()).await.unwrap();
+                let status = response.status();
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                (status, String::from_utf8_lossy(&body).to_string())
+            }
+        };
+
+        let (status, body) =
+            call("/?Action=TagUser&Version=2010-05-08&UserName=test&Tags.member.1.Key=a&Tags.member.1.Value=1").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<TagCount>1</TagCount>"));
+
+        let (status, body) =
+            call("/?Action=TagUser&Version=2021-01-01&UserName=test&Tags.member.1.Key=a&Tags.member.1.Value=1").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<TagCount>0</TagCount>"));
+
+        let (status, _) = call("/?Action=DeleteUser&Version=2021-01-01&UserName=test&Tags=").await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = call("/?Action=DeleteUser&Version=2010-05-08&UserName=test&Tags=").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("<Code>InvalidAction</Code>"));
+        assert!(body.contains("Could not find operation DeleteUser for version 2010-05-08"));
+
+        let (status, body) = call("/?Action=TagUser&Version=2015-01-01&UserName=test&Tags=").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("<Code>NoSuchVersion</Code>"));
+        assert!(body.contains("The requested version (2015-01-01) of this service does not exist"));
+    }
+
+    #[derive(Deserialize)]
+    struct GetItemInput {
+        #[serde(rename = "TableName")]
+        table_name: String,
+    }
+
+    impl Validate for GetItemInput {
+        fn validate(&self, violations: &mut Violations) {
+            violations.length("tableName", &self.table_name, 3, 255);
+        }
+    }
+
+    #[derive(Serialize)]
+    struct GetItemOutput {
+        #[serde(rename = "TableName")]
+        table_name: String,
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_json_router() {
+        let router =
+            JsonRouter::new("DynamoDB_20120810", JsonErrorMapper::with_content_type(AWS_JSON_1_0_CONTENT_TYPE))
+                .with_operation("GetItem", |_parts: Parts, input: GetItemInput| async move {
+                    Ok::<_, BoxError>(GetItemOutput {
+                        table_name: input.table_name,
+                    })
+                })
+                .with_operation("DeleteTable", |_parts: Parts, _input: serde_json::Value| async move {
+                    Ok::<_, BoxError>(())
+                });
+        let request = |target: &str, content_type: &str, body: &'static str| {
+            Request::post("/")
+                .header("x-amz-target", target)
+                .header("content-type", content_type)
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let call = |req: Request<Body>| {
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(req).await.unwrap();
+                let status = response.status();
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap())
+            }
+        };
+
+        let mut req = request("DynamoDB_20120810.GetItem", "application/x-amz-json-1.0", r#"{"TableName": "Books"}"#);
+        req.extensions_mut().insert(RequestId::from_timestamp_and_random(0, 1));
+        let response = router.clone().oneshot(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "application/x-amz-json-1.0");
+        assert!(response.headers().contains_key("x-amzn-requestid"));
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            serde_json::json!({"TableName": "Books"})
+        );
+
+        let (status, body) = call(request("DynamoDB_20120810.DeleteTable", "application/x-amz-json-1.1", "")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, serde_json::json!({}));
+
+        for target in ["DynamoDB_20120810.Scan", "DynamoDBStreams_20120810.GetItem", "GetItem"] {
+            let (status, body) = call(request(target, "application/x-amz-json-1.0", "{}")).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["__type"], "UnknownOperationException");
+        }
+
+        let (_, body) =
+            call(request("DynamoDB_20120810.GetItem", "application/x-amz-json-1.0", r#"{"TableName": 1}"#)).await;
+        assert_eq!(body["__type"], "SerializationException");
+
+        let (_, body) = call(request("DynamoDB_20120810.GetItem", "application/json", "{}")).await;
+        assert_eq!(body["__type"], "InvalidContentType");
+    }
+
+    #[derive(Debug)]
+    struct ResourceNotFound(String);
+
+    impl Display for ResourceNotFound {
+        fn fmt(&self, f: &mut Formatter) -> FmtResult {
+            write!(f, "Requested resource not found: Table: {} not found", self.0)
+        }
+    }
+
+    impl Error for ResourceNotFound {}
+
+    impl ServiceError for ResourceNotFound {
+        fn error_code(&self) -> &'static str {
+            "ResourceNotFoundException"
+        }
+
+        fn http_status(&self) -> StatusCode {
+            StatusCode::BAD_REQUEST
+        }
+    }
+
+    struct GetItem;
+
+    #[async_trait]
+    impl Operation for GetItem {
+        const NAME: &'static str = "GetItem";
+        type Input = GetItemInput;
+        type Output = GetItemOutput;
+        type Error = ResourceNotFound;
+
+        async fn handle(&self, ctx: OperationContext, input: GetItemInput) -> Result<GetItemOutput, ResourceNotFound> {
+            assert!(ctx.principal().is_none());
+            if input.table_name == "Books" {
+                Ok(GetItemOutput {
+                    table_name: input.table_name,
+                })
+            } else {
+                Err(ResourceNotFound(input.table_name))
+            }
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_operation_registry() {
+        let registry = OperationRegistry::new("dynamodb").with_validated_operation(GetItem);
+        assert_eq!(registry.operation_names(), vec!["GetItem"]);
+
+        let json_router =
+            JsonRouter::new("DynamoDB_20120810", JsonErrorMapper::with_content_type(AWS_JSON_1_0_CONTENT_TYPE))
+                .with_registry(&registry);
+        let request = |body: &'static str| {
+            Request::post("/")
+                .header("x-amz-target", "DynamoDB_20120810.GetItem")
+                .header("content-type", "application/x-amz-json-1.0")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = json_router.clone().oneshot(request(r#"{"TableName": "Books"}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            serde_json::json!({"TableName": "Books"})
+        );
+
+        let response = json_router.clone().oneshot(request(r#"{"TableName": "Films"}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+        assert_eq!(body["__type"], "ResourceNotFoundException");
+        assert_eq!(body["message"], "Requested resource not found: Table: Films not found");
+
+        let response = json_router.clone().oneshot(request(r#"{"TableName": "TV"}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+        assert_eq!(body["__type"], "ValidationException");
+        assert_eq!(
+            body["message"],
+            "1 validation error detected: Value 'TV' at 'tableName' failed to satisfy constraint: Member must have \
+             length greater than or equal to 3"
+        );
+
+        let query_router = QueryRouter::new(NAMESPACE, XmlErrorMapper::new(NAMESPACE)).with_registry(&registry);
+        let response = query_router
+            .oneshot(Request::get("/?Action=GetItem&TableName=Films").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("<Code>ResourceNotFoundException</Code>"));
+
+        let (parts, _) = request("{}").into_parts();
+        let action = registry.resolve(&parts, None).unwrap();
+        assert_eq!(action.iam_action(), "dynamodb:GetItem");
+
+        let (parts, _) = Request::get("/?Action=Scan").body(()).unwrap().into_parts();
+        assert!(registry.resolve(&parts, None).is_none());
+    }
+}
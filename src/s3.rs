@@ -0,0 +1,276 @@
+use {
+    crate::{
+        error::{as_service_error, client_message},
+        ErrorContext, ErrorMapper, MessageCatalog, RequestId, SigningDetails,
+    },
+    async_trait::async_trait,
+    http::{header::HeaderMap, uri::PathAndQuery, Uri},
+    hyper::{Body, Response},
+    serde::Serialize,
+    std::sync::Arc,
+    tower::BoxError,
+};
+
+/// The S3 endpoints that accept virtual-hosted-style requests, where the bucket is named in the `Host` header (e.g.
+/// `bucket.s3.us-east-1.example.com`) rather than in the path.
+///
+/// When configured on [AwsSigV4VerifierService][crate::AwsSigV4VerifierService], such requests are verified as sent
+/// using the S3 canonicalization options, then passed to the service implementation with a path-style URI
+/// (`/bucket/key`) and the bucket name in an [S3Bucket] extension.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct S3VirtualHosts {
+    endpoints: Vec<String>,
+}
+
+impl S3VirtualHosts {
+    /// Create a new [S3VirtualHosts] recognizing buckets as subdomains of the given endpoints, e.g.
+    /// `s3.us-east-1.example.com`.
+    pub fn new<I, S>(endpoints: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            endpoints: endpoints.into_iter().map(|e| e.into().to_ascii_lowercase()).collect(),
+        }
+    }
+
+    /// Retreive the endpoints that accept virtual-hosted-style requests.
+    #[inline]
+    pub fn endpoints(&self) -> &[String] {
+        &self.endpoints
+    }
+
+    /// Returns the bucket named by a `Host` header value, if it is a valid bucket name under one of the endpoints.
+    pub fn bucket(&self, host: &str) -> Option<S3Bucket> {
+        let host = host.trim().to_ascii_lowercase();
+        let host = match host.rsplit_once(':') {
+            Some((host, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => host,
+            _ => host.as_str(),
+        };
+
+        self.endpoints.iter().find_map(|endpoint| {
+            let bucket = host.strip_suffix(endpoint.as_str())?.strip_suffix('.')?;
+            is_valid_bucket_name(bucket).then(|| S3Bucket(bucket.to_string()))
+        })
+    }
+
+    /// Returns the bucket named by the `Host` header of a request, if any.
+    pub(crate) fn bucket_for_headers(&self, headers: &HeaderMap) -> Option<S3Bucket> {
+        self.bucket(headers.get("host")?.to_str().ok()?)
+    }
+}
+
+/// The bucket named in the `Host` header of an S3 virtual-hosted-style request.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct S3Bucket(String);
+
+impl S3Bucket {
+    /// Retreive the bucket name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Indicates whether a name follows the S3 bucket naming rules: 3 to 63 lowercase letters, digits, dots, and hyphens,
+/// beginning and ending with a letter or digit.
+fn is_valid_bucket_name(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    (3..=63).contains(&bytes.len())
+        && bytes.iter().all(|&b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'.' || b == b'-')
+        && bytes[0].is_ascii_alphanumeric()
+        && bytes[bytes.len() - 1].is_ascii_alphanumeric()
+}
+
+/// Rewrite a virtual-hosted-style URI to path style by prefixing the path with the bucket name.
+pub(crate) fn path_style_uri(uri: &Uri, bucket: &S3Bucket) -> Uri {
+    let path_and_query = match uri.query() {
+        Some(query) => format!("/{}{}?{}", bucket.name(), uri.path(), query),
+        None => format!("/{}{}", bucket.name(), uri.path()),
+    };
+
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(PathAndQuery::try_from(path_and_query).expect("bucket names are valid path segments"));
+    Uri::from_parts(parts).expect("the rewritten URI differs only in its path")
+}
+
+/// An implementation of [ErrorMapper] that returns errors in the format used by Amazon S3: a bare `<Error>` root
+/// element with no namespace, containing `Code`, `Message`, `Resource`, and `RequestId` children.
+///
+/// The resource is the request path. The request id, if known, is also returned in the `x-amz-request-id` header.
+#[derive(Clone, Debug, Default)]
+pub struct S3XmlErrorMapper {
+    message_catalog: Option<Arc<MessageCatalog>>,
+}
+
+impl S3XmlErrorMapper {
+    /// Create a new [S3XmlErrorMapper].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the given [MessageCatalog] to render client-facing error messages.
+    pub fn with_message_catalog(mut self, message_catalog: Arc<MessageCatalog>) -> Self {
+        self.message_catalog = Some(message_catalog);
+        self
+    }
+
+    /// Map a service error to an S3-style response, including the resource if known.
+    fn map_s3_error(
+        &self,
+        e: BoxError,
+        request_id: Option<RequestId>,
+        resource: Option<&str>,
+        signing_details: Option<&SigningDetails>,
+    ) -> Result<Response<Body>, BoxError> {
+        match as_service_error(&e) {
+            Some(service_error) => {
+                let error = S3XmlError {
+                    code: service_error.error_code().to_string(),
+                    message: client_message(service_error, self.message_catalog.as_deref(), request_id),
+                    resource: resource.map(str::to_string),
+                    canonical_request: signing_details.map(|d| d.canonical_request().to_string()),
+                    string_to_sign: signing_details.map(|d| d.string_to_sign().to_string()),
+                    request_id,
+                };
+
+                let body = format!(r#"<?xml version="1.0" encoding="UTF-8"?>{}"#, quick_xml::se::to_string(&error)?);
+
+                let mut builder =
+                    Response::builder().status(service_error.http_status()).header("Content-Type", "application/xml");
+                if let Some(request_id) = request_id {
+                    builder = builder.header("x-amz-request-id", request_id.to_string());
+                }
+
+                builder.body(Body::from(body)).map_err(Into::into)
+            }
+            None => Err(e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename = "Error")]
+struct S3XmlError {
+    #[serde(rename = "$unflatten=Code")]
+    code: String,
+
+    #[serde(rename = "$unflatten=Message", skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+
+    #[serde(rename = "$unflatten=Resource", skip_serializing_if = "Option::is_none")]
+    resource: Option<String>,
+
+    #[serde(rename = "$unflatten=CanonicalRequest", skip_serializing_if = "Option::is_none")]
+    canonical_request: Option<String>,
+
+    #[serde(rename = "$unflatten=StringToSign", skip_serializing_if = "Option::is_none")]
+    string_to_sign: Option<String>,
+
+    #[serde(rename = "$unflatten=RequestId", skip_serializing_if = "Option::is_none")]
+    request_id: Option<RequestId>,
+}
+
+#[async_trait]
+impl ErrorMapper for S3XmlErrorMapper {
+    async fn map_error(self, e: BoxError, request_id: Option<RequestId>) -> Result<Response<Body>, BoxError> {
+        self.map_s3_error(e, request_id, None, None)
+    }
+
+    async fn map_error_with_context(self, e: BoxError, context: &ErrorContext) -> Result<Response<Body>, BoxError> {
+        self.map_s3_error(e, context.request_id(), Some(context.uri().path()), context.signing_details())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{path_style_uri, S3VirtualHosts, S3XmlErrorMapper},
+        crate::{ErrorContext, ErrorMapper, RequestId, SigningDetails, VerifierError},
+        http::{header::HeaderMap, Method, StatusCode, Uri},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_virtual_hosts() {
+        let virtual_hosts = S3VirtualHosts::new(["s3.us-east-1.example.com", "s3.example.com"]);
+        let bucket = |host: &str| virtual_hosts.bucket(host).map(|b| b.name().to_string());
+
+        assert_eq!(bucket("my-bucket.s3.us-east-1.example.com"), Some("my-bucket".to_string()));
+        assert_eq!(bucket("My.Bucket.S3.example.com:8443"), Some("my.bucket".to_string()));
+        assert_eq!(bucket("s3.us-east-1.example.com"), None);
+        assert_eq!(bucket("ab.s3.example.com"), None);
+        assert_eq!(bucket("-bucket.s3.example.com"), None);
+        assert_eq!(bucket("bucket.s3.us-west-2.example.com"), None);
+
+        let bucket = virtual_hosts.bucket("my-bucket.s3.example.com").unwrap();
+        assert_eq!(
+            path_style_uri(&Uri::from_static("/photos/cat.jpg?versionId=1"), &bucket),
+            Uri::from_static("/my-bucket/photos/cat.jpg?versionId=1")
+        );
+        assert_eq!(
+            path_style_uri(&Uri::from_static("https://my-bucket.s3.example.com/"), &bucket),
+            Uri::from_static("https://my-bucket.s3.example.com/my-bucket/")
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_s3_error() {
+        let request_id = RequestId::from_timestamp_and_random(0, 1);
+        let context = ErrorContext::new(
+            Method::GET,
+            Uri::from_static("/bucket/key?versionId=1"),
+            HeaderMap::new(),
+            "us-east-1".to_string(),
+            "s3".to_string(),
+            Some(request_id),
+        );
+        let response = S3XmlErrorMapper::new()
+            .map_error_with_context(VerifierError::RequestExpired.into(), &context)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()["x-amz-request-id"], request_id.to_string().as_str());
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&body),
+            format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?><Error><Code>RequestExpired</Code><Message>Request timestamp \
+                 is outside of the allowed time window</Message><Resource>/bucket/key</Resource><RequestId>{request_id}\
+                 </RequestId></Error>"
+            )
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_s3_error_signing_details() {
+        let details = SigningDetails::new(
+            "GET\n/bucket/key\n\nhost:s3.example.com\n\nhost\nUNSIGNED-PAYLOAD".to_string(),
+            "AWS4-HMAC-SHA256\n20150830T123600Z\n20150830/us-east-1/s3/aws4_request\nabc".to_string(),
+            vec!["host".to_string()],
+            "20150830/us-east-1/s3/aws4_request".to_string(),
+        );
+        let context = ErrorContext::new(
+            Method::GET,
+            Uri::from_static("/bucket/key"),
+            HeaderMap::new(),
+            "us-east-1".to_string(),
+            "s3".to_string(),
+            None,
+        )
+        .with_signing_details(details);
+        let response = S3XmlErrorMapper::new()
+            .map_error_with_context(VerifierError::SignatureDoesNotMatch.into(), &context)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("<CanonicalRequest>GET\n/bucket/key\n"));
+        assert!(body.contains("<StringToSign>AWS4-HMAC-SHA256\n20150830T123600Z\n"));
+    }
+}
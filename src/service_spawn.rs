@@ -1,66 +1,38 @@
 use {
-    crate::{AwsSigV4VerifierService, ErrorMapper},
-    derive_builder::Builder,
+    crate::{
+        AwsSigV4VerifierService, AwsSigV4VerifierServiceBuilder, AwsSigV4VerifierServiceBuilderError, ConnectInfo,
+        ErrorMapper,
+    },
     http::method::Method,
     hyper::{body::Body, server::conn::AddrStream, service::Service, Request, Response},
     scratchstack_aws_signature::{
         GetSigningKeyRequest, GetSigningKeyResponse, SignatureOptions, SignedHeaderRequirements,
     },
     std::{
+        any::type_name,
+        fmt::{Debug, Formatter, Result as FmtResult},
         future::Future,
         pin::Pin,
         task::{Context, Poll},
     },
-    tokio::net::TcpStream,
-    tokio_rustls::server::TlsStream,
     tower::BoxError,
 };
 
+#[cfg(feature = "tls")]
+use {tokio::net::TcpStream, tokio_rustls::server::TlsStream};
+
+/// The future returned by a [SpawnService] for each connection.
+type SpawnFuture<G, S, E> = Pin<Box<dyn Future<Output = Result<AwsSigV4VerifierService<G, S, E>, BoxError>> + Send>>;
+
 /// A Hyper service spawner that wraps a SigV4 signing key provider ([`GetSigningKeyRequest`] ->
 /// [`GetSigningKeyResponse`]), an HTTP request handler ([`Request<Body>`] -> [`Response<Body>`]) for handling
 /// requests that pass authentication, and an error mapper ([`ErrorMapper`]) for converting authentication errors into
 /// HTTP responses.
-#[derive(Builder, Clone, Debug)]
-pub struct SpawnService<G, S, E>
-where
-    G: Service<GetSigningKeyRequest, Response = GetSigningKeyResponse, Error = BoxError> + Clone + Send + 'static,
-    G::Future: Send,
-    S: Service<Request<Body>, Response = Response<Body>, Error = BoxError> + Clone + Send + 'static,
-    S::Future: Send,
-    E: ErrorMapper,
-{
-    /// The region this service is operating in.
-    #[builder(setter(into))]
-    region: String,
-
-    /// The name of this service.
-    #[builder(setter(into))]
-    service: String,
-
-    /// The allowed HTTP request methods.
-    #[builder(default)]
-    allowed_request_methods: Vec<Method>,
-
-    /// The allowed HTTP content types.
-    #[builder(default)]
-    allowed_content_types: Vec<String>,
-
-    /// The HTTP headers that must be signed in the SigV4 signature.
-    #[builder(default)]
-    signed_header_requirements: SignedHeaderRequirements,
-
-    /// The signing key provider.
-    get_signing_key: G,
-
-    /// The service implementation.
-    implementation: S,
-
-    /// The mapper for converting authentication errors into HTTP responses.
-    error_mapper: E,
-
-    /// Options for the signature verification process.
-    #[builder(default)]
-    signature_options: SignatureOptions,
+///
+/// The spawner holds an [AwsSigV4VerifierServiceBuilder] with every setting of the verifier, and builds a verifier from
+/// it for each connection with the [ConnectInfo] of that connection.
+pub struct SpawnService<G, S, E> {
+    builder: AwsSigV4VerifierServiceBuilder<G, S, E>,
 }
 
 impl<G, S, E> SpawnService<G, S, E>
@@ -71,11 +43,51 @@ where
     S::Future: Send,
     E: ErrorMapper,
 {
+    /// Create a new [SpawnService] from a verifier builder, checking that all of its required fields are set.
+    pub fn new(builder: AwsSigV4VerifierServiceBuilder<G, S, E>) -> Result<Self, AwsSigV4VerifierServiceBuilderError> {
+        builder.build()?;
+        Ok(Self {
+            builder,
+        })
+    }
+
     /// Create a new [SpawnServiceBuilder] for constructing a [SpawnService].
+    #[deprecated(note = "configure an AwsSigV4VerifierServiceBuilder and pass it to SpawnService::new")]
+    #[allow(deprecated)]
     #[inline]
     pub fn builder() -> SpawnServiceBuilder<G, S, E> {
         SpawnServiceBuilder::default()
     }
+
+    /// Returns a verifier for a new connection.
+    fn spawn(&self, connect_info: Option<ConnectInfo>) -> SpawnFuture<G, S, E> {
+        let mut builder = self.builder.clone();
+        builder.connect_info(connect_info);
+        Box::pin(async move { builder.build().map_err(Into::into) })
+    }
+}
+
+impl<G, S, E> Clone for SpawnService<G, S, E>
+where
+    G: Clone,
+    S: Clone,
+    E: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            builder: self.builder.clone(),
+        }
+    }
+}
+
+impl<G, S, E> Debug for SpawnService<G, S, E> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("SpawnService")
+            .field("get_signing_key", &type_name::<G>())
+            .field("implementation", &type_name::<S>())
+            .field("error_mapper", &type_name::<E>())
+            .finish_non_exhaustive()
+    }
 }
 
 impl<G, S, E> Service<&AddrStream> for SpawnService<G, S, E>
@@ -88,40 +100,18 @@ where
 {
     type Response = AwsSigV4VerifierService<G, S, E>;
     type Error = BoxError;
-    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Future = SpawnFuture<G, S, E>;
 
     fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         Poll::Ready(Ok(()))
     }
 
-    fn call(&mut self, _req: &AddrStream) -> Self::Future {
-        let region = self.region.clone();
-        let service = self.service.clone();
-        let allowed_request_methods = self.allowed_request_methods.clone();
-        let allowed_content_types = self.allowed_content_types.clone();
-        let signed_header_requirements = self.signed_header_requirements.clone();
-        let get_signing_key = self.get_signing_key.clone();
-        let implementation = self.implementation.clone();
-        let error_mapper = self.error_mapper.clone();
-        let signature_options = self.signature_options;
-
-        Box::pin(async move {
-            AwsSigV4VerifierService::builder()
-                .region(region)
-                .service(service)
-                .allowed_request_methods(allowed_request_methods)
-                .allowed_content_types(allowed_content_types)
-                .signed_header_requirements(signed_header_requirements)
-                .get_signing_key(get_signing_key)
-                .implementation(implementation)
-                .error_mapper(error_mapper)
-                .signature_options(signature_options)
-                .build()
-                .map_err(Into::into)
-        })
+    fn call(&mut self, req: &AddrStream) -> Self::Future {
+        self.spawn(Some(ConnectInfo::new(req.remote_addr(), Some(req.local_addr()))))
     }
 }
 
+#[cfg(feature = "tls")]
 impl<G, S, E> Service<&TlsStream<TcpStream>> for SpawnService<G, S, E>
 where
     G: Service<GetSigningKeyRequest, Response = GetSigningKeyResponse, Error = BoxError> + Clone + Send + 'static,
@@ -132,36 +122,122 @@ where
 {
     type Response = AwsSigV4VerifierService<G, S, E>;
     type Error = BoxError;
-    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Future = SpawnFuture<G, S, E>;
 
     fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         Poll::Ready(Ok(()))
     }
 
-    fn call(&mut self, _req: &TlsStream<TcpStream>) -> Self::Future {
-        let region = self.region.clone();
-        let service = self.service.clone();
-        let allowed_request_methods = self.allowed_request_methods.clone();
-        let allowed_content_types = self.allowed_content_types.clone();
-        let signed_header_requirements = self.signed_header_requirements.clone();
-        let get_signing_key = self.get_signing_key.clone();
-        let implementation = self.implementation.clone();
-        let error_mapper = self.error_mapper.clone();
-        let signature_options = self.signature_options;
-
-        Box::pin(async move {
-            AwsSigV4VerifierService::builder()
-                .region(region)
-                .service(service)
-                .allowed_request_methods(allowed_request_methods)
-                .allowed_content_types(allowed_content_types)
-                .signed_header_requirements(signed_header_requirements)
-                .get_signing_key(get_signing_key)
-                .implementation(implementation)
-                .error_mapper(error_mapper)
-                .signature_options(signature_options)
-                .build()
-                .map_err(Into::into)
-        })
+    fn call(&mut self, req: &TlsStream<TcpStream>) -> Self::Future {
+        let (tcp_stream, _) = req.get_ref();
+        self.spawn(
+            tcp_stream.peer_addr().ok().map(|remote_addr| ConnectInfo::new(remote_addr, tcp_stream.local_addr().ok())),
+        )
+    }
+}
+
+/// Builder for [SpawnService].
+///
+/// This only sets the settings a [SpawnService] had before it was built from an [AwsSigV4VerifierServiceBuilder];
+/// use one of those with [SpawnService::new] for the rest.
+#[deprecated(note = "configure an AwsSigV4VerifierServiceBuilder and pass it to SpawnService::new")]
+pub struct SpawnServiceBuilder<G, S, E> {
+    builder: AwsSigV4VerifierServiceBuilder<G, S, E>,
+}
+
+#[allow(deprecated)]
+impl<G, S, E> SpawnServiceBuilder<G, S, E>
+where
+    G: Service<GetSigningKeyRequest, Response = GetSigningKeyResponse, Error = BoxError> + Clone + Send + 'static,
+    G::Future: Send,
+    S: Service<Request<Body>, Response = Response<Body>, Error = BoxError> + Clone + Send + 'static,
+    S::Future: Send,
+    E: ErrorMapper,
+{
+    /// The region this service is operating in.
+    pub fn region<VALUE: Into<String>>(&mut self, value: VALUE) -> &mut Self {
+        self.builder.region(value);
+        self
+    }
+
+    /// The name of this service.
+    pub fn service<VALUE: Into<String>>(&mut self, value: VALUE) -> &mut Self {
+        self.builder.service(value);
+        self
+    }
+
+    /// The allowed HTTP request methods.
+    pub fn allowed_request_methods(&mut self, value: Vec<Method>) -> &mut Self {
+        self.builder.allowed_request_methods(value);
+        self
+    }
+
+    /// The allowed HTTP content types.
+    pub fn allowed_content_types(&mut self, value: Vec<String>) -> &mut Self {
+        self.builder.allowed_content_types(value);
+        self
+    }
+
+    /// The HTTP headers that must be signed in the SigV4 signature.
+    pub fn signed_header_requirements(&mut self, value: SignedHeaderRequirements) -> &mut Self {
+        self.builder.signed_header_requirements(value);
+        self
+    }
+
+    /// The signing key provider.
+    pub fn get_signing_key(&mut self, value: G) -> &mut Self {
+        self.builder.get_signing_key(value);
+        self
+    }
+
+    /// The service implementation.
+    pub fn implementation(&mut self, value: S) -> &mut Self {
+        self.builder.implementation(value);
+        self
+    }
+
+    /// The mapper for converting authentication errors into HTTP responses.
+    pub fn error_mapper(&mut self, value: E) -> &mut Self {
+        self.builder.error_mapper(value);
+        self
+    }
+
+    /// Options for the signature verification process.
+    pub fn signature_options(&mut self, value: SignatureOptions) -> &mut Self {
+        self.builder.signature_options(value);
+        self
+    }
+
+    /// Builds a new [SpawnService].
+    pub fn build(&self) -> Result<SpawnService<G, S, E>, AwsSigV4VerifierServiceBuilderError> {
+        SpawnService::new(self.builder.clone())
+    }
+}
+
+#[allow(deprecated)]
+impl<G, S, E> Clone for SpawnServiceBuilder<G, S, E>
+where
+    G: Clone,
+    S: Clone,
+    E: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            builder: self.builder.clone(),
+        }
+    }
+}
+
+#[allow(deprecated)]
+impl<G, S, E> Default for SpawnServiceBuilder<G, S, E>
+where
+    G: Clone,
+    S: Clone,
+    E: Clone,
+{
+    fn default() -> Self {
+        Self {
+            builder: AwsSigV4VerifierServiceBuilder::default(),
+        }
     }
 }
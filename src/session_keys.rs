@@ -0,0 +1,413 @@
+use {
+    chrono::{DateTime, Utc},
+    derive_builder::Builder,
+    scratchstack_aws_principal::{SessionData, SessionValue},
+    std::net::IpAddr,
+};
+
+/// `aws:username`: The friendly name of the IAM user making the request.
+pub const USERNAME: &str = "aws:username";
+
+/// `aws:userid`: The unique id of the principal making the request.
+pub const USER_ID: &str = "aws:userid";
+
+/// `aws:PrincipalType`: The type of the principal making the request (`Account`, `User`, `AssumedRole`, etc.).
+pub const PRINCIPAL_TYPE: &str = "aws:PrincipalType";
+
+/// `aws:PrincipalAccount`: The account id of the principal making the request.
+pub const PRINCIPAL_ACCOUNT: &str = "aws:PrincipalAccount";
+
+/// `aws:PrincipalArn`: The ARN of the principal making the request.
+pub const PRINCIPAL_ARN: &str = "aws:PrincipalArn";
+
+/// `aws:PrincipalIsAWSService`: Whether the request was made by an AWS service principal.
+pub const PRINCIPAL_IS_AWS_SERVICE: &str = "aws:PrincipalIsAWSService";
+
+/// `aws:PrincipalOrgID`: The id of the organization the principal's account belongs to.
+pub const PRINCIPAL_ORG_ID: &str = "aws:PrincipalOrgID";
+
+/// `aws:PrincipalOrgPaths`: The organization path of the principal's account.
+pub const PRINCIPAL_ORG_PATHS: &str = "aws:PrincipalOrgPaths";
+
+/// `aws:PrincipalTag/`: The prefix for tags attached to the principal. Use [principal_tag_key] to form a full key.
+pub const PRINCIPAL_TAG_PREFIX: &str = "aws:PrincipalTag/";
+
+/// `aws:MultiFactorAuthPresent`: Whether the credentials used to sign the request were obtained using MFA.
+pub const MULTI_FACTOR_AUTH_PRESENT: &str = "aws:MultiFactorAuthPresent";
+
+/// `aws:MultiFactorAuthAge`: The number of seconds since the principal was authenticated using MFA.
+pub const MULTI_FACTOR_AUTH_AGE: &str = "aws:MultiFactorAuthAge";
+
+/// `aws:RequestedRegion`: The region the request was made to.
+pub const REQUESTED_REGION: &str = "aws:RequestedRegion";
+
+/// `aws:ViaAWSService`: Whether the request was made by an AWS service on behalf of the principal.
+pub const VIA_AWS_SERVICE: &str = "aws:ViaAWSService";
+
+/// `aws:SourceIp`: The IP address of the client making the request.
+pub const SOURCE_IP: &str = "aws:SourceIp";
+
+/// `aws:SecureTransport`: Whether the request was sent over TLS.
+pub const SECURE_TRANSPORT: &str = "aws:SecureTransport";
+
+/// `aws:CurrentTime`: The time the request was received.
+pub const CURRENT_TIME: &str = "aws:CurrentTime";
+
+/// `aws:EpochTime`: The time the request was received, in seconds since the Unix epoch.
+pub const EPOCH_TIME: &str = "aws:EpochTime";
+
+/// `aws:UserAgent`: The `User-Agent` header sent by the client.
+pub const USER_AGENT: &str = "aws:UserAgent";
+
+/// `aws:TokenIssueTime`: The time the temporary credentials used to sign the request were issued.
+pub const TOKEN_ISSUE_TIME: &str = "aws:TokenIssueTime";
+
+/// `sts:RoleSessionName`: The session name of the role session making the request.
+pub const ROLE_SESSION_NAME: &str = "sts:RoleSessionName";
+
+/// `scratchstack:ServiceSpecificCredential`: The service the service-specific credential used to sign the request is
+/// restricted to. Only present for requests signed with service-specific credentials.
+pub const SERVICE_SPECIFIC_CREDENTIAL: &str = "scratchstack:ServiceSpecificCredential";
+
+/// Returns the session data key for the principal tag with the given name (`aws:PrincipalTag/<tag_key>`).
+pub fn principal_tag_key(tag_key: &str) -> String {
+    format!("{PRINCIPAL_TAG_PREFIX}{tag_key}")
+}
+
+/// Typed accessors for [SessionData] values.
+///
+/// Getters return `None` if the key is not present or holds a value of a different type.
+pub trait SessionDataExt {
+    /// Returns the string value for the given key.
+    fn get_string(&self, key: &str) -> Option<&str>;
+
+    /// Returns the boolean value for the given key.
+    fn get_bool(&self, key: &str) -> Option<bool>;
+
+    /// Returns the integer value for the given key.
+    fn get_integer(&self, key: &str) -> Option<i64>;
+
+    /// Returns the IP address value for the given key.
+    fn get_ip_addr(&self, key: &str) -> Option<IpAddr>;
+
+    /// Returns the timestamp value for the given key.
+    fn get_timestamp(&self, key: &str) -> Option<DateTime<Utc>>;
+
+    /// Sets the given key to a string value.
+    fn set_string(&mut self, key: &str, value: impl Into<String>);
+
+    /// Sets the given key to a boolean value.
+    fn set_bool(&mut self, key: &str, value: bool);
+
+    /// Sets the given key to an integer value.
+    fn set_integer(&mut self, key: &str, value: i64);
+
+    /// Sets the given key to an IP address value.
+    fn set_ip_addr(&mut self, key: &str, value: IpAddr);
+
+    /// Sets the given key to a timestamp value.
+    fn set_timestamp(&mut self, key: &str, value: DateTime<Utc>);
+
+    /// Returns the value of `aws:username`.
+    #[inline]
+    fn username(&self) -> Option<&str> {
+        self.get_string(USERNAME)
+    }
+
+    /// Returns the value of `aws:userid`.
+    #[inline]
+    fn user_id(&self) -> Option<&str> {
+        self.get_string(USER_ID)
+    }
+
+    /// Returns the value of `aws:PrincipalArn`.
+    #[inline]
+    fn principal_arn(&self) -> Option<&str> {
+        self.get_string(PRINCIPAL_ARN)
+    }
+
+    /// Returns the value of `aws:PrincipalAccount`.
+    #[inline]
+    fn principal_account(&self) -> Option<&str> {
+        self.get_string(PRINCIPAL_ACCOUNT)
+    }
+
+    /// Returns the value of `aws:RequestedRegion`.
+    #[inline]
+    fn requested_region(&self) -> Option<&str> {
+        self.get_string(REQUESTED_REGION)
+    }
+
+    /// Returns the value of the principal tag with the given name.
+    #[inline]
+    fn principal_tag(&self, tag_key: &str) -> Option<&str> {
+        self.get_string(&principal_tag_key(tag_key))
+    }
+
+    /// Returns the value of `scratchstack:ServiceSpecificCredential`.
+    #[inline]
+    fn service_specific_credential(&self) -> Option<&str> {
+        self.get_string(SERVICE_SPECIFIC_CREDENTIAL)
+    }
+}
+
+impl SessionDataExt for SessionData {
+    fn get_string(&self, key: &str) -> Option<&str> {
+        match self.get(key) {
+            Some(SessionValue::String(s)) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key) {
+            Some(SessionValue::Bool(b)) => Some(*b),
+            _ => None,
+        }
+    }
+
+    fn get_integer(&self, key: &str) -> Option<i64> {
+        match self.get(key) {
+            Some(SessionValue::Integer(i)) => Some(*i),
+            _ => None,
+        }
+    }
+
+    fn get_ip_addr(&self, key: &str) -> Option<IpAddr> {
+        match self.get(key) {
+            Some(SessionValue::IpAddr(ip)) => Some(*ip),
+            _ => None,
+        }
+    }
+
+    fn get_timestamp(&self, key: &str) -> Option<DateTime<Utc>> {
+        match self.get(key) {
+            Some(SessionValue::Timestamp(t)) => Some(*t),
+            _ => None,
+        }
+    }
+
+    fn set_string(&mut self, key: &str, value: impl Into<String>) {
+        self.insert(key, SessionValue::String(value.into()));
+    }
+
+    fn set_bool(&mut self, key: &str, value: bool) {
+        self.insert(key, SessionValue::Bool(value));
+    }
+
+    fn set_integer(&mut self, key: &str, value: i64) {
+        self.insert(key, SessionValue::Integer(value));
+    }
+
+    fn set_ip_addr(&mut self, key: &str, value: IpAddr) {
+        self.insert(key, SessionValue::IpAddr(value));
+    }
+
+    fn set_timestamp(&mut self, key: &str, value: DateTime<Utc>) {
+        self.insert(key, SessionValue::Timestamp(value));
+    }
+}
+
+/// The standard session data for a request made by an IAM user.
+#[derive(Builder, Clone, Debug)]
+#[builder(setter(into))]
+pub struct UserSessionData {
+    /// The friendly name of the user (`aws:username`).
+    user_name: String,
+
+    /// The unique id of the user (`aws:userid`).
+    user_id: String,
+
+    /// The account id the user belongs to (`aws:PrincipalAccount`).
+    account_id: String,
+
+    /// The ARN of the user (`aws:PrincipalArn`).
+    user_arn: String,
+
+    /// The region the request was made to (`aws:RequestedRegion`).
+    requested_region: String,
+
+    /// Whether the credentials were obtained using MFA (`aws:MultiFactorAuthPresent`).
+    #[builder(default)]
+    multi_factor_auth_present: bool,
+}
+
+impl UserSessionData {
+    /// Create a new [UserSessionDataBuilder] for constructing a [UserSessionData].
+    #[inline]
+    pub fn builder() -> UserSessionDataBuilder {
+        UserSessionDataBuilder::default()
+    }
+
+    /// Write the session keys for this user into the given [SessionData].
+    pub fn populate(&self, session_data: &mut SessionData) {
+        session_data.set_string(USERNAME, &self.user_name);
+        session_data.set_string(USER_ID, &self.user_id);
+        session_data.set_string(PRINCIPAL_TYPE, "User");
+        session_data.set_bool(MULTI_FACTOR_AUTH_PRESENT, self.multi_factor_auth_present);
+        session_data.set_string(PRINCIPAL_ACCOUNT, &self.account_id);
+        session_data.set_string(PRINCIPAL_ARN, &self.user_arn);
+        session_data.set_bool(PRINCIPAL_IS_AWS_SERVICE, false);
+        session_data.set_string(REQUESTED_REGION, &self.requested_region);
+        session_data.set_bool(VIA_AWS_SERVICE, false);
+    }
+}
+
+impl From<&UserSessionData> for SessionData {
+    fn from(user: &UserSessionData) -> Self {
+        let mut session_data = SessionData::new();
+        user.populate(&mut session_data);
+        session_data
+    }
+}
+
+/// The standard session data for a request made with the temporary credentials of an assumed role.
+#[derive(Builder, Clone, Debug)]
+#[builder(setter(into))]
+pub struct AssumedRoleSessionData {
+    /// The unique id of the role (e.g. `AROAEXAMPLE`); `aws:userid` is this followed by `:` and the session name.
+    role_id: String,
+
+    /// The name of the role session (`sts:RoleSessionName`).
+    role_session_name: String,
+
+    /// The account id the role belongs to (`aws:PrincipalAccount`).
+    account_id: String,
+
+    /// The ARN of the role (`aws:PrincipalArn`).
+    role_arn: String,
+
+    /// The region the request was made to (`aws:RequestedRegion`).
+    requested_region: String,
+
+    /// The time the credentials were issued (`aws:TokenIssueTime`).
+    token_issue_time: DateTime<Utc>,
+
+    /// Whether the credentials were obtained using MFA (`aws:MultiFactorAuthPresent`).
+    #[builder(default)]
+    multi_factor_auth_present: bool,
+
+    /// The number of seconds since the principal was authenticated using MFA (`aws:MultiFactorAuthAge`), if they were.
+    #[builder(default, setter(into, strip_option))]
+    multi_factor_auth_age: Option<i64>,
+}
+
+impl AssumedRoleSessionData {
+    /// Create a new [AssumedRoleSessionDataBuilder] for constructing an [AssumedRoleSessionData].
+    #[inline]
+    pub fn builder() -> AssumedRoleSessionDataBuilder {
+        AssumedRoleSessionDataBuilder::default()
+    }
+
+    /// Write the session keys for this role session into the given [SessionData].
+    pub fn populate(&self, session_data: &mut SessionData) {
+        session_data.set_string(USER_ID, format!("{}:{}", self.role_id, self.role_session_name));
+        session_data.set_string(ROLE_SESSION_NAME, &self.role_session_name);
+        session_data.set_string(PRINCIPAL_TYPE, "AssumedRole");
+        session_data.set_bool(MULTI_FACTOR_AUTH_PRESENT, self.multi_factor_auth_present);
+        session_data.set_string(PRINCIPAL_ACCOUNT, &self.account_id);
+        session_data.set_string(PRINCIPAL_ARN, &self.role_arn);
+        session_data.set_bool(PRINCIPAL_IS_AWS_SERVICE, false);
+        session_data.set_string(REQUESTED_REGION, &self.requested_region);
+        session_data.set_timestamp(TOKEN_ISSUE_TIME, self.token_issue_time);
+        session_data.set_bool(VIA_AWS_SERVICE, false);
+        if let Some(multi_factor_auth_age) = self.multi_factor_auth_age {
+            session_data.set_integer(MULTI_FACTOR_AUTH_AGE, multi_factor_auth_age);
+        }
+    }
+}
+
+impl From<&AssumedRoleSessionData> for SessionData {
+    fn from(role: &AssumedRoleSessionData) -> Self {
+        let mut session_data = SessionData::new();
+        role.populate(&mut session_data);
+        session_data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        chrono::TimeZone,
+        pretty_assertions::assert_eq,
+        scratchstack_aws_principal::SessionData,
+        std::net::{IpAddr, Ipv4Addr},
+    };
+
+    #[test]
+    fn test_typed_accessors() {
+        let mut sd = SessionData::new();
+        sd.set_string(USERNAME, "alice");
+        sd.set_bool(SECURE_TRANSPORT, true);
+        sd.set_ip_addr(SOURCE_IP, IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+        sd.set_string(&principal_tag_key("team"), "blue");
+
+        assert_eq!(sd.username(), Some("alice"));
+        assert_eq!(sd.get_bool(SECURE_TRANSPORT), Some(true));
+        assert_eq!(sd.get_ip_addr(SOURCE_IP), Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))));
+        assert_eq!(sd.principal_tag("team"), Some("blue"));
+
+        // Wrong type or missing key.
+        assert_eq!(sd.get_bool(USERNAME), None);
+        assert_eq!(sd.user_id(), None);
+    }
+
+    #[test]
+    fn test_user_session_data() {
+        let user = UserSessionData::builder()
+            .user_name("alice")
+            .user_id("AIDAEXAMPLE")
+            .account_id("123456789012")
+            .user_arn("arn:aws:iam::123456789012:user/alice")
+            .requested_region("us-east-1")
+            .build()
+            .unwrap();
+        let sd = SessionData::from(&user);
+        assert_eq!(sd.username(), Some("alice"));
+        assert_eq!(sd.user_id(), Some("AIDAEXAMPLE"));
+        assert_eq!(sd.get_string(PRINCIPAL_TYPE), Some("User"));
+        assert_eq!(sd.principal_arn(), Some("arn:aws:iam::123456789012:user/alice"));
+        assert_eq!(sd.get_bool(MULTI_FACTOR_AUTH_PRESENT), Some(false));
+        assert_eq!(sd.requested_region(), Some("us-east-1"));
+        assert_eq!(sd.service_specific_credential(), None);
+    }
+
+    #[test]
+    fn test_assumed_role_session_data() {
+        let issued = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+        let role = AssumedRoleSessionData::builder()
+            .role_id("AROAEXAMPLE")
+            .role_session_name("alice")
+            .account_id("123456789012")
+            .role_arn("arn:aws:iam::123456789012:role/Admin")
+            .requested_region("us-east-1")
+            .token_issue_time(issued)
+            .build()
+            .unwrap();
+        let sd = SessionData::from(&role);
+        assert_eq!(sd.user_id(), Some("AROAEXAMPLE:alice"));
+        assert_eq!(sd.get_string(ROLE_SESSION_NAME), Some("alice"));
+        assert_eq!(sd.get_string(PRINCIPAL_TYPE), Some("AssumedRole"));
+        assert_eq!(sd.principal_arn(), Some("arn:aws:iam::123456789012:role/Admin"));
+        assert_eq!(sd.get_timestamp(TOKEN_ISSUE_TIME), Some(issued));
+        assert_eq!(sd.username(), None);
+        assert_eq!(sd.get_bool(MULTI_FACTOR_AUTH_PRESENT), Some(false));
+        assert_eq!(sd.get_integer(MULTI_FACTOR_AUTH_AGE), None);
+
+        let role = AssumedRoleSessionData::builder()
+            .role_id("AROAEXAMPLE")
+            .role_session_name("alice")
+            .account_id("123456789012")
+            .role_arn("arn:aws:iam::123456789012:role/Admin")
+            .requested_region("us-east-1")
+            .token_issue_time(issued)
+            .multi_factor_auth_present(true)
+            .multi_factor_auth_age(300)
+            .build()
+            .unwrap();
+        let sd = SessionData::from(&role);
+        assert_eq!(sd.get_bool(MULTI_FACTOR_AUTH_PRESENT), Some(true));
+        assert_eq!(sd.get_integer(MULTI_FACTOR_AUTH_AGE), Some(300));
+    }
+}
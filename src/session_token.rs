@@ -0,0 +1,348 @@
+#![warn(clippy::all)]
+
+use {
+    crate::{
+        keystream::{apply_keystream, derive_key, HmacSha256},
+        session_keys::{SessionDataExt, MULTI_FACTOR_AUTH_AGE, MULTI_FACTOR_AUTH_PRESENT},
+        Clock, SystemClock,
+    },
+    chrono::{DateTime, TimeZone, Utc},
+    hmac::Mac,
+    rand::{thread_rng, RngCore},
+    scratchstack_aws_principal::SessionData,
+    scratchstack_aws_signature::SignatureError,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::BTreeMap,
+        fmt::{Debug, Formatter, Result as FmtResult},
+        sync::Arc,
+    },
+    tower::BoxError,
+};
+
+/// The version of the token format.
+const TOKEN_VERSION: u8 = 1;
+
+/// The length of the nonce used to encrypt the claims.
+const NONCE_LEN: usize = 16;
+
+/// The length of the HMAC-SHA256 tag ending each token.
+const TAG_LEN: usize = 32;
+
+const MSG_INVALID_TOKEN: &str = "The security token included in the request is invalid.";
+const MSG_EXPIRED_TOKEN: &str = "The security token included in the request is expired";
+
+/// The claims carried by a self-contained session token.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SessionTokenClaims {
+    #[serde(rename = "k")]
+    access_key: String,
+
+    #[serde(rename = "p")]
+    principal_arn: String,
+
+    #[serde(rename = "e")]
+    expiration: i64,
+
+    #[serde(rename = "t", default, skip_serializing_if = "BTreeMap::is_empty")]
+    session_tags: BTreeMap<String, String>,
+
+    #[serde(rename = "m", default, skip_serializing_if = "Option::is_none")]
+    mfa_authenticated_at: Option<i64>,
+}
+
+impl SessionTokenClaims {
+    /// Create a new set of [SessionTokenClaims] for the temporary access key, acting as the principal, that expire at
+    /// the given time. Times are kept to the second.
+    pub fn new(access_key: impl Into<String>, principal_arn: impl Into<String>, expiration: DateTime<Utc>) -> Self {
+        Self {
+            access_key: access_key.into(),
+            principal_arn: principal_arn.into(),
+            expiration: expiration.timestamp(),
+            session_tags: BTreeMap::new(),
+            mfa_authenticated_at: None,
+        }
+    }
+
+    /// Add a session tag, returning the updated claims.
+    pub fn with_session_tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.session_tags.insert(key.into(), value.into());
+        self
+    }
+
+    /// Record that the principal was authenticated using MFA at the given time, returning the updated claims.
+    pub fn with_mfa_authentication(mut self, authenticated_at: DateTime<Utc>) -> Self {
+        self.mfa_authenticated_at = Some(authenticated_at.timestamp());
+        self
+    }
+
+    /// Retreive the access key the token was issued with.
+    #[inline]
+    pub fn access_key(&self) -> &str {
+        &self.access_key
+    }
+
+    /// Retreive the ARN of the principal the credentials act as, e.g.
+    /// `arn:aws:sts::123456789012:assumed-role/Admin/alice`.
+    #[inline]
+    pub fn principal_arn(&self) -> &str {
+        &self.principal_arn
+    }
+
+    /// Retreive the time the token expires.
+    pub fn expiration(&self) -> DateTime<Utc> {
+        Utc.timestamp_opt(self.expiration, 0).single().expect("claims are only created from valid times")
+    }
+
+    /// Retreive the session tags, which are available to policies as `aws:PrincipalTag/<key>`.
+    #[inline]
+    pub fn session_tags(&self) -> &BTreeMap<String, String> {
+        &self.session_tags
+    }
+
+    /// Retreive the time the principal was authenticated using MFA, if they were.
+    pub fn mfa_authenticated_at(&self) -> Option<DateTime<Utc>> {
+        self.mfa_authenticated_at.and_then(|t| Utc.timestamp_opt(t, 0).single())
+    }
+
+    /// Write `aws:MultiFactorAuthPresent` and, if the principal was authenticated using MFA, `aws:MultiFactorAuthAge`
+    /// as of `now` into the given [SessionData].
+    pub fn populate_multi_factor_auth(&self, session_data: &mut SessionData, now: DateTime<Utc>) {
+        session_data.set_bool(MULTI_FACTOR_AUTH_PRESENT, self.mfa_authenticated_at.is_some());
+        if let Some(mfa_authenticated_at) = self.mfa_authenticated_at {
+            session_data.set_integer(MULTI_FACTOR_AUTH_AGE, (now.timestamp() - mfa_authenticated_at).max(0));
+        }
+    }
+}
+
+/// Mints and verifies self-contained session tokens, so temporary credentials can be validated without looking them up.
+///
+/// A token carries its [SessionTokenClaims] encrypted and signed with keys derived from a service key, along with the
+/// id of that key. Tokens are minted with the current key; keys added with
+/// [with_verification_key][Self::with_verification_key] are only used to verify tokens, so the service key can be
+/// rotated by minting with a new key while still accepting tokens minted with the old one until they expire. The claims
+/// are encrypted with an HMAC-SHA256 counter mode keystream from a random nonce, so clients can't read them, and the
+/// token is encoded with URL-safe base64.
+///
+/// Tokens minted with an unknown key, that have been modified, or that were issued for a different access key are
+/// rejected with `InvalidClientTokenId`; expired tokens are rejected with `ExpiredToken`.
+///
+/// ```
+/// use {
+///     chrono::{Duration, Utc},
+///     scratchstack_http_framework::session_token::{SessionTokenClaims, SessionTokenCodec},
+/// };
+///
+/// let codec = SessionTokenCodec::new("2022-10", b"current key").with_verification_key("2022-09", b"previous key");
+/// let claims = SessionTokenClaims::new(
+///     "ASIAEXAMPLE",
+///     "arn:aws:sts::123456789012:assumed-role/Admin/alice",
+///     Utc::now() + Duration::hours(1),
+/// )
+/// .with_session_tag("team", "blue");
+///
+/// let token = codec.mint(&claims).unwrap();
+/// assert_eq!(codec.verify(&token, "ASIAEXAMPLE").unwrap(), claims);
+/// ```
+#[derive(Clone)]
+pub struct SessionTokenCodec {
+    current_key_id: String,
+    keys: BTreeMap<String, TokenKeys>,
+    clock: Arc<dyn Clock>,
+}
+
+/// The keys derived from a service key.
+#[derive(Clone)]
+struct TokenKeys {
+    signing_key: [u8; 32],
+    encryption_key: [u8; 32],
+}
+
+impl TokenKeys {
+    fn new(key: &[u8]) -> Self {
+        let base_key = derive_key(key, b"scratchstack-session-token");
+        Self {
+            signing_key: derive_key(&base_key, b"signing"),
+            encryption_key: derive_key(&base_key, b"encryption"),
+        }
+    }
+
+    fn mac(&self, data: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.signing_key).expect("HMAC accepts keys of any length");
+        mac.update(data);
+        mac
+    }
+}
+
+impl SessionTokenCodec {
+    /// Create a new [SessionTokenCodec] minting tokens with the service key `key`, identified by `key_id`. Key ids are
+    /// at most 255 bytes long.
+    pub fn new(key_id: &str, key: &[u8]) -> Self {
+        assert!(key_id.len() <= u8::MAX as usize, "key ids are at most 255 bytes");
+        Self {
+            current_key_id: key_id.to_string(),
+            keys: BTreeMap::from([(key_id.to_string(), TokenKeys::new(key))]),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Also accept tokens minted with the service key `key`, identified by `key_id`, returning the updated codec.
+    pub fn with_verification_key(mut self, key_id: &str, key: &[u8]) -> Self {
+        if key_id != self.current_key_id {
+            self.keys.insert(key_id.to_string(), TokenKeys::new(key));
+        }
+        self
+    }
+
+    /// Use the given [Clock] to check the expiry of tokens, returning the updated codec.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Retreive the id of the key new tokens are minted with.
+    #[inline]
+    pub fn current_key_id(&self) -> &str {
+        &self.current_key_id
+    }
+
+    /// Mint a session token carrying the claims.
+    pub fn mint(&self, claims: &SessionTokenClaims) -> Result<String, BoxError> {
+        let keys = &self.keys[&self.current_key_id];
+        let mut payload = serde_json::to_vec(claims)?;
+        let mut nonce = [0; NONCE_LEN];
+        thread_rng().fill_bytes(&mut nonce);
+        apply_keystream(&keys.encryption_key, &nonce, &mut payload);
+
+        let mut token = Vec::with_capacity(2 + self.current_key_id.len() + NONCE_LEN + payload.len() + TAG_LEN);
+        token.push(TOKEN_VERSION);
+        token.push(self.current_key_id.len() as u8);
+        token.extend_from_slice(self.current_key_id.as_bytes());
+        token.extend_from_slice(&nonce);
+        token.extend_from_slice(&payload);
+        let tag = keys.mac(&token).finalize().into_bytes();
+        token.extend_from_slice(&tag);
+
+        Ok(base64::encode_config(token, base64::URL_SAFE_NO_PAD))
+    }
+
+    /// Verify a session token presented with `access_key`, returning its claims.
+    pub fn verify(&self, token: &str, access_key: &str) -> Result<SessionTokenClaims, SignatureError> {
+        let invalid = || SignatureError::InvalidClientTokenId(MSG_INVALID_TOKEN.to_string());
+
+        let token = base64::decode_config(token, base64::URL_SAFE_NO_PAD).map_err(|_| invalid())?;
+        if token.len() < 2 || token[0] != TOKEN_VERSION {
+            return Err(invalid());
+        }
+        let key_id_end = 2 + token[1] as usize;
+        if token.len() < key_id_end + NONCE_LEN + TAG_LEN {
+            return Err(invalid());
+        }
+        let keys = std::str::from_utf8(&token[2..key_id_end])
+            .ok()
+            .and_then(|key_id| self.keys.get(key_id))
+            .ok_or_else(invalid)?;
+
+        let (signed, tag) = token.split_at(token.len() - TAG_LEN);
+        keys.mac(signed).verify_slice(tag).map_err(|_| invalid())?;
+
+        let (nonce, payload) = signed[key_id_end..].split_at(NONCE_LEN);
+        let mut payload = payload.to_vec();
+        apply_keystream(&keys.encryption_key, nonce, &mut payload);
+        let claims: SessionTokenClaims = serde_json::from_slice(&payload).map_err(|_| invalid())?;
+
+        if claims.access_key != access_key {
+            return Err(invalid());
+        }
+        if claims.expiration <= self.clock.now().timestamp() {
+            return Err(SignatureError::ExpiredToken(MSG_EXPIRED_TOKEN.to_string()));
+        }
+
+        Ok(claims)
+    }
+}
+
+impl Debug for SessionTokenCodec {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("SessionTokenCodec")
+            .field("current_key_id", &self.current_key_id)
+            .field("key_ids", &self.keys.keys().collect::<Vec<_>>())
+            .field("clock", &self.clock)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{SessionTokenClaims, SessionTokenCodec},
+        crate::{
+            session_keys::{SessionDataExt, MULTI_FACTOR_AUTH_AGE, MULTI_FACTOR_AUTH_PRESENT},
+            FixedClock,
+        },
+        chrono::{Duration, TimeZone, Utc},
+        pretty_assertions::assert_eq,
+        scratchstack_aws_principal::SessionData,
+        scratchstack_aws_signature::SignatureError,
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_session_token_codec() {
+        let now = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+        let clock = |time| Arc::new(FixedClock::new(time));
+        let claims = SessionTokenClaims::new(
+            "ASIAEXAMPLE",
+            "arn:aws:sts::123456789012:assumed-role/Admin/alice",
+            now + Duration::hours(1),
+        )
+        .with_session_tag("team", "blue");
+        assert_eq!(claims.expiration(), now + Duration::hours(1));
+
+        let old = SessionTokenCodec::new("2022-09", b"old key").with_clock(clock(now));
+        let token = old.mint(&claims).unwrap();
+        let verified = old.verify(&token, "ASIAEXAMPLE").unwrap();
+        assert_eq!(verified, claims);
+        assert_eq!(verified.session_tags().get("team").map(String::as_str), Some("blue"));
+
+        // The claims are not readable from the token.
+        let decoded = base64::decode_config(&token, base64::URL_SAFE_NO_PAD).unwrap();
+        assert!(!String::from_utf8_lossy(&decoded).contains("alice"));
+
+        // After rotation, tokens minted with the old key are accepted until they expire.
+        let new = SessionTokenCodec::new("2022-10", b"new key").with_clock(clock(now));
+        assert!(matches!(new.verify(&token, "ASIAEXAMPLE"), Err(SignatureError::InvalidClientTokenId(_))));
+        let new = new.with_verification_key("2022-09", b"old key");
+        assert_eq!(new.verify(&token, "ASIAEXAMPLE").unwrap(), claims);
+        let new_token = new.mint(&claims).unwrap();
+        assert!(old.verify(&new_token, "ASIAEXAMPLE").is_err());
+        assert_eq!(new.verify(&new_token, "ASIAEXAMPLE").unwrap(), claims);
+
+        // Tokens are bound to their access key, can't be modified, and expire.
+        assert!(matches!(new.verify(&token, "ASIAOTHER"), Err(SignatureError::InvalidClientTokenId(_))));
+        let mut tampered = token.clone().into_bytes();
+        let last = tampered.len() - 5;
+        tampered[last] = if tampered[last] == b'A' {
+            b'B'
+        } else {
+            b'A'
+        };
+        assert!(new.verify(&String::from_utf8(tampered).unwrap(), "ASIAEXAMPLE").is_err());
+        assert!(new.verify("not a token", "ASIAEXAMPLE").is_err());
+        let expired = new.clone().with_clock(clock(now + Duration::hours(1)));
+        assert!(matches!(expired.verify(&token, "ASIAEXAMPLE"), Err(SignatureError::ExpiredToken(_))));
+
+        // MFA authentication is carried through the token.
+        let mut session_data = SessionData::new();
+        verified.populate_multi_factor_auth(&mut session_data, now);
+        assert_eq!(session_data.get_bool(MULTI_FACTOR_AUTH_PRESENT), Some(false));
+        assert_eq!(session_data.get_integer(MULTI_FACTOR_AUTH_AGE), None);
+
+        let mfa_claims = claims.with_mfa_authentication(now - Duration::minutes(5));
+        let verified = new.verify(&new.mint(&mfa_claims).unwrap(), "ASIAEXAMPLE").unwrap();
+        assert_eq!(verified.mfa_authenticated_at(), Some(now - Duration::minutes(5)));
+        verified.populate_multi_factor_auth(&mut session_data, now);
+        assert_eq!(session_data.get_bool(MULTI_FACTOR_AUTH_PRESENT), Some(true));
+        assert_eq!(session_data.get_integer(MULTI_FACTOR_AUTH_AGE), Some(300));
+    }
+}
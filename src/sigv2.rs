@@ -0,0 +1,313 @@
+use {
+    crate::{
+        canonical::{percent_decode, query_params, uri_encode},
+        VerifierError,
+    },
+    bytes::Bytes,
+    chrono::{DateTime, Duration, Utc},
+    hmac::{Hmac, Mac},
+    http::{header::HeaderMap, request::Parts, uri::Uri},
+    scratchstack_aws_principal::{Principal, SessionData},
+    sha1::Sha1,
+    sha2::Sha256,
+    std::fmt::{Debug, Formatter, Result as FmtResult},
+    tower::{util::BoxCloneService, BoxError, Service, ServiceExt},
+};
+
+/// The `SignatureVersion` parameter value identifying a SigV2 signature.
+pub const SIGV2_SIGNATURE_VERSION: &str = "2";
+
+/// The content type of requests whose parameters are sent in the body, as the AWS query protocol does for `POST`.
+const FORM_URLENCODED: &str = "application/x-www-form-urlencoded";
+
+/// A request for the secret key used to verify SigV2 signatures made with an access key.
+///
+/// SigV2 signatures are computed with the secret key itself rather than with a key derived from it, so they cannot be
+/// verified with the SigV4 signing key returned by a
+/// [GetSigningKeyRequest][scratchstack_aws_signature::GetSigningKeyRequest] provider.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GetSecretKeyRequest {
+    access_key: String,
+    session_token: Option<String>,
+}
+
+impl GetSecretKeyRequest {
+    /// Create a new [GetSecretKeyRequest].
+    pub fn new(access_key: impl Into<String>, session_token: Option<String>) -> Self {
+        Self {
+            access_key: access_key.into(),
+            session_token,
+        }
+    }
+
+    /// Returns the access key id used to sign the request.
+    #[inline]
+    pub fn access_key(&self) -> &str {
+        &self.access_key
+    }
+
+    /// Returns the session token supplied with the request, if any.
+    #[inline]
+    pub fn session_token(&self) -> Option<&str> {
+        self.session_token.as_deref()
+    }
+}
+
+/// The response to a [GetSecretKeyRequest].
+#[derive(Clone)]
+pub struct GetSecretKeyResponse {
+    principal: Principal,
+    session_data: SessionData,
+    secret_key: String,
+}
+
+impl GetSecretKeyResponse {
+    /// Create a new [GetSecretKeyResponse].
+    pub fn new(principal: Principal, session_data: SessionData, secret_key: impl Into<String>) -> Self {
+        Self {
+            principal,
+            session_data,
+            secret_key: secret_key.into(),
+        }
+    }
+
+    /// Returns the principal associated with the access key.
+    #[inline]
+    pub fn principal(&self) -> &Principal {
+        &self.principal
+    }
+
+    /// Returns the session data associated with the access key.
+    #[inline]
+    pub fn session_data(&self) -> &SessionData {
+        &self.session_data
+    }
+
+    /// Returns the secret key associated with the access key.
+    #[inline]
+    pub fn secret_key(&self) -> &str {
+        &self.secret_key
+    }
+}
+
+impl Debug for GetSecretKeyResponse {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("GetSecretKeyResponse")
+            .field("principal", &self.principal)
+            .field("session_data", &self.session_data)
+            .field("secret_key", &"<redacted>")
+            .finish()
+    }
+}
+
+/// A type-erased SigV2 secret key provider.
+pub type BoxGetSecretKey = BoxCloneService<GetSecretKeyRequest, GetSecretKeyResponse, BoxError>;
+
+/// Indicates whether a request without a SigV4 signature may carry a SigV2 signature: either the query string
+/// specifies `SignatureVersion=2`, or the parameters are sent as a form in the body.
+pub(crate) fn is_sigv2_request(headers: &HeaderMap, uri: &Uri) -> bool {
+    let in_query = uri
+        .query()
+        .map(|q| query_params(q).iter().any(|(k, v)| k == "SignatureVersion" && v == SIGV2_SIGNATURE_VERSION))
+        .unwrap_or(false);
+    in_query || is_form(headers)
+}
+
+/// Returns the request parameters from the query string and, for form requests, the body.
+fn sigv2_params(parts: &Parts, body: &[u8]) -> Vec<(String, String)> {
+    let mut params = parts.uri.query().map(query_params).unwrap_or_default();
+    if is_form(&parts.headers) {
+        let body = String::from_utf8_lossy(body);
+        params.extend(body.split('&').filter(|p| !p.is_empty()).map(|p| {
+            let (k, v) = p.split_once('=').unwrap_or((p, ""));
+            (percent_decode(&k.replace('+', " ")), percent_decode(&v.replace('+', " ")))
+        }));
+    }
+    params
+}
+
+fn is_form(headers: &HeaderMap) -> bool {
+    headers
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.split(';').next().unwrap_or("").trim().eq_ignore_ascii_case(FORM_URLENCODED))
+        .unwrap_or(false)
+}
+
+/// Create the SigV2 string to sign: the method, host, path, and the sorted, URI-encoded parameters other than
+/// `Signature`, separated by newlines.
+pub(crate) fn sigv2_string_to_sign(method: &str, host: &str, path: &str, params: &[(String, String)]) -> String {
+    let mut params: Vec<&(String, String)> = params.iter().filter(|(k, _)| k != "Signature").collect();
+    params.sort();
+    let query = params
+        .iter()
+        .map(|(k, v)| format!("{}={}", uri_encode(k.as_bytes(), true), uri_encode(v.as_bytes(), true)))
+        .collect::<Vec<_>>()
+        .join("&");
+    let path = if path.is_empty() {
+        "/"
+    } else {
+        path
+    };
+
+    format!("{method}\n{}\n{path}\n{query}", host.to_ascii_lowercase())
+}
+
+/// Validate a SigV2-signed request.
+///
+/// The caller is responsible for having determined that the request may be SigV2-signed and for buffering the body.
+pub(crate) async fn sigv2_validate_request(
+    parts: Parts,
+    body: Bytes,
+    get_secret_key: &mut BoxGetSecretKey,
+    server_timestamp: DateTime<Utc>,
+    max_clock_skew: Duration,
+) -> Result<(Parts, Bytes, GetSecretKeyResponse), BoxError> {
+    let params = sigv2_params(&parts, &body);
+    let get = |name: &str| params.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+
+    if get("SignatureVersion") != Some(SIGV2_SIGNATURE_VERSION) {
+        return Err(VerifierError::IncompleteSignature("Missing SignatureVersion").into());
+    }
+
+    let access_key = get("AWSAccessKeyId").ok_or(VerifierError::IncompleteSignature("Missing AWSAccessKeyId"))?;
+    let signature_method =
+        get("SignatureMethod").ok_or(VerifierError::IncompleteSignature("Missing SignatureMethod"))?;
+    let signature = get("Signature").ok_or(VerifierError::IncompleteSignature("Missing Signature"))?;
+    let signature = base64::decode(signature).map_err(|_| VerifierError::SignatureDoesNotMatch)?;
+
+    // SigV2 requests carry either the time they were signed or the time they expire.
+    let parse_time = |value: &str| {
+        DateTime::parse_from_rfc3339(value)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|_| VerifierError::IncompleteSignature("Malformed Timestamp or Expires"))
+    };
+    let within_window = match (get("Expires"), get("Timestamp")) {
+        (Some(expires), _) => server_timestamp <= parse_time(expires)?,
+        (None, Some(timestamp)) => {
+            let timestamp = parse_time(timestamp)?;
+            timestamp - max_clock_skew <= server_timestamp && server_timestamp <= timestamp + max_clock_skew
+        }
+        (None, None) => return Err(VerifierError::IncompleteSignature("Missing Timestamp or Expires").into()),
+    };
+    if !within_window {
+        return Err(VerifierError::RequestExpired.into());
+    }
+
+    let host = parts
+        .headers
+        .get("host")
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| parts.uri.host())
+        .ok_or(VerifierError::IncompleteSignature("Missing Host header"))?;
+    let string_to_sign = sigv2_string_to_sign(parts.method.as_str(), host, parts.uri.path(), &params);
+
+    let gsk_request = GetSecretKeyRequest::new(access_key, get("SecurityToken").map(str::to_string));
+    let gsk_response = get_secret_key.ready().await?.call(gsk_request).await?;
+    let secret_key = gsk_response.secret_key().as_bytes();
+
+    let verified = match signature_method {
+        "HmacSHA256" => {
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret_key).expect("HMAC accepts keys of any length");
+            mac.update(string_to_sign.as_bytes());
+            mac.verify_slice(&signature).is_ok()
+        }
+        "HmacSHA1" => {
+            let mut mac = Hmac::<Sha1>::new_from_slice(secret_key).expect("HMAC accepts keys of any length");
+            mac.update(string_to_sign.as_bytes());
+            mac.verify_slice(&signature).is_ok()
+        }
+        _ => return Err(VerifierError::IncompleteSignature("Unsupported SignatureMethod").into()),
+    };
+
+    if !verified {
+        return Err(VerifierError::SignatureDoesNotMatch.into());
+    }
+
+    Ok((parts, body, gsk_response))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{sigv2_string_to_sign, sigv2_validate_request, GetSecretKeyRequest, GetSecretKeyResponse},
+        crate::VerifierError,
+        bytes::Bytes,
+        chrono::{Duration, TimeZone, Utc},
+        hmac::{Hmac, Mac},
+        http::Request,
+        pretty_assertions::assert_eq,
+        scratchstack_aws_principal::{Principal, SessionData, User},
+        sha2::Sha256,
+        tower::{service_fn, util::BoxCloneService, BoxError},
+    };
+
+    const ACCESS_KEY: &str = "AKIDEXAMPLE";
+    const SECRET_KEY: &str = "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+
+    #[test]
+    fn test_string_to_sign() {
+        let params = vec![
+            ("Version".to_string(), "2012-11-05".to_string()),
+            ("Action".to_string(), "List Queues".to_string()),
+            ("Signature".to_string(), "ignored".to_string()),
+        ];
+        assert_eq!(
+            sigv2_string_to_sign("GET", "SQS.us-east-1.amazonaws.com", "", &params),
+            "GET\nsqs.us-east-1.amazonaws.com\n/\nAction=List%20Queues&Version=2012-11-05"
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_round_trip() {
+        let now = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+        let mut params = vec![
+            ("Action".to_string(), "ListQueues".to_string()),
+            ("AWSAccessKeyId".to_string(), ACCESS_KEY.to_string()),
+            ("SignatureMethod".to_string(), "HmacSHA256".to_string()),
+            ("SignatureVersion".to_string(), "2".to_string()),
+            ("Timestamp".to_string(), "2022-10-01T12:00:00Z".to_string()),
+        ];
+        let sts = sigv2_string_to_sign("POST", "example.com", "/", &params);
+        let mut mac = Hmac::<Sha256>::new_from_slice(SECRET_KEY.as_bytes()).unwrap();
+        mac.update(sts.as_bytes());
+        params.push(("Signature".to_string(), base64::encode(mac.finalize().into_bytes())));
+        let body = params
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v.replace('+', "%2B").replace('/', "%2F").replace('=', "%3D")))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let parts = || {
+            Request::post("/")
+                .header("host", "example.com")
+                .header("content-type", "application/x-www-form-urlencoded; charset=utf-8")
+                .body(())
+                .unwrap()
+                .into_parts()
+                .0
+        };
+        let mut gsk = BoxCloneService::new(service_fn(|req: GetSecretKeyRequest| async move {
+            assert_eq!(req.access_key(), ACCESS_KEY);
+            let principal = Principal::from(vec![User::new("aws", "123456789012", "/", "test").unwrap().into()]);
+            Ok::<_, BoxError>(GetSecretKeyResponse::new(principal, SessionData::new(), SECRET_KEY))
+        }));
+
+        let result =
+            sigv2_validate_request(parts(), Bytes::from(body.clone()), &mut gsk, now, Duration::minutes(15)).await;
+        assert!(result.is_ok());
+
+        // A stale timestamp is rejected.
+        let later = now + Duration::minutes(20);
+        let e = sigv2_validate_request(parts(), Bytes::from(body.clone()), &mut gsk, later, Duration::minutes(15))
+            .await
+            .unwrap_err();
+        assert!(matches!(e.downcast_ref::<VerifierError>(), Some(VerifierError::RequestExpired)));
+
+        // Altering a parameter breaks the signature.
+        let body = body.replace("ListQueues", "DeleteQueue");
+        let e =
+            sigv2_validate_request(parts(), Bytes::from(body), &mut gsk, now, Duration::minutes(15)).await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<VerifierError>(), Some(VerifierError::SignatureDoesNotMatch)));
+    }
+}
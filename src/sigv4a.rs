@@ -0,0 +1,514 @@
+use {
+    crate::{
+        canonical::{
+            header_or_query_param, query_params, request_timestamp, string_to_sign, within_time_window, AuthParams,
+            CanonicalRequest, UNSIGNED_PAYLOAD, X_AMZ_CONTENT_SHA256,
+        },
+        date::DateHeaderOptions,
+        VerifierError,
+    },
+    bytes::Bytes,
+    chrono::{DateTime, Duration, NaiveDate, Utc},
+    hmac::{Hmac, Mac},
+    http::request::Parts,
+    p256::ecdsa::{signature::Verifier, Signature, SigningKey, VerifyingKey},
+    scratchstack_aws_principal::{Principal, SessionData},
+    scratchstack_aws_signature::SignatureOptions,
+    sha2::{Digest, Sha256},
+    tower::{util::BoxCloneService, BoxError, Service, ServiceExt},
+};
+
+/// The algorithm identifier for SigV4A (asymmetric, multi-region) signatures.
+pub const SIGV4A_ALGORITHM: &str = "AWS4-ECDSA-P256-SHA256";
+
+/// The header (and, for presigned URLs, query parameter) listing the regions a SigV4A signature is valid for.
+const X_AMZ_REGION_SET: &str = "x-amz-region-set";
+
+/// The order of the P-256 group, minus 2. Derived private keys must not exceed this value.
+const P256_ORDER_MINUS_2: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xbc, 0xe6, 0xfa,
+    0xad, 0xa7, 0x17, 0x9e, 0x84, 0xf3, 0xb9, 0xca, 0xc2, 0xfc, 0x63, 0x25, 0x4f,
+];
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// A request for the public key used to verify SigV4A signatures made with an access key.
+///
+/// This is the SigV4A counterpart of [GetSigningKeyRequest][scratchstack_aws_signature::GetSigningKeyRequest].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GetVerificationKeyRequest {
+    access_key: String,
+    session_token: Option<String>,
+    request_date: NaiveDate,
+    region_set: Vec<String>,
+    service: String,
+}
+
+impl GetVerificationKeyRequest {
+    /// Create a new [GetVerificationKeyRequest].
+    pub fn new(
+        access_key: impl Into<String>,
+        session_token: Option<String>,
+        request_date: NaiveDate,
+        region_set: Vec<String>,
+        service: impl Into<String>,
+    ) -> Self {
+        Self {
+            access_key: access_key.into(),
+            session_token,
+            request_date,
+            region_set,
+            service: service.into(),
+        }
+    }
+
+    /// Returns the access key id used to sign the request.
+    #[inline]
+    pub fn access_key(&self) -> &str {
+        &self.access_key
+    }
+
+    /// Returns the session token supplied with the request, if any.
+    #[inline]
+    pub fn session_token(&self) -> Option<&str> {
+        self.session_token.as_deref()
+    }
+
+    /// Returns the date of the request.
+    #[inline]
+    pub fn request_date(&self) -> NaiveDate {
+        self.request_date
+    }
+
+    /// Returns the region set the signature is scoped to. This may contain wildcards, e.g. `*` or `us-*`.
+    #[inline]
+    pub fn region_set(&self) -> &[String] {
+        &self.region_set
+    }
+
+    /// Returns the service the signature is scoped to.
+    #[inline]
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+}
+
+/// The response to a [GetVerificationKeyRequest].
+#[derive(Clone, Debug)]
+pub struct GetVerificationKeyResponse {
+    principal: Principal,
+    session_data: SessionData,
+    verifying_key: VerifyingKey,
+}
+
+impl GetVerificationKeyResponse {
+    /// Create a new [GetVerificationKeyResponse].
+    pub fn new(principal: Principal, session_data: SessionData, verifying_key: VerifyingKey) -> Self {
+        Self {
+            principal,
+            session_data,
+            verifying_key,
+        }
+    }
+
+    /// Returns the principal associated with the access key.
+    #[inline]
+    pub fn principal(&self) -> &Principal {
+        &self.principal
+    }
+
+    /// Returns the session data associated with the access key.
+    #[inline]
+    pub fn session_data(&self) -> &SessionData {
+        &self.session_data
+    }
+
+    /// Returns the public key used to verify signatures made with the access key.
+    #[inline]
+    pub fn verifying_key(&self) -> &VerifyingKey {
+        &self.verifying_key
+    }
+}
+
+/// A type-erased SigV4A verification key provider.
+pub type BoxGetVerificationKey = BoxCloneService<GetVerificationKeyRequest, GetVerificationKeyResponse, BoxError>;
+
+/// Derive the SigV4A ECDSA signing (private) key for the given credentials.
+///
+/// This follows the key derivation used by the AWS SDKs: a NIST SP 800-108 counter-mode KDF using HMAC-SHA256, keyed
+/// with `AWS4A` + the secret key, retried with an incrementing counter until the result is a valid P-256 scalar.
+pub fn derive_signing_key(access_key: &str, secret_key: &str) -> SigningKey {
+    let input_key = format!("AWS4A{secret_key}");
+
+    for counter in 1u8..=254 {
+        let mut fixed_input = Vec::with_capacity(SIGV4A_ALGORITHM.len() + access_key.len() + 10);
+        fixed_input.extend_from_slice(&1u32.to_be_bytes());
+        fixed_input.extend_from_slice(SIGV4A_ALGORITHM.as_bytes());
+        fixed_input.push(0);
+        fixed_input.extend_from_slice(access_key.as_bytes());
+        fixed_input.push(counter);
+        fixed_input.extend_from_slice(&256u32.to_be_bytes());
+
+        let mut mac = HmacSha256::new_from_slice(input_key.as_bytes()).expect("HMAC accepts keys of any length");
+        mac.update(&fixed_input);
+        let mut candidate = [0u8; 32];
+        candidate.copy_from_slice(&mac.finalize().into_bytes());
+
+        // Byte-wise comparison of big-endian values is a numeric comparison.
+        if candidate <= P256_ORDER_MINUS_2 {
+            // The private key is candidate + 1, which is guaranteed to be in [1, n - 1].
+            for byte in candidate.iter_mut().rev() {
+                let (sum, overflow) = byte.overflowing_add(1);
+                *byte = sum;
+                if !overflow {
+                    break;
+                }
+            }
+
+            return SigningKey::from_bytes(&candidate).expect("derived key is a valid P-256 scalar");
+        }
+    }
+
+    // The probability of reaching this is on the order of 2^-8000.
+    panic!("Unable to derive a SigV4A key for access key {access_key}");
+}
+
+/// Derive the SigV4A ECDSA verifying (public) key for the given credentials.
+pub fn derive_verifying_key(access_key: &str, secret_key: &str) -> VerifyingKey {
+    derive_signing_key(access_key, secret_key).verifying_key()
+}
+
+/// Indicates whether the region set (which may contain wildcards) includes the given region.
+fn region_set_matches(region_set: &[String], region: &str) -> bool {
+    region_set.iter().any(|pattern| match pattern.strip_suffix('*') {
+        Some(prefix) => region.starts_with(prefix),
+        None => pattern == region,
+    })
+}
+
+/// Validate a SigV4A-signed request.
+///
+/// The caller is responsible for having determined that the request uses the [SIGV4A_ALGORITHM] and for buffering
+/// the body.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn sigv4a_validate_request(
+    parts: Parts,
+    body: Bytes,
+    auth: AuthParams,
+    region: &str,
+    service: &str,
+    get_verification_key: &mut BoxGetVerificationKey,
+    server_timestamp: DateTime<Utc>,
+    max_clock_skew: Duration,
+    options: SignatureOptions,
+) -> Result<(Parts, Bytes, GetVerificationKeyResponse), BoxError> {
+    // The SigV4A credential scope omits the region: <date>/<service>/aws4_request.
+    let scope = auth.scope_parts();
+    if scope.len() != 3 || scope[2] != "aws4_request" {
+        return Err(VerifierError::IncompleteSignature("Credential is not properly scoped").into());
+    }
+
+    if scope[1] != service {
+        return Err(VerifierError::IncompleteSignature("Credential should be scoped to the correct service").into());
+    }
+
+    if !auth.signed_headers.iter().any(|h| h == "host") {
+        return Err(VerifierError::IncompleteSignature("The host header must be signed").into());
+    }
+
+    // SigV4A requests are only ever timestamped by X-Amz-Date.
+    let x_amz_date_only = DateHeaderOptions::new().with_date_fallback(false);
+    let timestamp = request_timestamp(&parts.headers, &parts.uri, auth.presigned, x_amz_date_only)
+        .ok_or(VerifierError::IncompleteSignature("Missing or malformed X-Amz-Date"))?;
+    if timestamp.format("%Y%m%d").to_string() != scope[0] {
+        return Err(VerifierError::IncompleteSignature("Credential date does not match X-Amz-Date").into());
+    }
+
+    if !within_time_window(&parts.headers, &parts.uri, timestamp, auth.presigned, server_timestamp, max_clock_skew)? {
+        return Err(VerifierError::RequestExpired.into());
+    }
+
+    // The region set decides where the signature is valid, so it must be signed: in the query string of a presigned
+    // URL, or otherwise as a signed header.
+    let region_set = if auth.presigned {
+        parts
+            .uri
+            .query()
+            .and_then(|query| query_params(query).into_iter().find(|(k, _)| k == "X-Amz-Region-Set").map(|(_, v)| v))
+    } else {
+        if !auth.signed_headers.iter().any(|h| h == X_AMZ_REGION_SET) {
+            return Err(VerifierError::IncompleteSignature("The x-amz-region-set header must be signed").into());
+        }
+        parts.headers.get(X_AMZ_REGION_SET).and_then(|v| v.to_str().ok()).map(str::to_string)
+    };
+    let region_set: Vec<String> = region_set
+        .ok_or(VerifierError::IncompleteSignature("Missing X-Amz-Region-Set"))?
+        .split(',')
+        .map(|r| r.trim().to_string())
+        .collect();
+    if !region_set_matches(&region_set, region) {
+        return Err(VerifierError::IncompleteSignature("Credential should be scoped to a valid region set").into());
+    }
+
+    // A declared payload hash must match the body; otherwise the body could be altered without breaking the signature.
+    // The chunk signatures of STREAMING- payloads aren't verified for SigV4A, so those are rejected the same way.
+    if let Some(content_sha256) = parts.headers.get(X_AMZ_CONTENT_SHA256).and_then(|v| v.to_str().ok()) {
+        if content_sha256 != UNSIGNED_PAYLOAD
+            && !content_sha256.eq_ignore_ascii_case(&hex::encode(Sha256::digest(&body)))
+        {
+            return Err(VerifierError::ContentSha256Mismatch.into());
+        }
+    }
+
+    let session_token =
+        header_or_query_param(&parts.headers, &parts.uri, "x-amz-security-token", "X-Amz-Security-Token");
+    let gvk_request = GetVerificationKeyRequest::new(
+        auth.access_key.clone(),
+        session_token,
+        timestamp.date_naive(),
+        region_set,
+        service,
+    );
+    let gvk_response = get_verification_key.ready().await?.call(gvk_request).await?;
+
+    let canonical_request = CanonicalRequest::new(&parts, &body, &auth.signed_headers, auth.presigned, options);
+    let string_to_sign = string_to_sign(SIGV4A_ALGORITHM, &timestamp, &auth.scope, &canonical_request);
+
+    let signature = hex::decode(&auth.signature)
+        .ok()
+        .and_then(|der| Signature::from_der(&der).ok())
+        .ok_or(VerifierError::SignatureDoesNotMatch)?;
+
+    if gvk_response.verifying_key().verify(string_to_sign.as_bytes(), &signature).is_err() {
+        return Err(VerifierError::SignatureDoesNotMatch.into());
+    }
+
+    Ok((parts, body, gvk_response))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{
+            derive_signing_key, derive_verifying_key, region_set_matches, sigv4a_validate_request,
+            GetVerificationKeyRequest, GetVerificationKeyResponse, SIGV4A_ALGORITHM,
+        },
+        crate::{
+            canonical::{format_iso8601_basic, string_to_sign, AuthParams, CanonicalRequest},
+            VerifierError,
+        },
+        bytes::Bytes,
+        chrono::{Duration, TimeZone, Utc},
+        http::{request::Parts, Request},
+        p256::ecdsa::{signature::Signer, Signature},
+        scratchstack_aws_principal::{Principal, SessionData, User},
+        scratchstack_aws_signature::SignatureOptions,
+        scratchstack_errors::ServiceError,
+        tower::{service_fn, util::BoxCloneService, BoxError},
+    };
+
+    const ACCESS_KEY: &str = "AKIDEXAMPLE";
+    const SECRET_KEY: &str = "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+
+    #[test]
+    fn test_key_derivation_is_deterministic() {
+        assert_eq!(derive_verifying_key(ACCESS_KEY, SECRET_KEY), derive_verifying_key(ACCESS_KEY, SECRET_KEY));
+        assert_ne!(derive_verifying_key(ACCESS_KEY, SECRET_KEY), derive_verifying_key("AKIDOTHER", SECRET_KEY));
+    }
+
+    #[test]
+    fn test_region_set() {
+        assert!(region_set_matches(&["*".to_string()], "us-east-1"));
+        assert!(region_set_matches(&["eu-west-1".to_string(), "us-*".to_string()], "us-east-1"));
+        assert!(!region_set_matches(&["eu-*".to_string()], "us-east-1"));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_round_trip() {
+        let now = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+        let scope = "20221001/service/aws4_request";
+        let signed_headers = vec!["host".to_string(), "x-amz-date".to_string(), "x-amz-region-set".to_string()];
+        // http 0.2 request parts can't be cloned, so each validation gets its own.
+        let make_parts = || {
+            Request::post("/path?b=2&a=1")
+                .header("host", "example.com")
+                .header("x-amz-date", format_iso8601_basic(&now))
+                .header("x-amz-region-set", "us-*")
+                .body(())
+                .unwrap()
+                .into_parts()
+                .0
+        };
+        let parts = make_parts();
+        let body = Bytes::from_static(b"hello");
+
+        let canonical_request =
+            CanonicalRequest::new(&parts, &body, &signed_headers, false, SignatureOptions::default());
+        let sts = string_to_sign(SIGV4A_ALGORITHM, &now, scope, &canonical_request);
+        let signature: Signature = derive_signing_key(ACCESS_KEY, SECRET_KEY).sign(sts.as_bytes());
+        let auth = AuthParams::from_authorization_header(&format!(
+            "{SIGV4A_ALGORITHM} Credential={ACCESS_KEY}/{scope}, SignedHeaders={}, Signature={}",
+            signed_headers.join(";"),
+            hex::encode(signature.to_der().as_bytes())
+        ))
+        .unwrap();
+
+        let mut gvk = BoxCloneService::new(service_fn(|req: GetVerificationKeyRequest| async move {
+            assert_eq!(req.region_set(), &["us-*".to_string()]);
+            let principal = Principal::from(vec![User::new("aws", "123456789012", "/", "test").unwrap().into()]);
+            Ok::<_, BoxError>(GetVerificationKeyResponse::new(
+                principal,
+                SessionData::new(),
+                derive_verifying_key(req.access_key(), SECRET_KEY),
+            ))
+        }));
+
+        let result = sigv4a_validate_request(
+            make_parts(),
+            body.clone(),
+            auth.clone(),
+            "us-east-1",
+            "service",
+            &mut gvk,
+            now + Duration::minutes(1),
+            Duration::minutes(15),
+            SignatureOptions::default(),
+        )
+        .await;
+        assert!(result.is_ok());
+
+        // Wrong region.
+        let result = sigv4a_validate_request(
+            make_parts(),
+            body.clone(),
+            auth.clone(),
+            "eu-west-1",
+            "service",
+            &mut gvk,
+            now,
+            Duration::minutes(15),
+            SignatureOptions::default(),
+        )
+        .await;
+        assert!(result.is_err());
+
+        // Tampered body.
+        let result = sigv4a_validate_request(
+            parts,
+            Bytes::from_static(b"goodbye"),
+            auth,
+            "us-east-1",
+            "service",
+            &mut gvk,
+            now,
+            Duration::minutes(15),
+            SignatureOptions::default(),
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_rejected_requests() {
+        let now = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+        let scope = "20221001/service/aws4_request";
+        let mut gvk = BoxCloneService::new(service_fn(|req: GetVerificationKeyRequest| async move {
+            let principal = Principal::from(vec![User::new("aws", "123456789012", "/", "test").unwrap().into()]);
+            Ok::<_, BoxError>(GetVerificationKeyResponse::new(
+                principal,
+                SessionData::new(),
+                derive_verifying_key(req.access_key(), SECRET_KEY),
+            ))
+        }));
+        let sign = |parts: &Parts, body: &[u8], signed_headers: &[&str]| {
+            let signed_headers = signed_headers.iter().map(|h| h.to_string()).collect::<Vec<_>>();
+            let canonical_request =
+                CanonicalRequest::new(parts, body, &signed_headers, false, SignatureOptions::default());
+            let sts = string_to_sign(SIGV4A_ALGORITHM, &now, scope, &canonical_request);
+            let signature: Signature = derive_signing_key(ACCESS_KEY, SECRET_KEY).sign(sts.as_bytes());
+            AuthParams::from_authorization_header(&format!(
+                "{SIGV4A_ALGORITHM} Credential={ACCESS_KEY}/{scope}, SignedHeaders={}, Signature={}",
+                signed_headers.join(";"),
+                hex::encode(signature.to_der().as_bytes())
+            ))
+            .unwrap()
+        };
+        let verifier_error = |result: Result<_, BoxError>| match result {
+            Ok(_) => panic!("request was accepted"),
+            Err(e) => e.downcast::<VerifierError>().map(|e| e.error_code()).unwrap(),
+        };
+
+        // The region set must be signed, or it could be widened without breaking the signature.
+        let (parts, _) = Request::get("/")
+            .header("host", "example.com")
+            .header("x-amz-date", format_iso8601_basic(&now))
+            .header("x-amz-region-set", "*")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let auth = sign(&parts, b"", &["host", "x-amz-date"]);
+        let result = sigv4a_validate_request(
+            parts,
+            Bytes::new(),
+            auth,
+            "us-east-1",
+            "service",
+            &mut gvk,
+            now,
+            Duration::minutes(15),
+            SignatureOptions::default(),
+        )
+        .await;
+        assert_eq!(verifier_error(result), "IncompleteSignature");
+
+        // Chunk signatures aren't verified, so streaming payloads are rejected even if correctly signed.
+        let (parts, _) = Request::put("/")
+            .header("host", "example.com")
+            .header("x-amz-date", format_iso8601_basic(&now))
+            .header("x-amz-region-set", "us-east-1")
+            .header("x-amz-content-sha256", "STREAMING-AWS4-ECDSA-P256-SHA256-PAYLOAD")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let auth = sign(&parts, b"", &["host", "x-amz-content-sha256", "x-amz-date", "x-amz-region-set"]);
+        let result = sigv4a_validate_request(
+            parts,
+            Bytes::from_static(b"0;chunk-signature=00\r\n\r\n"),
+            auth,
+            "us-east-1",
+            "service",
+            &mut gvk,
+            now,
+            Duration::minutes(15),
+            SignatureOptions::default(),
+        )
+        .await;
+        assert_eq!(verifier_error(result), "XAmzContentSHA256Mismatch");
+
+        // Presigned requests must have a validity period that can be added to the request time.
+        let (parts, _) = Request::get(format!(
+            "/?X-Amz-Algorithm={SIGV4A_ALGORITHM}&X-Amz-Credential={ACCESS_KEY}%2F20221001%2Fservice%2Faws4_request&\
+             X-Amz-Date={}&X-Amz-Expires=9223372036854775807&X-Amz-Region-Set=us-east-1&X-Amz-SignedHeaders=host&\
+             X-Amz-Signature=00",
+            format_iso8601_basic(&now)
+        ))
+        .header("host", "example.com")
+        .body(())
+        .unwrap()
+        .into_parts();
+        let auth = AuthParams::from_request_head(&parts.headers, &parts.uri).unwrap();
+        let result = sigv4a_validate_request(
+            parts,
+            Bytes::new(),
+            auth,
+            "us-east-1",
+            "service",
+            &mut gvk,
+            now,
+            Duration::minutes(15),
+            SignatureOptions::default(),
+        )
+        .await;
+        assert_eq!(verifier_error(result), "AuthorizationQueryParametersError");
+    }
+}
@@ -0,0 +1,437 @@
+#![warn(clippy::all)]
+
+use {
+    crate::{
+        canonical::{form_params, query_params},
+        Decision, ErrorMapper, PolicyEvaluator, PolicyProvider, RequestContext, RequestId, ResolvedAction,
+        VerifierError,
+    },
+    http::header::CONTENT_TYPE,
+    hyper::{Body, Request, Response},
+    scratchstack_arn::Arn,
+    scratchstack_aws_principal::{Principal, User},
+    serde::Serialize,
+    std::{
+        fmt::Debug,
+        future::Future,
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+    },
+    tower::{BoxError, Service},
+};
+
+/// The XML namespace of IAM responses.
+pub const IAM_NAMESPACE: &str = "https://iam.amazonaws.com/doc/2010-05-08/";
+
+/// The decision for one action and resource, as reported by `SimulatePrincipalPolicy`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EvalDecision {
+    /// The policies allow the action.
+    Allowed,
+
+    /// A statement explicitly denies the action.
+    ExplicitDeny,
+
+    /// No statement allows the action.
+    ImplicitDeny,
+}
+
+impl EvalDecision {
+    /// Returns the decision as it appears in IAM responses, e.g. `allowed`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Allowed => "allowed",
+            Self::ExplicitDeny => "explicitDeny",
+            Self::ImplicitDeny => "implicitDeny",
+        }
+    }
+}
+
+impl From<Decision> for EvalDecision {
+    fn from(decision: Decision) -> Self {
+        match decision {
+            Decision::Allow => Self::Allowed,
+            Decision::Deny => Self::ExplicitDeny,
+            Decision::DefaultDeny => Self::ImplicitDeny,
+        }
+    }
+}
+
+/// The result of simulating one action against one resource.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EvaluationResult {
+    eval_action_name: String,
+    eval_resource_name: String,
+    eval_decision: EvalDecision,
+    matched_statements: Vec<String>,
+}
+
+impl EvaluationResult {
+    /// Retreive the IAM action simulated, e.g. `iam:GetUser`.
+    #[inline]
+    pub fn eval_action_name(&self) -> &str {
+        &self.eval_action_name
+    }
+
+    /// Retreive the ARN of the resource simulated, or `*` if no resource was given.
+    #[inline]
+    pub fn eval_resource_name(&self) -> &str {
+        &self.eval_resource_name
+    }
+
+    /// Retreive the decision.
+    #[inline]
+    pub fn eval_decision(&self) -> EvalDecision {
+        self.eval_decision
+    }
+
+    /// Retreive the ids (`Sid`) of the statements that matched.
+    #[inline]
+    pub fn matched_statements(&self) -> &[String] {
+        &self.matched_statements
+    }
+}
+
+/// Evaluates the policies of a principal against a matrix of actions and resources, using the same policy provider and
+/// evaluator as an [AuthorizerService][crate::AuthorizerService].
+#[derive(Clone, Debug)]
+pub struct PolicySimulator {
+    policy_provider: Arc<dyn PolicyProvider>,
+    evaluator: Arc<dyn PolicyEvaluator>,
+}
+
+impl PolicySimulator {
+    /// Create a new [PolicySimulator] that evaluates the policies from `policy_provider` with `evaluator`.
+    pub fn new(policy_provider: Arc<dyn PolicyProvider>, evaluator: Arc<dyn PolicyEvaluator>) -> Self {
+        Self {
+            policy_provider,
+            evaluator,
+        }
+    }
+
+    /// Simulate each action (e.g. `iam:GetUser`) against each resource ARN, with the condition keys in `context`. If no
+    /// resources are given, each action is simulated against `*`.
+    ///
+    /// Results are returned in action-major order.
+    pub async fn simulate(
+        &self,
+        principal: &Principal,
+        action_names: &[String],
+        resource_arns: &[String],
+        context: &RequestContext,
+    ) -> Result<Vec<EvaluationResult>, BoxError> {
+        let policies = self.policy_provider.get_effective_policies(principal).await?;
+        let wildcard = ["*".to_string()];
+        let resource_arns = if resource_arns.is_empty() {
+            &wildcard[..]
+        } else {
+            resource_arns
+        };
+        let mut results = Vec::with_capacity(action_names.len() * resource_arns.len());
+
+        for action_name in action_names {
+            let (service, action) = action_name
+                .split_once(':')
+                .ok_or_else(|| VerifierError::InvalidInput(format!("Invalid action name: {action_name}")))?;
+
+            for resource_arn in resource_arns {
+                let resources = if resource_arn == "*" {
+                    Vec::new()
+                } else {
+                    let arn: Arn = resource_arn
+                        .parse()
+                        .map_err(|_| VerifierError::InvalidInput(format!("Invalid resource ARN: {resource_arn}")))?;
+                    vec![arn]
+                };
+
+                let decision = policies.evaluate(
+                    self.evaluator.as_ref(),
+                    &ResolvedAction::new(service, action, resources),
+                    context,
+                )?;
+                results.push(EvaluationResult {
+                    eval_action_name: action_name.clone(),
+                    eval_resource_name: resource_arn.clone(),
+                    eval_decision: decision.decision().into(),
+                    matched_statements: decision.matched_statements().to_vec(),
+                });
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+/// A service implementing the IAM `SimulatePrincipalPolicy` action over the AWS Query protocol.
+///
+/// The `PolicySourceArn`, `ActionNames.member.N`, and `ResourceArns.member.N` parameters are read from the query
+/// string and form-encoded body. Only IAM user ARNs are accepted as the policy source, and `ContextEntries` and
+/// pagination are not supported; all results are returned in one response. Since statements are identified by their
+/// ids rather than their positions, each matched statement is reported with its `Sid` as the `SourcePolicyId`.
+///
+/// This is normally the implementation of an [AuthorizerService][crate::AuthorizerService], which decides whether the
+/// caller may simulate the policies.
+#[derive(Clone, Debug)]
+pub struct SimulatePrincipalPolicyService<E> {
+    simulator: PolicySimulator,
+    error_mapper: E,
+}
+
+impl<E: ErrorMapper> SimulatePrincipalPolicyService<E> {
+    /// Create a new [SimulatePrincipalPolicyService], rendering invalid requests with `error_mapper`.
+    pub fn new(simulator: PolicySimulator, error_mapper: E) -> Self {
+        Self {
+            simulator,
+            error_mapper,
+        }
+    }
+}
+
+impl<E: ErrorMapper> Service<Request<Body>> for SimulatePrincipalPolicyService<E> {
+    type Response = Response<Body>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _c: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let simulator = self.simulator.clone();
+        let error_mapper = self.error_mapper.clone();
+
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let request_id = parts.extensions.get::<RequestId>().copied();
+            let body = hyper::body::to_bytes(body).await?;
+
+            let mut params = parts.uri.query().map(query_params).unwrap_or_default();
+            let is_form = parts
+                .headers
+                .get(CONTENT_TYPE)
+                .and_then(|ct| ct.to_str().ok())
+                .map(|ct| ct.starts_with("application/x-www-form-urlencoded"))
+                .unwrap_or(false);
+            if is_form {
+                params.extend(form_params(&String::from_utf8_lossy(&body)));
+            }
+
+            let result = match simulate_request(&simulator, &params).await {
+                Ok(results) => results,
+                Err(e) => return error_mapper.map_error(e, request_id).await,
+            };
+
+            let response = XmlSimulateResponse {
+                xmlns: IAM_NAMESPACE.to_string(),
+                result: XmlSimulateResult {
+                    evaluation_results: XmlMembers {
+                        member: result.iter().map(XmlEvaluationResult::from).collect(),
+                    },
+                    is_truncated: false,
+                },
+                response_metadata: XmlResponseMetadata {
+                    request_id,
+                },
+            };
+
+            Response::builder()
+                .header(CONTENT_TYPE, "text/xml; charset=utf-8")
+                .body(Body::from(quick_xml::se::to_string(&response)?))
+                .map_err(Into::into)
+        })
+    }
+}
+
+async fn simulate_request(
+    simulator: &PolicySimulator,
+    params: &[(String, String)],
+) -> Result<Vec<EvaluationResult>, BoxError> {
+    let policy_source_arn = params
+        .iter()
+        .find(|(k, _)| k == "PolicySourceArn")
+        .map(|(_, v)| v.as_str())
+        .ok_or_else(|| VerifierError::InvalidInput("PolicySourceArn is required".to_string()))?;
+    let principal = user_principal(policy_source_arn)
+        .ok_or_else(|| VerifierError::InvalidInput(format!("Invalid PolicySourceArn: {policy_source_arn}")))?;
+
+    let action_names = list_param(params, "ActionNames");
+    if action_names.is_empty() {
+        return Err(VerifierError::InvalidInput("ActionNames is required".to_string()).into());
+    }
+    let resource_arns = list_param(params, "ResourceArns");
+
+    simulator.simulate(&principal, &action_names, &resource_arns, &RequestContext::builder().build()).await
+}
+
+/// Returns the principal for an IAM user ARN of the form `arn:<partition>:iam::<account>:user/<path>/<name>`.
+fn user_principal(arn: &str) -> Option<Principal> {
+    let arn: Arn = arn.parse().ok()?;
+    let user_path_name = arn.resource().strip_prefix("user/")?;
+    let (path, user_name) = match user_path_name.rsplit_once('/') {
+        Some((path, user_name)) => (format!("/{path}/"), user_name),
+        None => ("/".to_string(), user_path_name),
+    };
+
+    let user = User::new(arn.partition(), arn.account_id(), &path, user_name).ok()?;
+    Some(Principal::from(vec![user.into()]))
+}
+
+/// Returns the values of a Query protocol list parameter (`<name>.member.1`, `<name>.member.2`, ...) in order.
+fn list_param(params: &[(String, String)], name: &str) -> Vec<String> {
+    let prefix = format!("{name}.member.");
+    let mut members: Vec<(usize, String)> =
+        params.iter().filter_map(|(k, v)| Some((k.strip_prefix(&prefix)?.parse().ok()?, v.clone()))).collect();
+    members.sort_by_key(|(index, _)| *index);
+    members.into_iter().map(|(_, v)| v).collect()
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename = "SimulatePrincipalPolicyResponse")]
+struct XmlSimulateResponse {
+    xmlns: String,
+
+    #[serde(rename = "SimulatePrincipalPolicyResult")]
+    result: XmlSimulateResult,
+
+    #[serde(rename = "ResponseMetadata")]
+    response_metadata: XmlResponseMetadata,
+}
+
+#[derive(Debug, Serialize)]
+struct XmlSimulateResult {
+    #[serde(rename = "EvaluationResults")]
+    evaluation_results: XmlMembers<XmlEvaluationResult>,
+
+    #[serde(rename = "$unflatten=IsTruncated")]
+    is_truncated: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct XmlResponseMetadata {
+    #[serde(rename = "$unflatten=RequestId", skip_serializing_if = "Option::is_none")]
+    request_id: Option<RequestId>,
+}
+
+#[derive(Debug, Serialize)]
+struct XmlMembers<T> {
+    member: Vec<T>,
+}
+
+#[derive(Debug, Serialize)]
+struct XmlEvaluationResult {
+    #[serde(rename = "$unflatten=EvalActionName")]
+    eval_action_name: String,
+
+    #[serde(rename = "$unflatten=EvalResourceName")]
+    eval_resource_name: String,
+
+    // quick-xml writes enum variants as empty elements, so the decision is written as text.
+    #[serde(rename = "$unflatten=EvalDecision")]
+    eval_decision: &'static str,
+
+    #[serde(rename = "MatchedStatements")]
+    matched_statements: XmlMembers<XmlStatement>,
+}
+
+#[derive(Debug, Serialize)]
+struct XmlStatement {
+    #[serde(rename = "$unflatten=SourcePolicyId")]
+    source_policy_id: String,
+}
+
+impl From<&EvaluationResult> for XmlEvaluationResult {
+    fn from(result: &EvaluationResult) -> Self {
+        Self {
+            eval_action_name: result.eval_action_name.clone(),
+            eval_resource_name: result.eval_resource_name.clone(),
+            eval_decision: result.eval_decision.as_str(),
+            matched_statements: XmlMembers {
+                member: result
+                    .matched_statements
+                    .iter()
+                    .map(|sid| XmlStatement {
+                        source_policy_id: sid.clone(),
+                    })
+                    .collect(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{EvalDecision, PolicySimulator, SimulatePrincipalPolicyService},
+        crate::{
+            test_util::{StaticPolicies, GET_ONLY_POLICY},
+            IamPolicyEvaluator, RequestContext, XmlErrorMapper,
+        },
+        http::StatusCode,
+        hyper::{Body, Request},
+        scratchstack_aws_principal::Principal,
+        std::sync::Arc,
+        tower::ServiceExt,
+    };
+
+    fn simulator() -> PolicySimulator {
+        PolicySimulator::new(Arc::new(StaticPolicies::new(&[GET_ONLY_POLICY])), Arc::new(IamPolicyEvaluator::new()))
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_simulate() {
+        let results = simulator()
+            .simulate(
+                &Principal::from(Vec::new()),
+                &["iam:GetUser".to_string(), "iam:DeleteUser".to_string()],
+                &["arn:aws:iam::123456789012:user/test".to_string(), "*".to_string()],
+                &RequestContext::builder().build(),
+            )
+            .await
+            .unwrap();
+
+        let decisions: Vec<_> = results.iter().map(|r| (r.eval_action_name(), r.eval_decision())).collect();
+        assert_eq!(
+            decisions,
+            vec![
+                ("iam:GetUser", EvalDecision::Allowed),
+                ("iam:GetUser", EvalDecision::ImplicitDeny),
+                ("iam:DeleteUser", EvalDecision::ExplicitDeny),
+                ("iam:DeleteUser", EvalDecision::ExplicitDeny),
+            ]
+        );
+        assert_eq!(results[0].eval_resource_name(), "arn:aws:iam::123456789012:user/test");
+        assert_eq!(results[0].matched_statements(), &["AllowGet".to_string()]);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_simulate_service() {
+        let service = SimulatePrincipalPolicyService::new(simulator(), XmlErrorMapper::new(super::IAM_NAMESPACE));
+        let req = Request::post("/")
+            .header("content-type", "application/x-www-form-urlencoded; charset=utf-8")
+            .body(Body::from(
+                "Action=SimulatePrincipalPolicy&Version=2010-05-08\
+                 &PolicySourceArn=arn%3Aaws%3Aiam%3A%3A123456789012%3Auser%2Fdev%2Ftest\
+                 &ActionNames.member.1=iam%3AGetUser\
+                 &ResourceArns.member.1=arn%3Aaws%3Aiam%3A%3A123456789012%3Auser%2Ftest",
+            ))
+            .unwrap();
+
+        let response = service.clone().oneshot(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("<EvalActionName>iam:GetUser</EvalActionName>"));
+        assert!(body.contains("<EvalDecision>allowed</EvalDecision>"));
+        assert!(body.contains("<SourcePolicyId>AllowGet</SourcePolicyId>"));
+
+        let req = Request::get("/?Action=SimulatePrincipalPolicy&ActionNames.member.1=iam%3AGetUser")
+            .body(Body::empty())
+            .unwrap();
+        let response = service.oneshot(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("<Code>InvalidInput</Code>"));
+    }
+}
@@ -0,0 +1,72 @@
+use {
+    crate::{AuthOutcome, RequestId},
+    std::future::Future,
+};
+
+#[cfg(feature = "tracing")]
+use tracing::{field, info_span, Instrument, Span};
+
+/// Run the future inside a new `tracing` span covering the verification of a single request. Without the `tracing`
+/// feature, the future is returned as-is.
+pub(crate) fn instrument<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    #[cfg(feature = "tracing")]
+    let future = future.instrument(info_span!(
+        "sigv4_verify",
+        request_id = field::Empty,
+        access_key = field::Empty,
+        region = field::Empty,
+        service = field::Empty,
+        outcome = field::Empty,
+    ));
+
+    future
+}
+
+/// Record what is known about the request in the current span, if the `tracing` feature is enabled.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn record_request(request_id: RequestId, access_key: Option<&str>, region: &str, service: &str) {
+    #[cfg(feature = "tracing")]
+    {
+        let span = Span::current();
+        span.record("request_id", field::display(request_id));
+        if let Some(access_key) = access_key {
+            span.record("access_key", redact_access_key(access_key).as_str());
+        }
+        span.record("region", region);
+        span.record("service", service);
+    }
+}
+
+/// Record the outcome of authenticating the request in the current span, if the `tracing` feature is enabled.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn record_outcome(outcome: AuthOutcome) {
+    #[cfg(feature = "tracing")]
+    Span::current().record("outcome", field::debug(outcome));
+}
+
+/// Redact all but the first four characters of an access key, e.g. `AKID********`.
+#[cfg(feature = "tracing")]
+fn redact_access_key(access_key: &str) -> String {
+    access_key
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if i < 4 {
+                c
+            } else {
+                '*'
+            }
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use super::redact_access_key;
+
+    #[test]
+    fn test_redact_access_key() {
+        assert_eq!(redact_access_key("AKIDEXAMPLE"), "AKID*******");
+        assert_eq!(redact_access_key("AKI"), "AKI");
+    }
+}
@@ -0,0 +1,650 @@
+use {
+    crate::{
+        credentials::{constant_time_eq, SecretString},
+        router::QueryRouter,
+        session_keys::{
+            SessionDataExt, PRINCIPAL_ACCOUNT, PRINCIPAL_ARN, PRINCIPAL_TYPE, ROLE_SESSION_NAME, TOKEN_ISSUE_TIME,
+            USERNAME, USER_ID,
+        },
+        validate::{Validate, Violations},
+        Clock, ErrorMapper, ServiceErrorCatalog, SystemClock,
+    },
+    async_trait::async_trait,
+    chrono::{DateTime, Duration, Utc},
+    http::request::Parts,
+    rand::{distributions::Alphanumeric, thread_rng, Rng},
+    scratchstack_arn::Arn,
+    scratchstack_aws_principal::{AssumedRole, Principal, PrincipalIdentity, SessionData},
+    scratchstack_aws_signature::{GetSigningKeyRequest, GetSigningKeyResponse, SignatureError},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::HashMap,
+        fmt::{Debug, Formatter, Result as FmtResult},
+        future::Future,
+        pin::Pin,
+        sync::{Arc, Mutex},
+        task::{Context, Poll},
+    },
+    tower::{BoxError, Service},
+};
+
+/// The XML namespace of STS responses.
+pub const STS_NAMESPACE: &str = "https://sts.amazonaws.com/doc/2011-06-15/";
+
+/// The STS API version.
+pub const STS_VERSION: &str = "2011-06-15";
+
+/// The default lifetime of `GetSessionToken` credentials, 12 hours.
+const DEFAULT_SESSION_TOKEN_DURATION: i64 = 43_200;
+
+/// The maximum lifetime of `GetSessionToken` credentials, 36 hours.
+const MAX_SESSION_TOKEN_DURATION: i64 = 129_600;
+
+/// The default lifetime of `AssumeRole` credentials, 1 hour.
+const DEFAULT_ASSUME_ROLE_DURATION: i64 = 3_600;
+
+/// The maximum lifetime of `AssumeRole` credentials, 12 hours.
+const MAX_ASSUME_ROLE_DURATION: i64 = 43_200;
+
+/// The minimum lifetime of temporary credentials, 15 minutes.
+const MIN_DURATION: i64 = 900;
+
+const MSG_INVALID_TOKEN: &str = "The security token included in the request is invalid.";
+const MSG_EXPIRED_TOKEN: &str = "The security token included in the request is expired";
+
+/// Temporary credentials issued by a [TokenIssuer].
+#[derive(Clone, Eq, PartialEq)]
+pub struct Credentials {
+    access_key_id: String,
+    secret_access_key: SecretString,
+    session_token: String,
+    expiration: DateTime<Utc>,
+}
+
+impl Credentials {
+    /// Create a new set of [Credentials].
+    pub fn new(
+        access_key_id: impl Into<String>,
+        secret_access_key: impl Into<String>,
+        session_token: impl Into<String>,
+        expiration: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            access_key_id: access_key_id.into(),
+            secret_access_key: SecretString::new(secret_access_key),
+            session_token: session_token.into(),
+            expiration,
+        }
+    }
+
+    /// Retreive the access key id, which starts with `ASIA`.
+    #[inline]
+    pub fn access_key_id(&self) -> &str {
+        &self.access_key_id
+    }
+
+    /// Retreive the secret access key.
+    #[inline]
+    pub fn secret_access_key(&self) -> &str {
+        self.secret_access_key.expose_secret()
+    }
+
+    /// Retreive the session token, which must accompany requests signed with these credentials.
+    #[inline]
+    pub fn session_token(&self) -> &str {
+        &self.session_token
+    }
+
+    /// Retreive the time the credentials expire.
+    #[inline]
+    pub fn expiration(&self) -> DateTime<Utc> {
+        self.expiration
+    }
+}
+
+impl Debug for Credentials {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Credentials")
+            .field("access_key_id", &self.access_key_id)
+            .field("expiration", &self.expiration)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The role session returned by `AssumeRole`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AssumedRoleUser {
+    assumed_role_id: String,
+    arn: String,
+}
+
+impl AssumedRoleUser {
+    /// Create a new [AssumedRoleUser] from the session's id (`<role id>:<session name>`) and ARN.
+    pub fn new(assumed_role_id: impl Into<String>, arn: impl Into<String>) -> Self {
+        Self {
+            assumed_role_id: assumed_role_id.into(),
+            arn: arn.into(),
+        }
+    }
+
+    /// Retreive the id of the session, `<role id>:<session name>`.
+    #[inline]
+    pub fn assumed_role_id(&self) -> &str {
+        &self.assumed_role_id
+    }
+
+    /// Retreive the ARN of the session, e.g. `arn:aws:sts::123456789012:assumed-role/Admin/alice`.
+    #[inline]
+    pub fn arn(&self) -> &str {
+        &self.arn
+    }
+}
+
+/// A source of temporary credentials for the STS handlers created by [sts_router].
+///
+/// The issuer is only asked for credentials once the caller has been authenticated and, if the router is the
+/// implementation of an [AuthorizerService][crate::AuthorizerService], authorized to call the action; role trust
+/// policies are the issuer's responsibility. Issued credentials must also be accepted by the service's signing key
+/// provider for them to be usable; [MemoryTokenIssuer] is both.
+#[async_trait]
+pub trait TokenIssuer: Debug + Send + Sync {
+    /// Issue credentials for the caller itself, valid for `duration`, for `GetSessionToken`.
+    async fn get_session_token(
+        &self,
+        caller: &Principal,
+        session_data: &SessionData,
+        duration: Duration,
+    ) -> Result<Credentials, BoxError>;
+
+    /// Issue credentials for a session of the role, valid for `duration`, for `AssumeRole`.
+    async fn assume_role(
+        &self,
+        caller: &Principal,
+        role_arn: &Arn,
+        role_session_name: &str,
+        duration: Duration,
+    ) -> Result<(Credentials, AssumedRoleUser), BoxError>;
+}
+
+/// A [TokenIssuer] that keeps the credentials it issues in memory, and a signing key provider for them.
+///
+/// Clones share the same credentials, so one clone can be given to [sts_router] and another used as (or alongside) the
+/// verifier's signing key provider. Every role is assumable; services needing trust policies should check them before
+/// calling [assume_role][TokenIssuer::assume_role] or implement their own issuer. Requests signed with credentials
+/// this issuer doesn't know, or with the wrong session token, are rejected with `InvalidClientTokenId`, and those signed
+/// with expired credentials with `ExpiredToken`.
+#[derive(Clone, Debug)]
+pub struct MemoryTokenIssuer {
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+    clock: Arc<dyn Clock>,
+}
+
+/// The credentials and identity of an issued session.
+#[derive(Clone)]
+struct Session {
+    credentials: Credentials,
+    principal: Principal,
+    session_data: SessionData,
+}
+
+impl Debug for Session {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Session")
+            .field("credentials", &self.credentials)
+            .field("principal", &self.principal)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for MemoryTokenIssuer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryTokenIssuer {
+    /// Create a new [MemoryTokenIssuer] with no sessions.
+    pub fn new() -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Use the given [Clock] for the issue time and expiry of credentials, returning the updated issuer.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Returns the number of unexpired sessions.
+    pub fn session_count(&self) -> usize {
+        let now = self.clock.now();
+        self.sessions.lock().unwrap().values().filter(|session| session.credentials.expiration > now).count()
+    }
+
+    /// Generate credentials for a new session, removing any sessions that have expired.
+    fn issue(&self, principal: Principal, mut session_data: SessionData, duration: Duration) -> Credentials {
+        let now = self.clock.now();
+        let credentials = Credentials::new(
+            format!("ASIA{}", random_string(16).to_ascii_uppercase()),
+            random_string(40),
+            random_string(64),
+            now + duration,
+        );
+        session_data.set_timestamp(TOKEN_ISSUE_TIME, now);
+
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, session| session.credentials.expiration > now);
+        sessions.insert(
+            credentials.access_key_id.clone(),
+            Session {
+                credentials: credentials.clone(),
+                principal,
+                session_data,
+            },
+        );
+        credentials
+    }
+}
+
+#[async_trait]
+impl TokenIssuer for MemoryTokenIssuer {
+    async fn get_session_token(
+        &self,
+        caller: &Principal,
+        session_data: &SessionData,
+        duration: Duration,
+    ) -> Result<Credentials, BoxError> {
+        Ok(self.issue(caller.clone(), session_data.clone(), duration))
+    }
+
+    async fn assume_role(
+        &self,
+        _caller: &Principal,
+        role_arn: &Arn,
+        role_session_name: &str,
+        duration: Duration,
+    ) -> Result<(Credentials, AssumedRoleUser), BoxError> {
+        let invalid_arn = || ServiceErrorCatalog::validation(format!("{role_arn} is invalid"));
+        let role_path_name = role_arn.resource().strip_prefix("role/").ok_or_else(invalid_arn)?;
+        let role_name = role_path_name.rsplit('/').next().unwrap_or(role_path_name);
+
+        let assumed_role = AssumedRole::new(role_arn.partition(), role_arn.account_id(), role_name, role_session_name)
+            .map_err(|_| invalid_arn())?;
+        let arn = format!(
+            "arn:{}:sts::{}:assumed-role/{role_name}/{role_session_name}",
+            role_arn.partition(),
+            role_arn.account_id()
+        );
+        let role_id = format!("AROA{}", random_string(17).to_ascii_uppercase());
+        let user = AssumedRoleUser::new(format!("{role_id}:{role_session_name}"), &arn);
+
+        let mut session_data = SessionData::new();
+        session_data.set_string(USER_ID, user.assumed_role_id());
+        session_data.set_string(ROLE_SESSION_NAME, role_session_name);
+        session_data.set_string(PRINCIPAL_TYPE, "AssumedRole");
+        session_data.set_string(PRINCIPAL_ACCOUNT, role_arn.account_id());
+        session_data.set_string(PRINCIPAL_ARN, role_arn.to_string());
+
+        let principal = Principal::from(vec![PrincipalIdentity::from(assumed_role)]);
+        Ok((self.issue(principal, session_data, duration), user))
+    }
+}
+
+impl Service<GetSigningKeyRequest> for MemoryTokenIssuer {
+    type Response = GetSigningKeyResponse;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _c: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: GetSigningKeyRequest) -> Self::Future {
+        let session = self.sessions.lock().unwrap().get(req.access_key()).cloned();
+        let now = self.clock.now();
+
+        Box::pin(async move {
+            let Some(session) = session else {
+                return Err(SignatureError::InvalidClientTokenId(MSG_INVALID_TOKEN.to_string()).into());
+            };
+            let session_token = req.session_token().unwrap_or_default();
+            if !constant_time_eq(session_token.as_bytes(), session.credentials.session_token.as_bytes()) {
+                return Err(SignatureError::InvalidClientTokenId(MSG_INVALID_TOKEN.to_string()).into());
+            }
+            if session.credentials.expiration <= now {
+                return Err(SignatureError::ExpiredToken(MSG_EXPIRED_TOKEN.to_string()).into());
+            }
+
+            let signing_key =
+                session.credentials.secret_access_key.signing_key(req.request_date(), req.region(), req.service());
+            GetSigningKeyResponse::builder()
+                .principal(session.principal)
+                .session_data(session.session_data)
+                .signing_key(signing_key)
+                .build()
+                .map_err(Into::into)
+        })
+    }
+}
+
+/// Returns a [QueryRouter] implementing the STS `GetCallerIdentity`, `GetSessionToken`, and `AssumeRole` actions for
+/// API version 2011-06-15, issuing credentials with `issuer` and rendering errors with `error_mapper`.
+///
+/// The router expects to run behind the verifier, reading the caller from the [Principal] and [SessionData] request
+/// extensions. `DurationSeconds` defaults to 12 hours for `GetSessionToken` and 1 hour for `AssumeRole`, and is limited
+/// to 36 and 12 hours respectively; session policies, tags, and MFA parameters are not supported. As in AWS,
+/// `GetSessionToken` can't be called with temporary credentials.
+///
+/// ```
+/// use {
+///     scratchstack_http_framework::{
+///         sts::{sts_router, MemoryTokenIssuer, STS_NAMESPACE},
+///         XmlErrorMapper,
+///     },
+///     std::sync::Arc,
+/// };
+///
+/// let issuer = MemoryTokenIssuer::new();
+/// let router = sts_router(Arc::new(issuer.clone()), XmlErrorMapper::new(STS_NAMESPACE));
+/// ```
+pub fn sts_router<E: ErrorMapper>(issuer: Arc<dyn TokenIssuer>, error_mapper: E) -> QueryRouter<E> {
+    let session_issuer = issuer.clone();
+
+    QueryRouter::new(STS_NAMESPACE, error_mapper)
+        .with_version(STS_VERSION)
+        .with_action("GetCallerIdentity", |parts, _: GetCallerIdentityInput| async move { get_caller_identity(&parts) })
+        .with_validated_action("GetSessionToken", move |parts, input| {
+            get_session_token(session_issuer.clone(), parts, input)
+        })
+        .with_validated_action("AssumeRole", move |parts, input| assume_role(issuer.clone(), parts, input))
+}
+
+/// Returns the authenticated caller of a request.
+fn caller(parts: &Parts) -> Result<(&Principal, &SessionData), BoxError> {
+    match (parts.extensions.get::<Principal>(), parts.extensions.get::<SessionData>()) {
+        (Some(principal), Some(session_data)) if !principal.is_empty() => Ok((principal, session_data)),
+        _ => Err(ServiceErrorCatalog::access_denied("The request must be signed").into()),
+    }
+}
+
+fn get_caller_identity(parts: &Parts) -> Result<GetCallerIdentityOutput, BoxError> {
+    let (principal, session_data) = caller(parts)?;
+    let arn = session_data.principal_arn().map(str::to_string).unwrap_or_else(|| principal.to_string());
+    let account = session_data
+        .principal_account()
+        .map(str::to_string)
+        .or_else(|| arn.split(':').nth(4).map(str::to_string))
+        .unwrap_or_default();
+
+    Ok(GetCallerIdentityOutput {
+        user_id: session_data.user_id().or_else(|| session_data.get_string(USERNAME)).map(str::to_string),
+        account,
+        arn,
+    })
+}
+
+async fn get_session_token(
+    issuer: Arc<dyn TokenIssuer>,
+    parts: Parts,
+    input: GetSessionTokenInput,
+) -> Result<GetSessionTokenOutput, BoxError> {
+    let (principal, session_data) = caller(&parts)?;
+    if principal.iter().any(|identity| matches!(identity, PrincipalIdentity::AssumedRole(_))) {
+        return Err(ServiceErrorCatalog::access_denied("Cannot call GetSessionToken with session credentials").into());
+    }
+
+    let duration = Duration::seconds(input.duration_seconds.unwrap_or(DEFAULT_SESSION_TOKEN_DURATION));
+    let credentials = issuer.get_session_token(principal, session_data, duration).await?;
+    Ok(GetSessionTokenOutput {
+        credentials: XmlCredentials::from(&credentials),
+    })
+}
+
+async fn assume_role(
+    issuer: Arc<dyn TokenIssuer>,
+    parts: Parts,
+    input: AssumeRoleInput,
+) -> Result<AssumeRoleOutput, BoxError> {
+    let (principal, _) = caller(&parts)?;
+    let role_arn: Arn = input
+        .role_arn
+        .parse()
+        .map_err(|_| ServiceErrorCatalog::validation(format!("{} is invalid", input.role_arn)))?;
+
+    let duration = Duration::seconds(input.duration_seconds.unwrap_or(DEFAULT_ASSUME_ROLE_DURATION));
+    let (credentials, user) = issuer.assume_role(principal, &role_arn, &input.role_session_name, duration).await?;
+    Ok(AssumeRoleOutput {
+        assumed_role_user: XmlAssumedRoleUser {
+            assumed_role_id: user.assumed_role_id,
+            arn: user.arn,
+        },
+        credentials: XmlCredentials::from(&credentials),
+    })
+}
+
+/// Returns a random alphanumeric string.
+fn random_string(len: usize) -> String {
+    thread_rng().sample_iter(&Alphanumeric).take(len).map(char::from).collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct GetCallerIdentityInput {}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct GetSessionTokenInput {
+    duration_seconds: Option<i64>,
+}
+
+impl Validate for GetSessionTokenInput {
+    fn validate(&self, violations: &mut Violations) {
+        if let Some(duration_seconds) = self.duration_seconds {
+            violations.range("durationSeconds", duration_seconds, MIN_DURATION, MAX_SESSION_TOKEN_DURATION);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AssumeRoleInput {
+    role_arn: String,
+    role_session_name: String,
+    duration_seconds: Option<i64>,
+}
+
+impl Validate for AssumeRoleInput {
+    fn validate(&self, violations: &mut Violations) {
+        violations
+            .length("roleArn", &self.role_arn, 20, 2048)
+            .length("roleSessionName", &self.role_session_name, 2, 64)
+            .pattern("roleSessionName", &self.role_session_name, r"[\w+=,.@-]*", |s| {
+                s.chars().all(|c| c.is_ascii_alphanumeric() || "_+=,.@-".contains(c))
+            });
+        if let Some(duration_seconds) = self.duration_seconds {
+            violations.range("durationSeconds", duration_seconds, MIN_DURATION, MAX_ASSUME_ROLE_DURATION);
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct GetCallerIdentityOutput {
+    #[serde(rename = "$unflatten=UserId", skip_serializing_if = "Option::is_none")]
+    user_id: Option<String>,
+
+    #[serde(rename = "$unflatten=Account")]
+    account: String,
+
+    #[serde(rename = "$unflatten=Arn")]
+    arn: String,
+}
+
+#[derive(Debug, Serialize)]
+struct GetSessionTokenOutput {
+    #[serde(rename = "Credentials")]
+    credentials: XmlCredentials,
+}
+
+#[derive(Debug, Serialize)]
+struct AssumeRoleOutput {
+    #[serde(rename = "AssumedRoleUser")]
+    assumed_role_user: XmlAssumedRoleUser,
+
+    #[serde(rename = "Credentials")]
+    credentials: XmlCredentials,
+}
+
+#[derive(Debug, Serialize)]
+struct XmlAssumedRoleUser {
+    #[serde(rename = "$unflatten=AssumedRoleId")]
+    assumed_role_id: String,
+
+    #[serde(rename = "$unflatten=Arn")]
+    arn: String,
+}
+
+#[derive(Debug, Serialize)]
+struct XmlCredentials {
+    #[serde(rename = "$unflatten=AccessKeyId")]
+    access_key_id: String,
+
+    #[serde(rename = "$unflatten=SecretAccessKey")]
+    secret_access_key: String,
+
+    #[serde(rename = "$unflatten=SessionToken")]
+    session_token: String,
+
+    #[serde(rename = "$unflatten=Expiration")]
+    expiration: String,
+}
+
+impl From<&Credentials> for XmlCredentials {
+    fn from(credentials: &Credentials) -> Self {
+        Self {
+            access_key_id: credentials.access_key_id.clone(),
+            secret_access_key: credentials.secret_access_key().to_string(),
+            session_token: credentials.session_token.clone(),
+            expiration: credentials.expiration.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{sts_router, MemoryTokenIssuer, STS_NAMESPACE},
+        crate::{
+            session_keys::{SessionDataExt, UserSessionData, TOKEN_ISSUE_TIME},
+            test_util, FixedClock, XmlErrorMapper,
+        },
+        chrono::{Duration, TimeZone, Utc},
+        http::StatusCode,
+        hyper::{Body, Request},
+        pretty_assertions::assert_eq,
+        scratchstack_aws_principal::{Principal, PrincipalIdentity, SessionData, User},
+        scratchstack_aws_signature::{GetSigningKeyRequest, SignatureError},
+        std::sync::Arc,
+        tower::{BoxError, ServiceExt},
+    };
+
+    fn element<'a>(body: &'a str, name: &str) -> &'a str {
+        let start = body.find(&format!("<{name}>")).unwrap() + name.len() + 2;
+        let end = body.find(&format!("</{name}>")).unwrap();
+        &body[start..end]
+    }
+
+    fn signing_key_request(access_key: &str, session_token: &str) -> GetSigningKeyRequest {
+        test_util::signing_key_request(access_key, Some(session_token), "sts")
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_sts() {
+        let now = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+        let issuer = MemoryTokenIssuer::new().with_clock(Arc::new(FixedClock::new(now)));
+        let router = sts_router(Arc::new(issuer.clone()), XmlErrorMapper::new(STS_NAMESPACE));
+
+        let user_session_data = UserSessionData::builder()
+            .user_name("alice")
+            .user_id("AIDAEXAMPLE")
+            .account_id("123456789012")
+            .user_arn("arn:aws:iam::123456789012:user/alice")
+            .requested_region("us-east-1")
+            .build()
+            .unwrap();
+        let user = Principal::from(vec![User::new("aws", "123456789012", "/", "alice").unwrap().into()]);
+        let call = |query: &str, principal: Principal, session_data: SessionData| {
+            let mut req = Request::get(format!("/?Version=2011-06-15&{query}")).body(Body::empty()).unwrap();
+            req.extensions_mut().insert(principal);
+            req.extensions_mut().insert(session_data);
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(req).await?;
+                let status = response.status();
+                let body = hyper::body::to_bytes(response.into_body()).await?;
+                Ok::<_, BoxError>((status, String::from_utf8_lossy(&body).to_string()))
+            }
+        };
+
+        let (status, body) =
+            call("Action=GetCallerIdentity", user.clone(), SessionData::from(&user_session_data)).await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(element(&body, "Arn"), "arn:aws:iam::123456789012:user/alice");
+        assert_eq!(element(&body, "UserId"), "AIDAEXAMPLE");
+        assert_eq!(element(&body, "Account"), "123456789012");
+
+        let (status, body) = call(
+            "Action=AssumeRole&RoleArn=arn%3Aaws%3Aiam%3A%3A123456789012%3Arole%2Fops%2FAdmin&RoleSessionName=alice",
+            user.clone(),
+            SessionData::from(&user_session_data),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(element(&body, "Arn"), "arn:aws:sts::123456789012:assumed-role/Admin/alice");
+        assert_eq!(element(&body, "Expiration"), "2022-10-01T13:00:00Z");
+        let access_key = element(&body, "AccessKeyId");
+        assert!(access_key.starts_with("ASIA"));
+        assert_eq!(issuer.session_count(), 1);
+
+        // The issued credentials are accepted by the issuer as a signing key provider.
+        let response =
+            issuer.clone().oneshot(signing_key_request(access_key, element(&body, "SessionToken"))).await.unwrap();
+        assert!(matches!(response.principal().iter().next(), Some(PrincipalIdentity::AssumedRole(_))));
+        assert_eq!(response.session_data().principal_account(), Some("123456789012"));
+        assert_eq!(response.session_data().get_timestamp(TOKEN_ISSUE_TIME), Some(now));
+
+        let e = issuer.clone().oneshot(signing_key_request(access_key, "wrong")).await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<SignatureError>(), Some(SignatureError::InvalidClientTokenId(_))));
+
+        // Session credentials can't be used to get a session token.
+        let (status, body) =
+            call("Action=GetSessionToken", response.principal().clone(), response.session_data().clone())
+                .await
+                .unwrap();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(body.contains("<Code>AccessDeniedException</Code>"));
+
+        let (status, body) =
+            call("Action=GetSessionToken&DurationSeconds=600", user.clone(), SessionData::from(&user_session_data))
+                .await
+                .unwrap();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("<Code>ValidationException</Code>"));
+
+        let (status, body) = call("Action=GetSessionToken", user, SessionData::from(&user_session_data)).await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(element(&body, "Expiration"), "2022-10-02T00:00:00Z");
+        assert_eq!(issuer.session_count(), 2);
+
+        // Expired credentials are rejected.
+        let expired = MemoryTokenIssuer {
+            clock: Arc::new(FixedClock::new(now + Duration::hours(13))),
+            ..issuer.clone()
+        };
+        assert_eq!(expired.session_count(), 0);
+        let session_token = element(&body, "SessionToken");
+        let e = expired.oneshot(signing_key_request(element(&body, "AccessKeyId"), session_token)).await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<SignatureError>(), Some(SignatureError::ExpiredToken(_))));
+    }
+}
@@ -0,0 +1,74 @@
+//! Fixtures shared by the unit tests of this crate.
+
+#![allow(deprecated)]
+
+use {
+    crate::{Policy, PolicyAttachment, PolicyProvider, PolicySet},
+    async_trait::async_trait,
+    chrono::{Date, TimeZone, Utc},
+    scratchstack_aws_principal::Principal,
+    scratchstack_aws_signature::GetSigningKeyRequest,
+    tower::BoxError,
+};
+
+/// A policy allowing `iam:Get*` on users in account 123456789012 and explicitly denying `iam:Delete*`.
+pub(crate) const GET_ONLY_POLICY: &str = r#"{
+    "Version": "2012-10-17",
+    "Statement": [
+        {"Sid": "AllowGet", "Effect": "Allow", "Action": "iam:Get*", "Resource": "arn:aws:iam::123456789012:user/*"},
+        {"Sid": "DenyDelete", "Effect": "Deny", "Action": "iam:Delete*", "Resource": "*"}
+    ]
+}"#;
+
+/// A [PolicyProvider] that gives every principal the same inline policies.
+#[derive(Debug)]
+pub(crate) struct StaticPolicies(PolicySet);
+
+impl StaticPolicies {
+    /// Create a new [StaticPolicies] with an inline policy for each document.
+    pub(crate) fn new(documents: &[&str]) -> Self {
+        Self(
+            documents
+                .iter()
+                .enumerate()
+                .map(|(i, document)| {
+                    let attachment = PolicyAttachment::Inline {
+                        policy_name: format!("policy-{i}"),
+                    };
+                    Policy::new(attachment, *document)
+                })
+                .collect(),
+        )
+    }
+}
+
+#[async_trait]
+impl PolicyProvider for StaticPolicies {
+    async fn get_policies_for_principal(&self, _principal: &Principal) -> Result<PolicySet, BoxError> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Returns the date of the requests returned by [signing_key_request].
+///
+/// `scratchstack-aws-signature` dates signing key requests with a [Date], which chrono has deprecated.
+pub(crate) fn request_date() -> Date<Utc> {
+    Utc.ymd(2022, 10, 1)
+}
+
+/// Returns a request for the signing key of `access_key` (and `session_token`, if any) for `service` in `us-east-1` on
+/// the [request_date].
+pub(crate) fn signing_key_request(
+    access_key: &str,
+    session_token: Option<&str>,
+    service: &str,
+) -> GetSigningKeyRequest {
+    GetSigningKeyRequest::builder()
+        .access_key(access_key)
+        .session_token(session_token.map(str::to_string))
+        .request_date(request_date())
+        .region("us-east-1")
+        .service(service)
+        .build()
+        .unwrap()
+}
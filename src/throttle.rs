@@ -0,0 +1,477 @@
+use {
+    crate::{ConnectInfo, ErrorMapper, RequestId, VerifierError},
+    async_trait::async_trait,
+    hyper::{Body, Request, Response},
+    log::info,
+    scratchstack_aws_principal::Principal,
+    scratchstack_aws_signature::{GetSigningKeyRequest, GetSigningKeyResponse},
+    std::{
+        collections::HashMap,
+        fmt::{Debug, Display, Formatter, Result as FmtResult},
+        future::Future,
+        mem::replace,
+        net::IpAddr,
+        pin::Pin,
+        sync::{Arc, Mutex},
+        task::{Context, Poll},
+        time::Instant,
+    },
+    tokio::task::futures::TaskLocalFuture,
+    tower::{BoxError, Layer, Service, ServiceExt},
+};
+
+/// The number of tracked keys above which [TokenBucketLimiter] drops buckets that have refilled completely.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+tokio::task_local! {
+    /// The address of the client whose request is being verified, set while the signing key provider is called.
+    pub(crate) static CLIENT_IP: Option<IpAddr>;
+}
+
+/// Returns the address of the client whose request is being verified, if known.
+///
+/// This is only available to signing key providers called by an
+/// [AwsSigV4VerifierService][crate::AwsSigV4VerifierService], and only while their futures are being polled.
+pub fn current_client_ip() -> Option<IpAddr> {
+    CLIENT_IP.try_with(|client_ip| *client_ip).ok().flatten()
+}
+
+/// The key a request is rate limited by.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ThrottleKey {
+    /// The principal the request was authenticated as.
+    Principal(String),
+
+    /// The address of the client, for requests that have not been authenticated.
+    SourceIp(IpAddr),
+
+    /// The access key id a signing key is being looked up for.
+    AccessKey(String),
+}
+
+impl ThrottleKey {
+    /// Returns the key for a request: the authenticated principal if there is one, otherwise the client address.
+    pub fn for_request<B>(req: &Request<B>) -> Option<Self> {
+        match req.extensions().get::<Principal>() {
+            Some(principal) if !principal.is_empty() => Some(Self::Principal(principal.to_string())),
+            _ => req.extensions().get::<ConnectInfo>().map(|ci| Self::SourceIp(ci.client_ip())),
+        }
+    }
+}
+
+impl Display for ThrottleKey {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Principal(principal) => f.write_str(principal),
+            Self::SourceIp(ip) => write!(f, "{ip}"),
+            Self::AccessKey(access_key) => f.write_str(access_key),
+        }
+    }
+}
+
+/// A store of rate limit allowances.
+///
+/// [TokenBucketLimiter] keeps allowances in process memory. Services running several instances should implement this
+/// trait on top of a shared store (e.g. Redis) instead.
+#[async_trait]
+pub trait RateLimiter: Debug + Send + Sync {
+    /// Take one request from the key's allowance, returning `false` if the allowance is exhausted.
+    async fn try_acquire(&self, key: &ThrottleKey) -> Result<bool, BoxError>;
+}
+
+/// An in-memory [RateLimiter] implementing a token bucket per key.
+///
+/// Each bucket holds up to `capacity` tokens and refills at `refill_per_second` tokens per second; each request takes
+/// one token.
+#[derive(Debug)]
+pub struct TokenBucketLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+    buckets: Mutex<HashMap<ThrottleKey, Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucketLimiter {
+    /// Create a new [TokenBucketLimiter].
+    pub fn new(capacity: u32, refill_per_second: f64) -> Self {
+        Self {
+            capacity: capacity as f64,
+            refill_per_second,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        bucket.updated = now;
+    }
+}
+
+#[async_trait]
+impl RateLimiter for TokenBucketLimiter {
+    async fn try_acquire(&self, key: &ThrottleKey) -> Result<bool, BoxError> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() > PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| {
+                let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+                bucket.tokens + elapsed * self.refill_per_second < self.capacity
+            });
+        }
+
+        let bucket = buckets.entry(key.clone()).or_insert(Bucket {
+            tokens: self.capacity,
+            updated: now,
+        });
+        self.refill(bucket, now);
+
+        if bucket.tokens < 1.0 {
+            return Ok(false);
+        }
+
+        bucket.tokens -= 1.0;
+        Ok(true)
+    }
+}
+
+/// A Tower [Layer] that wraps a service in a [ThrottlingService].
+#[derive(Clone, Debug)]
+pub struct ThrottlingLayer<E> {
+    limiter: Arc<dyn RateLimiter>,
+    error_mapper: E,
+    slow_down: bool,
+}
+
+impl<E: ErrorMapper> ThrottlingLayer<E> {
+    /// Create a new [ThrottlingLayer] that rejects requests over the limiter's allowance with a `Throttling` error.
+    pub fn new(limiter: Arc<dyn RateLimiter>, error_mapper: E) -> Self {
+        Self {
+            limiter,
+            error_mapper,
+            slow_down: false,
+        }
+    }
+
+    /// Reject requests with the S3-style `503 SlowDown` error instead of `Throttling`.
+    pub fn with_slow_down(mut self) -> Self {
+        self.slow_down = true;
+        self
+    }
+}
+
+impl<S, E: ErrorMapper> Layer<S> for ThrottlingLayer<E> {
+    type Service = ThrottlingService<S, E>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ThrottlingService {
+            inner,
+            limiter: self.limiter.clone(),
+            error_mapper: self.error_mapper.clone(),
+            slow_down: self.slow_down,
+        }
+    }
+}
+
+/// A service that applies rate limits before passing requests to the wrapped service.
+///
+/// Requests are limited by the [Principal] in their extensions, so the service should normally be the implementation
+/// of an [AwsSigV4VerifierService][crate::AwsSigV4VerifierService]. Requests without a principal are limited by the
+/// client address from their [ConnectInfo] extension; requests with neither are passed through. Rejected requests are
+/// rendered by the error mapper.
+#[derive(Clone, Debug)]
+pub struct ThrottlingService<S, E> {
+    inner: S,
+    limiter: Arc<dyn RateLimiter>,
+    error_mapper: E,
+    slow_down: bool,
+}
+
+impl<S, E: ErrorMapper> ThrottlingService<S, E> {
+    /// Create a new [ThrottlingService] that rejects requests over the limiter's allowance with a `Throttling` error.
+    pub fn new(inner: S, limiter: Arc<dyn RateLimiter>, error_mapper: E) -> Self {
+        ThrottlingLayer::new(limiter, error_mapper).layer(inner)
+    }
+
+    /// Reject requests with the S3-style `503 SlowDown` error instead of `Throttling`.
+    pub fn with_slow_down(mut self) -> Self {
+        self.slow_down = true;
+        self
+    }
+}
+
+impl<S, E, B> Service<Request<B>> for ThrottlingService<S, E>
+where
+    S: Service<Request<B>, Response = Response<Body>, Error = BoxError> + Clone + Send + 'static,
+    S::Future: Send,
+    E: ErrorMapper,
+    B: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, c: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(c)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        // The clone may not be ready, so use the instance that was polled and leave the clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = replace(&mut self.inner, clone);
+        let limiter = self.limiter.clone();
+        let error_mapper = self.error_mapper.clone();
+        let slow_down = self.slow_down;
+
+        Box::pin(async move {
+            if let Some(key) = ThrottleKey::for_request(&req) {
+                if !limiter.try_acquire(&key).await? {
+                    info!("Throttled request from {}", key);
+                    let error = if slow_down {
+                        VerifierError::SlowDown
+                    } else {
+                        VerifierError::Throttling
+                    };
+                    let request_id = req.extensions().get::<RequestId>().copied();
+                    return error_mapper.map_error(error.into(), request_id).await;
+                }
+            }
+
+            inner.call(req).await
+        })
+    }
+}
+
+/// A signing key provider wrapper that rate limits lookups per access key id, and per client address when it is
+/// known, before passing them to the wrapped provider.
+///
+/// This protects the credential store from credential-stuffing attacks: a client cycling through guessed access keys
+/// is limited by its address, and a single access key under attack is limited however many clients are guessing its
+/// secret. Rejected lookups fail with [VerifierError::Throttling].
+///
+/// The client address is only available when the service is used as the signing key provider of an
+/// [AwsSigV4VerifierService][crate::AwsSigV4VerifierService]; see [current_client_ip]. Clones share the same limiters.
+pub struct RateLimitedSigningKeyService<G> {
+    inner: G,
+    limiter: Arc<dyn RateLimiter>,
+    source_ip_limiter: Option<Arc<dyn RateLimiter>>,
+}
+
+impl<G> RateLimitedSigningKeyService<G> {
+    /// Create a new [RateLimitedSigningKeyService] that limits lookups of each access key id to the limiter's
+    /// allowance.
+    pub fn new(inner: G, limiter: Arc<dyn RateLimiter>) -> Self {
+        Self {
+            inner,
+            limiter,
+            source_ip_limiter: None,
+        }
+    }
+
+    /// Also limit the lookups made for each client address to this limiter's allowance.
+    pub fn with_source_ip_limiter(mut self, limiter: Arc<dyn RateLimiter>) -> Self {
+        self.source_ip_limiter = Some(limiter);
+        self
+    }
+}
+
+impl<G: Clone> Clone for RateLimitedSigningKeyService<G> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            limiter: self.limiter.clone(),
+            source_ip_limiter: self.source_ip_limiter.clone(),
+        }
+    }
+}
+
+impl<G> Debug for RateLimitedSigningKeyService<G> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("RateLimitedSigningKeyService")
+            .field("inner", &std::any::type_name::<G>())
+            .field("limiter", &self.limiter)
+            .field("source_ip_limiter", &self.source_ip_limiter)
+            .finish()
+    }
+}
+
+impl<G> Service<GetSigningKeyRequest> for RateLimitedSigningKeyService<G>
+where
+    G: Service<GetSigningKeyRequest, Response = GetSigningKeyResponse, Error = BoxError> + Clone + Send + 'static,
+    G::Future: Send,
+{
+    type Response = GetSigningKeyResponse;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), Self::Error>> {
+        // The wrapped provider is only needed if the lookup is allowed, and is readied then.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: GetSigningKeyRequest) -> Self::Future {
+        let inner = self.inner.clone();
+        let limiter = self.limiter.clone();
+        let source_ip_limiter = self.source_ip_limiter.clone();
+
+        Box::pin(async move {
+            // The client address must be read here rather than in call(); it is only set while the future is polled.
+            let source_ip = current_client_ip().map(ThrottleKey::SourceIp);
+            let keys = [
+                (source_ip_limiter, source_ip),
+                (Some(limiter), Some(ThrottleKey::AccessKey(req.access_key().to_string()))),
+            ];
+
+            for (limiter, key) in keys {
+                if let (Some(limiter), Some(key)) = (limiter, key) {
+                    if !limiter.try_acquire(&key).await? {
+                        info!("Throttled signing key lookup for {}", key);
+                        return Err(VerifierError::Throttling.into());
+                    }
+                }
+            }
+
+            inner.oneshot(req).await
+        })
+    }
+}
+
+/// A service wrapper that makes the client address available to the wrapped signing key provider through
+/// [current_client_ip].
+pub(crate) struct ClientIpScope<S> {
+    inner: S,
+    client_ip: Option<IpAddr>,
+}
+
+impl<S> ClientIpScope<S> {
+    pub(crate) fn new(inner: S, client_ip: Option<IpAddr>) -> Self {
+        Self {
+            inner,
+            client_ip,
+        }
+    }
+}
+
+impl<S, Req> Service<Req> for ClientIpScope<S>
+where
+    S: Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = TaskLocalFuture<Option<IpAddr>, S::Future>;
+
+    fn poll_ready(&mut self, c: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(c)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        CLIENT_IP.scope(self.client_ip, self.inner.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{
+            ClientIpScope, RateLimitedSigningKeyService, RateLimiter, ThrottleKey, ThrottlingService,
+            TokenBucketLimiter,
+        },
+        crate::{test_util::signing_key_request, ConnectInfo, VerifierError, XmlErrorMapper},
+        http::StatusCode,
+        hyper::{Body, Request, Response},
+        scratchstack_aws_principal::{Principal, User},
+        scratchstack_aws_signature::{GetSigningKeyRequest, GetSigningKeyResponse, KSecretKey},
+        std::{
+            net::{IpAddr, Ipv4Addr, SocketAddr},
+            sync::Arc,
+        },
+        tower::{service_fn, BoxError, ServiceExt},
+    };
+
+    #[test_log::test(tokio::test)]
+    async fn test_token_bucket() {
+        let limiter = TokenBucketLimiter::new(2, 0.0);
+        let a = ThrottleKey::SourceIp(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+        let b = ThrottleKey::Principal("arn:aws:iam::123456789012:user/test".to_string());
+
+        assert!(limiter.try_acquire(&a).await.unwrap());
+        assert!(limiter.try_acquire(&a).await.unwrap());
+        assert!(!limiter.try_acquire(&a).await.unwrap());
+        assert!(limiter.try_acquire(&b).await.unwrap());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_throttling_service() {
+        let limiter = Arc::new(TokenBucketLimiter::new(1, 0.0));
+        let inner = service_fn(|_: Request<Body>| async { Ok::<_, BoxError>(Response::new(Body::empty())) });
+        let service =
+            ThrottlingService::new(inner, limiter, XmlErrorMapper::new("https://sts.amazonaws.com/doc/2011-06-15/"));
+        let request = || {
+            let mut req = Request::get("/").body(Body::empty()).unwrap();
+            req.extensions_mut().insert(ConnectInfo::new(SocketAddr::from(([192, 0, 2, 1], 40000)), None));
+            req
+        };
+
+        let response = service.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = service.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("<Code>Throttling</Code>"));
+
+        // Requests that can't be attributed to a principal or a client are not limited.
+        let response = service.oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    fn is_throttled(e: &BoxError) -> bool {
+        matches!(e.downcast_ref::<VerifierError>(), Some(VerifierError::Throttling))
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_rate_limited_signing_key() {
+        let inner = service_fn(|req: GetSigningKeyRequest| async move {
+            let k_signing = KSecretKey::from_str("secret").to_ksigning(req.request_date(), req.region(), req.service());
+            let principal = Principal::from(vec![User::new("aws", "123456789012", "/", "test").unwrap().into()]);
+            Ok::<_, BoxError>(
+                GetSigningKeyResponse::builder().principal(principal).signing_key(k_signing).build().unwrap(),
+            )
+        });
+
+        let service = RateLimitedSigningKeyService::new(inner, Arc::new(TokenBucketLimiter::new(2, 0.0)))
+            .with_source_ip_limiter(Arc::new(TokenBucketLimiter::new(3, 0.0)));
+        service.clone().oneshot(signing_key_request("AKIDEXAMPLE", None, "service")).await.unwrap();
+        service.clone().oneshot(signing_key_request("AKIDEXAMPLE", None, "service")).await.unwrap();
+        let e = service.clone().oneshot(signing_key_request("AKIDEXAMPLE", None, "service")).await.unwrap_err();
+        assert!(is_throttled(&e), "{e}");
+
+        // Other access keys have their own allowance, and lookups without a client address aren't limited by address.
+        for _ in 0..2 {
+            service.clone().oneshot(signing_key_request("AKIDOTHER", None, "service")).await.unwrap();
+        }
+
+        // Lookups from the same client address share an allowance across access keys.
+        let ip = Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+        for access_key in ["AKID1", "AKID2", "AKID3"] {
+            ClientIpScope::new(service.clone(), ip)
+                .oneshot(signing_key_request(access_key, None, "service"))
+                .await
+                .unwrap();
+        }
+        let e = ClientIpScope::new(service.clone(), ip)
+            .oneshot(signing_key_request("AKID4", None, "service"))
+            .await
+            .unwrap_err();
+        assert!(is_throttled(&e), "{e}");
+
+        let ip = Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)));
+        ClientIpScope::new(service, ip).oneshot(signing_key_request("AKID4", None, "service")).await.unwrap();
+    }
+}
@@ -0,0 +1,123 @@
+use {
+    crate::VerifierError,
+    log::error,
+    std::{
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll},
+        time::Duration,
+    },
+    tokio::time::{sleep, Sleep},
+    tower::{BoxError, Service},
+};
+
+/// A service wrapper that fails calls taking longer than `timeout` with [VerifierError::InternalFailure], so a stalled
+/// dependency is reported to the client instead of holding the connection open. If `timeout` is unset, calls are
+/// passed through unchanged.
+#[derive(Clone, Debug)]
+pub(crate) struct TimeoutService<S> {
+    inner: S,
+    timeout: Option<Duration>,
+    name: &'static str,
+}
+
+impl<S> TimeoutService<S> {
+    /// Create a new [TimeoutService]. `name` describes the wrapped service in log messages.
+    pub(crate) fn new(inner: S, timeout: Option<Duration>, name: &'static str) -> Self {
+        Self {
+            inner,
+            timeout,
+            name,
+        }
+    }
+}
+
+impl<S, R> Service<R> for TimeoutService<S>
+where
+    S: Service<R, Error = BoxError>,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = TimeoutFuture<S::Future>;
+
+    fn poll_ready(&mut self, c: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(c)
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        TimeoutFuture::new(self.inner.call(req), self.timeout, self.name)
+    }
+}
+
+/// A future that fails with [VerifierError::InternalFailure] if it does not complete within a timeout.
+pub(crate) struct TimeoutFuture<F> {
+    inner: Pin<Box<F>>,
+    sleep: Option<Pin<Box<Sleep>>>,
+    timeout: Option<Duration>,
+    name: &'static str,
+}
+
+impl<F> TimeoutFuture<F> {
+    /// Create a new [TimeoutFuture]. `name` describes the wrapped future in log messages.
+    pub(crate) fn new(inner: F, timeout: Option<Duration>, name: &'static str) -> Self {
+        Self {
+            inner: Box::pin(inner),
+            sleep: timeout.map(|timeout| Box::pin(sleep(timeout))),
+            timeout,
+            name,
+        }
+    }
+}
+
+impl<F, T> Future for TimeoutFuture<F>
+where
+    F: Future<Output = Result<T, BoxError>>,
+{
+    type Output = Result<T, BoxError>;
+
+    fn poll(mut self: Pin<&mut Self>, c: &mut Context) -> Poll<Self::Output> {
+        if let Poll::Ready(result) = self.inner.as_mut().poll(c) {
+            return Poll::Ready(result);
+        }
+
+        let timed_out = match self.sleep.as_mut() {
+            Some(sleep) => sleep.as_mut().poll(c).is_ready(),
+            None => false,
+        };
+
+        if timed_out {
+            error!("{} did not respond within {:?}", self.name, self.timeout.unwrap_or_default());
+            Poll::Ready(Err(VerifierError::InternalFailure.into()))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::TimeoutService,
+        crate::VerifierError,
+        std::time::Duration,
+        tower::{service_fn, BoxError, ServiceExt},
+    };
+
+    #[test_log::test(tokio::test)]
+    async fn test_timeout() {
+        let slow = || {
+            service_fn(|delay: u64| async move {
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                Ok::<_, BoxError>(delay)
+            })
+        };
+
+        let service = TimeoutService::new(slow(), Some(Duration::from_millis(50)), "test service");
+        assert_eq!(service.clone().oneshot(0).await.unwrap(), 0);
+        let e = service.oneshot(1000).await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<VerifierError>(), Some(VerifierError::InternalFailure)));
+
+        let service = TimeoutService::new(slow(), None, "test service");
+        assert_eq!(service.oneshot(100).await.unwrap(), 100);
+    }
+}
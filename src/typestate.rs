@@ -0,0 +1,194 @@
+use {
+    crate::{AwsSigV4VerifierService, AwsSigV4VerifierServiceBuilder, ErrorMapper, SpawnService},
+    hyper::{body::Body, Request, Response},
+    scratchstack_aws_signature::{GetSigningKeyRequest, GetSigningKeyResponse},
+    tower::{BoxError, Service},
+};
+
+/// Marker type for a required builder field that has not been set yet.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Unset;
+
+/// A builder for [AwsSigV4VerifierService] that tracks the required fields in its type.
+///
+/// Unlike [AwsSigV4VerifierServiceBuilder], forgetting to call [get_signing_key][Self::get_signing_key],
+/// [implementation][Self::implementation], or [error_mapper][Self::error_mapper] is a compile-time error: the `build`
+/// and `into_builder` methods only exist once all three have been supplied.
+///
+/// ```ignore
+/// let verifier = AwsSigV4VerifierServiceTypedBuilder::new("us-east-1", "service")
+///     .get_signing_key(get_signing_key)
+///     .implementation(implementation)
+///     .error_mapper(XmlErrorMapper::new("https://service.example.com/doc/2022-10-01/"))
+///     .build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct AwsSigV4VerifierServiceTypedBuilder<G, S, E> {
+    region: String,
+    service: String,
+    get_signing_key: G,
+    implementation: S,
+    error_mapper: E,
+}
+
+impl AwsSigV4VerifierServiceTypedBuilder<Unset, Unset, Unset> {
+    /// Create a new typed builder for a verifier operating in the given region and service.
+    pub fn new(region: impl Into<String>, service: impl Into<String>) -> Self {
+        Self {
+            region: region.into(),
+            service: service.into(),
+            get_signing_key: Unset,
+            implementation: Unset,
+            error_mapper: Unset,
+        }
+    }
+}
+
+impl<G, S, E> AwsSigV4VerifierServiceTypedBuilder<G, S, E> {
+    /// Set the signing key provider.
+    pub fn get_signing_key<G2>(self, get_signing_key: G2) -> AwsSigV4VerifierServiceTypedBuilder<G2, S, E> {
+        AwsSigV4VerifierServiceTypedBuilder {
+            region: self.region,
+            service: self.service,
+            get_signing_key,
+            implementation: self.implementation,
+            error_mapper: self.error_mapper,
+        }
+    }
+
+    /// Set the service implementation.
+    pub fn implementation<S2>(self, implementation: S2) -> AwsSigV4VerifierServiceTypedBuilder<G, S2, E> {
+        AwsSigV4VerifierServiceTypedBuilder {
+            region: self.region,
+            service: self.service,
+            get_signing_key: self.get_signing_key,
+            implementation,
+            error_mapper: self.error_mapper,
+        }
+    }
+
+    /// Set the mapper for converting authentication errors into HTTP responses.
+    pub fn error_mapper<E2>(self, error_mapper: E2) -> AwsSigV4VerifierServiceTypedBuilder<G, S, E2> {
+        AwsSigV4VerifierServiceTypedBuilder {
+            region: self.region,
+            service: self.service,
+            get_signing_key: self.get_signing_key,
+            implementation: self.implementation,
+            error_mapper,
+        }
+    }
+}
+
+impl<G, S, E> AwsSigV4VerifierServiceTypedBuilder<G, S, E>
+where
+    G: Service<GetSigningKeyRequest, Response = GetSigningKeyResponse, Error = BoxError> + Clone + Send + 'static,
+    G::Future: Send,
+    S: Service<Request<Body>, Response = Response<Body>, Error = BoxError> + Clone + Send + 'static,
+    S::Future: Send,
+    E: ErrorMapper,
+{
+    /// Convert this into an [AwsSigV4VerifierServiceBuilder] with all required fields populated, for setting
+    /// optional fields.
+    pub fn into_builder(self) -> AwsSigV4VerifierServiceBuilder<G, S, E> {
+        let mut builder = AwsSigV4VerifierServiceBuilder::default();
+        builder
+            .region(self.region)
+            .service(self.service)
+            .get_signing_key(self.get_signing_key)
+            .implementation(self.implementation)
+            .error_mapper(self.error_mapper);
+        builder
+    }
+
+    /// Build the [AwsSigV4VerifierService] with default values for all optional fields.
+    pub fn build(self) -> AwsSigV4VerifierService<G, S, E> {
+        self.into_builder().build().expect("all required fields of AwsSigV4VerifierService are set")
+    }
+}
+
+/// A builder for [SpawnService] that tracks the required fields in its type.
+///
+/// This is the [SpawnService] counterpart to [AwsSigV4VerifierServiceTypedBuilder].
+#[derive(Clone, Debug)]
+pub struct SpawnServiceTypedBuilder<G, S, E> {
+    region: String,
+    service: String,
+    get_signing_key: G,
+    implementation: S,
+    error_mapper: E,
+}
+
+impl SpawnServiceTypedBuilder<Unset, Unset, Unset> {
+    /// Create a new typed builder for a service spawner operating in the given region and service.
+    pub fn new(region: impl Into<String>, service: impl Into<String>) -> Self {
+        Self {
+            region: region.into(),
+            service: service.into(),
+            get_signing_key: Unset,
+            implementation: Unset,
+            error_mapper: Unset,
+        }
+    }
+}
+
+impl<G, S, E> SpawnServiceTypedBuilder<G, S, E> {
+    /// Set the signing key provider.
+    pub fn get_signing_key<G2>(self, get_signing_key: G2) -> SpawnServiceTypedBuilder<G2, S, E> {
+        SpawnServiceTypedBuilder {
+            region: self.region,
+            service: self.service,
+            get_signing_key,
+            implementation: self.implementation,
+            error_mapper: self.error_mapper,
+        }
+    }
+
+    /// Set the service implementation.
+    pub fn implementation<S2>(self, implementation: S2) -> SpawnServiceTypedBuilder<G, S2, E> {
+        SpawnServiceTypedBuilder {
+            region: self.region,
+            service: self.service,
+            get_signing_key: self.get_signing_key,
+            implementation,
+            error_mapper: self.error_mapper,
+        }
+    }
+
+    /// Set the mapper for converting authentication errors into HTTP responses.
+    pub fn error_mapper<E2>(self, error_mapper: E2) -> SpawnServiceTypedBuilder<G, S, E2> {
+        SpawnServiceTypedBuilder {
+            region: self.region,
+            service: self.service,
+            get_signing_key: self.get_signing_key,
+            implementation: self.implementation,
+            error_mapper,
+        }
+    }
+}
+
+impl<G, S, E> SpawnServiceTypedBuilder<G, S, E>
+where
+    G: Service<GetSigningKeyRequest, Response = GetSigningKeyResponse, Error = BoxError> + Clone + Send + 'static,
+    G::Future: Send,
+    S: Service<Request<Body>, Response = Response<Body>, Error = BoxError> + Clone + Send + 'static,
+    S::Future: Send,
+    E: ErrorMapper,
+{
+    /// Convert this into an [AwsSigV4VerifierServiceBuilder] with all required fields populated, for setting
+    /// optional fields before passing it to [SpawnService::new].
+    pub fn into_builder(self) -> AwsSigV4VerifierServiceBuilder<G, S, E> {
+        let mut builder = AwsSigV4VerifierServiceBuilder::default();
+        builder
+            .region(self.region)
+            .service(self.service)
+            .get_signing_key(self.get_signing_key)
+            .implementation(self.implementation)
+            .error_mapper(self.error_mapper);
+        builder
+    }
+
+    /// Build the [SpawnService] with default values for all optional fields.
+    pub fn build(self) -> SpawnService<G, S, E> {
+        SpawnService::new(self.into_builder()).expect("all required fields of SpawnService are set")
+    }
+}
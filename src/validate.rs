@@ -0,0 +1,230 @@
+use {
+    crate::{ServiceErrorCatalog, StandardError},
+    std::fmt::Display,
+};
+
+/// Constraints on a handler input type, checked by the routers after the input is deserialized.
+///
+/// Implementations report every violated constraint to the [Violations] collector rather than stopping at the first,
+/// so the client sees all of the problems with its request at once.
+///
+/// ```
+/// use scratchstack_http_framework::validate::{Validate, Violations};
+///
+/// struct CreateUserRequest {
+///     user_name: String,
+///     path: Option<String>,
+///     max_sessions: u32,
+/// }
+///
+/// impl Validate for CreateUserRequest {
+///     fn validate(&self, violations: &mut Violations) {
+///         violations
+///             .length("userName", &self.user_name, 1, 64)
+///             .pattern("userName", &self.user_name, r"[\w+=,.@-]+", |s| {
+///                 s.chars().all(|c| c.is_ascii_alphanumeric() || "_+=,.@-".contains(c))
+///             })
+///             .required("path", &self.path)
+///             .range("maxSessions", self.max_sessions, 1, 10);
+///     }
+/// }
+/// ```
+pub trait Validate {
+    /// Report the constraints this value violates.
+    fn validate(&self, violations: &mut Violations);
+}
+
+/// A collector of constraint violations, rendered as an AWS-style `ValidationException`.
+///
+/// Each violation is described as `Value '<value>' at '<member>' failed to satisfy constraint: <constraint>`, and the
+/// error message lists them all: `2 validation errors detected: <violation>; <violation>`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Violations {
+    prefix: String,
+    messages: Vec<String>,
+}
+
+impl Violations {
+    /// Create a new, empty [Violations] collector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report that a required member is missing.
+    pub fn required<T>(&mut self, member: &str, value: &Option<T>) -> &mut Self {
+        if value.is_none() {
+            self.messages.push(format!(
+                "Value null at '{}{member}' failed to satisfy constraint: Member must not be null",
+                self.prefix
+            ));
+        }
+        self
+    }
+
+    /// Report a string member whose length, in characters, is not between `min` and `max` inclusive.
+    pub fn length(&mut self, member: &str, value: &str, min: usize, max: usize) -> &mut Self {
+        let length = value.chars().count();
+        if length < min {
+            self.violation(member, value, &format!("Member must have length greater than or equal to {min}"));
+        } else if length > max {
+            self.violation(member, value, &format!("Member must have length less than or equal to {max}"));
+        }
+        self
+    }
+
+    /// Report a member whose value is not between `min` and `max` inclusive.
+    pub fn range<T: PartialOrd + Display>(&mut self, member: &str, value: T, min: T, max: T) -> &mut Self {
+        if value < min {
+            self.violation(member, value, &format!("Member must have value greater than or equal to {min}"));
+        } else if value > max {
+            self.violation(member, value, &format!("Member must have value less than or equal to {max}"));
+        }
+        self
+    }
+
+    /// Report a string member that doesn't match a pattern. `matches` checks the value against the pattern; `pattern`
+    /// is only used to describe the constraint.
+    pub fn pattern<F: FnOnce(&str) -> bool>(
+        &mut self,
+        member: &str,
+        value: &str,
+        pattern: &str,
+        matches: F,
+    ) -> &mut Self {
+        if !matches(value) {
+            self.violation(member, value, &format!("Member must satisfy regular expression pattern: {pattern}"));
+        }
+        self
+    }
+
+    /// Report a member that violates the given constraint, e.g. `Member must satisfy enum value set: [Active,
+    /// Inactive]`.
+    pub fn violation<T: Display>(&mut self, member: &str, value: T, constraint: &str) -> &mut Self {
+        self.messages
+            .push(format!("Value '{value}' at '{}{member}' failed to satisfy constraint: {constraint}", self.prefix));
+        self
+    }
+
+    /// Report the violations of a nested structure, with its members prefixed by `member`, e.g. `tags.1.key`.
+    pub fn nested<T: Validate + ?Sized>(&mut self, member: &str, value: &T) -> &mut Self {
+        let prefix_len = self.prefix.len();
+        self.prefix.push_str(member);
+        self.prefix.push('.');
+        value.validate(self);
+        self.prefix.truncate(prefix_len);
+        self
+    }
+
+    /// Indicates whether no violations were reported.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Retreive the descriptions of the reported violations.
+    #[inline]
+    pub fn messages(&self) -> &[String] {
+        &self.messages
+    }
+
+    /// Returns a `ValidationException` listing the reported violations, or `Ok(())` if there were none.
+    pub fn into_result(self) -> Result<(), StandardError> {
+        match self.messages.len() {
+            0 => Ok(()),
+            1 => Err(ServiceErrorCatalog::validation(format!("1 validation error detected: {}", self.messages[0]))),
+            n => Err(ServiceErrorCatalog::validation(format!(
+                "{n} validation errors detected: {}",
+                self.messages.join("; ")
+            ))),
+        }
+    }
+}
+
+/// Check a value's constraints, returning a `ValidationException` listing all of its violations.
+pub fn check<T: Validate + ?Sized>(value: &T) -> Result<(), StandardError> {
+    let mut violations = Violations::new();
+    value.validate(&mut violations);
+    violations.into_result()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{check, Validate, Violations},
+        pretty_assertions::assert_eq,
+        scratchstack_errors::ServiceError,
+    };
+
+    struct Tag {
+        key: String,
+    }
+
+    impl Validate for Tag {
+        fn validate(&self, violations: &mut Violations) {
+            violations.length("key", &self.key, 1, 8);
+        }
+    }
+
+    struct TagUserRequest {
+        user_name: Option<String>,
+        tags: Vec<Tag>,
+        max_items: u32,
+    }
+
+    impl Validate for TagUserRequest {
+        fn validate(&self, violations: &mut Violations) {
+            violations.required("userName", &self.user_name).range("maxItems", self.max_items, 1, 1000);
+            if let Some(user_name) = &self.user_name {
+                violations.pattern("userName", user_name, "[a-z]+", |s| s.chars().all(|c| c.is_ascii_lowercase()));
+            }
+            for (i, tag) in self.tags.iter().enumerate() {
+                violations.nested(&format!("tags.{}", i + 1), tag);
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let request = TagUserRequest {
+            user_name: Some("test".to_string()),
+            tags: vec![Tag {
+                key: "a".to_string(),
+            }],
+            max_items: 100,
+        };
+        assert!(check(&request).is_ok());
+
+        let request = TagUserRequest {
+            user_name: None,
+            tags: vec![
+                Tag {
+                    key: "a".to_string(),
+                },
+                Tag {
+                    key: "abcdefghi".to_string(),
+                },
+            ],
+            max_items: 0,
+        };
+        let error = check(&request).unwrap_err();
+        assert_eq!(error.error_code(), "ValidationException");
+        assert_eq!(
+            error.to_string(),
+            "3 validation errors detected: Value null at 'userName' failed to satisfy constraint: Member must not be \
+             null; Value '0' at 'maxItems' failed to satisfy constraint: Member must have value greater than or equal \
+             to 1; Value 'abcdefghi' at 'tags.2.key' failed to satisfy constraint: Member must have length less than \
+             or equal to 8"
+        );
+
+        let request = TagUserRequest {
+            user_name: Some("Test".to_string()),
+            tags: vec![],
+            max_items: 1,
+        };
+        assert_eq!(
+            check(&request).unwrap_err().to_string(),
+            "1 validation error detected: Value 'Test' at 'userName' failed to satisfy constraint: Member must satisfy \
+             regular expression pattern: [a-z]+"
+        );
+    }
+}
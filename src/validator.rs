@@ -0,0 +1,13 @@
+use {async_trait::async_trait, http::request::Parts, std::fmt::Debug, tower::BoxError};
+
+/// A check run on the request head before the request is authenticated, after the built-in request method and content
+/// type checks.
+///
+/// Validators run in the order they were installed; the first error rejects the request. Errors that are
+/// [SignatureError][scratchstack_aws_signature::SignatureError]s or [VerifierError][crate::VerifierError]s are
+/// rendered by the error mapper.
+#[async_trait]
+pub trait RequestValidator: Debug + Send + Sync {
+    /// Validate the request head.
+    async fn validate(&self, parts: &Parts) -> Result<(), BoxError>;
+}